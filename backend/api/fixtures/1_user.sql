@@ -4,4 +4,4 @@ values ('1f241e1b-b537-493f-a230-075cb16315be', 'SGkgdGhpcyBpcyBhIHRlc3QgdG9rZW4
         '2020-08-08T00:11:21Z'::timestamptz, 'Bobby', 'Tables', 'en_US', 'en_US', true, true, 'US/Pacific-New', 'test org');
 
 -- 1 is "Admin", 2 is "ManageCategory", 3 is "ManageImage", 4 is "ManageJig"
-insert into "user_scope" (user_id, scope) values ('1f241e1b-b537-493f-a230-075cb16315be', 1), ('1f241e1b-b537-493f-a230-075cb16315be', 2), ('1f241e1b-b537-493f-a230-075cb16315be', 3), ('1f241e1b-b537-493f-a230-075cb16315be', 4), ('1f241e1b-b537-493f-a230-075cb16315be', 5), ('1f241e1b-b537-493f-a230-075cb16315be', 6);
+insert into "user_scope" (user_id, scope) values ('1f241e1b-b537-493f-a230-075cb16315be', 1), ('1f241e1b-b537-493f-a230-075cb16315be', 2), ('1f241e1b-b537-493f-a230-075cb16315be', 3), ('1f241e1b-b537-493f-a230-075cb16315be', 4), ('1f241e1b-b537-493f-a230-075cb16315be', 5), ('1f241e1b-b537-493f-a230-075cb16315be', 6), ('1f241e1b-b537-493f-a230-075cb16315be', 8);
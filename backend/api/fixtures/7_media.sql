@@ -0,0 +1,27 @@
+-- media in every library, newest first: web image, user audio and user image (created at the same time),
+-- gif animation, sticker image, canvas image.
+insert into image_metadata (id, name, description, is_premium, created_at, kind, uploaded_at)
+values ('6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e1', 'canvas', 'a canvas image', false, '2021-01-01T00:00:00Z'::timestamptz, 0, '2021-01-01T00:01:00Z'::timestamptz),
+       ('6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e2', 'sticker', 'a sticker image', false, '2021-01-02T00:00:00Z'::timestamptz, 1, null);
+
+insert into animation (id, name, description, is_premium, created_at, looping, variant, uploaded_at)
+values ('6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e3', 'gif', 'a gif animation', false, '2021-01-03T00:00:00Z'::timestamptz, true, 0, '2021-01-03T00:01:00Z'::timestamptz);
+
+insert into user_image_library (id, user_id, created_at, uploaded_at)
+values ('6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e4', '1f241e1b-b537-493f-a230-075cb16315be', '2021-01-04T00:00:00Z'::timestamptz, '2021-01-04T00:01:00Z'::timestamptz);
+
+insert into user_audio_library (id, user_id, created_at)
+values ('6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e5', '1f241e1b-b537-493f-a230-075cb16315be', '2021-01-04T00:00:00Z'::timestamptz);
+
+insert into web_media_library (id, hash, kind, created_at, uploaded_at)
+values ('6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e6', '\x01', 0, '2021-01-06T00:00:00Z'::timestamptz, '2021-01-06T00:01:00Z'::timestamptz);
+
+insert into web_media_library_url (media_url, media_id, created_at, imported_by, host)
+values ('https://cdn.example.com/image.png', '6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e6', '2021-01-06T00:00:00Z'::timestamptz, '1f241e1b-b537-493f-a230-075cb16315be', 'cdn.example.com');
+
+-- perceptual hashes, the canvas image's is 0 and the rest are 3, 7 and 8 bits away from it.
+insert into media_phash (library, media_id, phash)
+values (0, '6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e1', 0),
+       (0, '6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e2', 7),
+       (2, '6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e6', 72340172838076672),
+       (1, '6fa0a6f4-7654-11eb-b1b3-7f2ad2f4d1e4', 255);
@@ -0,0 +1,13 @@
+-- more users, for testing what users can do to (and see of) each other: two without any scopes,
+-- and one that can access premium media.
+insert into "user" (id, firebase_id, username, email, created_at, given_name, family_name, language, locale,
+                    opt_into_edu_resources, over_18, timezone, organization)
+values ('7b6cc3f4-7b0c-11eb-b1b3-3b4f1e2c7a01', null, 'alice', 'alice@test.test',
+        '2021-03-01T00:00:00Z'::timestamptz, 'Alice', 'Tables', 'en_US', 'en_US', true, true, 'US/Pacific-New', 'test org'),
+       ('7b6cc3f4-7b0c-11eb-b1b3-3b4f1e2c7a02', null, 'bob', 'bob@test.test',
+        '2021-03-01T00:00:00Z'::timestamptz, 'Bob', 'Tables', 'en_US', 'en_US', true, true, 'US/Pacific-New', 'test org'),
+       ('7b6cc3f4-7b0c-11eb-b1b3-3b4f1e2c7a03', null, 'carol', 'carol@test.test',
+        '2021-03-01T00:00:00Z'::timestamptz, 'Carol', 'Tables', 'en_US', 'en_US', true, true, 'US/Pacific-New', 'test org');
+
+-- 7 is "PremiumMedia"
+insert into "user_scope" (user_id, scope) values ('7b6cc3f4-7b0c-11eb-b1b3-3b4f1e2c7a03', 7);
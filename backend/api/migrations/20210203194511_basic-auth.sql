@@ -0,0 +1,24 @@
+-- users authenticated by the built in identity provider don't have a firebase id.
+alter table "user" alter column firebase_id drop not null;
+
+create table user_auth_basic
+(
+    user_id    uuid primary key references "user" (id) on delete cascade,
+    email      citext unique not null,
+    -- argon2 encoded hash (contains the salt and parameters)
+    password   text          not null,
+    created_at timestamptz   not null default now(),
+    updated_at timestamptz
+);
+
+create table user_auth_token
+(
+    -- sha256 of the token, the token itself is never stored.
+    token_hash bytea primary key,
+    user_id    uuid        not null references "user" (id) on delete cascade,
+    kind       int2        not null,
+    created_at timestamptz not null default now(),
+    expires_at timestamptz not null
+);
+
+create index user_auth_token_user_id_idx on user_auth_token (user_id);
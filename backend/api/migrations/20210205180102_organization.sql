@@ -0,0 +1,58 @@
+create table organization
+(
+    id           uuid primary key     default uuid_generate_v1mc(),
+    display_name text        not null,
+    created_at   timestamptz not null default now(),
+    updated_at   timestamptz
+);
+
+create table organization_member
+(
+    organization_id uuid        not null references organization (id) on delete cascade,
+    user_id         uuid        not null references "user" (id) on delete cascade,
+    -- see `shared::domain::organization::OrganizationRole`
+    role            int2        not null,
+    created_at      timestamptz not null default now(),
+    primary key (organization_id, user_id)
+);
+
+create index organization_member_user_id_idx on organization_member (user_id);
+
+create table organization_invite
+(
+    -- sha256 of the token, the token itself is never stored.
+    token_hash      bytea primary key,
+    organization_id uuid        not null references organization (id) on delete cascade,
+    role            int2        not null,
+    created_by      uuid        references "user" (id) on delete set null,
+    created_at      timestamptz not null default now(),
+    expires_at      timestamptz not null
+);
+
+create table classroom
+(
+    id              uuid primary key     default uuid_generate_v1mc(),
+    organization_id uuid        not null references organization (id) on delete cascade,
+    owner_id        uuid        not null references "user" (id) on delete cascade,
+    display_name    text        not null,
+    created_at      timestamptz not null default now(),
+    updated_at      timestamptz
+);
+
+create index classroom_organization_id_idx on classroom (organization_id);
+
+create table classroom_student
+(
+    classroom_id uuid        not null references classroom (id) on delete cascade,
+    user_id      uuid        not null references "user" (id) on delete cascade,
+    created_at   timestamptz not null default now(),
+    primary key (classroom_id, user_id)
+);
+
+create table classroom_jig
+(
+    classroom_id uuid        not null references classroom (id) on delete cascade,
+    jig_id       uuid        not null references jig (id) on delete cascade,
+    assigned_at  timestamptz not null default now(),
+    primary key (classroom_id, jig_id)
+);
@@ -0,0 +1,25 @@
+create table play_session
+(
+    id           uuid primary key     default uuid_generate_v1mc(),
+    jig_id       uuid        not null references jig (id) on delete cascade,
+    user_id      uuid        not null references "user" (id) on delete cascade,
+    classroom_id uuid        references classroom (id) on delete set null,
+    created_at   timestamptz not null default now()
+);
+
+create index play_session_classroom_id_idx on play_session (classroom_id);
+
+create table play_session_event
+(
+    id            uuid primary key     default uuid_generate_v1mc(),
+    session_id    uuid        not null references play_session (id) on delete cascade,
+    module_id     uuid        not null references module (id) on delete cascade,
+    -- see `shared::domain::session::SessionEventKind`
+    kind          int2        not null,
+    score         int4,
+    attempts      int4,
+    time_spent_ms int8,
+    created_at    timestamptz not null default now()
+);
+
+create index play_session_event_session_id_idx on play_session_event (session_id);
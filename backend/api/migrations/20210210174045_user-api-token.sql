@@ -0,0 +1,15 @@
+-- personal access tokens, for scripts and tooling.
+create table user_api_token
+(
+    id           uuid primary key     default uuid_generate_v1mc(),
+    user_id      uuid        not null references "user" (id) on delete cascade,
+    -- sha256 of the token, the token itself is only shown once, at creation.
+    token_hash   bytea unique not null,
+    display_name text        not null,
+    -- subset of the user's `user_scope`s
+    scopes       int2[]      not null,
+    created_at   timestamptz not null default now(),
+    last_used_at timestamptz
+);
+
+create index user_api_token_user_id_idx on user_api_token (user_id);
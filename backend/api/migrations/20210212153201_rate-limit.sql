@@ -0,0 +1,9 @@
+-- token buckets for rate limiting, see `http::rate_limit`.
+create table rate_limit_bucket
+(
+    key        text primary key,
+    tokens     float8      not null,
+    updated_at timestamptz not null
+);
+
+create index rate_limit_bucket_updated_at_idx on rate_limit_bucket (updated_at);
@@ -0,0 +1,19 @@
+create table upload_session
+(
+    id           uuid primary key     default uuid_generate_v1mc(),
+    user_id      uuid        not null references "user" (id) on delete cascade,
+    -- see `shared::media::MediaLibrary`
+    library      int2        not null,
+    media_id     uuid        not null,
+    -- see `shared::domain::upload::UploadKind`
+    kind         int2        not null,
+    -- the storage backend's id for the multipart upload.
+    upload_id    text        not null,
+    size         int8        not null,
+    part_size    int8        not null,
+    created_at   timestamptz not null default now(),
+    expires_at   timestamptz not null,
+    completed_at timestamptz
+);
+
+create index upload_session_user_id_idx on upload_session (user_id);
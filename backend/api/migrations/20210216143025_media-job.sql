@@ -0,0 +1,35 @@
+-- background jobs for processing uploaded media, see `jobs`.
+create table media_job
+(
+    id           uuid primary key     default uuid_generate_v1mc(),
+    -- see `jobs::JobKind`
+    kind         int2        not null,
+    -- see `shared::media::MediaLibrary`
+    library      int2        not null,
+    media_id     uuid        not null,
+    -- 0 = queued, 1 = dead (ran out of attempts, or failed permanently)
+    status       int2        not null default 0,
+    attempts     int4        not null default 0,
+    max_attempts int4        not null,
+    -- when the job can next be run.
+    run_at       timestamptz not null default now(),
+    -- while set (and in the future) a worker is running the job.
+    locked_until timestamptz,
+    last_error   text,
+    created_at   timestamptz not null default now(),
+    updated_at   timestamptz
+);
+
+create index media_job_queued_idx on media_job (run_at) where status = 0;
+create index media_job_media_id_idx on media_job (media_id);
+
+-- see `shared::media::ProcessingStatus`, `null` means that nothing has been uploaded.
+alter table image_metadata add column processing_status int2;
+alter table user_image_library add column processing_status int2;
+alter table animation add column processing_status int2;
+alter table user_audio_library add column processing_status int2;
+
+update image_metadata set processing_status = 1 where uploaded_at is not null;
+update user_image_library set processing_status = 1 where uploaded_at is not null;
+update animation set processing_status = 1 where uploaded_at is not null;
+update user_audio_library set processing_status = 1 where uploaded_at is not null;
@@ -0,0 +1,12 @@
+-- who imported each url, and the url's host (so imports can be filtered by source domain)
+alter table web_media_library_url
+    add column imported_by uuid references "user" (id) on delete set null,
+    add column host        text;
+
+update web_media_library_url
+set host = lower(substring(media_url from '^[a-zA-Z][a-zA-Z0-9+.-]*://(?:[^/?#@]*@)?([^/?#:]+)'));
+
+create index web_media_library_url_media_id on web_media_library_url (media_id);
+create index web_media_library_url_host on web_media_library_url (host);
+create index web_media_library_url_imported_by on web_media_library_url (imported_by);
+create index web_media_library_created_at on web_media_library (created_at desc, id desc);
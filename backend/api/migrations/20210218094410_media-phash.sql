@@ -0,0 +1,34 @@
+-- perceptual hashes of images (see `image_ops::perceptual_hash`), for finding near duplicates.
+create table media_phash
+(
+    library    int2        not null,
+    media_id   uuid        not null,
+    phash      int8        not null,
+    updated_at timestamptz not null default now(),
+    primary key (library, media_id)
+);
+
+-- two hashes within a hamming distance of 7 have at least one byte in common,
+-- so these let similar images be found without comparing against every hash.
+create index media_phash_byte_0 on media_phash ((phash & 255));
+create index media_phash_byte_1 on media_phash (((phash >> 8) & 255));
+create index media_phash_byte_2 on media_phash (((phash >> 16) & 255));
+create index media_phash_byte_3 on media_phash (((phash >> 24) & 255));
+create index media_phash_byte_4 on media_phash (((phash >> 32) & 255));
+create index media_phash_byte_5 on media_phash (((phash >> 40) & 255));
+create index media_phash_byte_6 on media_phash (((phash >> 48) & 255));
+create index media_phash_byte_7 on media_phash (((phash >> 56) & 255));
+
+create function delete_media_phash() returns trigger
+    language plpgsql
+as
+$$
+begin
+    delete from media_phash where library = tg_argv[0]::int2 and media_id = old.id;
+    return null;
+end;
+$$;
+
+create trigger delete_media_phash after delete on image_metadata for each row execute procedure delete_media_phash(0);
+create trigger delete_media_phash after delete on user_image_library for each row execute procedure delete_media_phash(1);
+create trigger delete_media_phash after delete on web_media_library for each row execute procedure delete_media_phash(2);
@@ -0,0 +1,30 @@
+-- moderation of user library and web library images, media without a row here predates moderation (and is treated as approved).
+create table media_moderation
+(
+    library     int2        not null,
+    media_id    uuid        not null,
+    -- 0: pending, 1: approved, 2: rejected
+    status      int2        not null,
+    reason      text,
+    reviewed_by uuid references "user" (id) on delete set null,
+    reviewed_at timestamptz,
+    created_at  timestamptz not null default now(),
+    updated_at  timestamptz,
+    primary key (library, media_id)
+);
+
+create index media_moderation_status on media_moderation (status, created_at desc, media_id desc);
+create index media_moderation_created_at on media_moderation (created_at desc, media_id desc);
+
+create function delete_media_moderation() returns trigger
+    language plpgsql
+as
+$$
+begin
+    delete from media_moderation where library = tg_argv[0]::int2 and media_id = old.id;
+    return null;
+end;
+$$;
+
+create trigger delete_media_moderation after delete on user_image_library for each row execute procedure delete_media_moderation(1);
+create trigger delete_media_moderation after delete on web_media_library for each row execute procedure delete_media_moderation(2);
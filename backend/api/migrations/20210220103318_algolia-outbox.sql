@@ -0,0 +1,94 @@
+-- pending changes to the algolia index, written by triggers so that nothing (deletes in particular) gets missed.
+-- there's at most one pending operation per object, newer operations replace older ones.
+create table algolia_outbox
+(
+    object_id       uuid        not null primary key,
+    -- 0: upsert, 1: delete
+    operation       int2        not null,
+    attempts        int4        not null default 0,
+    last_error      text,
+    -- set once `attempts` runs out, failed operations aren't retried until they're enqueued again.
+    failed_at       timestamptz,
+    next_attempt_at timestamptz not null default now(),
+    enqueued_at     timestamptz not null default now()
+);
+
+create index algolia_outbox_pending on algolia_outbox (next_attempt_at) where failed_at is null;
+create index algolia_outbox_failed on algolia_outbox (failed_at desc, object_id) where failed_at is not null;
+
+create function enqueue_algolia_operation(object_id uuid, operation int2) returns void
+    language sql
+as
+$$
+insert into algolia_outbox (object_id, operation)
+values (object_id, operation)
+on conflict (object_id) do update
+set operation       = excluded.operation,
+    attempts        = 0,
+    last_error      = null,
+    failed_at       = null,
+    next_attempt_at = now(),
+    enqueued_at     = now()
+$$;
+
+create function enqueue_algolia_image() returns trigger
+    language plpgsql
+as
+$$
+begin
+    if tg_op = 'DELETE' then
+        perform enqueue_algolia_operation(old.id, 1::int2);
+    else
+        perform enqueue_algolia_operation(new.id, 0::int2);
+    end if;
+
+    return null;
+end;
+$$;
+
+-- changes to an image's metadata bump its `updated_at`, so they're covered by the update trigger.
+create trigger enqueue_algolia after insert or delete on image_metadata for each row execute procedure enqueue_algolia_image();
+create trigger enqueue_algolia_update after update on image_metadata for each row
+    when (old.* is distinct from new.*)
+execute procedure enqueue_algolia_image();
+
+-- images are indexed with the names of their metadata, so renaming metadata needs to reindex every image that has it.
+create function enqueue_algolia_metadata_images() returns trigger
+    language plpgsql
+as
+$$
+begin
+    execute format(
+        'select enqueue_algolia_operation(image_id, 0::int2) from %I where %I = $1',
+        tg_argv[0],
+        tg_argv[1]
+    ) using new.id;
+
+    return null;
+end;
+$$;
+
+create trigger enqueue_algolia after update of display_name on style for each row
+    when (old.display_name is distinct from new.display_name)
+execute procedure enqueue_algolia_metadata_images('image_style', 'style_id');
+
+create trigger enqueue_algolia after update of display_name on age_range for each row
+    when (old.display_name is distinct from new.display_name)
+execute procedure enqueue_algolia_metadata_images('image_age_range', 'age_range_id');
+
+create trigger enqueue_algolia after update of display_name on affiliation for each row
+    when (old.display_name is distinct from new.display_name)
+execute procedure enqueue_algolia_metadata_images('image_affiliation', 'affiliation_id');
+
+create trigger enqueue_algolia after update of name on category for each row
+    when (old.name is distinct from new.name)
+execute procedure enqueue_algolia_metadata_images('image_category', 'category_id');
+
+-- carry over anything that `last_synced_at` says hasn't been synced yet, the outbox replaces it.
+insert into algolia_outbox (object_id, operation)
+select id, 0
+from image_metadata
+where last_synced_at is null
+   or (updated_at is not null and last_synced_at < updated_at);
+
+alter table image_metadata drop column last_synced_at;
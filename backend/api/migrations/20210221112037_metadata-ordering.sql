@@ -0,0 +1,63 @@
+-- metadata can be reordered through the api now, which shifts every item between the old and new index,
+-- so the unique indexes need to be checked at the end of the transaction rather than after every row.
+alter table style
+    drop constraint style_index_key,
+    add constraint style_index_key unique (index) deferrable initially deferred;
+
+alter table age_range
+    drop constraint age_range_index_key,
+    add constraint age_range_index_key unique (index) deferrable initially deferred;
+
+alter table affiliation
+    drop constraint affiliation_index_key,
+    add constraint affiliation_index_key unique (index) deferrable initially deferred;
+
+alter table subject
+    drop constraint subject_index_key,
+    add constraint subject_index_key unique (index) deferrable initially deferred;
+
+alter table content_type
+    drop constraint content_type_index_key,
+    add constraint content_type_index_key unique (index) deferrable initially deferred;
+
+-- anything without an index (which previously sorted last) goes at the end, oldest first.
+update style
+set index = indexed.index
+from (select id, (select coalesce(max(index) + 1, 0) from style) + row_number() over (order by created_at, id) - 1 as index
+      from style
+      where index is null) indexed
+where style.id = indexed.id;
+
+update age_range
+set index = indexed.index
+from (select id, (select coalesce(max(index) + 1, 0) from age_range) + row_number() over (order by created_at, id) - 1 as index
+      from age_range
+      where index is null) indexed
+where age_range.id = indexed.id;
+
+update affiliation
+set index = indexed.index
+from (select id, (select coalesce(max(index) + 1, 0) from affiliation) + row_number() over (order by created_at, id) - 1 as index
+      from affiliation
+      where index is null) indexed
+where affiliation.id = indexed.id;
+
+update subject
+set index = indexed.index
+from (select subject_id, (select coalesce(max(index) + 1, 0) from subject) + row_number() over (order by created_at, subject_id) - 1 as index
+      from subject
+      where index is null) indexed
+where subject.subject_id = indexed.subject_id;
+
+update content_type
+set index = indexed.index
+from (select content_type_id, (select coalesce(max(index) + 1, 0) from content_type) + row_number() over (order by created_at, content_type_id) - 1 as index
+      from content_type
+      where index is null) indexed
+where content_type.content_type_id = indexed.content_type_id;
+
+alter table style alter column index set not null;
+alter table age_range alter column index set not null;
+alter table affiliation alter column index set not null;
+alter table subject alter column index set not null;
+alter table content_type alter column index set not null;
@@ -0,0 +1,91 @@
+-- translated names for categories and metadata, `language` is a lowercase language tag (`he`, `en-us`, etc).
+-- the untranslated name is used when there's no translation for the requested language.
+create table category_translation
+(
+    category_id uuid        not null references category (id) on delete cascade,
+    language    text        not null,
+    name        text        not null,
+    created_at  timestamptz not null default now(),
+    primary key (category_id, language)
+);
+
+create table style_translation
+(
+    style_id     uuid        not null references style (id) on delete cascade,
+    language     text        not null,
+    display_name text        not null,
+    created_at   timestamptz not null default now(),
+    primary key (style_id, language)
+);
+
+create table age_range_translation
+(
+    age_range_id uuid        not null references age_range (id) on delete cascade,
+    language     text        not null,
+    display_name text        not null,
+    created_at   timestamptz not null default now(),
+    primary key (age_range_id, language)
+);
+
+create table affiliation_translation
+(
+    affiliation_id uuid        not null references affiliation (id) on delete cascade,
+    language       text        not null,
+    display_name   text        not null,
+    created_at     timestamptz not null default now(),
+    primary key (affiliation_id, language)
+);
+
+create table subject_translation
+(
+    subject_id   uuid        not null references subject (subject_id) on delete cascade,
+    language     text        not null,
+    display_name text        not null,
+    created_at   timestamptz not null default now(),
+    primary key (subject_id, language)
+);
+
+create table content_type_translation
+(
+    content_type_id uuid        not null references content_type (content_type_id) on delete cascade,
+    language        text        not null,
+    display_name    text        not null,
+    created_at      timestamptz not null default now(),
+    primary key (content_type_id, language)
+);
+
+-- images are indexed with every translation of their metadata's names.
+create function enqueue_algolia_translation_images() returns trigger
+    language plpgsql
+as
+$$
+declare
+    meta_id uuid;
+begin
+    if tg_op = 'DELETE' then
+        meta_id := (to_jsonb(old) ->> tg_argv[1])::uuid;
+    else
+        meta_id := (to_jsonb(new) ->> tg_argv[1])::uuid;
+    end if;
+
+    execute format(
+        'select enqueue_algolia_operation(image_id, 0::int2) from %I where %I = $1',
+        tg_argv[0],
+        tg_argv[1]
+    ) using meta_id;
+
+    return null;
+end;
+$$;
+
+create trigger enqueue_algolia after insert or update or delete on category_translation for each row
+execute procedure enqueue_algolia_translation_images('image_category', 'category_id');
+
+create trigger enqueue_algolia after insert or update or delete on style_translation for each row
+execute procedure enqueue_algolia_translation_images('image_style', 'style_id');
+
+create trigger enqueue_algolia after insert or update or delete on age_range_translation for each row
+execute procedure enqueue_algolia_translation_images('image_age_range', 'age_range_id');
+
+create trigger enqueue_algolia after insert or update or delete on affiliation_translation for each row
+execute procedure enqueue_algolia_translation_images('image_affiliation', 'affiliation_id');
@@ -0,0 +1,27 @@
+-- jigs can be tagged like images are.
+create table jig_category
+(
+    jig_id      uuid        not null references jig (id) on delete cascade,
+    category_id uuid        not null references category (id) on delete cascade,
+    created_at  timestamptz not null default now(),
+    unique (jig_id, category_id)
+);
+
+create table jig_age_range
+(
+    jig_id       uuid        not null references jig (id) on delete cascade,
+    age_range_id uuid        not null references age_range (id),
+    created_at   timestamptz not null default now(),
+    unique (jig_id, age_range_id)
+);
+
+create table jig_affiliation
+(
+    jig_id         uuid        not null references jig (id) on delete cascade,
+    affiliation_id uuid        not null references affiliation (id),
+    created_at     timestamptz not null default now(),
+    unique (jig_id, affiliation_id)
+);
+
+-- for counting the jigs in each category.
+create index jig_category_category_id on jig_category (category_id);
@@ -0,0 +1,33 @@
+-- bulk imports of images into the global image library, see `image_import`.
+create table image_import
+(
+    id         uuid primary key     default uuid_generate_v1mc(),
+    created_by uuid        references "user" (id) on delete set null,
+    created_at timestamptz not null default now()
+);
+
+create table image_import_item
+(
+    id           uuid primary key     default uuid_generate_v1mc(),
+    import_id    uuid        not null references image_import (id) on delete cascade,
+    -- the item's position in the manifest.
+    index        int4        not null,
+    name         text        not null,
+    description  text        not null,
+    is_premium   bool        not null,
+    publish_at   timestamptz,
+    kind         int2        not null,
+    styles       uuid[]      not null,
+    age_ranges   uuid[]      not null,
+    affiliations uuid[]      not null,
+    categories   uuid[]      not null,
+    -- `null` means that the file gets uploaded through the api.
+    source_url   text,
+    -- see `shared::domain::image::import::ImageImportStatus`
+    status       int2        not null,
+    error        text,
+    image_id     uuid        references image_metadata (id) on delete set null,
+    created_at   timestamptz not null default now(),
+    updated_at   timestamptz,
+    unique (import_id, index)
+);
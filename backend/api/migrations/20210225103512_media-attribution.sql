@@ -0,0 +1,37 @@
+-- accessibility and licensing info for images and animations.
+-- `license` is a `shared::media::License`
+alter table image_metadata
+    add column attribution text,
+    add column license     int2,
+    add column source_url  text;
+
+alter table animation
+    add column attribution text,
+    add column license     int2,
+    add column source_url  text;
+
+-- `language` is normalized, see `language::normalize`.
+create table image_alt_text
+(
+    image_id   uuid        not null references image_metadata (id) on delete cascade,
+    language   text        not null,
+    text       text        not null,
+    created_at timestamptz not null default now(),
+    primary key (image_id, language)
+);
+
+create table animation_alt_text
+(
+    animation_id uuid        not null references animation (id) on delete cascade,
+    language     text        not null,
+    text         text        not null,
+    created_at   timestamptz not null default now(),
+    primary key (animation_id, language)
+);
+
+-- alt text is searchable, so changing it needs to get the image re-indexed.
+create trigger bump_image_updated
+    after insert or update or delete
+    on image_alt_text
+    for each row
+execute procedure update_image();
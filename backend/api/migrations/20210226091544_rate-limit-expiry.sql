@@ -0,0 +1,8 @@
+-- buckets that have refilled are the same as missing ones, so they're deleted once they're full again.
+-- existing buckets are treated as full.
+alter table rate_limit_bucket
+    add column expires_at timestamptz not null default now();
+
+drop index rate_limit_bucket_updated_at_idx;
+
+create index rate_limit_bucket_expires_at_idx on rate_limit_bucket (expires_at);
@@ -0,0 +1,8 @@
+-- `user_id` wasn't recorded for user library media until recently, and there's nothing else that records who uploaded it
+-- (upload sessions can only be created for media that already has an owner, and module bodies are opaque).
+-- so media from before then intentionally has no owner, which makes it unreachable for everyone but admins.
+comment on column user_image_library.user_id is
+    'The user that uploaded the image, `null` for images uploaded before owners were recorded (only admins can access those).';
+
+comment on column user_audio_library.user_id is
+    'The user that uploaded the audio, `null` for audio uploaded before owners were recorded (only admins can access those).';
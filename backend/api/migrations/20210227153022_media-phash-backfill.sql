@@ -0,0 +1,21 @@
+-- images uploaded before perceptual hashes were computed don't have one,
+-- hash them in the background (4 = `jobs::JobKind::HashImage`, it doesn't change the image's processing status).
+insert into media_job (kind, library, media_id, max_attempts)
+select 4, library, id, 5
+from (
+    select 0::int2 as library, id
+    from image_metadata
+    where uploaded_at is not null
+    union all
+    select 1::int2 as library, id
+    from user_image_library
+    where uploaded_at is not null
+    union all
+    -- web media is stored as soon as it's added, `kind` 0 and 3 are png images (see `image_ops::MediaKind`)
+    select 2::int2 as library, id
+    from web_media_library
+    where kind in (0, 3)
+) image
+where not exists(
+    select 1 from media_phash where media_phash.library = image.library and media_phash.media_id = image.id
+);
@@ -0,0 +1,3 @@
+-- creating user library media never set `created_at`, so it always failed.
+alter table user_image_library alter column created_at set default now();
+alter table user_audio_library alter column created_at set default now();
@@ -0,0 +1,93 @@
+-- the document that the postgres search fallback (`query/search_image.sql`) matches against, stored so that it can be indexed.
+-- a generated column can't look at other tables, so the names of the image's metadata are kept up to date by triggers.
+create function image_metadata_document(image_id uuid) returns tsvector
+    language sql
+    stable
+as
+$$
+select setweight(to_tsvector('simple', coalesce((
+           select string_agg(category.name, ' ')
+           from image_category
+           inner join category on category.id = image_category.category_id
+           where image_category.image_id = $1
+       ), '')), 'C')
+    || setweight(to_tsvector('simple', coalesce((
+           select string_agg(style.display_name, ' ')
+           from image_style
+           inner join style on style.id = image_style.style_id
+           where image_style.image_id = $1
+       ), '')), 'D')
+    || setweight(to_tsvector('simple', coalesce((
+           select string_agg(age_range.display_name, ' ')
+           from image_age_range
+           inner join age_range on age_range.id = image_age_range.age_range_id
+           where image_age_range.image_id = $1
+       ), '')), 'D')
+$$;
+
+alter table image_metadata
+    add column metadata_document tsvector not null default '';
+
+update image_metadata
+set metadata_document = image_metadata_document(id);
+
+alter table image_metadata
+    add column search_document tsvector generated always as (
+        setweight(to_tsvector('simple', name), 'A')
+            || setweight(to_tsvector('simple', description), 'B')
+            || metadata_document
+    ) stored;
+
+create index image_metadata_search_document on image_metadata using gin (search_document);
+
+create function update_image_metadata_document() returns trigger
+    language plpgsql
+as
+$$
+begin
+    if tg_op = 'DELETE' then
+        update image_metadata set metadata_document = image_metadata_document(id) where id = old.image_id;
+    else
+        update image_metadata set metadata_document = image_metadata_document(id) where id = new.image_id;
+    end if;
+
+    return null;
+end;
+$$;
+
+create trigger update_metadata_document after insert or delete on image_category for each row
+execute procedure update_image_metadata_document();
+
+create trigger update_metadata_document after insert or delete on image_style for each row
+execute procedure update_image_metadata_document();
+
+create trigger update_metadata_document after insert or delete on image_age_range for each row
+execute procedure update_image_metadata_document();
+
+-- renaming metadata needs to update the document of every image that has it.
+create function update_metadata_images_document() returns trigger
+    language plpgsql
+as
+$$
+begin
+    execute format(
+        'update image_metadata set metadata_document = image_metadata_document(id) where id in (select image_id from %I where %I = $1)',
+        tg_argv[0],
+        tg_argv[1]
+    ) using new.id;
+
+    return null;
+end;
+$$;
+
+create trigger update_metadata_document after update of name on category for each row
+    when (old.name is distinct from new.name)
+execute procedure update_metadata_images_document('image_category', 'category_id');
+
+create trigger update_metadata_document after update of display_name on style for each row
+    when (old.display_name is distinct from new.display_name)
+execute procedure update_metadata_images_document('image_style', 'style_id');
+
+create trigger update_metadata_document after update of display_name on age_range for each row
+    when (old.display_name is distinct from new.display_name)
+execute procedure update_metadata_images_document('image_age_range', 'age_range_id');
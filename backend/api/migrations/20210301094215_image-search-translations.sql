@@ -0,0 +1,79 @@
+-- the postgres search fallback matches every translation of an image's metadata names too, like algolia does.
+create or replace function image_metadata_document(image_id uuid) returns tsvector
+    language sql
+    stable
+as
+$$
+select setweight(to_tsvector('simple', coalesce((
+           select string_agg(names.name, ' ')
+           from image_category
+           inner join category on category.id = image_category.category_id
+           cross join lateral (
+               select category.name
+               union all
+               select name from category_translation where category_id = category.id
+           ) names
+           where image_category.image_id = $1
+       ), '')), 'C')
+    || setweight(to_tsvector('simple', coalesce((
+           select string_agg(names.display_name, ' ')
+           from image_style
+           inner join style on style.id = image_style.style_id
+           cross join lateral (
+               select style.display_name
+               union all
+               select display_name from style_translation where style_id = style.id
+           ) names
+           where image_style.image_id = $1
+       ), '')), 'D')
+    || setweight(to_tsvector('simple', coalesce((
+           select string_agg(names.display_name, ' ')
+           from image_age_range
+           inner join age_range on age_range.id = image_age_range.age_range_id
+           cross join lateral (
+               select age_range.display_name
+               union all
+               select display_name from age_range_translation where age_range_id = age_range.id
+           ) names
+           where image_age_range.image_id = $1
+       ), '')), 'D')
+$$;
+
+update image_metadata
+set metadata_document = image_metadata_document(id)
+where exists(select 1 from image_category inner join category_translation using (category_id) where image_id = image_metadata.id)
+   or exists(select 1 from image_style inner join style_translation using (style_id) where image_id = image_metadata.id)
+   or exists(select 1 from image_age_range inner join age_range_translation using (age_range_id) where image_id = image_metadata.id);
+
+-- like `enqueue_algolia_translation_images`, but for the postgres search document.
+create function update_translation_images_document() returns trigger
+    language plpgsql
+as
+$$
+declare
+    meta_id uuid;
+begin
+    if tg_op = 'DELETE' then
+        meta_id := (to_jsonb(old) ->> tg_argv[1])::uuid;
+    else
+        meta_id := (to_jsonb(new) ->> tg_argv[1])::uuid;
+    end if;
+
+    execute format(
+        'update image_metadata set metadata_document = image_metadata_document(id) where id in (select image_id from %I where %I = $1)',
+        tg_argv[0],
+        tg_argv[1]
+    ) using meta_id;
+
+    return null;
+end;
+$$;
+
+create trigger update_metadata_document after insert or update or delete on category_translation for each row
+execute procedure update_translation_images_document('image_category', 'category_id');
+
+create trigger update_metadata_document after insert or update or delete on style_translation for each row
+execute procedure update_translation_images_document('image_style', 'style_id');
+
+create trigger update_metadata_document after insert or update or delete on age_range_translation for each row
+execute procedure update_translation_images_document('image_age_range', 'age_range_id');
@@ -0,0 +1,25 @@
+-- how many images and jigs have a category, shared by the category queries.
+-- only ones that are published count if `published_only` is set.
+create function category_image_count(category_id uuid, published_only bool) returns int8
+    language sql
+    stable
+as
+$$
+select count(*)
+from image_category
+         inner join image_metadata on image_metadata.id = image_category.image_id
+where image_category.category_id = $1
+  and (not $2 or image_metadata.publish_at <= now())
+$$;
+
+create function category_jig_count(category_id uuid, published_only bool) returns int8
+    language sql
+    stable
+as
+$$
+select count(*)
+from jig_category
+         inner join jig on jig.id = jig_category.jig_id
+where jig_category.category_id = $1
+  and (not $2 or jig.publish_at <= now())
+$$;
@@ -0,0 +1,4 @@
+-- imported images keep their attribution and license, see `20210225103512_media-attribution`.
+alter table image_import_item
+    add column attribution text,
+    add column license     int2;
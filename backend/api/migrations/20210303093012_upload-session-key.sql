@@ -0,0 +1,13 @@
+-- where an upload goes depends on things that can change while it's in progress (global media can be made premium),
+-- so the key is decided when the session is created.
+alter table upload_session add column key text;
+
+-- existing sessions upload to the media's public key, except for user images (which are quarantined).
+update upload_session
+set key = case when library = 1 and kind = 0 then 'quarantine/' else '' end -- `MediaLibrary::User`, `UploadKind::Image`
+    || 'media/'
+    || (array ['global', 'user', 'web'])[library + 1]
+    || '/' || media_id || '/'
+    || (array ['original.png', 'animation.gif', 'audio.mp3'])[kind + 1];
+
+alter table upload_session alter column key set not null;
@@ -0,0 +1,2 @@
+-- modules were never given a default id, so creating a jig (which creates its modules) always failed.
+alter table module alter column id set default uuid_generate_v1mc();
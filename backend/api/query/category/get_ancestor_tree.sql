@@ -13,11 +13,15 @@ with recursive links as
 
 select distinct id,
        category.parent_id,
-       name,
+       coalesce((select translation.name
+                 from category_translation translation
+                 where translation.category_id = category.id and translation.language = any($2)
+                 order by array_position($2, translation.language)
+                 limit 1), category.name) as "name!",
        category.index,
        created_at,
        updated_at,
-       (select count(*) from image_category where category_id = id)::int8 as "image_count!",
-       0::int8                                                            as "jig_count!"
+       category_image_count(category.id, $3) as "image_count!",
+       category_jig_count(category.id, $3) as "jig_count!"
 from category
          inner join links using (id);
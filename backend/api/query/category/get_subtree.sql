@@ -12,11 +12,14 @@ with recursive path(id, index, parent_id) as (
 select distinct id as "id!",
        path.index::int2 as "index!",
        path.parent_id,
-       name,
+       coalesce((select translation.name
+                 from category_translation translation
+                 where translation.category_id = category.id and translation.language = any($2)
+                 order by array_position($2, translation.language)
+                 limit 1), category.name) as "name!",
        created_at,
        updated_at,
-       (select count(*) from image_category where category_id = id)::int8 as "image_count!",
-       0::int8                                                            as "jig_count!"
-
+       category_image_count(category.id, $3) as "image_count!",
+       category_jig_count(category.id, $3) as "jig_count!"
 from path
          inner join category using (id);
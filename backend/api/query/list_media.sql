@@ -1,51 +1,68 @@
-select id as "id!",
-    case kind
-        -- PngCanvasImage
-        when 0 then 3
-        -- PngStickerImage
-        when 1 then 0
-    end::int2 "kind!: MediaKind", 
-    created_at as "created_at!",
-    updated_at,
-    uploaded_at,
-    0::int2 as "library!: MediaLibrary" -- global
-from image_metadata
-union all
-select id as "id!",
-    case variant
-        -- GifAnimation
-        when 0 then 1
-        -- SpritesheetAnimation
-        when 1 then 2
-    end::int2 "kind!: MediaKind", 
-    created_at as "created_at!",
-    updated_at,
-    uploaded_at,
-    0::int2 as "library!: MediaLibrary" -- global
-from animation
-union all
-select id as "id!",
-    -- PngStickerImage
-    0::int2 as "kind!: MediaKind",
-    created_at as "created_at!",
-    updated_at,
-    uploaded_at,
-    1::int2 as "library!: MediaLibrary" -- user
-from user_image_library
-union all
-select id as "id!",
-    -- Mp3Audio
-    4::int2 as "kind!: MediaKind",
-    created_at as "created_at!",
-    updated_at,
-    uploaded_at,
-    1::int2 as "library!: MediaLibrary" -- user
-from user_audio_library
-union all
 select id as "id!",
     kind as "kind!: MediaKind",
     created_at as "created_at!",
     updated_at,
     uploaded_at,
-    2::int2 as "library!: MediaLibrary" -- web
-from web_media_library
+    library as "library!: MediaLibrary"
+from (
+    select id,
+        case kind
+            -- PngCanvasImage
+            when 0 then 3
+            -- PngStickerImage
+            when 1 then 0
+        end::int2 as kind,
+        created_at,
+        updated_at,
+        uploaded_at,
+        0::int2 as library -- global
+    from image_metadata
+    union all
+    select id,
+        case variant
+            -- GifAnimation
+            when 0 then 1
+            -- SpritesheetAnimation
+            when 1 then 2
+        end::int2 as kind,
+        created_at,
+        updated_at,
+        uploaded_at,
+        0::int2 as library -- global
+    from animation
+    union all
+    select id,
+        -- PngStickerImage
+        0::int2 as kind,
+        created_at,
+        updated_at,
+        uploaded_at,
+        1::int2 as library -- user
+    from user_image_library
+    union all
+    select id,
+        -- Mp3Audio
+        4::int2 as kind,
+        created_at,
+        updated_at,
+        uploaded_at,
+        1::int2 as library -- user
+    from user_audio_library
+    union all
+    select id,
+        kind,
+        created_at,
+        updated_at,
+        uploaded_at,
+        2::int2 as library -- web
+    from web_media_library
+) media
+where ($1::int2 is null or library = $1)
+    and ($2::int2[] is null or kind = any($2))
+    and ($3::bool is null or (uploaded_at is not null) = $3)
+    and ($4::timestamptz is null or created_at >= $4)
+    and ($5::timestamptz is null or created_at < $5)
+    -- the cursor is the last item of the previous page.
+    and ($6::timestamptz is null or (created_at, id) < ($6, $7::uuid))
+order by created_at desc, id desc
+limit $8
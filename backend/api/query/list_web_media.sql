@@ -0,0 +1,23 @@
+select id,
+    kind as "kind: MediaKind",
+    created_at,
+    updated_at
+from web_media_library
+where ($1::int2[] is null or kind = any($1))
+    and (
+        ($2::text is null and $3::timestamptz is null and $4::timestamptz is null and $5::uuid is null)
+        or exists(
+            select 1
+            from web_media_library_url
+            where media_id = id
+                -- a domain matches itself and its subdomains (`$9` is `%.<domain>`, see `db::like_escape`).
+                and ($2::text is null or host = $2 or host like $9)
+                and ($3::timestamptz is null or web_media_library_url.created_at >= $3)
+                and ($4::timestamptz is null or web_media_library_url.created_at < $4)
+                and ($5::uuid is null or imported_by = $5)
+        )
+    )
+    -- the cursor is the last item of the previous page.
+    and ($6::timestamptz is null or (created_at, id) < ($6, $7::uuid))
+order by created_at desc, id desc
+limit $8
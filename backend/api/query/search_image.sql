@@ -0,0 +1,53 @@
+with matches as (
+    select id,
+        created_at,
+        ts_rank(search_document, query) as rank
+    from image_metadata
+    cross join websearch_to_tsquery('simple', $1) as query
+    -- an empty query matches everything.
+    where ($1 = '' or search_document @@ query)
+        and ($2::bool is null or is_premium = $2)
+        and ($3::bool is null or coalesce(publish_at <= now(), false) = $3)
+        -- images need to have all of the requested metadata.
+        and $4::uuid[] <@ array(select style_id from image_style where image_id = id)
+        and $5::uuid[] <@ array(select age_range_id from image_age_range where image_id = id)
+        and $6::uuid[] <@ array(select affiliation_id from image_affiliation where image_id = id)
+        and $7::uuid[] <@ array(select category_id from image_category where image_id = id)
+),
+-- only counted when facets are requested ($10), since it means scanning every match's metadata.
+facets as (
+    select 'styles' as facet, style_id as id, count(*) as count
+    from image_style
+    inner join matches on matches.id = image_style.image_id
+    where $10
+    group by style_id
+    union all
+    select 'age_ranges', age_range_id, count(*)
+    from image_age_range
+    inner join matches on matches.id = image_age_range.image_id
+    where $10
+    group by age_range_id
+    union all
+    select 'affiliations', affiliation_id, count(*)
+    from image_affiliation
+    inner join matches on matches.id = image_affiliation.image_id
+    where $10
+    group by affiliation_id
+    union all
+    select 'categories', category_id, count(*)
+    from image_category
+    inner join matches on matches.id = image_category.image_id
+    where $10
+    group by category_id
+)
+select array(
+        select id
+        from matches
+        order by rank desc, created_at desc, id
+        limit $8
+        offset $9
+    ) as "ids!",
+    (select count(*) from matches) as "total!",
+    array(select facet from facets order by facet, id) as "facet_names!",
+    array(select id from facets order by facet, id) as "facet_ids!",
+    array(select count from facets order by facet, id) as "facet_counts!"
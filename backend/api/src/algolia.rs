@@ -1,7 +1,5 @@
 use algolia::{
-    filter::{AndFilterable, BooleanFilter, CmpFilter, CommonFilter, FacetFilter, FilterOperator},
-    request::{BatchWriteRequests, SearchQuery, VirtualKeyRestrictions},
-    response::SearchResponse,
+    request::{BatchWriteRequests, VirtualKeyRestrictions},
     ApiKey, AppId, Client as Inner,
 };
 use anyhow::Context;
@@ -11,19 +9,29 @@ use futures::TryStreamExt;
 use serde::Serialize;
 use shared::{
     domain::{
-        category::CategoryId, image::ImageId, meta::AffiliationId, meta::AgeRangeId, meta::StyleId,
+        admin::SearchIndexOperation, category::CategoryId, meta::AffiliationId, meta::AgeRangeId,
+        meta::StyleId,
     },
-    media::MediaGroupKind,
+    media::{License, MediaGroupKind},
 };
 use sqlx::PgPool;
-use std::{convert::TryInto, time::Duration, time::Instant};
+use std::{collections::HashSet, sync::Arc, time::Duration, time::Instant};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+use crate::{db::search_index, search::ImageSearchResults};
 use migration::ResyncKind;
 
 mod migration;
+mod rest;
 
+/// How many changes from the outbox are made at once.
+const BATCH_SIZE: i64 = 100;
+
+/// How many times a change is tried before it's marked as failed (with backoff, that takes a bit over 5 hours).
+const MAX_ATTEMPTS: i32 = 15;
+
+// the `*_names` fields have the untranslated names followed by every translation, so that searches in any language match.
 #[derive(Serialize)]
 struct BatchImage<'a> {
     name: &'a str,
@@ -38,6 +46,9 @@ struct BatchImage<'a> {
     category_names: &'a [String],
     publish_at: Option<i64>,
     is_premium: bool,
+    alt_text: &'a [String],
+    attribution: Option<&'a str>,
+    license: Option<License>,
 }
 
 #[derive(Serialize)]
@@ -50,6 +61,7 @@ enum BatchMedia<'a> {
 pub struct Manager {
     pub db: PgPool,
     pub inner: Inner,
+    pub rest: rest::Client,
     pub index: String,
 }
 
@@ -64,7 +76,8 @@ impl Manager {
         };
 
         Ok(Some(Self {
-            inner: Inner::new(AppId::new(app_id), ApiKey(key))?,
+            inner: Inner::new(AppId::new(app_id.clone()), ApiKey(key.clone()))?,
+            rest: rest::Client::new(app_id, key),
             index,
             db,
         }))
@@ -123,7 +136,7 @@ select algolia_index_version as "algolia_index_version!" from "settings"
         let migrations_to_run = &migration::INDEXING_MIGRATIONS[(algolia_version as usize)..];
 
         for (idx, (_, updater)) in migrations_to_run.iter().enumerate() {
-            updater(&self.inner, &self.index).await.with_context(|| {
+            updater(self).await.with_context(|| {
                 anyhow::anyhow!(
                     "error while running algolia updater #{}",
                     idx + (algolia_version as usize) + 1
@@ -143,9 +156,7 @@ select algolia_index_version as "algolia_index_version!" from "settings"
 
         match resync_mask {
             ResyncKind::Complete => {
-                sqlx::query!("update image_metadata set last_synced_at = null")
-                    .execute(&mut txn)
-                    .await?;
+                search_index::reindex_all(&mut txn).await?;
             }
             ResyncKind::None => {}
         }
@@ -175,13 +186,11 @@ select algolia_index_version as "algolia_index_version!" from "settings"
     }
 
     async fn update_images(&self) -> anyhow::Result<bool> {
-        let mut txn = self.db.begin().await?;
-
         let is_outdated = sqlx::query!(
             r#"select algolia_index_version != $1 as "outdated!" from settings"#,
             migration::INDEX_VERSION
         )
-        .fetch_one(&mut txn)
+        .fetch_one(&self.db)
         .await?
         .outdated;
 
@@ -189,9 +198,76 @@ select algolia_index_version as "algolia_index_version!" from "settings"
             return Ok(false);
         }
 
-        let sync_time = Utc::now();
+        let batch = search_index::next_batch(&self.db, BATCH_SIZE).await?;
+
+        if batch.is_empty() {
+            return Ok(true);
+        }
+
+        let (upserts, deletes): (Vec<_>, Vec<_>) = batch
+            .into_iter()
+            .partition(|it| it.operation == SearchIndexOperation::Upsert);
+
+        if !upserts.is_empty() {
+            log::debug!("Updating a batch of {} image(s)", upserts.len());
+
+            match self.upsert_images(&upserts).await {
+                Ok(()) => search_index::complete(&self.db, &upserts).await?,
+                Err(e) => {
+                    log::warn!(
+                        "failed to update a batch of images in algolia, retrying them one at a time: {:?}",
+                        e
+                    );
+
+                    // so that one bad image doesn't hold back (or use up the attempts of) the rest of the batch.
+                    for upsert in upserts.chunks(1) {
+                        match self.upsert_images(upsert).await {
+                            Ok(()) => search_index::complete(&self.db, upsert).await?,
+                            Err(e) => {
+                                log::warn!("failed to update an image in algolia: {:?}", e);
+                                search_index::retry(
+                                    &self.db,
+                                    upsert,
+                                    &format!("{:?}", e),
+                                    MAX_ATTEMPTS,
+                                )
+                                .await?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if !deletes.is_empty() {
+            log::debug!("Deleting {} image(s)", deletes.len());
+        }
+
+        for delete in deletes {
+            let delete = std::slice::from_ref(&delete);
+            let res = self
+                .inner
+                .delete_object(&self.index, &delete[0].object_id.to_string())
+                .await;
+
+            match res {
+                Ok(_) => search_index::complete(&self.db, delete).await?,
+                Err(e) => {
+                    let e = anyhow::Error::from(e);
+                    log::warn!("failed to delete an image from algolia: {:?}", e);
+                    search_index::retry(&self.db, delete, &format!("{:?}", e), MAX_ATTEMPTS)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Indexes the current state of `images`.
+    async fn upsert_images(&self, images: &[search_index::Pending]) -> anyhow::Result<()> {
+        let ids: Vec<_> = images.iter().map(|it| it.object_id).collect();
 
-        // todo: allow for some way to do a partial update (for example, by having a channel for queueing partial updates)
         let requests: Vec<_> = sqlx::query!(
             r#"
 select id,
@@ -201,30 +277,52 @@ select id,
     array((select affiliation.display_name
            from affiliation
                     inner join image_affiliation on affiliation.id = image_affiliation.affiliation_id
+           where image_affiliation.image_id = image_metadata.id
+           union all
+           select affiliation_translation.display_name
+           from affiliation_translation
+                    inner join image_affiliation using (affiliation_id)
            where image_affiliation.image_id = image_metadata.id))                            as "affiliation_names!",
     array((select style_id from image_style where image_id = image_metadata.id))             as "styles!",
     array((select style.display_name
            from style
                     inner join image_style on style.id = image_style.style_id
+           where image_style.image_id = image_metadata.id
+           union all
+           select style_translation.display_name
+           from style_translation
+                    inner join image_style using (style_id)
            where image_style.image_id = image_metadata.id))                                  as "style_names!",
     array((select age_range_id from image_age_range where image_id = image_metadata.id))     as "age_ranges!",
     array((select age_range.display_name
            from age_range
                     inner join image_age_range on age_range.id = image_age_range.age_range_id
+           where image_age_range.image_id = image_metadata.id
+           union all
+           select age_range_translation.display_name
+           from age_range_translation
+                    inner join image_age_range using (age_range_id)
            where image_age_range.image_id = image_metadata.id))                              as "age_range_names!",
     array((select category_id from image_category where image_id = image_metadata.id))       as "categories!",
     array((select name
            from category
                     inner join image_category on category.id = image_category.category_id
+           where image_category.image_id = image_metadata.id
+           union all
+           select category_translation.name
+           from category_translation
+                    inner join image_category using (category_id)
            where image_category.image_id = image_metadata.id))                               as "category_names!",
     publish_at,
-    is_premium
+    is_premium,
+    array((select text from image_alt_text where image_id = image_metadata.id))              as "alt_text!",
+    attribution,
+    license as "license: License"
  from image_metadata
- where last_synced_at is null or (updated_at is not null and last_synced_at < updated_at and updated_at <= $1)
- limit 100;
-     "#, &sync_time
+ where id = any($1)
+     "#, &ids
         )
-        .fetch(&mut txn)
+        .fetch(&self.db)
         .map_ok(|row| algolia::request::BatchWriteRequest::UpdateObject {
             body: match serde_json::to_value(&BatchMedia::Image(BatchImage {
                 name: &row.name,
@@ -239,6 +337,9 @@ select id,
                 category_names: &row.category_names,
                 publish_at: row.publish_at.map(|t| t.timestamp_nanos()),
                 is_premium: row.is_premium,
+                alt_text: &row.alt_text,
+                attribution: row.attribution.as_deref(),
+                license: row.license,
             }))
             .expect("failed to serialize BatchImage to json")
             {
@@ -250,101 +351,81 @@ select id,
         .try_collect()
         .await?;
 
+        // images that were deleted before they could be indexed are skipped, their delete replaced the upsert in the outbox.
         if requests.is_empty() {
-            return Ok(true);
+            return Ok(());
         }
 
-        log::debug!("Updating a batch of {} image(s)", requests.len());
-
         let request = algolia::request::BatchWriteRequests { requests };
         let ids = self.batch_media(request).await?;
 
         log::debug!("Updated a batch of {} image(s)", ids.len());
 
-        sqlx::query!(
-            "update image_metadata set last_synced_at = $1 where id = any($2)",
-            sync_time,
-            &ids
-        )
-        .execute(&mut txn)
-        .await?;
-
-        Ok(true)
+        Ok(())
     }
 }
 
-macro_rules! with_client {
-    ($client:expr; $ret:expr) => {{
-        let c: &Inner = match &$client {
-            Some(c) => c,
-            None => return Ok($ret),
-        };
-
-        c
-    }};
-
-    ($client:expr) => { with_client!($client; ()) };
-}
-
 #[derive(Clone)]
 pub struct Client {
-    inner: Option<Inner>,
+    rest: Option<Arc<rest::Client>>,
     index: String,
     frontend_search_parent_key: Option<ApiKey>,
+    can_index: bool,
 }
 
-fn filters_for_ids<T: Into<Uuid> + Copy>(
-    filters: &mut Vec<Box<dyn AndFilterable>>,
+/// Adds a filter for each of `ids` (in Algolia's filter syntax) to `filters`.
+fn facet_filters_for_ids<T: Into<Uuid> + Copy>(
+    filters: &mut Vec<String>,
     facet_name: &str,
     ids: &[T],
 ) {
     for id in ids.iter().copied() {
         let id: Uuid = id.into();
-        filters.push(Box::new(CommonFilter {
-            filter: FacetFilter {
-                facet_name: facet_name.to_owned(),
-                value: id.to_string(),
-            },
-            invert: false,
-        }))
-    }
-}
-
-fn media_filter(kind: MediaGroupKind, invert: bool) -> CommonFilter<FacetFilter> {
-    CommonFilter {
-        filter: FacetFilter {
-            facet_name: "media_kind".to_owned(),
-            value: kind.to_str().to_owned(),
-        },
-        invert,
+        filters.push(format!("{}:\"{}\"", facet_name, id));
     }
 }
 
 impl Client {
     pub fn new(settings: Option<AlgoliaSettings>) -> anyhow::Result<Self> {
         if let Some(settings) = settings {
-            let app_id = algolia::AppId::new(settings.application_id);
             let frontend_search_parent_key = settings.frontend_search_key.map(ApiKey);
-
-            let (inner, index) = match (settings.backend_search_key, settings.media_index) {
-                (Some(key), Some(index)) => (Some(Inner::new(app_id, ApiKey(key))?), index),
+            // mirrors `Manager::new`
+            let can_index = settings.management_key.is_some() && settings.media_index.is_some();
+
+            let (rest, index) = match (settings.backend_search_key, settings.media_index) {
+                (Some(key), Some(index)) => (
+                    Some(Arc::new(rest::Client::new(settings.application_id, key))),
+                    index,
+                ),
                 _ => (None, String::new()),
             };
 
             Ok(Self {
-                inner,
+                rest,
                 index,
                 frontend_search_parent_key,
+                can_index,
             })
         } else {
             Ok(Self {
-                inner: None,
+                rest: None,
                 index: String::new(),
                 frontend_search_parent_key: None,
+                can_index: false,
             })
         }
     }
 
+    /// Whether or not the client is configured for searching.
+    pub const fn can_search(&self) -> bool {
+        self.rest.is_some()
+    }
+
+    /// Whether or not this server is configured to keep the index up to date (see [`Manager`]).
+    pub const fn can_index(&self) -> bool {
+        self.can_index
+    }
+
     pub fn generate_virtual_key(
         &self,
         user_id: Option<Uuid>,
@@ -358,6 +439,8 @@ impl Client {
         })
     }
 
+    /// Searches for images, and counts how many of the matching images have each style, age range, affiliation and category
+    /// if `with_facets` is set (a map of facet name, `styles`, `age_ranges`, etc, to a map of id to count).
     // todo: return ImageId (can't because of repr issues in sqlx)
     pub async fn search_image(
         &self,
@@ -369,77 +452,136 @@ impl Client {
         age_ranges: &[AgeRangeId],
         affiliations: &[AffiliationId],
         categories: &[CategoryId],
-    ) -> anyhow::Result<Option<(Vec<Uuid>, u32, u64)>> {
+        with_facets: bool,
+    ) -> anyhow::Result<Option<ImageSearchResults>> {
         let compare_time = Utc::now().timestamp_nanos();
 
-        let client = with_client!(self.inner; None);
-
-        let mut filters = algolia::filter::AndFilter {
-            filters: vec![Box::new(media_filter(MediaGroupKind::Image, false))],
+        let rest = match &self.rest {
+            Some(rest) => rest,
+            None => return Ok(None),
         };
 
+        let mut filters = vec![format!("media_kind:\"{}\"", MediaGroupKind::Image.to_str())];
+
         if let Some(is_published) = is_published {
-            filters.filters.push(Box::new(CommonFilter {
-                filter: CmpFilter::new("publish_at".to_owned(), FilterOperator::Le, compare_time),
-                invert: !is_published,
-            }))
+            let filter = format!("publish_at <= {}", compare_time);
+            filters.push(if is_published {
+                filter
+            } else {
+                format!("NOT {}", filter)
+            });
         }
 
         if let Some(is_premium) = is_premium {
-            filters.filters.push(Box::new(CommonFilter {
-                filter: BooleanFilter {
-                    facet_name: "is_premium".to_owned(),
-                    value: is_premium,
-                },
-                invert: false,
-            }))
+            filters.push(format!("is_premium:{}", is_premium));
         }
 
-        filters_for_ids(&mut filters.filters, "styles", styles);
-        filters_for_ids(&mut filters.filters, "age_ranges", age_ranges);
-        filters_for_ids(&mut filters.filters, "affiliations", affiliations);
-        filters_for_ids(&mut filters.filters, "categories", categories);
+        facet_filters_for_ids(&mut filters, "styles", styles);
+        facet_filters_for_ids(&mut filters, "age_ranges", age_ranges);
+        facet_filters_for_ids(&mut filters, "affiliations", affiliations);
+        facet_filters_for_ids(&mut filters, "categories", categories);
 
-        let results: SearchResponse = client
+        let facets: &[&str] = match with_facets {
+            true => &["styles", "age_ranges", "affiliations", "categories"],
+            false => &[],
+        };
+
+        let results = rest
             .search(
                 &self.index,
-                SearchQuery {
-                    query: Some(query),
+                &rest::SearchQuery {
+                    query,
+                    filters: &filters.join(" AND "),
                     page,
-                    get_ranking_info: true,
-                    filters: Some(filters),
                     hits_per_page: None,
+                    restrict_searchable_attributes: &[],
+                    facets,
+                    max_values_per_facet: Some(1000),
+                    attributes_to_retrieve: &[],
+                    attributes_to_highlight: &[],
                 },
             )
             .await?;
 
-        let pages = results.page_count.try_into()?;
-        let total_hits = results.hit_count as u64;
-
-        let results = results
+        let ids = results
             .hits
             .into_iter()
             .map(|hit| hit.object_id.parse())
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(Some((results, pages, total_hits)))
-    }
+        let facets = match with_facets {
+            true => Some(
+                results
+                    .facets
+                    .into_iter()
+                    .map(|(facet, values)| {
+                        let values = values
+                            .into_iter()
+                            .map(|(id, count)| Ok((id.parse()?, count)))
+                            .collect::<anyhow::Result<_>>()?;
+
+                        Ok((facet, values))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            false => None,
+        };
 
-    pub async fn delete_image(&self, id: ImageId) {
-        if let Err(e) = self.try_delete_image(id).await {
-            log::warn!(
-                "failed to delete image with id {} from algolia: {}",
-                id.0.to_hyphenated(),
-                e
-            );
-        }
+        Ok(Some(ImageSearchResults {
+            ids,
+            pages: results.nb_pages,
+            total_hits: results.nb_hits,
+            facets,
+        }))
     }
 
-    pub async fn try_delete_image(&self, ImageId(id): ImageId) -> anyhow::Result<()> {
-        with_client!(self.inner)
-            .delete_object(&self.index, &id.to_string())
+    /// Gets the names of published images that match `prefix`, best match first, without duplicates.
+    pub async fn suggest_image_names(
+        &self,
+        prefix: &str,
+        limit: u32,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        let compare_time = Utc::now().timestamp_nanos();
+
+        let rest = match &self.rest {
+            Some(rest) => rest,
+            None => return Ok(None),
+        };
+
+        let filters = format!(
+            "media_kind:\"{}\" AND publish_at <= {}",
+            MediaGroupKind::Image.to_str(),
+            compare_time
+        );
+
+        // some images share a name, so get extra to still have enough once they're merged.
+        let results = rest
+            .search(
+                &self.index,
+                &rest::SearchQuery {
+                    query: prefix,
+                    filters: &filters,
+                    page: None,
+                    hits_per_page: Some(limit * 2),
+                    restrict_searchable_attributes: &["name"],
+                    facets: &[],
+                    max_values_per_facet: None,
+                    attributes_to_retrieve: &["name"],
+                    attributes_to_highlight: &[],
+                },
+            )
             .await?;
 
-        Ok(())
+        let mut names: Vec<_> = results
+            .hits
+            .into_iter()
+            .filter_map(|hit| hit.name)
+            .collect();
+
+        let mut seen = HashSet::new();
+        names.retain(|name| seen.insert(name.to_lowercase()));
+        names.truncate(limit as usize);
+
+        Ok(Some(names))
     }
 }
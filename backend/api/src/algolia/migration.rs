@@ -10,20 +10,14 @@ pub enum ResyncKind {
     Complete,
 }
 
-fn bad_batch_object<'a>(
-    client: &'a super::Inner,
-    index: &'a str,
-) -> BoxFuture<'a, anyhow::Result<()>> {
+fn bad_batch_object(manager: &super::Manager) -> BoxFuture<'_, anyhow::Result<()>> {
     Box::pin(async move {
-        client.delete_object(index, "batch").await?;
+        manager.inner.delete_object(&manager.index, "batch").await?;
         Ok(())
     })
 }
 
-fn set_searchable_fields_v1<'a>(
-    client: &'a super::Inner,
-    index: &'a str,
-) -> BoxFuture<'a, anyhow::Result<()>> {
+fn set_searchable_fields_v1(manager: &super::Manager) -> BoxFuture<'_, anyhow::Result<()>> {
     let settings = SetSettings {
         searchable_attributes: Some(
             SearchableAttributes::build()
@@ -36,15 +30,15 @@ fn set_searchable_fields_v1<'a>(
     };
 
     Box::pin(async move {
-        client.set_settings(index, &settings).await?;
+        manager
+            .inner
+            .set_settings(&manager.index, &settings)
+            .await?;
         Ok(())
     })
 }
 
-fn set_attributes_for_faceting_v1<'a>(
-    client: &'a super::Inner,
-    index: &'a str,
-) -> BoxFuture<'a, anyhow::Result<()>> {
+fn set_attributes_for_faceting_v1(manager: &super::Manager) -> BoxFuture<'_, anyhow::Result<()>> {
     let settings = SetSettings {
         searchable_attributes: None,
         attributes_for_faceting: Some(vec![
@@ -58,15 +52,15 @@ fn set_attributes_for_faceting_v1<'a>(
     };
 
     Box::pin(async move {
-        client.set_settings(index, &settings).await?;
+        manager
+            .inner
+            .set_settings(&manager.index, &settings)
+            .await?;
         Ok(())
     })
 }
 
-fn set_searchable_fields_v2<'a>(
-    client: &'a super::Inner,
-    index: &'a str,
-) -> BoxFuture<'a, anyhow::Result<()>> {
+fn set_searchable_fields_v2(manager: &super::Manager) -> BoxFuture<'_, anyhow::Result<()>> {
     let settings = SetSettings {
         searchable_attributes: Some(
             SearchableAttributes::build()
@@ -84,15 +78,15 @@ fn set_searchable_fields_v2<'a>(
     };
 
     Box::pin(async move {
-        client.set_settings(index, &settings).await?;
+        manager
+            .inner
+            .set_settings(&manager.index, &settings)
+            .await?;
         Ok(())
     })
 }
 
-fn set_attributes_for_faceting_v2<'a>(
-    client: &'a super::Inner,
-    index: &'a str,
-) -> BoxFuture<'a, anyhow::Result<()>> {
+fn set_attributes_for_faceting_v2(manager: &super::Manager) -> BoxFuture<'_, anyhow::Result<()>> {
     let settings = SetSettings {
         searchable_attributes: None,
         attributes_for_faceting: Some(vec![
@@ -107,17 +101,68 @@ fn set_attributes_for_faceting_v2<'a>(
     };
 
     Box::pin(async move {
-        client.set_settings(index, &settings).await?;
+        manager
+            .inner
+            .set_settings(&manager.index, &settings)
+            .await?;
         Ok(())
     })
 }
 
-fn empty<'a>(_client: &'a super::Inner, _index: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+/// Makes the metadata facets countable (for search results' facet counts), which the `algolia` crate can't express yet.
+fn set_attributes_for_faceting_v3(manager: &super::Manager) -> BoxFuture<'_, anyhow::Result<()>> {
+    Box::pin(async move {
+        manager
+            .rest
+            .set_attributes_for_faceting(
+                &manager.index,
+                &[
+                    "filterOnly(publish_at)",
+                    "filterOnly(is_premium)",
+                    "styles",
+                    "age_ranges",
+                    "affiliations",
+                    "categories",
+                    "filterOnly(media_kind)",
+                ],
+            )
+            .await
+    })
+}
+
+fn set_searchable_fields_v3(manager: &super::Manager) -> BoxFuture<'_, anyhow::Result<()>> {
+    let settings = SetSettings {
+        searchable_attributes: Some(
+            SearchableAttributes::build()
+                .single(Attribute("name".to_owned()))
+                .single(Attribute("description".to_owned()))
+                .multi(vec![
+                    Attribute("category_names".to_owned()),
+                    Attribute("style_names".to_owned()),
+                    Attribute("age_range_names".to_owned()),
+                    Attribute("affiliation_names".to_owned()),
+                ])
+                .single(Attribute("alt_text".to_owned()))
+                .single(Attribute("attribution".to_owned()))
+                .finish(),
+        ),
+        attributes_for_faceting: None,
+    };
+
+    Box::pin(async move {
+        manager
+            .inner
+            .set_settings(&manager.index, &settings)
+            .await?;
+        Ok(())
+    })
+}
+
+fn empty(_manager: &super::Manager) -> BoxFuture<'_, anyhow::Result<()>> {
     Box::pin(futures::future::ok(()))
 }
 
-pub type MigrateFunction =
-    for<'a> fn(&'a super::Inner, &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+pub type MigrateFunction = for<'a> fn(&'a super::Manager) -> BoxFuture<'a, anyhow::Result<()>>;
 
 pub const INDEXING_MIGRATIONS: &[(ResyncKind, MigrateFunction)] = &[
     (ResyncKind::Complete, bad_batch_object),
@@ -128,6 +173,8 @@ pub const INDEXING_MIGRATIONS: &[(ResyncKind, MigrateFunction)] = &[
     (ResyncKind::Complete, empty),
     (ResyncKind::Complete, empty),
     (ResyncKind::Complete, set_attributes_for_faceting_v2),
+    (ResyncKind::None, set_attributes_for_faceting_v3),
+    (ResyncKind::Complete, set_searchable_fields_v3),
 ];
 
 pub const INDEX_VERSION: i16 = INDEXING_MIGRATIONS.len() as i16;
@@ -0,0 +1,168 @@
+//! Requests that the `algolia` crate doesn't support (yet), made directly against Algolia's REST api.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+pub struct Client {
+    http: reqwest::Client,
+    app_id: String,
+    api_key: String,
+}
+
+/// A search of an index, see <https://www.algolia.com/doc/api-reference/search-api-parameters/>.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery<'a> {
+    pub query: &'a str,
+    pub filters: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hits_per_page: Option<u32>,
+    /// Only match the query against these attributes (instead of all of the index's searchable attributes).
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub restrict_searchable_attributes: &'a [&'a str],
+    /// Facets to count the values of (for every matching object, not just the returned page).
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub facets: &'a [&'a str],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_values_per_facet: Option<u32>,
+    pub attributes_to_retrieve: &'a [&'a str],
+    pub attributes_to_highlight: &'a [&'a str],
+}
+
+#[derive(Deserialize)]
+pub struct SearchHit {
+    #[serde(rename = "objectID")]
+    pub object_id: String,
+    /// Only present if `name` is in the query's `attributes_to_retrieve`.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResponse {
+    pub hits: Vec<SearchHit>,
+    pub nb_hits: u64,
+    pub nb_pages: u32,
+    /// By facet name, then by value. Only the requested facets are counted, and facets that nothing matches are missing.
+    #[serde(default)]
+    pub facets: HashMap<String, HashMap<String, u64>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FacetSettings<'a> {
+    attributes_for_faceting: &'a [&'a str],
+}
+
+impl Client {
+    pub fn new(app_id: String, api_key: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            app_id,
+            api_key,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, host: &str, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, &format!("https://{}/1/{}", host, path))
+            .header("X-Algolia-Application-Id", &self.app_id)
+            .header("X-Algolia-API-Key", &self.api_key)
+    }
+
+    /// Searches the index, counting facet values in the same request (unlike the `algolia` crate's search).
+    pub async fn search(
+        &self,
+        index: &str,
+        query: &SearchQuery<'_>,
+    ) -> anyhow::Result<SearchResponse> {
+        let resp = self
+            .request(
+                reqwest::Method::POST,
+                &format!("{}-dsn.algolia.net", self.app_id),
+                &format!("indexes/{}/query", index),
+            )
+            .json(query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Sets the index's `attributesForFaceting` (in Algolia's format, for example `filterOnly(publish_at)`).
+    pub async fn set_attributes_for_faceting(
+        &self,
+        index: &str,
+        attributes: &[&str],
+    ) -> anyhow::Result<()> {
+        self.request(
+            reqwest::Method::PUT,
+            &format!("{}.algolia.net", self.app_id),
+            &format!("indexes/{}/settings", index),
+        )
+        .json(&FacetSettings {
+            attributes_for_faceting: attributes,
+        })
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{SearchQuery, SearchResponse};
+
+    #[test]
+    fn name_search_query() {
+        let query = SearchQuery {
+            query: "cre",
+            filters: "media_kind:\"image\"",
+            page: None,
+            hits_per_page: Some(10),
+            restrict_searchable_attributes: &["name"],
+            facets: &[],
+            max_values_per_facet: None,
+            attributes_to_retrieve: &["name"],
+            attributes_to_highlight: &[],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&query).unwrap(),
+            json!({
+                "query": "cre",
+                "filters": "media_kind:\"image\"",
+                "hitsPerPage": 10,
+                "restrictSearchableAttributes": ["name"],
+                "attributesToRetrieve": ["name"],
+                "attributesToHighlight": [],
+            })
+        );
+    }
+
+    #[test]
+    fn hits_without_names() {
+        let resp: SearchResponse = serde_json::from_value(json!({
+            "hits": [{ "objectID": "a", "name": "Creative canvas" }, { "objectID": "b" }],
+            "nbHits": 2,
+            "nbPages": 1,
+        }))
+        .unwrap();
+
+        let names: Vec<_> = resp.hits.iter().map(|hit| hit.name.as_deref()).collect();
+
+        assert_eq!(names, [Some("Creative canvas"), None]);
+        assert!(resp.facets.is_empty());
+    }
+}
@@ -1,10 +1,18 @@
 pub(crate) mod animation;
 pub(crate) mod audio;
 pub(crate) mod category;
+pub(crate) mod classroom;
 pub(crate) mod image;
 pub(crate) mod jig;
+pub(crate) mod job;
 pub(crate) mod meta;
+pub(crate) mod moderation;
 pub(crate) mod module;
+pub(crate) mod organization;
+pub(crate) mod phash;
+pub(crate) mod search_index;
+pub(crate) mod session;
+pub(crate) mod upload;
 pub(crate) mod user;
 
 use config::DB_POOL_CONNECTIONS;
@@ -88,6 +96,56 @@ async fn recycle_metadata<'a, T: Metadata>(
     Ok(())
 }
 
+/// Adds every item of `meta` to each of `ids`, skipping the ones they already have.
+async fn add_metadata<T: Metadata>(
+    conn: &mut PgConnection,
+    table: &str,
+    ids: &[Uuid],
+    meta: &[T],
+) -> sqlx::Result<()> {
+    if ids.is_empty() || meta.is_empty() {
+        return Ok(());
+    }
+
+    let meta: Vec<Uuid> = meta.iter().map(|it| (*it).into()).collect();
+
+    sqlx::query(&format!(
+        "insert into {0}_{1} ({0}_id, {1}_id) select * from unnest($1::uuid[]) as ids cross join unnest($2::uuid[]) as meta on conflict do nothing",
+        table,
+        T::TABLE
+    ))
+    .bind(ids)
+    .bind(&meta)
+    .execute(&mut *conn)
+    .await
+    .map(drop)
+}
+
+/// Removes every item of `meta` from each of `ids`.
+async fn remove_metadata<T: Metadata>(
+    conn: &mut PgConnection,
+    table: &str,
+    ids: &[Uuid],
+    meta: &[T],
+) -> sqlx::Result<()> {
+    if ids.is_empty() || meta.is_empty() {
+        return Ok(());
+    }
+
+    let meta: Vec<Uuid> = meta.iter().map(|it| (*it).into()).collect();
+
+    sqlx::query(&format!(
+        "delete from {0}_{1} where {0}_id = any($1) and {1}_id = any($2)",
+        table,
+        T::TABLE
+    ))
+    .bind(ids)
+    .bind(&meta)
+    .execute(&mut *conn)
+    .await
+    .map(drop)
+}
+
 fn generate_metadata_insert(base_table: &str, meta_kind: &str, binds: usize) -> String {
     debug_assert_ne!(binds, 0);
     debug_assert_ne!(binds, i16::MAX as usize);
@@ -111,3 +169,25 @@ pub(crate) const fn nul_if_empty<T>(arr: &[T]) -> Option<&[T]> {
         Some(arr)
     }
 }
+
+/// Escapes the wildcards in `s`, so that it's matched literally in a `like` or `ilike` pattern.
+pub(crate) fn like_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Makes an `ilike` pattern that matches strings starting with `prefix` (wildcards in `prefix` are matched literally).
+pub(crate) fn like_prefix(prefix: &str) -> String {
+    let mut pattern = like_escape(prefix);
+    pattern.push('%');
+    pattern
+}
@@ -1,5 +1,8 @@
 use chrono::{DateTime, Utc};
-use shared::domain::animation::{AnimationId, AnimationKind, AnimationMetadata};
+use shared::{
+    domain::animation::{AnimationId, AnimationKind, AnimationMetadata},
+    media::{AltText, License, ProcessingStatus},
+};
 use sqlx::{PgConnection, PgPool};
 
 pub async fn delete(db: &PgPool, animation: AnimationId) -> sqlx::Result<Option<AnimationKind>> {
@@ -25,10 +28,14 @@ pub async fn create(
     is_looping: bool,
     publish_at: Option<DateTime<Utc>>,
     variant: AnimationKind,
+    attribution: Option<&str>,
+    license: Option<License>,
+    source_url: Option<&str>,
 ) -> sqlx::Result<AnimationId> {
     let id: AnimationId = sqlx::query!(
         r#"
-insert into animation (name, description, is_premium, publish_at, variant, looping) values ($1, $2, $3, $4, $5, $6)
+insert into animation (name, description, is_premium, publish_at, variant, looping, attribution, license, source_url)
+values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
 returning id as "id: AnimationId"
         "#,
         name,
@@ -37,6 +44,9 @@ returning id as "id: AnimationId"
         publish_at,
         variant as i16,
         is_looping,
+        attribution,
+        license.map(|it| it as i16),
+        source_url,
     )
     .fetch_one(conn)
     .await?
@@ -45,9 +55,38 @@ returning id as "id: AnimationId"
     Ok(id)
 }
 
+/// Replaces the animation's alt text with `alt_text` ((language, text) pairs, with normalized languages).
+pub async fn set_alt_text(
+    conn: &mut PgConnection,
+    animation: AnimationId,
+    alt_text: &[(String, String)],
+) -> sqlx::Result<()> {
+    let (languages, texts): (Vec<_>, Vec<_>) = alt_text.iter().cloned().unzip();
+
+    sqlx::query!(
+        "delete from animation_alt_text where animation_id = $1",
+        animation.0
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+insert into animation_alt_text (animation_id, language, text)
+select $1, language, text
+from unnest($2::text[], $3::text[]) as t(language, text)
+"#,
+        animation.0,
+        &languages,
+        &texts
+    )
+    .execute(conn)
+    .await
+    .map(drop)
+}
+
 pub async fn get_one(db: &PgPool, id: AnimationId) -> sqlx::Result<Option<AnimationMetadata>> {
-    sqlx::query_as!(
-        AnimationMetadata,
+    let metadata = sqlx::query!(
         r#"
 select id as "id: AnimationId",
        name,
@@ -57,12 +96,41 @@ select id as "id: AnimationId",
        created_at,
        updated_at,
        variant as "kind: AnimationKind",
-       looping as is_looping
+       looping as is_looping,
+       attribution,
+       license as "license: License",
+       source_url,
+       processing_status as "processing_status: ProcessingStatus",
+       array((select language from animation_alt_text where animation_id = id order by language)) as "alt_text_languages!",
+       array((select text from animation_alt_text where animation_id = id order by language)) as "alt_texts!"
 from animation
 where id = $1
 "#,
         id.0
     )
     .fetch_optional(db)
-    .await
+    .await?
+    .map(|it| AnimationMetadata {
+        id: it.id,
+        name: it.name,
+        description: it.description,
+        is_premium: it.is_premium,
+        publish_at: it.publish_at,
+        kind: it.kind,
+        is_looping: it.is_looping,
+        alt_text: it
+            .alt_text_languages
+            .into_iter()
+            .zip(it.alt_texts)
+            .map(|(language, text)| AltText { language, text })
+            .collect(),
+        attribution: it.attribution,
+        license: it.license,
+        source_url: it.source_url,
+        created_at: it.created_at,
+        updated_at: it.updated_at,
+        processing_status: it.processing_status,
+    });
+
+    Ok(metadata)
 }
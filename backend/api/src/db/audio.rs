@@ -1,14 +1,19 @@
 pub mod user {
     use futures::stream::BoxStream;
-    use shared::domain::audio::{user::UserAudio, AudioId};
+    use shared::{
+        domain::audio::{user::UserAudio, AudioId},
+        media::ProcessingStatus,
+    };
     use sqlx::PgPool;
+    use uuid::Uuid;
 
-    pub async fn create(conn: &PgPool) -> sqlx::Result<AudioId> {
+    pub async fn create(conn: &PgPool, user_id: Uuid) -> sqlx::Result<AudioId> {
         let id: AudioId = sqlx::query!(
             r#"
-insert into user_audio_library default values
+insert into user_audio_library (user_id) values ($1)
 returning id as "id: AudioId"
 "#,
+            user_id
         )
         .fetch_one(conn)
         .await?
@@ -27,7 +32,11 @@ returning id as "id: AudioId"
     pub async fn get(db: &PgPool, image: AudioId) -> sqlx::Result<Option<UserAudio>> {
         sqlx::query_as!(
             UserAudio,
-            r#"select id as "id: AudioId" from user_audio_library where id = $1"#,
+            r#"
+select id as "id: AudioId", processing_status as "processing_status: ProcessingStatus"
+from user_audio_library
+where id = $1
+"#,
             image.0
         )
         .fetch_optional(db)
@@ -37,7 +46,11 @@ returning id as "id: AudioId"
     pub fn list(db: &PgPool) -> BoxStream<'_, sqlx::Result<UserAudio>> {
         sqlx::query_as!(
             UserAudio,
-            r#"select id as "id: AudioId" from user_audio_library order by created_at desc"#,
+            r#"
+select id as "id: AudioId", processing_status as "processing_status: ProcessingStatus"
+from user_audio_library
+order by created_at desc
+"#,
         )
         .fetch(db)
     }
@@ -7,19 +7,29 @@ use shared::domain::category::{Category, CategoryId};
 use sqlx::{Done as _, Executor, PgPool};
 use uuid::Uuid;
 
-pub async fn get_top_level(db: &sqlx::PgPool) -> anyhow::Result<Vec<Category>> {
+pub async fn get_top_level(
+    db: &sqlx::PgPool,
+    languages: &[String],
+    published_only: bool,
+) -> anyhow::Result<Vec<Category>> {
     sqlx::query!(
         r#"
 select id                                                                 as "id: CategoryId",
-       name,
+       coalesce((select translation.name
+                 from category_translation translation
+                 where translation.category_id = category.id and translation.language = any($1)
+                 order by array_position($1, translation.language)
+                 limit 1), category.name) as "name!",
        created_at,
        updated_at,
-       (select count(*)::int8 from image_category where category_id = id) as "image_count!",
-       0::int8                                                            as "jig_count!"
+       category_image_count(category.id, $2) as "image_count!",
+       category_jig_count(category.id, $2) as "jig_count!"
 from category
 where parent_id is null
 order by index
- "#
+ "#,
+        languages,
+        published_only
     )
     .fetch(db)
     .map_ok(|it| Category {
@@ -36,21 +46,31 @@ order by index
     .map_err(Into::into)
 }
 
-pub async fn get_exact(db: &sqlx::PgPool, ids: &[Uuid]) -> sqlx::Result<Vec<Category>> {
+pub async fn get_exact(
+    db: &sqlx::PgPool,
+    ids: &[Uuid],
+    languages: &[String],
+    published_only: bool,
+) -> sqlx::Result<Vec<Category>> {
     sqlx::query!(
         r#"
 select id                                                                 as "id: CategoryId",
-       name,
+       coalesce((select translation.name
+                 from category_translation translation
+                 where translation.category_id = category.id and translation.language = any($2)
+                 order by array_position($2, translation.language)
+                 limit 1), category.name) as "name!",
        created_at,
        updated_at,
-       (select count(*)::int8 from image_category where category_id = id) as "image_count!",
-       0::int8                                                            as "jig_count!"
-
+       category_image_count(category.id, $3) as "image_count!",
+       category_jig_count(category.id, $3) as "jig_count!"
 from category
          inner join unnest($1::uuid[]) with ordinality t(id, ord) USING (id)
 order by t.ord
 "#,
-        ids
+        ids,
+        languages,
+        published_only
     )
     .fetch(db)
     .map_ok(|it| Category {
@@ -66,38 +86,115 @@ order by t.ord
     .await
 }
 
-pub async fn get_subtree(db: &sqlx::PgPool, ids: &[Uuid]) -> sqlx::Result<Vec<Category>> {
-    sqlx::query_file_as!(RawCategory, "query/category/get_subtree.sql", ids)
-        .fetch_all(db)
-        .await
-        .map(build_tree)
+pub async fn get_subtree(
+    db: &sqlx::PgPool,
+    ids: &[Uuid],
+    languages: &[String],
+    published_only: bool,
+) -> sqlx::Result<Vec<Category>> {
+    sqlx::query_file_as!(
+        RawCategory,
+        "query/category/get_subtree.sql",
+        ids,
+        languages,
+        published_only
+    )
+    .fetch_all(db)
+    .await
+    .map(build_tree)
 }
 
-pub async fn get_tree(db: &sqlx::PgPool) -> sqlx::Result<Vec<Category>> {
+pub async fn get_tree(
+    db: &sqlx::PgPool,
+    languages: &[String],
+    published_only: bool,
+) -> sqlx::Result<Vec<Category>> {
     sqlx::query_as!(
         RawCategory,
         r#"
 select id,
        parent_id,
-       name,
+       coalesce((select translation.name
+                 from category_translation translation
+                 where translation.category_id = category.id and translation.language = any($1)
+                 order by array_position($1, translation.language)
+                 limit 1), category.name) as "name!",
        index,
        created_at,
        updated_at,
-       (select count(*) from image_category where category_id = id)::int8 as "image_count!",
-       0::int8                                                            as "jig_count!"
+       category_image_count(category.id, $2) as "image_count!",
+       category_jig_count(category.id, $2) as "jig_count!"
 from category
-"#
+"#,
+        languages,
+        published_only
     )
     .fetch_all(db)
     .await
     .map(build_tree)
 }
 
-pub async fn get_ancestor_tree(db: &sqlx::PgPool, ids: &[Uuid]) -> sqlx::Result<Vec<Category>> {
-    sqlx::query_file_as!(RawCategory, "query/category/get_ancestor_tree.sql", ids)
-        .fetch_all(db)
-        .await
-        .map(build_tree)
+pub async fn get_ancestor_tree(
+    db: &sqlx::PgPool,
+    ids: &[Uuid],
+    languages: &[String],
+    published_only: bool,
+) -> sqlx::Result<Vec<Category>> {
+    sqlx::query_file_as!(
+        RawCategory,
+        "query/category/get_ancestor_tree.sql",
+        ids,
+        languages,
+        published_only
+    )
+    .fetch_all(db)
+    .await
+    .map(build_tree)
+}
+
+/// Replaces the translations of a category's name, returns `false` if the category doesn't exist.
+///
+/// `translations` is pairs of (normalized) language and name.
+pub async fn set_translations(
+    db: &PgPool,
+    CategoryId(id): CategoryId,
+    translations: &[(String, String)],
+) -> sqlx::Result<bool> {
+    let mut txn = db.begin().await?;
+
+    let exists = sqlx::query!("select id from category where id = $1 for update", id)
+        .fetch_optional(&mut txn)
+        .await?
+        .is_some();
+
+    if !exists {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "delete from category_translation where category_id = $1",
+        id
+    )
+    .execute(&mut txn)
+    .await?;
+
+    let (languages, names): (Vec<_>, Vec<_>) = translations.iter().cloned().unzip();
+
+    sqlx::query!(
+        r#"
+insert into category_translation (category_id, language, name)
+select $1, * from unnest($2::text[], $3::text[])
+"#,
+        id,
+        &languages,
+        &names
+    )
+    .execute(&mut txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(true)
 }
 
 pub async fn create(
@@ -345,6 +442,128 @@ where index > $1 and index <= $2 is not false and parent_id is not distinct from
     .map(drop)
 }
 
+/// How many images and JIGs were moved by [`merge`], ones that already had the target category aren't counted.
+pub struct Merged {
+    pub image_count: u64,
+    pub jig_count: u64,
+}
+
+/// Merges the category `from` into `into`.
+///
+/// Every image and JIG in `from` is moved to `into`, `from`'s children are moved to the end of `into`'s children,
+/// then `from` (and its translations) are deleted.
+pub async fn merge(
+    db: &PgPool,
+    CategoryId(from): CategoryId,
+    CategoryId(into): CategoryId,
+) -> Result<Merged, error::CategoryMerge> {
+    async fn inner(
+        db: &PgPool,
+        from: Uuid,
+        into: Uuid,
+    ) -> sqlx::Result<Result<Merged, error::CategoryMerge>> {
+        let mut txn = db.begin().await?;
+
+        txn.execute("set transaction isolation level repeatable read")
+            .await?;
+
+        let source = sqlx::query!(
+            "select parent_id, index from category where id = $1 for update",
+            from
+        )
+        .fetch_optional(&mut txn)
+        .await?;
+
+        let source = match source {
+            Some(source) => source,
+            None => return Ok(Err(error::CategoryMerge::CategoryNotFound)),
+        };
+
+        let target_exists = sqlx::query!(
+            r#"select exists(select 1 from category where id = $1) as "exists!""#,
+            into
+        )
+        .fetch_one(&mut txn)
+        .await?
+        .exists;
+
+        if !target_exists {
+            return Ok(Err(error::CategoryMerge::TargetNotFound));
+        }
+
+        // merging into yourself (or one of your descendants) would delete the target along with the source.
+        if would_cycle(&mut txn, from, into).await? {
+            return Ok(Err(error::CategoryMerge::Cycle));
+        }
+
+        sqlx::query!(
+            r#"
+update category
+set parent_id  = $2,
+    index      = (select count(*)::int2 from category where parent_id = $2) + children.index,
+    updated_at = now()
+from (select id, (row_number() over (order by index) - 1)::int2 as index from category where parent_id = $1) children
+where category.id = children.id
+"#,
+            from,
+            into
+        )
+        .execute(&mut txn)
+        .await?;
+
+        let image_count = sqlx::query!(
+            r#"
+insert into image_category (image_id, category_id)
+select image_id, $2
+from image_category
+where category_id = $1
+on conflict do nothing
+"#,
+            from,
+            into
+        )
+        .execute(&mut txn)
+        .await?
+        .rows_affected();
+
+        let jig_count = sqlx::query!(
+            r#"
+insert into jig_category (jig_id, category_id)
+select jig_id, $2
+from jig_category
+where category_id = $1
+on conflict do nothing
+"#,
+            from,
+            into
+        )
+        .execute(&mut txn)
+        .await?
+        .rows_affected();
+
+        sqlx::query!("delete from category where id = $1", from)
+            .execute(&mut txn)
+            .await?;
+
+        backshift(&mut txn, source.parent_id, source.index, None).await?;
+
+        txn.commit().await?;
+
+        Ok(Ok(Merged {
+            image_count,
+            jig_count,
+        }))
+    }
+
+    loop {
+        match inner(db, from, into).await {
+            Ok(res) => return res,
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("40001") => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
 pub async fn delete(db: &PgPool, id: CategoryId) -> Result<(), Delete> {
     async fn inner(db: &PgPool, id: CategoryId) -> sqlx::Result<()> {
         let mut txn = db.begin().await?;
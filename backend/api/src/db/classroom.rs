@@ -0,0 +1,209 @@
+use shared::domain::{
+    classroom::{AssignedJig, Classroom, ClassroomId},
+    jig::JigId,
+    organization::{OrganizationId, OrganizationRole},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// What a user is allowed to do with a classroom.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// The user is an admin of the classroom's organization,
+    /// or owns the classroom (and is still a teacher in its organization).
+    Manage,
+
+    /// The user is a student in the classroom.
+    View,
+
+    /// The user can't see the classroom at all.
+    Denied,
+}
+
+pub async fn create(
+    pool: &PgPool,
+    organization_id: OrganizationId,
+    owner_id: Uuid,
+    display_name: &str,
+) -> sqlx::Result<ClassroomId> {
+    sqlx::query!(
+        r#"
+insert into classroom (organization_id, owner_id, display_name)
+values ($1, $2, $3)
+returning id as "id: ClassroomId"
+"#,
+        organization_id.0,
+        owner_id,
+        display_name
+    )
+    .fetch_one(pool)
+    .await
+    .map(|it| it.id)
+}
+
+pub async fn get(pool: &PgPool, id: ClassroomId) -> sqlx::Result<Option<Classroom>> {
+    sqlx::query!(
+        r#"
+select id                                                                      as "id: ClassroomId",
+       organization_id                                                         as "organization_id: OrganizationId",
+       owner_id,
+       display_name,
+       created_at,
+       array(select user_id from classroom_student where classroom_id = $1) as "students!: Vec<Uuid>"
+from classroom
+where id = $1
+"#,
+        id.0
+    )
+    .fetch_optional(pool)
+    .await
+    .map(|it| {
+        it.map(|row| Classroom {
+            id: row.id,
+            organization_id: row.organization_id,
+            owner_id: row.owner_id,
+            display_name: row.display_name,
+            students: row.students,
+            created_at: row.created_at,
+        })
+    })
+}
+
+/// Returns `None` if the classroom doesn't exist.
+pub async fn access(pool: &PgPool, id: ClassroomId, user_id: Uuid) -> sqlx::Result<Option<Access>> {
+    // note: owners only manage their classroom while they're still teaching in its organization.
+    let row = sqlx::query!(
+        r#"
+select exists(select 1
+              from organization_member
+              where organization_member.organization_id = classroom.organization_id
+                and user_id = $2
+                and (role = $3 or (role = $4 and owner_id = $2)))              as "can_manage!",
+       exists(select 1 from classroom_student where classroom_id = $1 and user_id = $2) as "is_student!"
+from classroom
+where id = $1
+"#,
+        id.0,
+        user_id,
+        OrganizationRole::Admin as i16,
+        OrganizationRole::Teacher as i16,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| {
+        if row.can_manage {
+            Access::Manage
+        } else if row.is_student {
+            Access::View
+        } else {
+            Access::Denied
+        }
+    }))
+}
+
+/// Adds a student to the classroom.
+///
+/// Returns the user's role in the classroom's organization (`None` if they aren't a member of it),
+/// the student is only added if that role is [`Student`](OrganizationRole::Student).
+pub async fn add_student(
+    pool: &PgPool,
+    id: ClassroomId,
+    user_id: Uuid,
+) -> sqlx::Result<Option<OrganizationRole>> {
+    let role = sqlx::query!(
+        r#"
+select role as "role: OrganizationRole"
+from organization_member
+         inner join classroom on classroom.organization_id = organization_member.organization_id
+where classroom.id = $1
+  and organization_member.user_id = $2
+"#,
+        id.0,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|it| it.role);
+
+    if role != Some(OrganizationRole::Student) {
+        return Ok(role);
+    }
+
+    sqlx::query!(
+        "insert into classroom_student (classroom_id, user_id) values ($1, $2) on conflict do nothing",
+        id.0,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(role)
+}
+
+pub async fn remove_student(pool: &PgPool, id: ClassroomId, user_id: Uuid) -> sqlx::Result<()> {
+    sqlx::query!(
+        "delete from classroom_student where classroom_id = $1 and user_id = $2",
+        id.0,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .map(drop)
+}
+
+/// Assigns a JIG to the classroom.
+///
+/// Returns `false` if the JIG doesn't exist.
+pub async fn assign_jig(pool: &PgPool, id: ClassroomId, jig_id: JigId) -> sqlx::Result<bool> {
+    if !super::jig::exists(pool, jig_id).await? {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "insert into classroom_jig (classroom_id, jig_id) values ($1, $2) on conflict do nothing",
+        id.0,
+        jig_id.0
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(true)
+}
+
+pub async fn is_jig_assigned(pool: &PgPool, id: ClassroomId, jig_id: JigId) -> sqlx::Result<bool> {
+    sqlx::query!(
+        r#"select exists(select 1 from classroom_jig where classroom_id = $1 and jig_id = $2) as "exists!""#,
+        id.0,
+        jig_id.0
+    )
+    .fetch_one(pool)
+    .await
+    .map(|it| it.exists)
+}
+
+pub async fn unassign_jig(pool: &PgPool, id: ClassroomId, jig_id: JigId) -> sqlx::Result<()> {
+    sqlx::query!(
+        "delete from classroom_jig where classroom_id = $1 and jig_id = $2",
+        id.0,
+        jig_id.0
+    )
+    .execute(pool)
+    .await
+    .map(drop)
+}
+
+pub async fn list_jigs(pool: &PgPool, id: ClassroomId) -> sqlx::Result<Vec<AssignedJig>> {
+    sqlx::query_as!(
+        AssignedJig,
+        r#"
+select jig_id as "jig_id: JigId", assigned_at
+from classroom_jig
+where classroom_id = $1
+order by assigned_at desc
+"#,
+        id.0
+    )
+    .fetch_all(pool)
+    .await
+}
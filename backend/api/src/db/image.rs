@@ -1,25 +1,33 @@
-use super::recycle_metadata;
+use super::{add_metadata, recycle_metadata, remove_metadata};
 use chrono::{DateTime, Utc};
 use futures::stream::BoxStream;
-use shared::domain::{
-    category::CategoryId,
-    image::{Image, ImageId, ImageKind},
-    meta::{AffiliationId, AgeRangeId, StyleId},
+use shared::{
+    domain::{
+        category::CategoryId,
+        image::{Image, ImageId, ImageKind},
+        meta::{AffiliationId, AgeRangeId, StyleId},
+    },
+    media::License,
 };
 use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
 pub mod user {
     use futures::stream::BoxStream;
-    use shared::domain::image::{user::UserImage, ImageId};
+    use shared::{
+        domain::image::{user::UserImage, ImageId},
+        media::ProcessingStatus,
+    };
     use sqlx::PgPool;
+    use uuid::Uuid;
 
-    pub async fn create(conn: &PgPool) -> sqlx::Result<ImageId> {
+    pub async fn create(conn: &PgPool, user_id: Uuid) -> sqlx::Result<ImageId> {
         let id: ImageId = sqlx::query!(
             r#"
-insert into user_image_library default values
+insert into user_image_library (user_id) values ($1)
 returning id as "id: ImageId"
 "#,
+            user_id
         )
         .fetch_one(conn)
         .await?
@@ -38,7 +46,11 @@ returning id as "id: ImageId"
     pub async fn get(db: &PgPool, image: ImageId) -> sqlx::Result<Option<UserImage>> {
         sqlx::query_as!(
             UserImage,
-            r#"select id as "id: ImageId" from user_image_library where id = $1"#,
+            r#"
+select id as "id: ImageId", processing_status as "processing_status: ProcessingStatus"
+from user_image_library
+where id = $1
+"#,
             image.0
         )
         .fetch_optional(db)
@@ -48,12 +60,333 @@ returning id as "id: ImageId"
     pub fn list(db: &PgPool) -> BoxStream<'_, sqlx::Result<UserImage>> {
         sqlx::query_as!(
             UserImage,
-            r#"select id as "id: ImageId" from user_image_library order by created_at desc"#,
+            r#"
+select id as "id: ImageId", processing_status as "processing_status: ProcessingStatus"
+from user_image_library
+order by created_at desc
+"#,
         )
         .fetch(db)
     }
 }
 
+/// Bulk imports, see [`crate::image_import`].
+pub mod import {
+    use chrono::{DateTime, Utc};
+    use shared::{
+        domain::{
+            category::CategoryId,
+            image::{
+                import::{ImageImportItem, ImageImportItemStatus, ImageImportStatus},
+                ImageId, ImageKind,
+            },
+            meta::{AffiliationId, AgeRangeId, MetaKind, StyleId},
+        },
+        media::License,
+    };
+    use sqlx::{PgConnection, PgPool};
+    use uuid::Uuid;
+
+    use crate::db::Metadata;
+
+    /// An item of an import, with everything needed to create its image.
+    pub struct Item {
+        pub name: String,
+        pub description: String,
+        pub is_premium: bool,
+        pub publish_at: Option<DateTime<Utc>>,
+        pub kind: ImageKind,
+        pub styles: Vec<StyleId>,
+        pub age_ranges: Vec<AgeRangeId>,
+        pub affiliations: Vec<AffiliationId>,
+        pub categories: Vec<CategoryId>,
+        pub attribution: Option<String>,
+        pub license: Option<License>,
+        pub source_url: Option<String>,
+        pub status: ImageImportStatus,
+    }
+
+    /// Finds the first of `meta` that doesn't exist.
+    async fn first_missing<T: Metadata>(
+        conn: &mut PgConnection,
+        meta: impl Iterator<Item = T>,
+    ) -> sqlx::Result<Option<Uuid>> {
+        let meta: Vec<Uuid> = meta.map(Into::into).collect();
+
+        if meta.is_empty() {
+            return Ok(None);
+        }
+
+        sqlx::query_as::<_, (Uuid,)>(&format!(
+            "select wanted.id from unnest($1::uuid[]) as wanted(id) where not exists (select 1 from {0} where {0}.id = wanted.id) limit 1",
+            T::TABLE
+        ))
+        .bind(&meta)
+        .fetch_optional(&mut *conn)
+        .await
+        .map(|it| it.map(|(id,)| id))
+    }
+
+    /// Finds metadata used by any of `items` that doesn't exist.
+    pub async fn missing_metadata(
+        conn: &mut PgConnection,
+        items: &[ImageImportItem],
+    ) -> sqlx::Result<Option<(Uuid, MetaKind)>> {
+        let styles = items.iter().flat_map(|it| it.styles.iter().copied());
+        if let Some(id) = first_missing(&mut *conn, styles).await? {
+            return Ok(Some((id, MetaKind::Style)));
+        }
+
+        let age_ranges = items.iter().flat_map(|it| it.age_ranges.iter().copied());
+        if let Some(id) = first_missing(&mut *conn, age_ranges).await? {
+            return Ok(Some((id, MetaKind::AgeRange)));
+        }
+
+        let affiliations = items.iter().flat_map(|it| it.affiliations.iter().copied());
+        if let Some(id) = first_missing(&mut *conn, affiliations).await? {
+            return Ok(Some((id, MetaKind::Affiliation)));
+        }
+
+        let categories = items.iter().flat_map(|it| it.categories.iter().copied());
+        if let Some(id) = first_missing(&mut *conn, categories).await? {
+            return Ok(Some((id, MetaKind::Category)));
+        }
+
+        Ok(None)
+    }
+
+    /// Formats `ids` as an array literal, since postgres can't unnest an array of arrays
+    /// (so [`create`] sends each item's metadata as one of these).
+    fn array_literal<T: Into<Uuid> + Copy>(ids: &[T]) -> String {
+        let ids: Vec<_> = ids
+            .iter()
+            .map(|&id| Into::<Uuid>::into(id).to_string())
+            .collect();
+
+        format!("{{{}}}", ids.join(","))
+    }
+
+    /// Creates an import from a manifest, returns the import's ID and the IDs of the items that need to be downloaded.
+    pub async fn create(
+        conn: &mut PgConnection,
+        created_by: Uuid,
+        items: &[ImageImportItem],
+    ) -> sqlx::Result<(Uuid, Vec<Uuid>)> {
+        let id = sqlx::query!(
+            "insert into image_import (created_by) values ($1) returning id",
+            created_by
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .id;
+
+        let status = |item: &ImageImportItem| match item.source_url {
+            Some(_) => ImageImportStatus::Importing as i16,
+            None => ImageImportStatus::AwaitingUpload as i16,
+        };
+
+        let publish_at: Vec<_> = items
+            .iter()
+            .map(|it| it.publish_at.map(DateTime::<Utc>::from))
+            .collect();
+
+        let attribution: Vec<_> = items.iter().map(|it| it.attribution.as_deref()).collect();
+
+        let license: Vec<_> = items
+            .iter()
+            .map(|it| it.license.map(|it| it as i16))
+            .collect();
+
+        let source_url: Vec<_> = items.iter().map(|it| it.source_url.as_deref()).collect();
+
+        let downloads = sqlx::query!(
+            r#"
+insert into image_import_item
+    (import_id, index, name, description, is_premium, publish_at, kind, styles, age_ranges, affiliations, categories, attribution, license, source_url, status)
+select $1,
+       item.index - 1,
+       item.name,
+       item.description,
+       item.is_premium,
+       item.publish_at,
+       item.kind,
+       item.styles::uuid[],
+       item.age_ranges::uuid[],
+       item.affiliations::uuid[],
+       item.categories::uuid[],
+       item.attribution,
+       item.license,
+       item.source_url,
+       item.status
+from unnest($2::text[], $3::text[], $4::bool[], $5::timestamptz[], $6::int2[], $7::text[], $8::text[], $9::text[], $10::text[], $11::text[], $12::int2[], $13::text[], $14::int2[])
+         with ordinality as item(name, description, is_premium, publish_at, kind, styles, age_ranges, affiliations, categories, attribution, license, source_url, status, index)
+returning id, source_url is not null as "download!"
+"#,
+            id,
+            &items.iter().map(|it| it.name.clone()).collect::<Vec<_>>(),
+            &items.iter().map(|it| it.description.clone()).collect::<Vec<_>>(),
+            &items.iter().map(|it| it.is_premium).collect::<Vec<_>>(),
+            // nullable, which sqlx can't check.
+            &publish_at as _,
+            &items.iter().map(|it| it.kind as i16).collect::<Vec<_>>(),
+            &items.iter().map(|it| array_literal(&it.styles)).collect::<Vec<_>>(),
+            &items.iter().map(|it| array_literal(&it.age_ranges)).collect::<Vec<_>>(),
+            &items.iter().map(|it| array_literal(&it.affiliations)).collect::<Vec<_>>(),
+            &items.iter().map(|it| array_literal(&it.categories)).collect::<Vec<_>>(),
+            &attribution as _,
+            &license as _,
+            &source_url as _,
+            &items.iter().map(status).collect::<Vec<_>>(),
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .filter(|it| it.download)
+        .map(|it| it.id)
+        .collect();
+
+        Ok((id, downloads))
+    }
+
+    /// Gets an import's creation time and the status of each of its items (in manifest order).
+    pub async fn get(
+        db: &PgPool,
+        id: Uuid,
+    ) -> sqlx::Result<Option<(DateTime<Utc>, Vec<ImageImportItemStatus>)>> {
+        let created_at = sqlx::query!("select created_at from image_import where id = $1", id)
+            .fetch_optional(db)
+            .await?;
+
+        let created_at = match created_at {
+            Some(it) => it.created_at,
+            None => return Ok(None),
+        };
+
+        let items = sqlx::query!(
+            r#"
+select index,
+       name,
+       status   as "status: ImageImportStatus",
+       image_id as "image_id: ImageId",
+       error
+from image_import_item
+where import_id = $1
+order by index
+"#,
+            id
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|it| ImageImportItemStatus {
+            index: it.index as u32,
+            name: it.name,
+            status: it.status,
+            image_id: it.image_id,
+            error: it.error,
+        })
+        .collect();
+
+        Ok(Some((created_at, items)))
+    }
+
+    /// Finds an item by its index in the import's manifest, returns its ID and status.
+    pub async fn find_item(
+        db: &PgPool,
+        import_id: Uuid,
+        index: u32,
+    ) -> sqlx::Result<Option<(Uuid, ImageImportStatus)>> {
+        sqlx::query!(
+            r#"
+select id, status as "status: ImageImportStatus"
+from image_import_item
+where import_id = $1 and index = $2
+"#,
+            import_id,
+            index as i32
+        )
+        .fetch_optional(db)
+        .await
+        .map(|it| it.map(|it| (it.id, it.status)))
+    }
+
+    /// Gets the url to download an item from, `None` if the item doesn't exist or doesn't get downloaded.
+    pub async fn source_url(db: &PgPool, id: Uuid) -> sqlx::Result<Option<String>> {
+        sqlx::query!("select source_url from image_import_item where id = $1", id)
+            .fetch_optional(db)
+            .await
+            .map(|it| it.and_then(|it| it.source_url))
+    }
+
+    /// Gets an item, locking it until the end of the transaction.
+    pub async fn lock_item(conn: &mut PgConnection, id: Uuid) -> sqlx::Result<Option<Item>> {
+        let item = sqlx::query!(
+            r#"
+select name,
+       description,
+       is_premium,
+       publish_at,
+       kind    as "kind: ImageKind",
+       styles,
+       age_ranges,
+       affiliations,
+       categories,
+       attribution,
+       license as "license: License",
+       source_url,
+       status  as "status: ImageImportStatus"
+from image_import_item
+where id = $1
+for update
+"#,
+            id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|it| Item {
+            name: it.name,
+            description: it.description,
+            is_premium: it.is_premium,
+            publish_at: it.publish_at,
+            kind: it.kind,
+            styles: it.styles.into_iter().map(StyleId).collect(),
+            age_ranges: it.age_ranges.into_iter().map(AgeRangeId).collect(),
+            affiliations: it.affiliations.into_iter().map(AffiliationId).collect(),
+            categories: it.categories.into_iter().map(CategoryId).collect(),
+            attribution: it.attribution,
+            license: it.license,
+            source_url: it.source_url,
+            status: it.status,
+        });
+
+        Ok(item)
+    }
+
+    /// Marks an item as imported as `image`.
+    pub async fn set_imported(
+        conn: &mut PgConnection,
+        id: Uuid,
+        image: ImageId,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+update image_import_item
+set status     = $2,
+    image_id   = $3,
+    error      = null,
+    updated_at = now()
+where id = $1
+"#,
+            id,
+            ImageImportStatus::Imported as i16,
+            image.0
+        )
+        .execute(conn)
+        .await
+        .map(drop)
+    }
+}
+
 pub async fn create(
     conn: &mut PgConnection,
     name: &str,
@@ -61,10 +394,14 @@ pub async fn create(
     is_premium: bool,
     publish_at: Option<DateTime<Utc>>,
     kind: ImageKind,
+    attribution: Option<&str>,
+    license: Option<License>,
+    source_url: Option<&str>,
 ) -> sqlx::Result<ImageId> {
     let id: ImageId = sqlx::query!(
         r#"
-insert into image_metadata (name, description, is_premium, publish_at, kind) values ($1, $2, $3, $4, $5)
+insert into image_metadata (name, description, is_premium, publish_at, kind, attribution, license, source_url)
+values ($1, $2, $3, $4, $5, $6, $7, $8)
 returning id as "id: ImageId"
         "#,
         name,
@@ -72,6 +409,9 @@ returning id as "id: ImageId"
         is_premium,
         publish_at,
         kind as i16,
+        attribution,
+        license.map(|it| it as i16),
+        source_url,
     )
     .fetch_one(conn)
     .await?
@@ -109,6 +449,78 @@ pub async fn update_metadata(
     Ok(())
 }
 
+/// Replaces the image's alt text with `alt_text` ((language, text) pairs, with normalized languages).
+pub async fn set_alt_text(
+    conn: &mut PgConnection,
+    image: ImageId,
+    alt_text: &[(String, String)],
+) -> sqlx::Result<()> {
+    let (languages, texts): (Vec<_>, Vec<_>) = alt_text.iter().cloned().unzip();
+
+    sqlx::query!("delete from image_alt_text where image_id = $1", image.0)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query!(
+        r#"
+insert into image_alt_text (image_id, language, text)
+select $1, language, text
+from unnest($2::text[], $3::text[]) as t(language, text)
+"#,
+        image.0,
+        &languages,
+        &texts
+    )
+    .execute(conn)
+    .await
+    .map(drop)
+}
+
+/// Adds and removes categories, styles and age ranges on many images at once (removals happen first),
+/// returns `false` without changing anything if any of the images don't exist.
+pub async fn bulk_update_metadata(
+    db: &PgPool,
+    images: &[ImageId],
+    add_categories: &[CategoryId],
+    remove_categories: &[CategoryId],
+    add_styles: &[StyleId],
+    remove_styles: &[StyleId],
+    add_age_ranges: &[AgeRangeId],
+    remove_age_ranges: &[AgeRangeId],
+) -> sqlx::Result<bool> {
+    const TABLE: &str = "image";
+
+    let mut ids: Vec<Uuid> = images.iter().map(|it| it.0).collect();
+    ids.sort();
+    ids.dedup();
+
+    let mut txn = db.begin().await?;
+
+    let found = sqlx::query!(
+        "select id from image_metadata where id = any($1) for update",
+        &ids
+    )
+    .fetch_all(&mut txn)
+    .await?
+    .len();
+
+    if found != ids.len() {
+        return Ok(false);
+    }
+
+    remove_metadata(&mut txn, TABLE, &ids, remove_categories).await?;
+    remove_metadata(&mut txn, TABLE, &ids, remove_styles).await?;
+    remove_metadata(&mut txn, TABLE, &ids, remove_age_ranges).await?;
+
+    add_metadata(&mut txn, TABLE, &ids, add_categories).await?;
+    add_metadata(&mut txn, TABLE, &ids, add_styles).await?;
+    add_metadata(&mut txn, TABLE, &ids, add_age_ranges).await?;
+
+    txn.commit().await?;
+
+    Ok(true)
+}
+
 pub async fn update(
     conn: &mut PgConnection,
     id: ImageId,
@@ -116,6 +528,9 @@ pub async fn update(
     description: Option<&str>,
     is_premium: Option<bool>,
     publish_at: Option<Option<DateTime<Utc>>>,
+    attribution: Option<Option<&str>>,
+    license: Option<Option<License>>,
+    source_url: Option<Option<&str>>,
 ) -> sqlx::Result<bool> {
     if !sqlx::query!(
         r#"select exists(select 1 from image_metadata where id = $1) as "exists!""#,
@@ -157,6 +572,29 @@ where id = $1
         description,
         is_premium,
     )
+    .execute(&mut *conn)
+    .await?;
+
+    // these can be cleared, so a `false` flag means "don't update" rather than `null`.
+    sqlx::query!(
+        r#"
+update image_metadata
+set attribution = case when $2 then $3 else attribution end,
+    license     = case when $4 then $5 else license end,
+    source_url  = case when $6 then $7 else source_url end,
+    updated_at  = now()
+where id = $1
+  and (($2 and $3 is distinct from attribution) or
+       ($4 and $5 is distinct from license) or
+       ($6 and $7 is distinct from source_url))"#,
+        id.0,
+        attribution.is_some(),
+        attribution.flatten(),
+        license.is_some(),
+        license.flatten().map(|it| it as i16),
+        source_url.is_some(),
+        source_url.flatten(),
+    )
     .execute(conn)
     .await?;
 
@@ -173,6 +611,11 @@ select id,
        publish_at,
        created_at,
        updated_at,
+       processing_status,
+       attribution,
+       license,
+       source_url,
+       array((select row (language, text) from image_alt_text where image_id = id order by language)) as alt_text,
        array((select row (category_id) from image_category where image_id = id))       as categories,
        array((select row (style_id) from image_style where image_id = id))             as styles,
        array((select row (age_range_id) from image_age_range where image_id = id))     as age_ranges,
@@ -195,6 +638,11 @@ select id,
        publish_at,
        created_at,
        updated_at,
+       processing_status,
+       attribution,
+       license,
+       source_url,
+       array((select row (language, text) from image_alt_text where image_id = id order by language)) as alt_text,
        array((select row (category_id) from image_category where image_id = id))       as categories,
        array((select row (style_id) from image_style where image_id = id))             as styles,
        array((select row (age_range_id) from image_age_range where image_id = id))     as age_ranges,
@@ -2,8 +2,9 @@ use std::borrow::Cow;
 
 use chrono::{DateTime, Utc};
 use shared::domain::{
+    category::CategoryId,
     jig::{Jig, JigId, LiteModule, ModuleId, ModuleKind},
-    meta::ContentTypeId,
+    meta::{AffiliationId, AgeRangeId, ContentTypeId},
 };
 use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
@@ -33,6 +34,9 @@ pub async fn create(
     cover_id: Option<ModuleId>,
     module_ids: &[ModuleId],
     content_types: &[ContentTypeId],
+    categories: &[CategoryId],
+    age_ranges: &[AgeRangeId],
+    affiliations: &[AffiliationId],
     ending_id: Option<ModuleId>,
     creator_id: Uuid,
     publish_at: Option<DateTime<Utc>>,
@@ -71,6 +75,9 @@ returning id
     .await?;
 
     super::recycle_metadata(&mut transaction, "jig", jig.id, content_types).await?;
+    super::recycle_metadata(&mut transaction, "jig", jig.id, categories).await?;
+    super::recycle_metadata(&mut transaction, "jig", jig.id, age_ranges).await?;
+    super::recycle_metadata(&mut transaction, "jig", jig.id, affiliations).await?;
 
     // todo: batch
     for (idx, module_id) in module_ids.iter().enumerate() {
@@ -108,7 +115,10 @@ select id                                             as "id: JigId",
                       inner join module on module_id = module.id
              where jig_id = $1
              order by "index")                        as "modules!: Vec<(ModuleId, Option<ModuleKind>)>",
-        array(select row(content_type_id) from jig_content_type where jig_id = $1) as "content_types!: Vec<(ContentTypeId,)>"
+        array(select row(content_type_id) from jig_content_type where jig_id = $1) as "content_types!: Vec<(ContentTypeId,)>",
+        array(select row(category_id) from jig_category where jig_id = $1) as "categories!: Vec<(CategoryId,)>",
+        array(select row(age_range_id) from jig_age_range where jig_id = $1) as "age_ranges!: Vec<(AgeRangeId,)>",
+        array(select row(affiliation_id) from jig_affiliation where jig_id = $1) as "affiliations!: Vec<(AffiliationId,)>"
 from jig
 where id = $1"#,
         id.0
@@ -130,6 +140,9 @@ where id = $1"#,
             id, kind
         }).collect(),
         content_types: row.content_types.into_iter().map(|(it,)| it).collect(),
+        categories: row.categories.into_iter().map(|(it,)| it).collect(),
+        age_ranges: row.age_ranges.into_iter().map(|(it,)| it).collect(),
+        affiliations: row.affiliations.into_iter().map(|(it,)| it).collect(),
         creator_id: row.creator_id,
         author_id: row.author_id,
         publish_at: row.publish_at,
@@ -147,6 +160,9 @@ pub async fn update(
     modules: Option<&[ModuleId]>,
     ending_id: Option<ModuleId>,
     content_types: Option<&[ContentTypeId]>,
+    categories: Option<&[CategoryId]>,
+    age_ranges: Option<&[AgeRangeId]>,
+    affiliations: Option<&[AffiliationId]>,
     publish_at: Option<Option<DateTime<Utc>>>,
 ) -> sqlx::Result<bool> {
     let mut transaction = pool.begin().await?;
@@ -217,11 +233,33 @@ where id = $1
         super::recycle_metadata(&mut transaction, "jig", id.0, content_types).await?;
     }
 
+    if let Some(categories) = categories {
+        super::recycle_metadata(&mut transaction, "jig", id.0, categories).await?;
+    }
+
+    if let Some(age_ranges) = age_ranges {
+        super::recycle_metadata(&mut transaction, "jig", id.0, age_ranges).await?;
+    }
+
+    if let Some(affiliations) = affiliations {
+        super::recycle_metadata(&mut transaction, "jig", id.0, affiliations).await?;
+    }
+
     transaction.commit().await?;
 
     Ok(true)
 }
 
+pub async fn exists(pool: &PgPool, id: JigId) -> sqlx::Result<bool> {
+    sqlx::query!(
+        r#"select exists(select 1 from jig where id = $1) as "exists!""#,
+        id.0
+    )
+    .fetch_one(pool)
+    .await
+    .map(|it| it.exists)
+}
+
 pub async fn delete(pool: &PgPool, id: JigId) -> anyhow::Result<()> {
     sqlx::query!("delete from jig where id = $1", id.0)
         .execute(pool)
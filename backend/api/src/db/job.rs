@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use shared::{
+    domain::image::import::ImageImportStatus,
+    media::{MediaLibrary, ProcessingStatus},
+};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::jobs::JobKind;
+
+/// How many times a job gets run before it's dead lettered.
+const MAX_ATTEMPTS: i32 = 5;
+
+pub struct Job {
+    pub id: Uuid,
+    pub kind: JobKind,
+    pub library: MediaLibrary,
+    pub media_id: Uuid,
+    pub attempts: i32,
+    pub max_attempts: i32,
+}
+
+/// Queues up processing for the media, and marks it as processing.
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    kind: JobKind,
+    library: MediaLibrary,
+    media_id: Uuid,
+) -> sqlx::Result<()> {
+    enqueue_many(conn, kind, library, &[media_id]).await
+}
+
+/// Like [`enqueue`], but for many items of media at once.
+pub async fn enqueue_many(
+    conn: &mut PgConnection,
+    kind: JobKind,
+    library: MediaLibrary,
+    media_ids: &[Uuid],
+) -> sqlx::Result<()> {
+    if media_ids.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+insert into media_job (kind, library, media_id, max_attempts)
+select $1, $2, media_id, $4
+from unnest($3::uuid[]) as media_id
+"#,
+        kind as i16,
+        library as i16,
+        media_ids,
+        MAX_ATTEMPTS
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    set_processing_status(conn, kind, library, media_ids, ProcessingStatus::Processing).await
+}
+
+/// Takes the next job that's ready to run, it won't be given out again until `lease` has passed.
+pub async fn lease(db: &PgPool, lease: Duration) -> sqlx::Result<Option<Job>> {
+    sqlx::query_as!(
+        Job,
+        r#"
+update media_job
+set locked_until = now() + ($1::int8 * interval '1 second'),
+    attempts     = attempts + 1,
+    updated_at   = now()
+where id = (
+    select id
+    from media_job
+    where status = 0
+      and run_at <= now()
+      and (locked_until is null or locked_until < now())
+    order by run_at
+    limit 1
+    for update skip locked
+)
+returning id,
+          kind as "kind: JobKind",
+          library as "library: MediaLibrary",
+          media_id,
+          attempts,
+          max_attempts
+"#,
+        lease.as_secs() as i64
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Removes the job, and (unless there's more processing queued) marks the media as ready.
+pub async fn succeed(db: &PgPool, job: &Job) -> sqlx::Result<()> {
+    let mut txn = db.begin().await?;
+
+    sqlx::query!("delete from media_job where id = $1", job.id)
+        .execute(&mut txn)
+        .await?;
+
+    if !has_queued(&mut txn, job.kind, job.media_id).await? {
+        set_processing_status(
+            &mut txn,
+            job.kind,
+            job.library,
+            &[job.media_id],
+            ProcessingStatus::Ready,
+        )
+        .await?;
+    }
+
+    txn.commit().await
+}
+
+/// Schedules the job to be retried after `retry_in`.
+///
+/// If `retry_in` is `None` or the job is out of attempts, it gets dead lettered instead
+/// (and the media marked as failed, unless there's more processing queued).
+pub async fn fail(
+    db: &PgPool,
+    job: &Job,
+    error: &str,
+    retry_in: Option<Duration>,
+) -> sqlx::Result<()> {
+    let mut txn = db.begin().await?;
+
+    match retry_in {
+        Some(retry_in) if job.attempts < job.max_attempts => {
+            sqlx::query!(
+                r#"
+update media_job
+set run_at       = now() + ($2::int8 * interval '1 second'),
+    locked_until = null,
+    last_error   = $3,
+    updated_at   = now()
+where id = $1
+"#,
+                job.id,
+                retry_in.as_secs() as i64,
+                error
+            )
+            .execute(&mut txn)
+            .await?;
+        }
+
+        _ => {
+            sqlx::query!(
+                r#"
+update media_job
+set status       = 1,
+    locked_until = null,
+    last_error   = $2,
+    updated_at   = now()
+where id = $1
+"#,
+                job.id,
+                error
+            )
+            .execute(&mut txn)
+            .await?;
+
+            if !has_queued(&mut txn, job.kind, job.media_id).await? {
+                set_processing_status(
+                    &mut txn,
+                    job.kind,
+                    job.library,
+                    &[job.media_id],
+                    ProcessingStatus::Failed,
+                )
+                .await?;
+            }
+        }
+    }
+
+    txn.commit().await
+}
+
+/// Whether there's more of the same kind of processing queued for the media.
+async fn has_queued(conn: &mut PgConnection, kind: JobKind, media_id: Uuid) -> sqlx::Result<bool> {
+    sqlx::query!(
+        r#"select exists(select 1 from media_job where media_id = $1 and kind = $2 and status = 0) as "exists!""#,
+        media_id,
+        kind as i16
+    )
+    .fetch_one(conn)
+    .await
+    .map(|it| it.exists)
+}
+
+/// Updates the processing status of the media (and when it's ready, its `uploaded_at`).
+async fn set_processing_status(
+    conn: &mut PgConnection,
+    kind: JobKind,
+    library: MediaLibrary,
+    media_ids: &[Uuid],
+    status: ProcessingStatus,
+) -> sqlx::Result<()> {
+    let ready = status == ProcessingStatus::Ready;
+
+    match (library, kind) {
+        (MediaLibrary::Global, JobKind::ProcessImage) => sqlx::query!(
+            r#"
+update image_metadata
+set processing_status = $2,
+    uploaded_at       = case when $3 then now() else uploaded_at end
+where id = any($1)
+"#,
+            media_ids,
+            status as i16,
+            ready
+        )
+        .execute(conn)
+        .await
+        .map(drop),
+
+        (MediaLibrary::User, JobKind::ProcessImage) => sqlx::query!(
+            r#"
+update user_image_library
+set processing_status = $2,
+    uploaded_at       = case when $3 then now() else uploaded_at end
+where id = any($1)
+"#,
+            media_ids,
+            status as i16,
+            ready
+        )
+        .execute(conn)
+        .await
+        .map(drop),
+
+        (MediaLibrary::Global, JobKind::ProcessAnimation) => sqlx::query!(
+            r#"
+update animation
+set processing_status = $2,
+    uploaded_at       = case when $3 then now() else uploaded_at end
+where id = any($1)
+"#,
+            media_ids,
+            status as i16,
+            ready
+        )
+        .execute(conn)
+        .await
+        .map(drop),
+
+        (MediaLibrary::User, JobKind::ProcessAudio) => sqlx::query!(
+            r#"
+update user_audio_library
+set processing_status = $2,
+    uploaded_at       = case when $3 then now() else uploaded_at end
+where id = any($1)
+"#,
+            media_ids,
+            status as i16,
+            ready
+        )
+        .execute(conn)
+        .await
+        .map(drop),
+
+        // `media_ids` are the import items' ids.
+        (MediaLibrary::Global, JobKind::ImportImage) => {
+            let status = match status {
+                ProcessingStatus::Processing => ImageImportStatus::Importing,
+                ProcessingStatus::Ready => ImageImportStatus::Imported,
+                ProcessingStatus::Failed => ImageImportStatus::Failed,
+            };
+
+            sqlx::query!(
+                r#"
+update image_import_item
+set status     = $2,
+    error      = case
+                     when $3 then (select last_error from media_job where media_id = image_import_item.id order by updated_at desc nulls last limit 1)
+                 end,
+    updated_at = now()
+where id = any($1)
+"#,
+                media_ids,
+                status as i16,
+                status == ImageImportStatus::Failed
+            )
+            .execute(conn)
+            .await
+            .map(drop)
+        }
+
+        // there's no such media.
+        _ => Ok(()),
+    }
+}
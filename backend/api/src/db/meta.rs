@@ -1,70 +1,573 @@
 use shared::domain::meta::{
-    Affiliation, AffiliationId, AgeRange, AgeRangeId, ContentType, ContentTypeId, MetaKind, Style,
-    StyleId, Subject, SubjectId,
+    Affiliation, AffiliationId, AgeRange, AgeRangeId, ContentType, ContentTypeId, MetaKind,
+    MetaTaxonomy, Style, StyleId, Subject, SubjectId,
 };
-use sqlx::{postgres::PgDatabaseError, PgPool};
+use sqlx::{postgres::PgDatabaseError, PgConnection, PgPool};
 use uuid::Uuid;
 
-pub async fn get_style(db: &PgPool) -> sqlx::Result<Vec<Style>> {
+use crate::error;
+
+pub async fn get_style(db: &PgPool, languages: &[String]) -> sqlx::Result<Vec<Style>> {
     sqlx::query_as!(
         Style,
         r#"
-            select id as "id: StyleId", display_name, created_at, updated_at from style
-            order by index
-        "#
+select id as "id: StyleId",
+       coalesce((select translation.display_name
+                 from style_translation translation
+                 where translation.style_id = style.id and translation.language = any($1)
+                 order by array_position($1, translation.language)
+                 limit 1), style.display_name) as "display_name!",
+       created_at,
+       updated_at
+from style
+order by index
+"#,
+        languages
     )
     .fetch_all(db)
     .await
 }
 
-pub async fn get_age_ranges(db: &PgPool) -> sqlx::Result<Vec<AgeRange>> {
+pub async fn get_age_ranges(db: &PgPool, languages: &[String]) -> sqlx::Result<Vec<AgeRange>> {
     sqlx::query_as!(
         AgeRange,
         r#"
-            select id as "id: AgeRangeId", display_name, created_at, updated_at from age_range
-            order by index
-        "#
+select id as "id: AgeRangeId",
+       coalesce((select translation.display_name
+                 from age_range_translation translation
+                 where translation.age_range_id = age_range.id and translation.language = any($1)
+                 order by array_position($1, translation.language)
+                 limit 1), age_range.display_name) as "display_name!",
+       created_at,
+       updated_at
+from age_range
+order by index
+"#,
+        languages
     )
     .fetch_all(db)
     .await
 }
 
-pub async fn get_affiliations(db: &PgPool) -> sqlx::Result<Vec<Affiliation>> {
+pub async fn get_affiliations(db: &PgPool, languages: &[String]) -> sqlx::Result<Vec<Affiliation>> {
     sqlx::query_as!(
         Affiliation,
         r#"
-            select id as "id: AffiliationId", display_name, created_at, updated_at from affiliation
-            order by index
-        "#
+select id as "id: AffiliationId",
+       coalesce((select translation.display_name
+                 from affiliation_translation translation
+                 where translation.affiliation_id = affiliation.id and translation.language = any($1)
+                 order by array_position($1, translation.language)
+                 limit 1), affiliation.display_name) as "display_name!",
+       created_at,
+       updated_at
+from affiliation
+order by index
+"#,
+        languages
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Looks up the names of styles, age ranges, affiliations and categories by id, ids that don't exist are left out.
+///
+/// Names are in the first of `languages` they're translated to.
+pub async fn get_names(
+    db: &PgPool,
+    ids: &[Uuid],
+    languages: &[String],
+) -> sqlx::Result<Vec<(Uuid, String)>> {
+    sqlx::query!(
+        r#"
+select id as "id!", name as "name!" from (
+    select id,
+           coalesce((select translation.display_name
+                     from style_translation translation
+                     where translation.style_id = style.id and translation.language = any($2)
+                     order by array_position($2, translation.language)
+                     limit 1), style.display_name) as name
+    from style
+    where id = any($1)
+    union all
+    select id,
+           coalesce((select translation.display_name
+                     from age_range_translation translation
+                     where translation.age_range_id = age_range.id and translation.language = any($2)
+                     order by array_position($2, translation.language)
+                     limit 1), age_range.display_name)
+    from age_range
+    where id = any($1)
+    union all
+    select id,
+           coalesce((select translation.display_name
+                     from affiliation_translation translation
+                     where translation.affiliation_id = affiliation.id and translation.language = any($2)
+                     order by array_position($2, translation.language)
+                     limit 1), affiliation.display_name)
+    from affiliation
+    where id = any($1)
+    union all
+    select id,
+           coalesce((select translation.name
+                     from category_translation translation
+                     where translation.category_id = category.id and translation.language = any($2)
+                     order by array_position($2, translation.language)
+                     limit 1), category.name)
+    from category
+    where id = any($1)
+) names
+"#,
+        ids,
+        languages
+    )
+    .fetch_all(db)
+    .await
+    .map(|it| it.into_iter().map(|it| (it.id, it.name)).collect())
+}
+
+/// Finds (at most `limit` of each) categories, styles, age ranges and affiliations whose name, or a translation of it, matches `pattern` (see [`like_prefix`](super::like_prefix)).
+///
+/// Returns (kind, id, name) with the kind being `categories`, `styles`, `age_ranges` or `affiliations`, and the name in the first of `languages` it's translated to.
+pub async fn suggest(
+    db: &PgPool,
+    pattern: &str,
+    languages: &[String],
+    limit: u32,
+) -> sqlx::Result<Vec<(String, Uuid, String)>> {
+    sqlx::query!(
+        r#"
+select kind as "kind!", id as "id!", name as "name!" from (
+    (select 'categories' as kind,
+            id,
+            coalesce((select translation.name
+                      from category_translation translation
+                      where translation.category_id = category.id and translation.language = any($2)
+                      order by array_position($2, translation.language)
+                      limit 1), category.name) as name
+     from category
+     where category.name ilike $1
+        or exists(select 1 from category_translation where category_id = category.id and name ilike $1)
+     order by length(category.name), category.name
+     limit $3)
+    union all
+    (select 'styles',
+            id,
+            coalesce((select translation.display_name
+                      from style_translation translation
+                      where translation.style_id = style.id and translation.language = any($2)
+                      order by array_position($2, translation.language)
+                      limit 1), style.display_name)
+     from style
+     where display_name ilike $1
+        or exists(select 1 from style_translation where style_id = style.id and display_name ilike $1)
+     order by index
+     limit $3)
+    union all
+    (select 'age_ranges',
+            id,
+            coalesce((select translation.display_name
+                      from age_range_translation translation
+                      where translation.age_range_id = age_range.id and translation.language = any($2)
+                      order by array_position($2, translation.language)
+                      limit 1), age_range.display_name)
+     from age_range
+     where display_name ilike $1
+        or exists(select 1 from age_range_translation where age_range_id = age_range.id and display_name ilike $1)
+     order by index
+     limit $3)
+    union all
+    (select 'affiliations',
+            id,
+            coalesce((select translation.display_name
+                      from affiliation_translation translation
+                      where translation.affiliation_id = affiliation.id and translation.language = any($2)
+                      order by array_position($2, translation.language)
+                      limit 1), affiliation.display_name)
+     from affiliation
+     where display_name ilike $1
+        or exists(select 1 from affiliation_translation where affiliation_id = affiliation.id and display_name ilike $1)
+     order by index
+     limit $3)
+) suggestions
+"#,
+        pattern,
+        languages,
+        i64::from(limit)
     )
     .fetch_all(db)
     .await
+    .map(|it| it.into_iter().map(|it| (it.kind, it.id, it.name)).collect())
 }
 
-pub async fn get_subjects(db: &PgPool) -> sqlx::Result<Vec<Subject>> {
+pub async fn get_subjects(db: &PgPool, languages: &[String]) -> sqlx::Result<Vec<Subject>> {
     sqlx::query_as!(
         Subject,
         r#"
-            select subject_id as "id: SubjectId", display_name, created_at, updated_at from subject
-            order by index
-        "#
+select subject_id as "id: SubjectId",
+       coalesce((select translation.display_name
+                 from subject_translation translation
+                 where translation.subject_id = subject.subject_id and translation.language = any($1)
+                 order by array_position($1, translation.language)
+                 limit 1), subject.display_name) as "display_name!",
+       created_at,
+       updated_at
+from subject
+order by index
+"#,
+        languages
     )
     .fetch_all(db)
     .await
 }
 
-pub async fn get_content_types(db: &PgPool) -> sqlx::Result<Vec<ContentType>> {
+pub async fn get_content_types(
+    db: &PgPool,
+    languages: &[String],
+) -> sqlx::Result<Vec<ContentType>> {
     sqlx::query_as!(
         ContentType,
         r#"
-            select content_type_id as "id: ContentTypeId", display_name, created_at, updated_at from "content_type"
-            order by index
-        "#
+select content_type_id as "id: ContentTypeId",
+       coalesce((select translation.display_name
+                 from content_type_translation translation
+                 where translation.content_type_id = "content_type".content_type_id and translation.language = any($1)
+                 order by array_position($1, translation.language)
+                 limit 1), "content_type".display_name) as "display_name!",
+       created_at,
+       updated_at
+from "content_type"
+order by index
+"#,
+        languages
     )
     .fetch_all(db)
     .await
 }
 
+/// Where a kind of metadata is stored.
+struct TaxonomyTable {
+    table: &'static str,
+    id_column: &'static str,
+
+    /// The tables that link things to the metadata (with a `<table>_id` column), and the column for what's linked.
+    references: &'static [(&'static str, &'static str)],
+}
+
+const fn taxonomy_table(kind: MetaTaxonomy) -> TaxonomyTable {
+    match kind {
+        MetaTaxonomy::Style => TaxonomyTable {
+            table: "style",
+            id_column: "id",
+            references: &[("image_style", "image_id")],
+        },
+        MetaTaxonomy::AgeRange => TaxonomyTable {
+            table: "age_range",
+            id_column: "id",
+            references: &[
+                ("image_age_range", "image_id"),
+                ("user_age_range", "user_id"),
+                ("jig_age_range", "jig_id"),
+            ],
+        },
+        MetaTaxonomy::Affiliation => TaxonomyTable {
+            table: "affiliation",
+            id_column: "id",
+            references: &[
+                ("image_affiliation", "image_id"),
+                ("user_affiliation", "user_id"),
+                ("jig_affiliation", "jig_id"),
+            ],
+        },
+        MetaTaxonomy::Subject => TaxonomyTable {
+            table: "subject",
+            id_column: "subject_id",
+            references: &[("user_subject", "user_id")],
+        },
+        MetaTaxonomy::ContentType => TaxonomyTable {
+            table: "content_type",
+            id_column: "content_type_id",
+            references: &[("jig_content_type", "jig_id")],
+        },
+    }
+}
+
+// metadata is rarely changed, so rather than retrying on serialization failures (like categories do)
+// changes just take turns, reads aren't blocked.
+async fn lock_table(conn: &mut PgConnection, table: &TaxonomyTable) -> sqlx::Result<()> {
+    sqlx::query(&format!(
+        r#"lock table "{}" in share row exclusive mode"#,
+        table.table
+    ))
+    .execute(conn)
+    .await
+    .map(drop)
+}
+
+async fn count(conn: &mut PgConnection, table: &TaxonomyTable) -> sqlx::Result<i16> {
+    let (count,): (i64,) = sqlx::query_as(&format!(r#"select count(*) from "{}""#, table.table))
+        .fetch_one(conn)
+        .await?;
+
+    Ok(count as i16)
+}
+
+/// Moves everything from `start` (inclusive) to `end` (exclusive, or to the end if it's `None`) by `by`.
+async fn shift(
+    conn: &mut PgConnection,
+    table: &TaxonomyTable,
+    start: i16,
+    end: Option<i16>,
+    by: i16,
+) -> sqlx::Result<()> {
+    sqlx::query(&format!(
+        r#"update "{}" set index = index + $3, updated_at = now() where index >= $1 and ($2::int2 is null or index < $2)"#,
+        table.table
+    ))
+    .bind(start)
+    .bind(end)
+    .bind(by)
+    .execute(conn)
+    .await
+    .map(drop)
+}
+
+/// Creates a piece of metadata, returns its id and index.
+pub async fn create(
+    db: &PgPool,
+    kind: MetaTaxonomy,
+    display_name: &str,
+    index: Option<u16>,
+) -> sqlx::Result<(Uuid, u16)> {
+    let table = taxonomy_table(kind);
+    let mut txn = db.begin().await?;
+
+    lock_table(&mut txn, &table).await?;
+
+    let count = count(&mut txn, &table).await?;
+    let index = index.map_or(count, |it| (it.min(count as u16)) as i16);
+
+    shift(&mut txn, &table, index, None, 1).await?;
+
+    let (id,): (Uuid,) = sqlx::query_as(&format!(
+        r#"insert into "{0}" (display_name, index, created_at) values ($1, $2, now()) returning {1}"#,
+        table.table, table.id_column
+    ))
+    .bind(display_name)
+    .bind(index)
+    .fetch_one(&mut txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok((id, index as u16))
+}
+
+/// Renames and/or moves a piece of metadata, returns `false` if it doesn't exist.
+pub async fn update(
+    db: &PgPool,
+    kind: MetaTaxonomy,
+    id: Uuid,
+    display_name: Option<&str>,
+    index: Option<u16>,
+) -> sqlx::Result<bool> {
+    let table = taxonomy_table(kind);
+    let mut txn = db.begin().await?;
+
+    if index.is_some() {
+        lock_table(&mut txn, &table).await?;
+    }
+
+    let current: Option<(i16,)> = sqlx::query_as(&format!(
+        r#"select index from "{}" where {} = $1 for update"#,
+        table.table, table.id_column
+    ))
+    .bind(id)
+    .fetch_optional(&mut txn)
+    .await?;
+
+    let (current_index,) = match current {
+        Some(it) => it,
+        None => return Ok(false),
+    };
+
+    if let Some(display_name) = display_name {
+        sqlx::query(&format!(
+            r#"update "{}" set display_name = $2, updated_at = now() where {} = $1"#,
+            table.table, table.id_column
+        ))
+        .bind(id)
+        .bind(display_name)
+        .execute(&mut txn)
+        .await?;
+    }
+
+    if let Some(new_index) = index {
+        let last = count(&mut txn, &table).await? - 1;
+        let new_index = (new_index.min(last as u16)) as i16;
+
+        if new_index < current_index {
+            shift(&mut txn, &table, new_index, Some(current_index), 1).await?;
+        }
+
+        if new_index > current_index {
+            shift(&mut txn, &table, current_index + 1, Some(new_index + 1), -1).await?;
+        }
+
+        if new_index != current_index {
+            sqlx::query(&format!(
+                r#"update "{}" set index = $2, updated_at = now() where {} = $1"#,
+                table.table, table.id_column
+            ))
+            .bind(id)
+            .bind(new_index)
+            .execute(&mut txn)
+            .await?;
+        }
+    }
+
+    txn.commit().await?;
+
+    Ok(true)
+}
+
+/// Deletes a piece of metadata, if `replace_with` is given everything that had the metadata gets that instead.
+pub async fn delete(
+    db: &PgPool,
+    kind: MetaTaxonomy,
+    id: Uuid,
+    replace_with: Option<Uuid>,
+) -> Result<(), error::MetadataDelete> {
+    let table = taxonomy_table(kind);
+    let mut txn = db.begin().await?;
+
+    lock_table(&mut txn, &table).await?;
+
+    let select_index = format!(
+        r#"select index from "{}" where {} = $1"#,
+        table.table, table.id_column
+    );
+    let exists = |id: Uuid| sqlx::query_as::<_, (i16,)>(&select_index).bind(id);
+
+    let (index,) = exists(id)
+        .fetch_optional(&mut txn)
+        .await?
+        .ok_or(error::MetadataDelete::NotFound)?;
+
+    if let Some(replacement) = replace_with {
+        if replacement == id
+            || exists(replacement)
+                .fetch_optional(&mut txn)
+                .await?
+                .is_none()
+        {
+            return Err(error::MetadataDelete::ReplacementNotFound);
+        }
+    }
+
+    for (reference, owner) in table.references {
+        let meta_column = format!("{}_id", table.table);
+
+        match replace_with {
+            Some(replacement) => {
+                sqlx::query(&format!(
+                    r#"insert into "{0}" ({1}, {2}) select {1}, $2 from "{0}" where {2} = $1 on conflict do nothing"#,
+                    reference, owner, meta_column
+                ))
+                .bind(id)
+                .bind(replacement)
+                .execute(&mut txn)
+                .await?;
+
+                sqlx::query(&format!(
+                    r#"delete from "{}" where {} = $1"#,
+                    reference, meta_column
+                ))
+                .bind(id)
+                .execute(&mut txn)
+                .await?;
+            }
+
+            None => {
+                let (in_use,): (bool,) = sqlx::query_as(&format!(
+                    r#"select exists(select 1 from "{}" where {} = $1)"#,
+                    reference, meta_column
+                ))
+                .bind(id)
+                .fetch_one(&mut txn)
+                .await?;
+
+                if in_use {
+                    return Err(error::MetadataDelete::InUse);
+                }
+            }
+        }
+    }
+
+    sqlx::query(&format!(
+        r#"delete from "{}" where {} = $1"#,
+        table.table, table.id_column
+    ))
+    .bind(id)
+    .execute(&mut txn)
+    .await?;
+
+    shift(&mut txn, &table, index + 1, None, -1).await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Replaces the translations of a piece of metadata's name, returns `false` if it doesn't exist.
+///
+/// `translations` is pairs of (normalized) language and name.
+pub async fn set_translations(
+    db: &PgPool,
+    kind: MetaTaxonomy,
+    id: Uuid,
+    translations: &[(String, String)],
+) -> sqlx::Result<bool> {
+    let table = taxonomy_table(kind);
+    let mut txn = db.begin().await?;
+
+    let exists: Option<(i16,)> = sqlx::query_as(&format!(
+        r#"select index from "{}" where {} = $1 for update"#,
+        table.table, table.id_column
+    ))
+    .bind(id)
+    .fetch_optional(&mut txn)
+    .await?;
+
+    if exists.is_none() {
+        return Ok(false);
+    }
+
+    sqlx::query(&format!(
+        r#"delete from "{0}_translation" where {0}_id = $1"#,
+        table.table
+    ))
+    .bind(id)
+    .execute(&mut txn)
+    .await?;
+
+    let (languages, names): (Vec<_>, Vec<_>) = translations.iter().cloned().unzip();
+
+    sqlx::query(&format!(
+        r#"insert into "{0}_translation" ({0}_id, language, display_name) select $1, * from unnest($2::text[], $3::text[])"#,
+        table.table
+    ))
+    .bind(id)
+    .bind(&languages)
+    .bind(&names)
+    .execute(&mut txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(true)
+}
+
 // attempts to grab a uuid out of a string in the shape:
 // Key (<key>)=(<uuid>)<postfix>
 fn extract_uuid(s: &str) -> Option<Uuid> {
@@ -89,12 +592,16 @@ pub fn handle_metadata_err(err: sqlx::Error) -> MetaWrapperError {
 
     let id = db_err.detail().and_then(extract_uuid);
 
-    let kind = match db_err.constraint() {
-        Some("image_affiliation_affiliation_id_fkey") => MetaKind::Affiliation,
-        Some("image_age_range_age_range_id_fkey") => MetaKind::AgeRange,
-        Some("image_style_style_id_fkey") => MetaKind::Style,
-        Some("image_category_category_id_fkey") => MetaKind::Category,
-        Some("jig_content_type_content_type_id_fkey") => MetaKind::ContentType,
+    let kind = match db_err.constraint().unwrap_or_default() {
+        "image_affiliation_affiliation_id_fkey" | "jig_affiliation_affiliation_id_fkey" => {
+            MetaKind::Affiliation
+        }
+        "image_age_range_age_range_id_fkey" | "jig_age_range_age_range_id_fkey" => {
+            MetaKind::AgeRange
+        }
+        "image_style_style_id_fkey" => MetaKind::Style,
+        "image_category_category_id_fkey" | "jig_category_category_id_fkey" => MetaKind::Category,
+        "jig_content_type_content_type_id_fkey" => MetaKind::ContentType,
 
         _ => return MetaWrapperError::Sqlx(err),
     };
@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use shared::{
+    domain::admin::AdminModerationItem,
+    media::{MediaLibrary, ModerationStatus},
+};
+use sqlx::{Done as _, PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Records the moderation status of (newly uploaded) media, replacing any previous review.
+pub async fn set(
+    conn: &mut PgConnection,
+    library: MediaLibrary,
+    media_id: Uuid,
+    status: ModerationStatus,
+    reason: Option<&str>,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+insert into media_moderation (library, media_id, status, reason)
+values ($1, $2, $3, $4)
+on conflict (library, media_id) do update
+set status      = excluded.status,
+    reason      = excluded.reason,
+    reviewed_by = null,
+    reviewed_at = null,
+    created_at  = now(),
+    updated_at  = now()
+"#,
+        library as i16,
+        media_id,
+        status as i16,
+        reason
+    )
+    .execute(conn)
+    .await
+    .map(drop)
+}
+
+/// Records a classifier's verdict, as long as the media is still waiting to be reviewed.
+pub async fn classify(
+    db: &PgPool,
+    library: MediaLibrary,
+    media_id: Uuid,
+    status: ModerationStatus,
+    reason: Option<&str>,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+update media_moderation
+set status     = $3,
+    reason     = $4,
+    updated_at = now()
+where library = $1 and media_id = $2 and status = $5
+"#,
+        library as i16,
+        media_id,
+        status as i16,
+        reason,
+        ModerationStatus::Pending as i16
+    )
+    .execute(db)
+    .await
+    .map(drop)
+}
+
+/// Records an admin's review, returns `false` if the media hasn't been through moderation.
+pub async fn review(
+    db: &PgPool,
+    library: MediaLibrary,
+    media_id: Uuid,
+    status: ModerationStatus,
+    reason: Option<&str>,
+    reviewer: Uuid,
+) -> sqlx::Result<bool> {
+    sqlx::query!(
+        r#"
+update media_moderation
+set status      = $3,
+    reason      = coalesce($4, reason),
+    reviewed_by = $5,
+    reviewed_at = now(),
+    updated_at  = now()
+where library = $1 and media_id = $2
+"#,
+        library as i16,
+        media_id,
+        status as i16,
+        reason,
+        reviewer
+    )
+    .execute(db)
+    .await
+    .map(|it| it.rows_affected() > 0)
+}
+
+/// Returns `None` if the media hasn't been through moderation.
+pub async fn status(
+    db: &PgPool,
+    library: MediaLibrary,
+    media_id: Uuid,
+) -> sqlx::Result<Option<ModerationStatus>> {
+    sqlx::query!(
+        r#"select status as "status: ModerationStatus" from media_moderation where library = $1 and media_id = $2"#,
+        library as i16,
+        media_id
+    )
+    .fetch_optional(db)
+    .await
+    .map(|it| it.map(|it| it.status))
+}
+
+/// Lists moderated media, newest first, starting after `cursor`.
+pub async fn list(
+    db: &PgPool,
+    status: Option<ModerationStatus>,
+    library: Option<MediaLibrary>,
+    cursor: Option<(DateTime<Utc>, Uuid)>,
+    limit: i64,
+) -> sqlx::Result<Vec<AdminModerationItem>> {
+    let (cursor_created_at, cursor_id) = cursor.map_or((None, None), |(created_at, id)| {
+        (Some(created_at), Some(id))
+    });
+
+    sqlx::query!(
+        r#"
+select media_id,
+       library as "library: MediaLibrary",
+       status as "status: ModerationStatus",
+       reason,
+       created_at,
+       reviewed_by,
+       reviewed_at
+from media_moderation
+where ($1::int2 is null or status = $1)
+  and ($2::int2 is null or library = $2)
+  and ($3::timestamptz is null or (created_at, media_id) < ($3, $4::uuid))
+order by created_at desc, media_id desc
+limit $5
+"#,
+        status.map(|it| it as i16),
+        library.map(|it| it as i16),
+        cursor_created_at,
+        cursor_id,
+        limit
+    )
+    .fetch(db)
+    .map_ok(|it| AdminModerationItem {
+        id: it.media_id,
+        library: it.library,
+        status: it.status,
+        reason: it.reason,
+        created_at: it.created_at,
+        reviewed_by: it.reviewed_by,
+        reviewed_at: it.reviewed_at,
+    })
+    .try_collect()
+    .await
+}
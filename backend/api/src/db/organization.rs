@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use shared::domain::organization::{
+    Organization, OrganizationId, OrganizationMember, OrganizationRole,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn create(
+    pool: &PgPool,
+    display_name: &str,
+    creator_id: Uuid,
+) -> sqlx::Result<OrganizationId> {
+    let mut txn = pool.begin().await?;
+
+    let id = sqlx::query!(
+        r#"insert into organization (display_name) values ($1) returning id as "id: OrganizationId""#,
+        display_name
+    )
+    .fetch_one(&mut txn)
+    .await?
+    .id;
+
+    sqlx::query!(
+        "insert into organization_member (organization_id, user_id, role) values ($1, $2, $3)",
+        id.0,
+        creator_id,
+        OrganizationRole::Admin as i16,
+    )
+    .execute(&mut txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(id)
+}
+
+pub async fn get(pool: &PgPool, id: OrganizationId) -> sqlx::Result<Option<Organization>> {
+    sqlx::query!(
+        r#"
+select id                                                       as "id: OrganizationId",
+       display_name,
+       created_at,
+       array(select row (user_id, role)
+             from organization_member
+             where organization_id = $1
+             order by created_at)                               as "members!: Vec<(Uuid, OrganizationRole)>"
+from organization
+where id = $1
+"#,
+        id.0
+    )
+    .fetch_optional(pool)
+    .await
+    .map(|it| {
+        it.map(|row| Organization {
+            id: row.id,
+            display_name: row.display_name,
+            members: row
+                .members
+                .into_iter()
+                .map(|(user_id, role)| OrganizationMember { user_id, role })
+                .collect(),
+            created_at: row.created_at,
+        })
+    })
+}
+
+/// Returns the role `user_id` has in the organization, or `None` if they aren't a member (or the organization doesn't exist).
+pub async fn role(
+    pool: &PgPool,
+    id: OrganizationId,
+    user_id: Uuid,
+) -> sqlx::Result<Option<OrganizationRole>> {
+    sqlx::query!(
+        r#"select role as "role: OrganizationRole" from organization_member where organization_id = $1 and user_id = $2"#,
+        id.0,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map(|it| it.map(|it| it.role))
+}
+
+pub async fn create_invite(
+    pool: &PgPool,
+    id: OrganizationId,
+    role: OrganizationRole,
+    created_by: Uuid,
+    token_hash: &[u8],
+    expires_at: DateTime<Utc>,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+insert into organization_invite (token_hash, organization_id, role, created_by, expires_at)
+values ($1, $2, $3, $4, $5)
+"#,
+        token_hash,
+        id.0,
+        role as i16,
+        created_by,
+        expires_at
+    )
+    .execute(pool)
+    .await
+    .map(drop)
+}
+
+/// Consumes an invite, adding `user_id` to the organization it was for.
+///
+/// If the user is already a member, they keep whichever role is more privileged.
+/// Returns `None` if the token doesn't exist or has expired.
+pub async fn accept_invite(
+    pool: &PgPool,
+    token_hash: &[u8],
+    user_id: Uuid,
+) -> sqlx::Result<Option<(OrganizationId, OrganizationRole)>> {
+    let mut txn = pool.begin().await?;
+
+    let invite = sqlx::query!(
+        r#"
+delete from organization_invite
+where token_hash = $1 and expires_at > now()
+returning organization_id as "organization_id: OrganizationId", role
+"#,
+        token_hash
+    )
+    .fetch_optional(&mut txn)
+    .await?;
+
+    let invite = match invite {
+        Some(invite) => invite,
+        None => return Ok(None),
+    };
+
+    // note: roles are ordered from most to least privileged.
+    let role = sqlx::query!(
+        r#"
+insert into organization_member (organization_id, user_id, role)
+values ($1, $2, $3)
+on conflict (organization_id, user_id) do update set role = least(organization_member.role, excluded.role)
+returning role as "role: OrganizationRole"
+"#,
+        invite.organization_id.0,
+        user_id,
+        invite.role
+    )
+    .fetch_one(&mut txn)
+    .await?
+    .role;
+
+    txn.commit().await?;
+
+    Ok(Some((invite.organization_id, role)))
+}
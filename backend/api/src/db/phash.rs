@@ -0,0 +1,99 @@
+use shared::media::MediaLibrary;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// The largest hamming distance that [`similar`] can search for, see the `media_phash` migration.
+pub const MAX_DISTANCE: u32 = 7;
+
+pub struct Similar {
+    pub library: MediaLibrary,
+    pub media_id: Uuid,
+    pub distance: i32,
+}
+
+pub async fn set(
+    conn: &mut PgConnection,
+    library: MediaLibrary,
+    media_id: Uuid,
+    phash: i64,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+insert into media_phash (library, media_id, phash)
+values ($1, $2, $3)
+on conflict (library, media_id) do update
+set phash      = excluded.phash,
+    updated_at = now()
+"#,
+        library as i16,
+        media_id,
+        phash
+    )
+    .execute(conn)
+    .await
+    .map(drop)
+}
+
+pub async fn get(db: &PgPool, library: MediaLibrary, media_id: Uuid) -> sqlx::Result<Option<i64>> {
+    sqlx::query!(
+        "select phash from media_phash where library = $1 and media_id = $2",
+        library as i16,
+        media_id
+    )
+    .fetch_optional(db)
+    .await
+    .map(|it| it.map(|it| it.phash))
+}
+
+/// Finds media whose hash is within `max_distance` (capped at [`MAX_DISTANCE`]) of `phash`, most similar first.
+///
+/// `library` restricts the search to one library, and `exclude` is left out of the results.
+pub async fn similar(
+    db: &PgPool,
+    phash: i64,
+    max_distance: u32,
+    library: Option<MediaLibrary>,
+    exclude: Option<(MediaLibrary, Uuid)>,
+    limit: i64,
+) -> sqlx::Result<Vec<Similar>> {
+    let max_distance = max_distance.min(MAX_DISTANCE) as i32;
+    let (exclude_library, exclude_id) = exclude.map_or((None, None), |(library, id)| {
+        (Some(library as i16), Some(id))
+    });
+
+    sqlx::query_as!(
+        Similar,
+        r#"
+select library as "library!: MediaLibrary",
+       media_id as "media_id!",
+       distance as "distance!"
+from (
+    select library,
+           media_id,
+           length(replace((phash # $1)::bit(64)::text, '0', '')) as distance
+    from media_phash
+    where ((phash & 255) = ($1 & 255)
+        or ((phash >> 8) & 255) = (($1 >> 8) & 255)
+        or ((phash >> 16) & 255) = (($1 >> 16) & 255)
+        or ((phash >> 24) & 255) = (($1 >> 24) & 255)
+        or ((phash >> 32) & 255) = (($1 >> 32) & 255)
+        or ((phash >> 40) & 255) = (($1 >> 40) & 255)
+        or ((phash >> 48) & 255) = (($1 >> 48) & 255)
+        or ((phash >> 56) & 255) = (($1 >> 56) & 255))
+      and ($3::int2 is null or library = $3)
+      and ($4::int2 is null or (library, media_id) <> ($4, $5::uuid))
+) candidates
+where distance <= $2
+order by distance, library, media_id
+limit $6
+"#,
+        phash,
+        max_distance,
+        library.map(|it| it as i16),
+        exclude_library,
+        exclude_id,
+        limit
+    )
+    .fetch_all(db)
+    .await
+}
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use shared::domain::admin::{AdminSearchSyncFailure, SearchIndexOperation};
+use sqlx::{Done as _, PgConnection, PgPool};
+use uuid::Uuid;
+
+/// How many failed changes [`failed`] returns.
+const FAILED_LIST_LIMIT: i64 = 100;
+
+pub struct SyncStatus {
+    pub pending_count: u64,
+    pub failed_count: u64,
+    pub oldest_pending_at: Option<DateTime<Utc>>,
+}
+
+/// Queues every image to be reindexed and retries every failed change, returns how many images were queued.
+pub async fn reindex_all(conn: &mut PgConnection) -> sqlx::Result<u64> {
+    sqlx::query!(
+        r#"
+update algolia_outbox
+set attempts        = 0,
+    failed_at       = null,
+    next_attempt_at = now()
+where failed_at is not null
+"#
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let queued = sqlx::query!(
+        r#"
+insert into algolia_outbox (object_id, operation)
+select id, $1
+from image_metadata
+on conflict (object_id) do update
+set operation       = excluded.operation,
+    attempts        = 0,
+    last_error      = null,
+    failed_at       = null,
+    next_attempt_at = now(),
+    enqueued_at     = now()
+"#,
+        SearchIndexOperation::Upsert as i16
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    Ok(queued)
+}
+
+pub async fn status(db: &PgPool) -> sqlx::Result<SyncStatus> {
+    let row = sqlx::query!(
+        r#"
+select count(*) filter (where failed_at is null)     as "pending_count!",
+       count(*) filter (where failed_at is not null) as "failed_count!",
+       min(enqueued_at) filter (where failed_at is null) as oldest_pending_at
+from algolia_outbox
+"#
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(SyncStatus {
+        pending_count: row.pending_count as u64,
+        failed_count: row.failed_count as u64,
+        oldest_pending_at: row.oldest_pending_at,
+    })
+}
+
+/// Lists the most recent changes that ran out of retries, newest first.
+pub async fn failed(db: &PgPool) -> sqlx::Result<Vec<AdminSearchSyncFailure>> {
+    let rows = sqlx::query!(
+        r#"
+select object_id,
+       operation as "operation: SearchIndexOperation",
+       attempts,
+       last_error,
+       failed_at as "failed_at!"
+from algolia_outbox
+where failed_at is not null
+order by failed_at desc, object_id
+limit $1
+"#,
+        FAILED_LIST_LIMIT
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|it| AdminSearchSyncFailure {
+            object_id: it.object_id,
+            operation: it.operation,
+            attempts: it.attempts as u32,
+            last_error: it.last_error,
+            failed_at: it.failed_at,
+        })
+        .collect())
+}
+
+pub struct Pending {
+    pub object_id: Uuid,
+    pub operation: SearchIndexOperation,
+    pub enqueued_at: DateTime<Utc>,
+}
+
+/// Gets changes that are ready to be (re)tried, oldest first.
+pub async fn next_batch(db: &PgPool, limit: i64) -> sqlx::Result<Vec<Pending>> {
+    sqlx::query_as!(
+        Pending,
+        r#"
+select object_id, operation as "operation: SearchIndexOperation", enqueued_at
+from algolia_outbox
+where failed_at is null and next_attempt_at <= now()
+order by next_attempt_at, enqueued_at
+limit $1
+"#,
+        limit
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Removes changes that have been made, unless they were replaced by a newer change in the meantime.
+pub async fn complete(db: &PgPool, done: &[Pending]) -> sqlx::Result<()> {
+    let ids: Vec<_> = done.iter().map(|it| it.object_id).collect();
+    let enqueued_at: Vec<_> = done.iter().map(|it| it.enqueued_at).collect();
+
+    sqlx::query!(
+        r#"
+delete from algolia_outbox
+using unnest($1::uuid[], $2::timestamptz[]) as done(object_id, enqueued_at)
+where algolia_outbox.object_id = done.object_id
+  and algolia_outbox.enqueued_at = done.enqueued_at
+"#,
+        &ids,
+        &enqueued_at
+    )
+    .execute(db)
+    .await
+    .map(drop)
+}
+
+/// Schedules changes to be retried (with exponential backoff), or marks them as failed once they've been tried `max_attempts` times.
+pub async fn retry(
+    db: &PgPool,
+    failed: &[Pending],
+    error: &str,
+    max_attempts: i32,
+) -> sqlx::Result<()> {
+    let ids: Vec<_> = failed.iter().map(|it| it.object_id).collect();
+    let enqueued_at: Vec<_> = failed.iter().map(|it| it.enqueued_at).collect();
+
+    sqlx::query!(
+        r#"
+update algolia_outbox
+set attempts        = attempts + 1,
+    last_error      = $3,
+    next_attempt_at = now() + least(power(2, attempts) * 5, 3600) * interval '1 second',
+    failed_at       = case when attempts + 1 >= $4 then now() end
+from unnest($1::uuid[], $2::timestamptz[]) as failed(object_id, enqueued_at)
+where algolia_outbox.object_id = failed.object_id
+  and algolia_outbox.enqueued_at = failed.enqueued_at
+"#,
+        &ids,
+        &enqueued_at,
+        error,
+        max_attempts
+    )
+    .execute(db)
+    .await
+    .map(drop)
+}
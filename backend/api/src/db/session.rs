@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use shared::domain::{
+    classroom::ClassroomId,
+    jig::{JigId, ModuleId},
+    session::{
+        JigProgress, ProgressReport, ProgressSummary, SessionEventKind, SessionId, StudentProgress,
+    },
+};
+use sqlx::{Done as _, PgPool};
+use uuid::Uuid;
+
+pub async fn create(
+    pool: &PgPool,
+    jig_id: JigId,
+    user_id: Uuid,
+    classroom_id: Option<ClassroomId>,
+) -> sqlx::Result<SessionId> {
+    sqlx::query!(
+        r#"
+insert into play_session (jig_id, user_id, classroom_id)
+values ($1, $2, $3)
+returning id as "id: SessionId"
+"#,
+        jig_id.0,
+        user_id,
+        classroom_id.map(|it| it.0)
+    )
+    .fetch_one(pool)
+    .await
+    .map(|it| it.id)
+}
+
+/// Returns the user who started the session, or `None` if it doesn't exist.
+pub async fn owner(pool: &PgPool, id: SessionId) -> sqlx::Result<Option<Uuid>> {
+    sqlx::query!("select user_id from play_session where id = $1", id.0)
+        .fetch_optional(pool)
+        .await
+        .map(|it| it.map(|it| it.user_id))
+}
+
+/// Records an event for the session.
+///
+/// Returns `false` if the module isn't part of the session's JIG.
+pub async fn create_event(
+    pool: &PgPool,
+    id: SessionId,
+    module_id: ModuleId,
+    kind: SessionEventKind,
+    score: Option<i32>,
+    attempts: Option<i32>,
+    time_spent_ms: Option<i64>,
+) -> sqlx::Result<bool> {
+    let res = sqlx::query!(
+        r#"
+insert into play_session_event (session_id, module_id, kind, score, attempts, time_spent_ms)
+select $1, $2, $3, $4, $5, $6
+from play_session
+    inner join jig on jig.id = play_session.jig_id
+where play_session.id = $1
+  and (jig.cover_id = $2
+    or jig.ending_id = $2
+    or exists(select 1 from jig_module where jig_module.jig_id = jig.id and module_id = $2))
+"#,
+        id.0,
+        module_id.0,
+        kind as i16,
+        score,
+        attempts,
+        time_spent_ms
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+#[derive(Default, Clone, Copy)]
+struct Totals {
+    sessions: i64,
+    completed_modules: i64,
+    scored: i64,
+    score_total: i64,
+    attempts: i64,
+    time_spent_ms: i64,
+}
+
+impl Totals {
+    fn add(&mut self, other: &Self) {
+        self.sessions += other.sessions;
+        self.completed_modules += other.completed_modules;
+        self.scored += other.scored;
+        self.score_total += other.score_total;
+        self.attempts += other.attempts;
+        self.time_spent_ms += other.time_spent_ms;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn summary(&self) -> ProgressSummary {
+        ProgressSummary {
+            sessions: self.sessions,
+            completed_modules: self.completed_modules,
+            average_score: if self.scored > 0 {
+                Some(self.score_total as f64 / self.scored as f64)
+            } else {
+                None
+            },
+            attempts: self.attempts,
+            time_spent_ms: self.time_spent_ms,
+        }
+    }
+}
+
+/// Aggregates the results of the classroom's sessions, optionally filtered by JIG and / or student.
+pub async fn report(
+    pool: &PgPool,
+    classroom_id: ClassroomId,
+    jig_id: Option<JigId>,
+    user_id: Option<Uuid>,
+) -> sqlx::Result<ProgressReport> {
+    // one row per (jig, student), the rest of the aggregation happens below.
+    // note: only completed modules have results.
+    let rows = sqlx::query!(
+        r#"
+select jig_id                                                                            as "jig_id: JigId",
+       user_id,
+       count(distinct play_session.id)                                                   as "sessions!",
+       count(play_session_event.id) filter (where kind = $4)                             as "completed_modules!",
+       count(score) filter (where kind = $4)                                             as "scored!",
+       coalesce(sum(score) filter (where kind = $4), 0)::int8                            as "score_total!",
+       coalesce(sum(attempts) filter (where kind = $4), 0)::int8                         as "attempts!",
+       coalesce(sum(time_spent_ms) filter (where kind = $4), 0)::int8                    as "time_spent_ms!"
+from play_session
+         left join play_session_event on play_session_event.session_id = play_session.id
+where classroom_id = $1
+  and ($2::uuid is null or jig_id = $2)
+  and ($3::uuid is null or user_id = $3)
+group by jig_id, user_id
+"#,
+        classroom_id.0,
+        jig_id.map(|it| it.0),
+        user_id,
+        SessionEventKind::Completed as i16,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut total = Totals::default();
+    let mut jigs: BTreeMap<Uuid, Totals> = BTreeMap::new();
+    let mut students: BTreeMap<Uuid, Totals> = BTreeMap::new();
+
+    for row in rows {
+        let totals = Totals {
+            sessions: row.sessions,
+            completed_modules: row.completed_modules,
+            scored: row.scored,
+            score_total: row.score_total,
+            attempts: row.attempts,
+            time_spent_ms: row.time_spent_ms,
+        };
+
+        total.add(&totals);
+        jigs.entry(row.jig_id.0).or_default().add(&totals);
+        students.entry(row.user_id).or_default().add(&totals);
+    }
+
+    Ok(ProgressReport {
+        summary: total.summary(),
+        jigs: jigs
+            .into_iter()
+            .map(|(jig_id, totals)| JigProgress {
+                jig_id: JigId(jig_id),
+                summary: totals.summary(),
+            })
+            .collect(),
+        students: students
+            .into_iter()
+            .map(|(user_id, totals)| StudentProgress {
+                user_id,
+                summary: totals.summary(),
+            })
+            .collect(),
+    })
+}
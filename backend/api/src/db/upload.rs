@@ -0,0 +1,166 @@
+use chrono::{DateTime, Utc};
+use shared::{
+    domain::upload::{UploadKind, UploadSessionId},
+    media::MediaLibrary,
+};
+use sqlx::{Done as _, PgConnection, PgPool};
+use uuid::Uuid;
+
+pub struct Session {
+    pub id: UploadSessionId,
+    pub user_id: Uuid,
+    pub library: MediaLibrary,
+    pub media_id: Uuid,
+    pub kind: UploadKind,
+    /// The storage key that the file is uploaded to.
+    pub key: String,
+    pub upload_id: String,
+    pub size: i64,
+    pub part_size: i64,
+    pub expires_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    /// The number of parts the file is split into.
+    pub fn part_count(&self) -> i32 {
+        ((self.size + self.part_size - 1) / self.part_size).max(1) as i32
+    }
+
+    /// The size of part `number` (the last part can be shorter than the others).
+    pub fn part_len(&self, number: i32) -> i64 {
+        (self.size - i64::from(number - 1) * self.part_size).min(self.part_size)
+    }
+}
+
+pub async fn create(
+    db: &PgPool,
+    user_id: Uuid,
+    library: MediaLibrary,
+    media_id: Uuid,
+    kind: UploadKind,
+    key: &str,
+    upload_id: &str,
+    size: i64,
+    part_size: i64,
+    expires_at: DateTime<Utc>,
+) -> sqlx::Result<UploadSessionId> {
+    sqlx::query!(
+        r#"
+insert into upload_session (user_id, library, media_id, kind, key, upload_id, size, part_size, expires_at)
+values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+returning id as "id: UploadSessionId"
+"#,
+        user_id,
+        library as i16,
+        media_id,
+        kind as i16,
+        key,
+        upload_id,
+        size,
+        part_size,
+        expires_at
+    )
+    .fetch_one(db)
+    .await
+    .map(|it| it.id)
+}
+
+pub async fn get(db: &PgPool, id: UploadSessionId) -> sqlx::Result<Option<Session>> {
+    sqlx::query_as!(
+        Session,
+        r#"
+select id as "id: UploadSessionId",
+       user_id,
+       library as "library: MediaLibrary",
+       media_id,
+       kind as "kind: UploadKind",
+       key,
+       upload_id,
+       size,
+       part_size,
+       expires_at,
+       completed_at
+from upload_session
+where id = $1
+"#,
+        id.0
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Marks the session as completed, returns `false` if it was already completed.
+///
+/// The session's row stays locked until `conn`'s transaction ends, so rolling back resets the session.
+pub async fn complete(conn: &mut PgConnection, id: UploadSessionId) -> sqlx::Result<bool> {
+    let res = sqlx::query!(
+        "update upload_session set completed_at = now() where id = $1 and completed_at is null",
+        id.0
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn delete(db: &PgPool, id: UploadSessionId) -> sqlx::Result<()> {
+    sqlx::query!("delete from upload_session where id = $1", id.0)
+        .execute(db)
+        .await
+        .map(drop)
+}
+
+/// Returns the owner of the user library media, or `None` if it doesn't exist.
+pub async fn user_media_owner(
+    db: &PgPool,
+    kind: UploadKind,
+    media_id: Uuid,
+) -> sqlx::Result<Option<Option<Uuid>>> {
+    match kind {
+        UploadKind::Image => sqlx::query!(
+            "select user_id from user_image_library where id = $1",
+            media_id
+        )
+        .fetch_optional(db)
+        .await
+        .map(|it| it.map(|it| it.user_id)),
+
+        UploadKind::Audio => sqlx::query!(
+            "select user_id from user_audio_library where id = $1",
+            media_id
+        )
+        .fetch_optional(db)
+        .await
+        .map(|it| it.map(|it| it.user_id)),
+
+        UploadKind::Animation => Ok(None),
+    }
+}
+
+/// Returns whether the global library media exists.
+pub async fn global_media_exists(
+    db: &PgPool,
+    kind: UploadKind,
+    media_id: Uuid,
+) -> sqlx::Result<bool> {
+    match kind {
+        UploadKind::Image => sqlx::query!(
+            r#"select exists(select 1 from image_metadata where id = $1) as "exists!""#,
+            media_id
+        )
+        .fetch_one(db)
+        .await
+        .map(|it| it.exists),
+
+        UploadKind::Animation => sqlx::query!(
+            r#"select exists(select 1 from animation where id = $1) as "exists!""#,
+            media_id
+        )
+        .fetch_one(db)
+        .await
+        .map(|it| it.exists),
+
+        UploadKind::Audio => Ok(false),
+    }
+}
@@ -1,10 +1,11 @@
 use crate::{error, extractor::FirebaseId};
+use chrono::Duration;
 use chrono_tz::Tz;
 use shared::{
     domain::{
         auth::RegisterRequest,
         meta::{AffiliationId, AgeRangeId, SubjectId},
-        user::{OtherUser, UserProfile, UserScope},
+        user::{OtherUser, UserProfile, UserScope, UserToken, UserTokenId},
     },
     error::auth::RegisterErrorKind,
 };
@@ -112,11 +113,55 @@ pub async fn firebase_to_id(
         .map(|it| it.map(|it| it.id))
 }
 
+/// The way a newly registering user has proven their identity.
+pub enum RegisterCredentials<'a> {
+    Firebase(&'a FirebaseId),
+
+    /// An email and an (already hashed) password.
+    Basic {
+        email: &'a str,
+        password_hash: &'a str,
+    },
+}
+
+/// Kinds of single use tokens sent to users of the built in identity provider.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(i16)]
+pub enum AuthTokenKind {
+    VerifyEmail = 0,
+    ResetPassword = 1,
+}
+
+impl AuthTokenKind {
+    fn lifetime(self) -> Duration {
+        match self {
+            Self::VerifyEmail => Duration::days(1),
+            Self::ResetPassword => Duration::hours(1),
+        }
+    }
+}
+
+/// Returns the user's id and encoded password hash for the given email, if one exists.
+pub async fn basic_to_id(db: &sqlx::PgPool, email: &str) -> sqlx::Result<Option<(Uuid, String)>> {
+    sqlx::query!(
+        r#"select user_id, password from user_auth_basic where email = $1::text"#,
+        email
+    )
+    .fetch_optional(db)
+    .await
+    .map(|it| it.map(|it| (it.user_id, it.password)))
+}
+
 pub async fn register(
     db: &sqlx::PgPool,
-    FirebaseId(id): &FirebaseId,
+    credentials: RegisterCredentials<'_>,
     req: &RegisterRequest,
 ) -> Result<Uuid, error::Register> {
+    let firebase_id = match credentials {
+        RegisterCredentials::Firebase(FirebaseId(id)) => Some(id.as_str()),
+        RegisterCredentials::Basic { .. } => None,
+    };
+
     let mut txn = db.begin().await?;
 
     let user_id = sqlx::query!(
@@ -127,7 +172,7 @@ VALUES
     ($1, $2, $3::text, $4, $5, $6, $7, $8, $9, $10, $11, $12)
 returning id
         "#,
-        id,
+        firebase_id,
         &req.username,
         &req.email,
         req.over_18,
@@ -169,6 +214,31 @@ returning id
         e => e.into(),
     })?;
 
+    if let RegisterCredentials::Basic {
+        email,
+        password_hash,
+    } = credentials
+    {
+        sqlx::query!(
+            "insert into user_auth_basic (user_id, email, password) values ($1, $2::text, $3)",
+            user_id,
+            email,
+            password_hash
+        )
+        .execute(&mut txn)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(err)
+                if err.downcast_ref::<PgDatabaseError>().constraint()
+                    == Some("user_auth_basic_email_key") =>
+            {
+                error::Register::RegisterError(RegisterErrorKind::TakenEmail)
+            }
+
+            e => e.into(),
+        })?;
+    }
+
     update_metadata(
         &mut txn,
         user_id,
@@ -183,6 +253,228 @@ returning id
     Ok(user_id)
 }
 
+/// Stores a (hashed) single use token for the given user.
+pub async fn create_auth_token(
+    db: &sqlx::PgPool,
+    user_id: Uuid,
+    kind: AuthTokenKind,
+    token_hash: &[u8],
+) -> sqlx::Result<()> {
+    let mut txn = db.begin().await?;
+
+    // clean up any tokens that have expired while we're here.
+    sqlx::query!("delete from user_auth_token where expires_at <= now()")
+        .execute(&mut txn)
+        .await?;
+
+    sqlx::query!(
+        r#"
+insert into user_auth_token (token_hash, user_id, kind, expires_at)
+values ($1, $2, $3, now() + $4::int8 * interval '1 second')
+"#,
+        token_hash,
+        user_id,
+        kind as i16,
+        kind.lifetime().num_seconds(),
+    )
+    .execute(&mut txn)
+    .await?;
+
+    txn.commit().await
+}
+
+/// Consumes a single use token, returning the id of the user it was created for,
+/// or `None` if the token doesn't exist or has expired.
+pub async fn consume_auth_token(
+    conn: &mut PgConnection,
+    kind: AuthTokenKind,
+    token_hash: &[u8],
+) -> sqlx::Result<Option<Uuid>> {
+    sqlx::query!(
+        r#"
+delete from user_auth_token
+where token_hash = $1 and kind = $2 and expires_at > now()
+returning user_id
+"#,
+        token_hash,
+        kind as i16,
+    )
+    .fetch_optional(conn)
+    .await
+    .map(|it| it.map(|it| it.user_id))
+}
+
+/// Marks the email of the user the token was created for as verified.
+///
+/// Returns `false` if the token was invalid or expired.
+pub async fn verify_email(db: &sqlx::PgPool, token_hash: &[u8]) -> sqlx::Result<bool> {
+    let mut txn = db.begin().await?;
+
+    let user_id = match consume_auth_token(&mut txn, AuthTokenKind::VerifyEmail, token_hash).await?
+    {
+        Some(user_id) => user_id,
+        None => return Ok(false),
+    };
+
+    sqlx::query!(
+        r#"update "user" set email_verified = true, updated_at = now() where id = $1"#,
+        user_id
+    )
+    .execute(&mut txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(true)
+}
+
+/// Replaces the password of the user the token was created for.
+///
+/// Returns `false` if the token was invalid or expired.
+pub async fn reset_password(
+    db: &sqlx::PgPool,
+    token_hash: &[u8],
+    password_hash: &str,
+) -> sqlx::Result<bool> {
+    let mut txn = db.begin().await?;
+
+    let user_id =
+        match consume_auth_token(&mut txn, AuthTokenKind::ResetPassword, token_hash).await? {
+            Some(user_id) => user_id,
+            None => return Ok(false),
+        };
+
+    sqlx::query!(
+        "update user_auth_basic set password = $2, updated_at = now() where user_id = $1",
+        user_id,
+        password_hash
+    )
+    .execute(&mut txn)
+    .await?;
+
+    // any other outstanding reset tokens are now stale.
+    sqlx::query!(
+        "delete from user_auth_token where user_id = $1 and kind = $2",
+        user_id,
+        AuthTokenKind::ResetPassword as i16
+    )
+    .execute(&mut txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(true)
+}
+
+pub async fn scopes(db: &sqlx::PgPool, user_id: Uuid) -> sqlx::Result<Vec<i16>> {
+    sqlx::query!(
+        r#"select array(select scope from user_scope where user_id = $1) as "scopes!: Vec<i16>""#,
+        user_id
+    )
+    .fetch_one(db)
+    .await
+    .map(|it| it.scopes)
+}
+
+pub async fn create_token(
+    db: &sqlx::PgPool,
+    user_id: Uuid,
+    display_name: &str,
+    scopes: &[i16],
+    token_hash: &[u8],
+) -> sqlx::Result<UserTokenId> {
+    sqlx::query!(
+        r#"
+insert into user_api_token (user_id, display_name, scopes, token_hash)
+values ($1, $2, $3, $4)
+returning id as "id: UserTokenId"
+"#,
+        user_id,
+        display_name,
+        scopes,
+        token_hash
+    )
+    .fetch_one(db)
+    .await
+    .map(|it| it.id)
+}
+
+pub async fn list_tokens(db: &sqlx::PgPool, user_id: Uuid) -> anyhow::Result<Vec<UserToken>> {
+    sqlx::query!(
+        r#"
+select id as "id: UserTokenId", display_name, scopes, created_at, last_used_at
+from user_api_token
+where user_id = $1
+order by created_at desc
+"#,
+        user_id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Ok(UserToken {
+            id: row.id,
+            display_name: row.display_name,
+            scopes: row
+                .scopes
+                .into_iter()
+                .map(UserScope::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+        })
+    })
+    .collect()
+}
+
+pub async fn delete_token(db: &sqlx::PgPool, user_id: Uuid, id: UserTokenId) -> sqlx::Result<()> {
+    sqlx::query!(
+        "delete from user_api_token where id = $1 and user_id = $2",
+        id.0,
+        user_id
+    )
+    .execute(db)
+    .await
+    .map(drop)
+}
+
+/// Looks up the user a personal access token belongs to (marking the token as used).
+///
+/// Also returns whether the token can use `scope`, which requires both the token to have been granted it,
+/// and for the user to (still) have it. Any token can be used when `scope` is `None`.
+pub async fn token_user(
+    db: &sqlx::PgPool,
+    token_hash: &[u8],
+    scope: Option<i16>,
+) -> sqlx::Result<Option<(Uuid, bool)>> {
+    sqlx::query!(
+        r#"
+update user_api_token
+set last_used_at = now()
+where token_hash = $1
+returning user_id,
+    ($2::int2 is null or ($2 = any(scopes) and exists(select 1 from user_scope where user_scope.user_id = user_api_token.user_id and scope = $2))) as "has_scope!"
+"#,
+        token_hash,
+        scope
+    )
+    .fetch_optional(db)
+    .await
+    .map(|it| it.map(|it| (it.user_id, it.has_scope)))
+}
+
+/// Finds who owns a personal access token, without counting it as used.
+pub async fn token_owner(db: &sqlx::PgPool, token_hash: &[u8]) -> sqlx::Result<Option<Uuid>> {
+    sqlx::query!(
+        "select user_id from user_api_token where token_hash = $1",
+        token_hash
+    )
+    .fetch_optional(db)
+    .await
+    .map(|it| it.map(|it| it.user_id))
+}
+
 pub async fn update_metadata(
     conn: &mut PgConnection,
     user_id: Uuid,
@@ -1,4 +1,3 @@
-use actix_web::error::BlockingError;
 use actix_web::HttpResponse;
 use paperclip::actix::api_v2_errors;
 use shared::error::{auth::RegisterErrorKind, ApiError, EmptyError, MetadataNotFound};
@@ -142,6 +141,37 @@ impl Into<actix_web::Error> for Refresh {
     }
 }
 
+#[api_v2_errors(
+    code = 400,
+    description = "Bad Request: Invalid cursor",
+    code = 401,
+    code = 403,
+    code = 500
+)]
+#[derive(Debug)]
+pub enum ListMedia {
+    InvalidCursor,
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for ListMedia {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for ListMedia {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::InvalidCursor => {
+                BasicError::with_message(http::StatusCode::BAD_REQUEST, "Invalid cursor".to_owned())
+                    .into()
+            }
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
 #[api_v2_errors(
     code = 401,
     code = 403,
@@ -204,6 +234,93 @@ impl Into<actix_web::Error> for NotFound {
     }
 }
 
+#[api_v2_errors(
+    code = 400,
+    description = "Bad Request: The user isn't a student in the organization",
+    code = 401,
+    code = 403,
+    description = "Forbidden: Insufficient role within the organization",
+    code = 404,
+    description = "Not Found: Resource Not Found",
+    code = 500
+)]
+pub enum Organization {
+    Forbidden,
+    NotAStudent,
+    ResourceNotFound,
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for Organization {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for Organization {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::Forbidden => BasicError::with_message(
+                http::StatusCode::FORBIDDEN,
+                "Insufficient role within the organization".to_owned(),
+            )
+            .into(),
+
+            Self::NotAStudent => BasicError::with_message(
+                http::StatusCode::BAD_REQUEST,
+                "The user isn't a student in the organization".to_owned(),
+            )
+            .into(),
+
+            Self::ResourceNotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Resource Not Found".to_owned(),
+            )
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Bad Request: The event's results are invalid",
+    code = 401,
+    code = 403,
+    code = 404,
+    code = 500
+)]
+pub enum SessionEvent {
+    BadRequest(&'static str),
+    ResourceNotFound,
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for SessionEvent {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for SessionEvent {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::BadRequest(message) => {
+                BasicError::with_message(http::StatusCode::BAD_REQUEST, message.to_owned()).into()
+            }
+
+            Self::ResourceNotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Resource Not Found".to_owned(),
+            )
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
 #[api_v2_errors(
     code = 400,
     code = 401,
@@ -260,6 +377,152 @@ impl Into<actix_web::Error> for CategoryUpdate {
     }
 }
 
+#[api_v2_errors(
+    code = 400,
+    code = 401,
+    code = 403,
+    code = 404,
+    description = "Not Found: Category Not Found OR Target Category Not Found",
+    code = 422,
+    description = "Unprocessable Entity: Can't merge a category into itself or one of its descendants",
+    code = 500
+)]
+pub enum CategoryMerge {
+    CategoryNotFound,
+    TargetNotFound,
+    Cycle,
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for CategoryMerge {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for CategoryMerge {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::CategoryNotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Category Not Found".to_owned(),
+            )
+            .into(),
+
+            Self::TargetNotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Target Category Not Found".to_owned(),
+            )
+            .into(),
+
+            Self::Cycle => BasicError::with_message(
+                http::StatusCode::UNPROCESSABLE_ENTITY,
+                "Can't merge a category into itself or one of its descendants".to_owned(),
+            )
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    code = 401,
+    code = 403,
+    code = 404,
+    description = "Not Found: Metadata Not Found OR Replacement Metadata Not Found",
+    code = 409,
+    description = "Conflict: Metadata is still in use",
+    code = 500
+)]
+pub enum MetadataDelete {
+    NotFound,
+    ReplacementNotFound,
+    InUse,
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for MetadataDelete {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for MetadataDelete {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::NotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Metadata Not Found".to_owned(),
+            )
+            .into(),
+
+            Self::ReplacementNotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Replacement Metadata Not Found".to_owned(),
+            )
+            .into(),
+
+            Self::InUse => BasicError::with_message(
+                http::StatusCode::CONFLICT,
+                "Metadata is still in use, pass `replace_with` to reassign it".to_owned(),
+            )
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Bad Request: Invalid Language OR Duplicate Language",
+    code = 401,
+    code = 403,
+    code = 404,
+    description = "Not Found: Resource Not Found",
+    code = 500
+)]
+pub enum SetTranslations {
+    NotFound,
+    InvalidLanguage(String),
+    DuplicateLanguage(String),
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for SetTranslations {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for SetTranslations {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::NotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Resource Not Found".to_owned(),
+            )
+            .into(),
+
+            Self::InvalidLanguage(language) => BasicError::with_message(
+                http::StatusCode::BAD_REQUEST,
+                format!("Invalid language: `{}`", language),
+            )
+            .into(),
+
+            Self::DuplicateLanguage(language) => BasicError::with_message(
+                http::StatusCode::BAD_REQUEST,
+                format!("Duplicate language: `{}`", language),
+            )
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
 #[api_v2_errors(
     code = 400,
     code = 401,
@@ -276,15 +539,6 @@ pub enum Upload {
     InternalServerError(anyhow::Error),
 }
 
-impl Upload {
-    pub fn blocking_error(err: BlockingError<Self>) -> Self {
-        match err {
-            BlockingError::Canceled => anyhow::anyhow!("Thread pool is gone").into(),
-            BlockingError::Error(e) => e,
-        }
-    }
-}
-
 impl<T: Into<anyhow::Error>> From<T> for Upload {
     fn from(e: T) -> Self {
         Self::InternalServerError(e.into())
@@ -311,6 +565,7 @@ impl Into<actix_web::Error> for Upload {
 
 #[api_v2_errors(
     code = 400,
+    description = "Bad Request: Invalid Alt Text OR Invalid Source Url",
     code = 401,
     code = 403,
     code = 420,
@@ -318,6 +573,7 @@ impl Into<actix_web::Error> for Upload {
     code = 500
 )]
 pub enum CreateWithMetadata {
+    BadRequest(String),
     InternalServerError(anyhow::Error),
     MissingMetadata(MetadataNotFound),
 }
@@ -331,6 +587,10 @@ impl<T: Into<anyhow::Error>> From<T> for CreateWithMetadata {
 impl Into<actix_web::Error> for CreateWithMetadata {
     fn into(self) -> actix_web::Error {
         match self {
+            Self::BadRequest(message) => {
+                BasicError::with_message(http::StatusCode::BAD_REQUEST, message).into()
+            }
+
             Self::MissingMetadata(data) => ApiError {
                 code: http::StatusCode::UNPROCESSABLE_ENTITY,
                 message: "Metadata not Found".to_owned(),
@@ -344,6 +604,7 @@ impl Into<actix_web::Error> for CreateWithMetadata {
 
 #[api_v2_errors(
     code = 400,
+    description = "Bad Request: Invalid Alt Text OR Invalid Source Url",
     code = 401,
     code = 403,
     code = 404,
@@ -353,6 +614,7 @@ impl Into<actix_web::Error> for CreateWithMetadata {
     code = 500
 )]
 pub enum UpdateWithMetadata {
+    BadRequest(String),
     ResourceNotFound,
     InternalServerError(anyhow::Error),
     MissingMetadata(MetadataNotFound),
@@ -367,6 +629,10 @@ impl<T: Into<anyhow::Error>> From<T> for UpdateWithMetadata {
 impl Into<actix_web::Error> for UpdateWithMetadata {
     fn into(self) -> actix_web::Error {
         match self {
+            Self::BadRequest(message) => {
+                BasicError::with_message(http::StatusCode::BAD_REQUEST, message).into()
+            }
+
             Self::MissingMetadata(data) => ApiError {
                 code: http::StatusCode::UNPROCESSABLE_ENTITY,
                 message: "Metadata not Found".to_owned(),
@@ -406,13 +672,175 @@ impl From<MetaWrapperError> for UpdateWithMetadata {
     }
 }
 
+#[api_v2_errors(
+    code = 400,
+    description = "Bad Request: Too many images",
+    code = 401,
+    code = 403,
+    code = 404,
+    description = "Not Found: Image Not Found",
+    code = 422,
+    description = "Unprocessable Entity: Metadata not Found"
+    code = 500
+)]
+pub enum BulkTagImages {
+    TooManyImages(usize),
+    ImageNotFound,
+    InternalServerError(anyhow::Error),
+    MissingMetadata(MetadataNotFound),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for BulkTagImages {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for BulkTagImages {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::TooManyImages(max) => BasicError::with_message(
+                http::StatusCode::BAD_REQUEST,
+                format!("Too many images, at most {} can be changed at once", max),
+            )
+            .into(),
+
+            Self::ImageNotFound => {
+                BasicError::with_message(http::StatusCode::NOT_FOUND, "Image Not Found".to_owned())
+                    .into()
+            }
+
+            Self::MissingMetadata(data) => ApiError {
+                code: http::StatusCode::UNPROCESSABLE_ENTITY,
+                message: "Metadata not Found".to_owned(),
+                extra: data,
+            }
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+impl From<MetaWrapperError> for BulkTagImages {
+    fn from(e: MetaWrapperError) -> Self {
+        match e {
+            MetaWrapperError::Sqlx(e) => Self::InternalServerError(e.into()),
+            MetaWrapperError::MissingMetadata { id, kind } => {
+                Self::MissingMetadata(MetadataNotFound { id, kind })
+            }
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Bad Request: Invalid Manifest",
+    code = 401,
+    code = 403,
+    code = 422,
+    description = "Unprocessable Entity: Metadata not Found"
+    code = 500
+)]
+pub enum ImageImport {
+    InvalidManifest(String),
+    InternalServerError(anyhow::Error),
+    MissingMetadata(MetadataNotFound),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for ImageImport {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for ImageImport {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::InvalidManifest(message) => {
+                BasicError::with_message(http::StatusCode::BAD_REQUEST, message).into()
+            }
+
+            Self::MissingMetadata(data) => ApiError {
+                code: http::StatusCode::UNPROCESSABLE_ENTITY,
+                message: "Metadata not Found".to_owned(),
+                extra: data,
+            }
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    code = 401,
+    code = 403,
+    code = 404,
+    description = "Not Found: Resource Not Found",
+    code = 409,
+    description = "Conflict: The item is being downloaded or has already been imported",
+    code = 422,
+    description = "Unprocessable Entity: Invalid Content OR Metadata not Found"
+    code = 500
+)]
+pub enum ImageImportUpload {
+    ResourceNotFound,
+    AlreadyImporting,
+    InvalidMedia,
+    InternalServerError(anyhow::Error),
+    MissingMetadata(MetadataNotFound),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for ImageImportUpload {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for ImageImportUpload {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::ResourceNotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Resource Not Found".to_owned(),
+            )
+            .into(),
+
+            Self::AlreadyImporting => BasicError::with_message(
+                http::StatusCode::CONFLICT,
+                "The item is being downloaded or has already been imported".to_owned(),
+            )
+            .into(),
+
+            Self::InvalidMedia => BasicError::with_message(
+                http::StatusCode::UNPROCESSABLE_ENTITY,
+                "Invalid Content".to_owned(),
+            )
+            .into(),
+
+            Self::MissingMetadata(data) => ApiError {
+                code: http::StatusCode::UNPROCESSABLE_ENTITY,
+                message: "Metadata not Found".to_owned(),
+                extra: data,
+            }
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
 #[api_v2_errors(
     code = 400,
     code = 420,
     description = "Unprocessable Entity: No username was provided OR "
     "Another user with the provided email already exists OR "
     "Another user with the provided firebase-id already exists OR "
-    "Another user with the provided username already exists",
+    "Another user with the provided username already exists OR "
+    "The provided email doesn't match the credentials OR "
+    "The provided password is too short",
     code = 500
 )]
 pub enum Register {
@@ -435,6 +863,8 @@ impl Into<actix_web::Error> for Register {
                     RegisterErrorKind::TakenEmail => "Email already taken",
                     RegisterErrorKind::TakenId => "Firebase ID already associated with a user",
                     RegisterErrorKind::TakenUsername => "Username already taken",
+                    RegisterErrorKind::EmailMismatch => "Email doesn't match credentials",
+                    RegisterErrorKind::WeakPassword => "Password is too short",
                     _ => "Unprocessable Entity",
                 };
 
@@ -449,3 +879,266 @@ impl Into<actix_web::Error> for Register {
         }
     }
 }
+
+#[api_v2_errors(
+    code = 400,
+    code = 401,
+    code = 403,
+    description = "Forbidden: Personal access tokens can't manage tokens OR "
+    "The user doesn't have one of the requested scopes",
+    code = 500
+)]
+pub enum ManageTokens {
+    PersonalToken,
+    ScopeNotHeld,
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for ManageTokens {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for ManageTokens {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::PersonalToken => BasicError::with_message(
+                http::StatusCode::FORBIDDEN,
+                "Personal access tokens can't be used to manage tokens".to_owned(),
+            )
+            .into(),
+
+            Self::ScopeNotHeld => BasicError::with_message(
+                http::StatusCode::FORBIDDEN,
+                "Can't grant a scope the user doesn't have".to_owned(),
+            )
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    code = 404,
+    description = "Not Found: Invalid or expired token",
+    code = 420,
+    description = "Unprocessable Entity: Password is too short",
+    code = 500
+)]
+pub enum ResetPassword {
+    InvalidToken,
+    WeakPassword,
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for ResetPassword {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for ResetPassword {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::InvalidToken => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Invalid or expired token".to_owned(),
+            )
+            .into(),
+
+            Self::WeakPassword => BasicError::with_message(
+                http::StatusCode::UNPROCESSABLE_ENTITY,
+                "Password is too short".to_owned(),
+            )
+            .into(),
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Bad Request: The url isn't a public http(s) url",
+    code = 401,
+    code = 403,
+    code = 413,
+    description = "Payload Too Large: The fetched media is too large",
+    code = 415,
+    description = "Unsupported Media Type: The url doesn't point to an image",
+    code = 420,
+    description = "Unprocessable Entity: The media was rejected by moderation",
+    code = 500,
+    code = 502,
+    description = "Bad Gateway: The url couldn't be fetched"
+)]
+#[derive(Debug)]
+pub enum WebMedia {
+    InvalidUrl(&'static str),
+    ForbiddenHost,
+    TooLarge,
+    UnsupportedMediaType,
+    Rejected,
+    TooManyRedirects,
+    Upstream(String),
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for WebMedia {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for WebMedia {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::InvalidUrl(reason) => BasicError::with_message(
+                http::StatusCode::BAD_REQUEST,
+                format!("Invalid url: {}", reason),
+            )
+            .into(),
+
+            Self::ForbiddenHost => BasicError::with_message(
+                http::StatusCode::BAD_REQUEST,
+                "Url doesn't resolve to a public address".to_owned(),
+            )
+            .into(),
+
+            Self::TooLarge => BasicError::new(http::StatusCode::PAYLOAD_TOO_LARGE).into(),
+
+            Self::UnsupportedMediaType => BasicError::with_message(
+                http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Url doesn't point to a supported image".to_owned(),
+            )
+            .into(),
+
+            Self::Rejected => BasicError::with_message(
+                http::StatusCode::UNPROCESSABLE_ENTITY,
+                "Media was rejected by moderation".to_owned(),
+            )
+            .into(),
+
+            Self::TooManyRedirects => BasicError::with_message(
+                http::StatusCode::BAD_GATEWAY,
+                "Too many redirects".to_owned(),
+            )
+            .into(),
+
+            Self::Upstream(message) => {
+                BasicError::with_message(http::StatusCode::BAD_GATEWAY, message).into()
+            }
+
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    code = 401,
+    description = "Unauthorized: The media isn't public and no credentials were provided",
+    code = 403,
+    description = "Forbidden: The media is premium and the user doesn't have access to premium media",
+    code = 404,
+    description = "Not Found: The media doesn't exist (or belongs to another user)",
+    code = 500,
+    code = 501
+)]
+#[derive(Debug)]
+pub enum MediaFile {
+    Unauthorized,
+    Forbidden,
+    ResourceNotFound,
+    DisabledService(ServiceKind),
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for MediaFile {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for MediaFile {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::Unauthorized => BasicError::new(http::StatusCode::UNAUTHORIZED).into(),
+            Self::Forbidden => BasicError::with_message(
+                http::StatusCode::FORBIDDEN,
+                "Premium media requires the `PremiumMedia` scope".to_owned(),
+            )
+            .into(),
+            Self::ResourceNotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Resource Not Found".to_owned(),
+            )
+            .into(),
+            Self::DisabledService(s) => s.into(),
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
+
+#[api_v2_errors(
+    code = 400,
+    description = "Bad Request: The media can't be uploaded directly OR The file is too large OR "
+    "The parts don't match the uploaded ones",
+    code = 401,
+    code = 403,
+    code = 404,
+    description = "Not Found: Resource Not Found",
+    code = 409,
+    description = "Conflict: The upload has already been completed",
+    code = 410,
+    description = "Gone: The upload session has expired",
+    code = 500,
+    code = 501
+)]
+#[derive(Debug)]
+pub enum UploadSession {
+    BadRequest(&'static str),
+    Forbidden,
+    ResourceNotFound,
+    Completed,
+    Expired,
+    DisabledService(ServiceKind),
+    InternalServerError(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for UploadSession {
+    fn from(e: T) -> Self {
+        Self::InternalServerError(e.into())
+    }
+}
+
+impl Into<actix_web::Error> for UploadSession {
+    fn into(self) -> actix_web::Error {
+        match self {
+            Self::BadRequest(message) => {
+                BasicError::with_message(http::StatusCode::BAD_REQUEST, message.to_owned()).into()
+            }
+            Self::Forbidden => BasicError::new(http::StatusCode::FORBIDDEN).into(),
+            Self::ResourceNotFound => BasicError::with_message(
+                http::StatusCode::NOT_FOUND,
+                "Resource Not Found".to_owned(),
+            )
+            .into(),
+            Self::Completed => BasicError::with_message(
+                http::StatusCode::CONFLICT,
+                "Upload has already been completed".to_owned(),
+            )
+            .into(),
+            Self::Expired => BasicError::with_message(
+                http::StatusCode::GONE,
+                "Upload session has expired".to_owned(),
+            )
+            .into(),
+            Self::DisabledService(s) => s.into(),
+            Self::InternalServerError(e) => crate::error::ise(e),
+        }
+    }
+}
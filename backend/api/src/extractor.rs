@@ -12,7 +12,7 @@ use actix_web::{
 };
 use config::{COOKIE_DOMAIN, MAX_SIGNIN_COOKIE_DURATION};
 use core::settings::RuntimeSettings;
-use futures::future::{self, FutureExt};
+use futures::future::{self, BoxFuture, FutureExt};
 use http::StatusCode;
 use jsonwebtoken as jwt;
 use jwt::EncodingKey;
@@ -27,26 +27,42 @@ use sqlx::postgres::PgPool;
 use std::{marker::PhantomData, sync::Arc};
 use uuid::Uuid;
 
-fn try_insecure_decode(token: &str) -> Option<FirebaseId> {
+pub fn try_insecure_decode(token: &str) -> Option<FirebaseId> {
     let claims: jwkkeys::Claims = jsonwebtoken::dangerous_insecure_decode(token).ok()?.claims;
     let user_id = claims.sub;
     Some(FirebaseId(user_id))
 }
 
+/// A user's identity, as asserted by one of the supported identity providers.
+pub enum Identity {
+    /// The user authenticated with firebase.
+    Firebase(FirebaseId),
+
+    /// The user authenticated with the built in (email + password) provider.
+    ///
+    /// Note: the password *hasn't* been checked yet, since what that means depends on whether the user is signing in or registering.
+    Basic(BasicCredentials),
+}
+
 #[derive(Apiv2Security)]
 #[openapi(
     apiKey,
-    alias = "firebaseApiKey",
+    alias = "identityApiKey",
     in = "header",
     name = "Authorization",
-    description = "Use format 'Bearer TOKEN'"
+    description = "Use format 'Bearer TOKEN' (firebase) or 'Basic BASE64(EMAIL:PASSWORD)'"
 )]
-pub struct FirebaseUser {
-    pub id: FirebaseId,
+pub struct IdentityUser {
+    pub identity: Identity,
 }
 
 pub struct FirebaseId(pub String);
 
+pub struct BasicCredentials {
+    pub email: String,
+    pub password: String,
+}
+
 // stolen from the stdlib and modified (to work on stable)
 fn split_once(s: &'_ str, delimiter: char) -> Option<(&'_ str, &'_ str)> {
     let start = s.find(delimiter)?;
@@ -54,15 +70,81 @@ fn split_once(s: &'_ str, delimiter: char) -> Option<(&'_ str, &'_ str)> {
     Some((&s[..start], &s[end..]))
 }
 
-fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+fn authorization(headers: &HeaderMap) -> Option<(&str, &str)> {
     let header: &HeaderValue = headers.get(header::AUTHORIZATION)?;
 
     split_once(header.to_str().ok()?, ' ')
-        .filter(|(kind, _)| kind.eq_ignore_ascii_case("bearer"))
-        .map(|(_, token)| token)
 }
 
-impl FromRequest for FirebaseUser {
+fn decode_basic_credentials(credentials: &str) -> Option<BasicCredentials> {
+    let decoded = base64::decode(credentials).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+
+    let (email, password) = split_once(&decoded, ':')?;
+
+    Some(BasicCredentials {
+        email: email.to_owned(),
+        password: password.to_owned(),
+    })
+}
+
+fn verify_firebase(
+    req: &actix_web::HttpRequest,
+    token: String,
+) -> ReadyOrNot<'static, Result<IdentityUser, BasicError>> {
+    let settings: &Data<RuntimeSettings> = req.app_data().unwrap();
+    let settings = settings.clone();
+
+    let invalid_token = || {
+        BasicError::with_message(
+            StatusCode::UNAUTHORIZED,
+            "Unauthorized: Invalid Token".to_owned(),
+        )
+    };
+
+    // HACK for testing.
+    if settings.firebase_assume_valid() {
+        return futures::future::ready(
+            try_insecure_decode(&token)
+                .map(|id| IdentityUser {
+                    identity: Identity::Firebase(id),
+                })
+                .ok_or_else(invalid_token),
+        )
+        .into();
+    }
+
+    let jwk_verifier = match req
+        .app_data::<Option<Arc<JwkVerifier>>>()
+        .cloned()
+        .flatten()
+    {
+        Some(jwk_verifier) => jwk_verifier,
+        None => {
+            return futures::future::err(BasicError::with_message(
+                StatusCode::NOT_IMPLEMENTED,
+                "Firebase authentication is disabled".to_owned(),
+            ))
+            .into()
+        }
+    };
+
+    async move {
+        // todo: more specific errors.
+        let id = jwk_verifier
+            .verify(&token, 3)
+            .await
+            .map_err(|_| invalid_token())?;
+
+        Ok(IdentityUser {
+            identity: Identity::Firebase(id),
+        })
+    }
+    .boxed()
+    .into()
+}
+
+impl FromRequest for IdentityUser {
     type Error = BasicError;
     type Future = ReadyOrNot<'static, Result<Self, Self::Error>>;
     type Config = ();
@@ -70,50 +152,42 @@ impl FromRequest for FirebaseUser {
         req: &actix_web::HttpRequest,
         _payload: &mut actix_web::dev::Payload,
     ) -> Self::Future {
-        let settings: &Data<RuntimeSettings> = req.app_data().unwrap();
-        let settings = settings.clone();
-        let jwk_verifier: &Arc<JwkVerifier> = req.app_data().unwrap();
-        let jwk_verifier = jwk_verifier.clone();
-
-        // this whole dance is to avoid cloning the headers.
-        let token = match bearer_token(req.headers()) {
-            Some(token) => token.to_owned(),
+        let (kind, credentials) = match authorization(req.headers()) {
+            Some(it) => it,
             None => {
                 return futures::future::err(BasicError::with_message(
                     StatusCode::UNAUTHORIZED,
-                    "Unauthorized: Missing Bearer Token".to_owned(),
+                    "Unauthorized: Missing Authorization Header".to_owned(),
                 ))
                 .into()
             }
         };
 
-        let invalid_token = || {
-            BasicError::with_message(
-                StatusCode::UNAUTHORIZED,
-                "Unauthorized: Invalid Token".to_owned(),
-            )
-        };
+        if kind.eq_ignore_ascii_case("bearer") {
+            // this whole dance is to avoid cloning the headers.
+            return verify_firebase(req, credentials.to_owned());
+        }
 
-        // HACK for testing.
-        if settings.firebase_assume_valid() {
+        if kind.eq_ignore_ascii_case("basic") {
             return futures::future::ready(
-                try_insecure_decode(&token)
-                    .map(|id| Self { id })
-                    .ok_or_else(invalid_token),
+                decode_basic_credentials(credentials)
+                    .map(|it| Self {
+                        identity: Identity::Basic(it),
+                    })
+                    .ok_or_else(|| {
+                        BasicError::with_message(
+                            StatusCode::UNAUTHORIZED,
+                            "Unauthorized: Invalid Basic Credentials".to_owned(),
+                        )
+                    }),
             )
             .into();
         }
 
-        async move {
-            // todo: more specific errors.
-            let id = jwk_verifier
-                .verify(&token, 3)
-                .await
-                .map_err(|_| invalid_token())?;
-
-            Ok(Self { id })
-        }
-        .boxed()
+        futures::future::err(BasicError::with_message(
+            StatusCode::UNAUTHORIZED,
+            "Unauthorized: Unsupported Authorization Scheme".to_owned(),
+        ))
         .into()
     }
 }
@@ -122,6 +196,50 @@ fn csrf_header(headers: &HeaderMap) -> Option<&str> {
     headers.get(CSRF_HEADER_NAME)?.to_str().ok()
 }
 
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    authorization(headers)
+        .filter(|(kind, _)| kind.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token)
+}
+
+/// Authenticates a request via a personal access token (`Authorization: Bearer TOKEN`), these don't need a CSRF header.
+///
+/// Returns `None` if there's no bearer token to check.
+/// If `scope` is provided, the token must be allowed to use it.
+fn personal_token_claims(
+    req: &actix_web::HttpRequest,
+    scope: Option<UserScope>,
+) -> Option<BoxFuture<'static, Result<AuthClaims, actix_web::Error>>> {
+    let token_hash = crate::password::hash_token(bearer_token(req.headers())?);
+    let db: &Data<PgPool> = req.app_data().expect("Missing `Data` for db?");
+    let db = db.as_ref().clone();
+
+    let future = async move {
+        let (user_id, has_scope) =
+            crate::db::user::token_user(&db, &token_hash, scope.map(|it| it as i16))
+                .await
+                .map_err(Into::into)
+                .map_err(crate::error::ise)?
+                .ok_or_else(|| {
+                    BasicError::with_message(
+                        StatusCode::UNAUTHORIZED,
+                        "Unauthorized: Invalid Token".to_owned(),
+                    )
+                })?;
+
+        if !has_scope {
+            return Err(BasicError::new(StatusCode::FORBIDDEN).into());
+        }
+
+        Ok(AuthClaims {
+            id: user_id,
+            csrf: None,
+        })
+    };
+
+    Some(future.boxed())
+}
+
 fn check_cookie_csrf<'a>(
     cookie: Option<Cookie<'a>>,
     csrf: Option<&'a str>,
@@ -157,20 +275,28 @@ fn check_cookie_csrf<'a>(
 pub struct WrapAuthClaimsNoDb(pub AuthClaims);
 
 impl FromRequest for WrapAuthClaimsNoDb {
-    type Error = BasicError;
-    type Future = future::Ready<Result<Self, Self::Error>>;
+    type Error = actix_web::Error;
+    type Future = ReadyOrNot<'static, Result<Self, Self::Error>>;
     type Config = ();
     fn from_request(
         req: &actix_web::HttpRequest,
         _payload: &mut actix_web::dev::Payload,
     ) -> Self::Future {
         let cookie = req.cookie(JWT_COOKIE_NAME);
+
+        // personal access tokens are the one case where we *do* need the db.
+        if cookie.is_none() {
+            if let Some(claims) = personal_token_claims(req, None) {
+                return claims.map(|it| it.map(Self)).boxed().into();
+            }
+        }
+
         let csrf = csrf_header(req.headers());
         let settings: &Data<RuntimeSettings> = req.app_data().expect("Settings??");
 
         let (cookie, csrf) = match check_cookie_csrf(cookie, csrf) {
             Ok((cookie, csrf)) => (cookie, csrf),
-            Err(e) => return futures::future::err(e),
+            Err(e) => return futures::future::err(e.into()).into(),
         };
 
         future::ready(
@@ -189,8 +315,10 @@ impl FromRequest for WrapAuthClaimsNoDb {
                         )
                     })
                 })
-                .map(Self),
+                .map(Self)
+                .map_err(Into::into),
         )
+        .into()
     }
 }
 
@@ -253,6 +381,15 @@ impl Scope for ScopeManageAnimation {
     }
 }
 
+#[derive(Apiv2Schema)]
+pub struct ScopeManageMetadata;
+
+impl Scope for ScopeManageMetadata {
+    fn scope() -> UserScope {
+        UserScope::ManageMetadata
+    }
+}
+
 #[derive(Apiv2Security)]
 #[openapi(
     apiKey,
@@ -276,6 +413,21 @@ impl<S: Scope> FromRequest for AuthUserWithScope<S> {
         _payload: &mut actix_web::dev::Payload,
     ) -> Self::Future {
         let cookie = req.cookie(JWT_COOKIE_NAME);
+
+        if cookie.is_none() {
+            if let Some(claims) = personal_token_claims(req, Some(S::scope())) {
+                return claims
+                    .map(|it| {
+                        it.map(|claims| Self {
+                            claims,
+                            _phantom: PhantomData,
+                        })
+                    })
+                    .boxed()
+                    .into();
+            }
+        }
+
         let csrf = csrf_header(req.headers());
         let settings: &Data<RuntimeSettings> = req.app_data().expect("Settings??");
         let db: &Data<PgPool> = req.app_data().expect("Missing `Data` for db?");
@@ -362,6 +514,47 @@ impl FromRequest for WrapAuthClaimsCookieDbNoCsrf {
     }
 }
 
+/// Optional authentication for read-only routes that browsers request directly (for instance via `<img src>`),
+/// where there's no way to attach a CSRF header.
+///
+/// Requests without credentials (or with an invalid cookie) are treated as anonymous,
+/// but an invalid personal access token is still an error.
+#[derive(Apiv2Security)]
+#[openapi(
+    apiKey,
+    in = "header",
+    name = "Authorization",
+    description = "Use format 'Bearer TOKEN'"
+)]
+#[repr(transparent)]
+pub struct MaybeAuthClaimsNoCsrf(pub Option<AuthClaims>);
+
+impl FromRequest for MaybeAuthClaimsNoCsrf {
+    type Error = actix_web::Error;
+    type Future = ReadyOrNot<'static, Result<Self, Self::Error>>;
+    type Config = ();
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let cookie = match req.cookie(JWT_COOKIE_NAME) {
+            Some(cookie) => cookie,
+            None => {
+                return match personal_token_claims(req, None) {
+                    Some(claims) => claims.map(|it| it.map(|it| Self(Some(it)))).boxed().into(),
+                    None => future::ok(Self(None)).into(),
+                }
+            }
+        };
+
+        let settings: &Data<RuntimeSettings> = req.app_data().expect("Settings??");
+
+        let claims = crate::jwt::get_claims(cookie.value(), &settings.jwt_decoding_key()).ok();
+
+        future::ok(Self(claims)).into()
+    }
+}
+
 pub fn reply_signin_auth(
     user_id: Uuid,
     jwt_encoding_key: &EncodingKey,
@@ -1,14 +1,16 @@
 mod cors;
 mod endpoints;
+mod pagination;
+mod rate_limit;
 
-use crate::{error::BasicError, jwkkeys::JwkVerifier, s3};
+use crate::{error::BasicError, jwkkeys::JwkVerifier, mail::Mailer, moderation::Moderator, s3};
 use actix_service::Service;
 use actix_web::dev::{MessageBody, ServiceRequest, ServiceResponse};
 use actix_web::HttpResponse;
 use config::JSON_BODY_LIMIT;
 use core::{
     http::{get_addr, get_tcp_fd},
-    settings::RuntimeSettings,
+    settings::{RateLimitSettings, RuntimeSettings},
 };
 use futures::Future;
 use paperclip::actix::OpenApiExt;
@@ -57,22 +59,38 @@ where
 pub async fn run(
     pool: PgPool,
     settings: RuntimeSettings,
-    jwk_verifier: Arc<JwkVerifier>,
+    jwk_verifier: Option<Arc<JwkVerifier>>,
     s3: s3::Client,
+    moderator: Moderator,
+    mailer: Mailer,
     algolia: crate::algolia::Client,
+    rate_limit_settings: RateLimitSettings,
 ) -> anyhow::Result<()> {
     let local_insecure = settings.is_local();
     let api_port = settings.api_port;
 
+    // shared between workers, otherwise each worker would get its own in memory buckets.
+    let rate_limiter = Arc::new(rate_limit::RateLimiter::new(
+        rate_limit_settings,
+        pool.clone(),
+    ));
+
+    let search = crate::search::Client::new(algolia.clone(), pool.clone());
+
     let server = actix_web::HttpServer::new(move || {
         actix_web::App::new()
             .data(pool.clone())
             .data(settings.clone())
             .data(s3.clone())
+            .data(moderator.clone())
+            .data(mailer.clone())
             .data(algolia.clone())
+            .data(search.clone())
             .app_data(jwk_verifier.clone())
             .wrap(actix_web::middleware::Logger::default())
             .wrap_fn(log_ise)
+            .wrap_fn(crate::language::vary)
+            .wrap(rate_limit::RateLimit(rate_limiter.clone()))
             .wrap(cors::get(local_insecure))
             .service(get_spec)
             .app_data(
@@ -99,6 +117,10 @@ pub async fn run(
             .configure(endpoints::animation::configure)
             .configure(endpoints::search::configure)
             .configure(endpoints::media::configure)
+            .configure(endpoints::organization::configure)
+            .configure(endpoints::classroom::configure)
+            .configure(endpoints::session::configure)
+            .configure(endpoints::upload::configure)
             .with_json_spec_at("/spec.json")
             .build()
     });
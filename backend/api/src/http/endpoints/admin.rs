@@ -9,17 +9,23 @@ use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
 use paperclip::actix::{
     api_v2_operation,
-    web::{Data, HttpRequest, Path, ServiceConfig},
+    web::{Data, HttpRequest, Path, Query, ServiceConfig},
     NoContent,
 };
 use shared::{
     api::{endpoints::admin, ApiEndpoint},
     domain::{
-        admin::{AdminListMediaResponse, AdminMediaItem},
+        admin::{
+            AdminListMediaQuery, AdminListMediaResponse, AdminMediaItem,
+            AdminMergeCategoryResponse, AdminModerationListQuery, AdminModerationListResponse,
+            AdminReindexResponse, AdminSearchSyncResponse, AdminSimilarImage,
+            AdminSimilarImagesQuery, AdminSimilarImagesResponse,
+        },
         auth::SigninSuccess,
+        category::CategoryId,
         image::ImageKind,
     },
-    media::{FileKind, MediaLibrary, PngImageFile},
+    media::{FileKind, MediaLibrary, ModerationStatus, PngImageFile},
 };
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -28,9 +34,12 @@ use crate::image_ops::MediaKind;
 use crate::{
     db,
     error::{self, ServiceKind},
-    extractor::{reply_signin_auth, AuthUserWithScope, ScopeAdmin},
+    extractor::{
+        reply_signin_auth, AuthUserWithScope, ScopeAdmin, ScopeManageCategory, ScopeManageImage,
+    },
+    http::pagination,
     image_ops::regenerate_images,
-    s3,
+    moderation, s3,
 };
 
 /// Impersonate another user
@@ -156,9 +165,17 @@ async fn refresh_image_files(
         _ => {}
     }
 
+    // quarantined images stay quarantined.
+    let location = moderation::location(&db, library, id).await?;
+
     // handle the 404 here (image isn't uploaded)
     let original = s3
-        .download_media_file(library, id, FileKind::ImagePng(PngImageFile::Original))
+        .download_media_file(
+            location,
+            library,
+            id,
+            FileKind::ImagePng(PngImageFile::Original),
+        )
         .await?
         .ok_or(error::Refresh::DisabledService(ServiceKind::S3))?
         .ok_or(error::Refresh::ResourceNotFound)?;
@@ -174,7 +191,7 @@ async fn refresh_image_files(
         BlockingError::Error(e) => e,
     })?;
 
-    s3.upload_png_images_resized_thumb(library, id, resized, thumbnail)
+    s3.upload_png_images_resized_thumb(location, library, id, resized, thumbnail)
         .await?;
 
     match library {
@@ -206,35 +223,295 @@ async fn refresh_image_files(
     Ok(NoContent)
 }
 
+/// List media, newest first.
 #[api_v2_operation]
 async fn list_media(
     _auth: AuthUserWithScope<ScopeAdmin>,
     db: Data<PgPool>,
-) -> actix_web::Result<Json<AdminListMediaResponse>, error::Server> {
-    let items: Vec<AdminMediaItem> = sqlx::query_file!("query/list_media.sql")
-        .fetch(db.as_ref())
-        .map_ok(|row| AdminMediaItem {
-            id: row.id,
-            kind: dbg!(row.kind).to_shared(),
-            created_at: row.created_at,
-            updated_at: row.updated_at,
-            uploaded_at: row.uploaded_at.clone(),
-            file_etag: row.uploaded_at.map(|uploaded_at| {
-                EntityTag::strong(uploaded_at.timestamp_nanos().to_string()).to_string()
-            }),
-            library: row.library,
-        })
-        .try_collect()
-        .await?;
+    query: Option<Query<<admin::ListMedia as ApiEndpoint>::Req>>,
+) -> actix_web::Result<Json<<admin::ListMedia as ApiEndpoint>::Res>, error::ListMedia> {
+    let query = query.map_or_else(AdminListMediaQuery::default, Query::into_inner);
+
+    let (cursor_created_at, cursor_id) = match query.cursor.as_deref() {
+        Some(cursor) => {
+            let (created_at, id) =
+                pagination::decode_cursor(cursor).ok_or(error::ListMedia::InvalidCursor)?;
+            (Some(created_at), Some(id))
+        }
+        None => (None, None),
+    };
+
+    let limit = pagination::limit(query.limit);
+
+    let kinds = query.kind.map(MediaKind::for_filter);
+
+    // fetch one extra item to know if there's another page.
+    let mut items: Vec<AdminMediaItem> = sqlx::query_file!(
+        "query/list_media.sql",
+        query.library.map(|it| it as i16),
+        kinds.as_deref(),
+        query.uploaded,
+        query.created_after,
+        query.created_before,
+        cursor_created_at,
+        cursor_id,
+        i64::from(limit) + 1
+    )
+    .fetch(db.as_ref())
+    .map_ok(|row| AdminMediaItem {
+        id: row.id,
+        kind: row.kind.to_shared(),
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+        uploaded_at: row.uploaded_at.clone(),
+        file_etag: row.uploaded_at.map(|uploaded_at| {
+            EntityTag::strong(uploaded_at.timestamp_nanos().to_string()).to_string()
+        }),
+        library: row.library,
+    })
+    .try_collect()
+    .await?;
+
+    let next_cursor = if items.len() > limit as usize {
+        items.truncate(limit as usize);
+        items
+            .last()
+            .map(|it| pagination::encode_cursor(it.created_at, it.id))
+    } else {
+        None
+    };
+
+    Ok(Json(AdminListMediaResponse {
+        media: items,
+        next_cursor,
+    }))
+}
+
+/// Default hamming distance for finding similar images.
+const DEFAULT_SIMILAR_DISTANCE: u32 = 4;
+
+/// Find images that look like the given image.
+#[api_v2_operation]
+async fn similar_images(
+    _auth: AuthUserWithScope<ScopeAdmin>,
+    db: Data<PgPool>,
+    Path((library, id)): Path<(MediaLibrary, Uuid)>,
+    query: Option<Query<<admin::SimilarImages as ApiEndpoint>::Req>>,
+) -> actix_web::Result<Json<<admin::SimilarImages as ApiEndpoint>::Res>, error::NotFound> {
+    let query = query.map_or_else(AdminSimilarImagesQuery::default, Query::into_inner);
+
+    let phash = db::phash::get(&db, library, id)
+        .await?
+        .ok_or(error::NotFound::ResourceNotFound)?;
+
+    let limit = pagination::limit(query.limit);
+
+    let images = db::phash::similar(
+        &db,
+        phash,
+        query.max_distance.unwrap_or(DEFAULT_SIMILAR_DISTANCE),
+        query.library,
+        Some((library, id)),
+        i64::from(limit),
+    )
+    .await?
+    .into_iter()
+    .map(|it| AdminSimilarImage {
+        id: it.media_id,
+        library: it.library,
+        distance: it.distance as u32,
+    })
+    .collect();
+
+    Ok(Json(AdminSimilarImagesResponse { images }))
+}
+
+/// List media that has been through moderation, newest first.
+#[api_v2_operation]
+async fn list_moderation(
+    _auth: AuthUserWithScope<ScopeAdmin>,
+    db: Data<PgPool>,
+    query: Option<Query<<admin::ListModeration as ApiEndpoint>::Req>>,
+) -> actix_web::Result<Json<<admin::ListModeration as ApiEndpoint>::Res>, error::ListMedia> {
+    let query = query.map_or_else(AdminModerationListQuery::default, Query::into_inner);
+
+    let cursor = match query.cursor.as_deref() {
+        Some(cursor) => {
+            Some(pagination::decode_cursor(cursor).ok_or(error::ListMedia::InvalidCursor)?)
+        }
+        None => None,
+    };
+
+    let limit = pagination::limit(query.limit);
+
+    // fetch one extra item to know if there's another page.
+    let mut items = db::moderation::list(
+        &db,
+        query.status,
+        query.library,
+        cursor,
+        i64::from(limit) + 1,
+    )
+    .await?;
+
+    let next_cursor = if items.len() > limit as usize {
+        items.truncate(limit as usize);
+        items
+            .last()
+            .map(|it| pagination::encode_cursor(it.created_at, it.id))
+    } else {
+        None
+    };
+
+    Ok(Json(AdminModerationListResponse { items, next_cursor }))
+}
+
+/// Approve media, taking it out of quarantine.
+#[api_v2_operation]
+async fn approve_media(
+    auth: AuthUserWithScope<ScopeAdmin>,
+    db: Data<PgPool>,
+    s3: Data<s3::Client>,
+    Path((library, id)): Path<(MediaLibrary, Uuid)>,
+) -> actix_web::Result<NoContent, error::NotFound> {
+    let found = db::moderation::review(
+        &db,
+        library,
+        id,
+        ModerationStatus::Approved,
+        None,
+        auth.claims.id,
+    )
+    .await?;
+
+    if !found {
+        return Err(error::NotFound::ResourceNotFound);
+    }
+
+    moderation::move_files(&s3, library, id, ModerationStatus::Approved).await?;
+
+    Ok(NoContent)
+}
+
+/// Reject media, keeping it hidden.
+#[api_v2_operation]
+async fn reject_media(
+    auth: AuthUserWithScope<ScopeAdmin>,
+    db: Data<PgPool>,
+    s3: Data<s3::Client>,
+    Path((library, id)): Path<(MediaLibrary, Uuid)>,
+    req: Option<Json<<admin::RejectMedia as ApiEndpoint>::Req>>,
+) -> actix_web::Result<NoContent, error::NotFound> {
+    let req = req.map_or_else(Default::default, Json::into_inner);
+
+    let found = db::moderation::review(
+        &db,
+        library,
+        id,
+        ModerationStatus::Rejected,
+        req.reason.as_deref(),
+        auth.claims.id,
+    )
+    .await?;
 
-    dbg!(items.len());
+    if !found {
+        return Err(error::NotFound::ResourceNotFound);
+    }
+
+    // media that was approved before might be public.
+    moderation::move_files(&s3, library, id, ModerationStatus::Rejected).await?;
 
-    dbg!(items
-        .iter()
-        .filter(|it| matches!(it.kind, shared::media::MediaKind::Image(ImageKind::Canvas)))
-        .count());
+    Ok(NoContent)
+}
 
-    Ok(Json(AdminListMediaResponse { media: items }))
+/// Get how far behind the search index is, and which changes to it have failed.
+#[api_v2_operation]
+async fn search_sync_status(
+    _auth: AuthUserWithScope<ScopeAdmin>,
+    db: Data<PgPool>,
+    algolia: Data<crate::algolia::Client>,
+) -> actix_web::Result<Json<<admin::SearchSyncStatus as ApiEndpoint>::Res>, error::Server> {
+    let status = db::search_index::status(&db).await?;
+    let failed = db::search_index::failed(&db).await?;
+
+    let lag_seconds = status
+        .oldest_pending_at
+        .map_or(0, |it| (Utc::now() - it).num_seconds().max(0) as u64);
+
+    Ok(Json(AdminSearchSyncResponse {
+        indexing_enabled: algolia.can_index(),
+        pending_count: status.pending_count,
+        failed_count: status.failed_count,
+        oldest_pending_at: status.oldest_pending_at,
+        lag_seconds,
+        failed,
+    }))
+}
+
+/// Queue every searchable object to be reindexed.
+#[api_v2_operation]
+async fn reindex(
+    _auth: AuthUserWithScope<ScopeAdmin>,
+    db: Data<PgPool>,
+) -> actix_web::Result<Json<<admin::Reindex as ApiEndpoint>::Res>, error::Server> {
+    let mut txn = db.begin().await?;
+
+    let queued = db::search_index::reindex_all(&mut txn).await?;
+
+    txn.commit().await?;
+
+    Ok(Json(AdminReindexResponse { queued }))
+}
+
+/// Merge a category into another one.
+#[api_v2_operation]
+async fn merge_category(
+    _auth: AuthUserWithScope<ScopeManageCategory>,
+    db: Data<PgPool>,
+    path: Path<CategoryId>,
+    req: Json<<admin::MergeCategory as ApiEndpoint>::Req>,
+) -> actix_web::Result<Json<<admin::MergeCategory as ApiEndpoint>::Res>, error::CategoryMerge> {
+    let merged = db::category::merge(&db, path.into_inner(), req.into_inner().into).await?;
+
+    Ok(Json(AdminMergeCategoryResponse {
+        image_count: merged.image_count,
+        jig_count: merged.jig_count,
+    }))
+}
+
+/// Maximum amount of images that can be changed by a single bulk request.
+const MAX_BULK_IMAGES: usize = 1000;
+
+/// Add and remove categories, styles and age ranges on many images at once.
+#[api_v2_operation]
+async fn bulk_tag_images(
+    _auth: AuthUserWithScope<ScopeManageImage>,
+    db: Data<PgPool>,
+    req: Json<<admin::BulkTagImages as ApiEndpoint>::Req>,
+) -> actix_web::Result<NoContent, error::BulkTagImages> {
+    let req = req.into_inner();
+
+    if req.image_ids.len() > MAX_BULK_IMAGES {
+        return Err(error::BulkTagImages::TooManyImages(MAX_BULK_IMAGES));
+    }
+
+    let found = db::image::bulk_update_metadata(
+        &db,
+        &req.image_ids,
+        &req.add_categories,
+        &req.remove_categories,
+        &req.add_styles,
+        &req.remove_styles,
+        &req.add_age_ranges,
+        &req.remove_age_ranges,
+    )
+    .await
+    .map_err(db::meta::handle_metadata_err)?;
+
+    match found {
+        true => Ok(NoContent),
+        false => Err(error::BulkTagImages::ImageNotFound),
+    }
 }
 
 pub fn configure(cfg: &mut ServiceConfig<'_>) {
@@ -249,5 +526,39 @@ pub fn configure(cfg: &mut ServiceConfig<'_>) {
     .route(
         admin::ListMedia::PATH,
         admin::ListMedia::METHOD.route().to(list_media),
+    )
+    .route(
+        admin::SimilarImages::PATH,
+        admin::SimilarImages::METHOD.route().to(similar_images),
+    )
+    .route(
+        admin::ListModeration::PATH,
+        admin::ListModeration::METHOD.route().to(list_moderation),
+    )
+    .route(
+        admin::ApproveMedia::PATH,
+        admin::ApproveMedia::METHOD.route().to(approve_media),
+    )
+    .route(
+        admin::RejectMedia::PATH,
+        admin::RejectMedia::METHOD.route().to(reject_media),
+    )
+    .route(
+        admin::SearchSyncStatus::PATH,
+        admin::SearchSyncStatus::METHOD
+            .route()
+            .to(search_sync_status),
+    )
+    .route(
+        admin::Reindex::PATH,
+        admin::Reindex::METHOD.route().to(reindex),
+    )
+    .route(
+        admin::MergeCategory::PATH,
+        admin::MergeCategory::METHOD.route().to(merge_category),
+    )
+    .route(
+        admin::BulkTagImages::PATH,
+        admin::BulkTagImages::METHOD.route().to(bulk_tag_images),
     );
 }
@@ -17,7 +17,10 @@ use sqlx::{postgres::PgDatabaseError, PgPool};
 use crate::{
     db, error,
     extractor::{AuthUserWithScope, ScopeManageAnimation, WrapAuthClaimsNoDb},
-    s3,
+    fetch,
+    jobs::JobKind,
+    language,
+    s3::{self, Location},
 };
 
 fn check_conflict_delete(err: sqlx::Error) -> error::Delete {
@@ -43,14 +46,17 @@ async fn delete(
         .map_err(check_conflict_delete)?;
 
     if let Some(kind) = kind {
-        let file = match kind {
-            AnimationKind::Gif => FileKind::AnimationGif,
-            // todo:
-            _ => return Err(anyhow::anyhow!("Unsupported animation kind").into()),
-        };
+        s3.delete_media(MediaLibrary::Global, FileKind::AnimationGif, animation.0)
+            .await;
 
-        s3.delete_media(MediaLibrary::Global, file, animation.0)
+        if let AnimationKind::Spritesheet = kind {
+            s3.delete_media(
+                MediaLibrary::Global,
+                FileKind::AnimationSpritesheet,
+                animation.0,
+            )
             .await;
+        }
     }
 
     Ok(NoContent)
@@ -65,6 +71,15 @@ async fn create(
 ) -> Result<CreatedJson<<animation::Create as ApiEndpoint>::Res>, error::CreateWithMetadata> {
     let req = req.into_inner();
 
+    let alt_text = language::normalize_alt_text(req.alt_text)
+        .map_err(error::CreateWithMetadata::BadRequest)?;
+
+    if !req.source_url.as_deref().map_or(true, fetch::is_web_url) {
+        return Err(error::CreateWithMetadata::BadRequest(
+            "Invalid source url".to_owned(),
+        ));
+    }
+
     let mut txn = db.begin().await?;
     let id = db::animation::create(
         &mut txn,
@@ -74,9 +89,16 @@ async fn create(
         req.is_looping,
         req.publish_at.map(DateTime::<Utc>::from),
         req.variant,
+        req.attribution.as_deref(),
+        req.license,
+        req.source_url.as_deref(),
     )
     .await?;
 
+    if !alt_text.is_empty() {
+        db::animation::set_alt_text(&mut txn, id, &alt_text).await?;
+    }
+
     // todo: have these exist
     // db::animation::update_metadata(
     //     &mut txn,
@@ -105,40 +127,36 @@ async fn upload(
 ) -> Result<NoContent, error::Upload> {
     let mut txn = db.begin().await?;
 
-    let kind = sqlx::query!(
-        r#"select variant as "kind: AnimationKind" from animation where id = $1 for update"#,
+    let is_premium = sqlx::query!(
+        r#"select is_premium from animation where id = $1 for update"#,
         id.0
     )
     .fetch_optional(&mut txn)
     .await?
     .ok_or(error::Upload::ResourceNotFound)?
-    .kind;
+    .is_premium;
 
-    if !matches!(kind, AnimationKind::Gif) {
-        return Err(anyhow::anyhow!("Unimplemented Animation Kind: {:?}", kind).into());
+    // spritesheets are generated from gifs as well (in the background, along with validation),
+    // but at least make sure that it's a gif.
+    if !matches!(image::guess_format(&bytes), Ok(image::ImageFormat::Gif)) {
+        return Err(error::Upload::InvalidMedia);
     }
 
-    let validated: Bytes = actix_web::web::block(move || {
-        let _original = image::load_from_memory_with_format(&bytes, image::ImageFormat::Gif)
-            .or(Err(error::Upload::InvalidMedia));
-        Ok(bytes)
-    })
-    .await
-    .map_err(error::Upload::blocking_error)?;
-
-    s3.upload_media(
-        validated.to_vec(),
+    s3.upload_media_to(
+        Location::visible(MediaLibrary::Global, is_premium),
+        bytes.to_vec(),
         MediaLibrary::Global,
         id.0,
         FileKind::AnimationGif,
     )
     .await?;
 
-    sqlx::query!(
-        "update animation set uploaded_at = now() where id = $1",
-        id.0
+    db::job::enqueue(
+        &mut txn,
+        JobKind::ProcessAnimation,
+        MediaLibrary::Global,
+        id.0,
     )
-    .execute(&mut txn)
     .await?;
 
     txn.commit().await?;
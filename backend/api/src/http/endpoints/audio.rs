@@ -14,7 +14,12 @@ fn check_conflict_delete(err: sqlx::Error) -> error::Delete {
 }
 
 pub mod user {
-    use crate::{db, error, extractor::WrapAuthClaimsNoDb, s3};
+    use crate::{
+        db, error,
+        extractor::WrapAuthClaimsNoDb,
+        jobs::JobKind,
+        s3::{self, Location},
+    };
     use futures::TryStreamExt;
     use paperclip::actix::{
         api_v2_operation,
@@ -38,10 +43,10 @@ pub mod user {
     #[api_v2_operation]
     pub(super) async fn create(
         db: Data<PgPool>,
-        _claims: WrapAuthClaimsNoDb,
+        claims: WrapAuthClaimsNoDb,
     ) -> Result<CreatedJson<<endpoints::audio::user::Create as ApiEndpoint>::Res>, error::NotFound>
     {
-        let id = db::audio::user::create(db.as_ref()).await?;
+        let id = db::audio::user::create(db.as_ref(), claims.0.id).await?;
         Ok(CreatedJson(CreateResponse { id }))
     }
 
@@ -64,26 +69,18 @@ pub mod user {
         .await?
         .ok_or(error::Upload::ResourceNotFound)?;
 
-        // todo: use the duration
-        let _duration = {
-            let bytes = bytes.clone();
-            tokio::task::spawn_blocking(move || {
-                mp3_metadata::read_from_slice(&bytes).map_err(|_it| error::Upload::InvalidMedia)
-            })
-            .await
-            .unwrap()?
-        };
-
-        s3.upload_media(bytes.to_vec(), MediaLibrary::User, id.0, FileKind::AudioMp3)
-            .await?;
-
-        sqlx::query!(
-            "update user_audio_library set uploaded_at = now() where id = $1",
-            id.0
+        // user library media is never public.
+        s3.upload_media_to(
+            Location::Private,
+            bytes.to_vec(),
+            MediaLibrary::User,
+            id.0,
+            FileKind::AudioMp3,
         )
-        .execute(&mut txn)
         .await?;
 
+        db::job::enqueue(&mut txn, JobKind::ProcessAudio, MediaLibrary::User, id.0).await?;
+
         txn.commit().await?;
 
         Ok(NoContent)
@@ -4,10 +4,11 @@ use crate::{
     extractor::AuthUserWithScope,
     extractor::ScopeManageCategory,
     extractor::WrapAuthClaimsNoDb,
+    language,
 };
 use paperclip::actix::{
     api_v2_errors, api_v2_operation,
-    web::{self, Data, Json, Query, ServiceConfig},
+    web::{self, Data, HttpRequest, Json, Query, ServiceConfig},
     NoContent,
 };
 use shared::api::endpoints::{category, ApiEndpoint};
@@ -54,22 +55,26 @@ impl Into<actix_web::Error> for CreateError {
 async fn get_categories(
     db: Data<PgPool>,
     _claims: WrapAuthClaimsNoDb,
+    http_req: HttpRequest,
     req: Option<Query<<category::Get as ApiEndpoint>::Req>>,
 ) -> actix_web::Result<Json<<category::Get as ApiEndpoint>::Res>, error::Server> {
     let req = req.map_or_else(GetCategoryRequest::default, Query::into_inner);
+    let languages = language::preferred(&http_req, req.lang.as_deref());
 
     let categories = match req.scope {
         Some(CategoryTreeScope::Decendants) if req.ids.is_empty() => {
-            db::category::get_tree(&db).await?
+            db::category::get_tree(&db, &languages, req.published_only).await?
         }
         Some(CategoryTreeScope::Ancestors) | None if req.ids.is_empty() => {
-            db::category::get_top_level(&db).await?
+            db::category::get_top_level(&db, &languages, req.published_only).await?
+        }
+        Some(CategoryTreeScope::Decendants) => {
+            db::category::get_subtree(&db, &req.ids, &languages, req.published_only).await?
         }
-        Some(CategoryTreeScope::Decendants) => db::category::get_subtree(&db, &req.ids).await?,
         Some(CategoryTreeScope::Ancestors) => {
-            db::category::get_ancestor_tree(&db, &req.ids).await?
+            db::category::get_ancestor_tree(&db, &req.ids, &languages, req.published_only).await?
         }
-        None => db::category::get_exact(&db, &req.ids).await?,
+        None => db::category::get_exact(&db, &req.ids, &languages, req.published_only).await?,
     };
 
     Ok(Json(CategoryResponse { categories }))
@@ -127,6 +132,25 @@ async fn delete_category(
     Ok(NoContent)
 }
 
+/// Replace the translations of a category's name.
+#[api_v2_operation]
+async fn set_translations(
+    db: Data<PgPool>,
+    _claims: AuthUserWithScope<ScopeManageCategory>,
+    path: web::Path<CategoryId>,
+    req: Json<<category::SetTranslations as ApiEndpoint>::Req>,
+) -> actix_web::Result<NoContent, error::SetTranslations> {
+    let translations = language::normalize_translations(req.into_inner().translations)?;
+
+    let exists = db::category::set_translations(&db, path.into_inner(), &translations).await?;
+
+    if !exists {
+        return Err(error::SetTranslations::NotFound);
+    }
+
+    Ok(NoContent)
+}
+
 pub fn configure(cfg: &mut ServiceConfig<'_>) {
     cfg.route(
         category::Get::PATH,
@@ -143,5 +167,11 @@ pub fn configure(cfg: &mut ServiceConfig<'_>) {
     .route(
         category::Delete::PATH,
         category::Delete::METHOD.route().to(delete_category),
+    )
+    .route(
+        category::SetTranslations::PATH,
+        category::SetTranslations::METHOD
+            .route()
+            .to(set_translations),
     );
 }
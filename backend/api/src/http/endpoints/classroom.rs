@@ -0,0 +1,190 @@
+use paperclip::actix::{
+    api_v2_operation,
+    web::{Data, Json, Path, ServiceConfig},
+    NoContent,
+};
+use shared::{
+    api::{endpoints::classroom, ApiEndpoint},
+    domain::{
+        classroom::{AssignedJigListResponse, ClassroomId},
+        jig::JigId,
+        organization::{OrganizationId, OrganizationRole},
+        CreateResponse,
+    },
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::{self, classroom::Access},
+    error,
+    extractor::WrapAuthClaimsNoDb,
+};
+
+/// Checks that the user can at least view the classroom, returning what they're allowed to do.
+async fn check_access(
+    db: &PgPool,
+    id: ClassroomId,
+    user_id: Uuid,
+) -> Result<Access, error::Organization> {
+    match db::classroom::access(db, id, user_id).await? {
+        None | Some(Access::Denied) => Err(error::Organization::ResourceNotFound),
+        Some(access) => Ok(access),
+    }
+}
+
+async fn check_manage(
+    db: &PgPool,
+    id: ClassroomId,
+    user_id: Uuid,
+) -> Result<(), error::Organization> {
+    match check_access(db, id, user_id).await? {
+        Access::Manage => Ok(()),
+        _ => Err(error::Organization::Forbidden),
+    }
+}
+
+/// Create a classroom.
+#[api_v2_operation]
+async fn create(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    path: Path<OrganizationId>,
+    req: Json<<classroom::Create as ApiEndpoint>::Req>,
+) -> Result<Json<<classroom::Create as ApiEndpoint>::Res>, error::Organization> {
+    let organization_id = path.into_inner();
+
+    match db::organization::role(&db, organization_id, claims.0.id).await? {
+        Some(OrganizationRole::Admin) | Some(OrganizationRole::Teacher) => {}
+        Some(OrganizationRole::Student) => return Err(error::Organization::Forbidden),
+        None => return Err(error::Organization::ResourceNotFound),
+    }
+
+    let id = db::classroom::create(&db, organization_id, claims.0.id, &req.display_name).await?;
+
+    Ok(Json(CreateResponse { id }))
+}
+
+/// Get a classroom.
+#[api_v2_operation]
+async fn get(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    path: Path<ClassroomId>,
+) -> Result<Json<<classroom::Get as ApiEndpoint>::Res>, error::Organization> {
+    let id = path.into_inner();
+
+    check_access(&db, id, claims.0.id).await?;
+
+    let classroom = db::classroom::get(&db, id)
+        .await?
+        .ok_or(error::Organization::ResourceNotFound)?;
+
+    Ok(Json(classroom))
+}
+
+/// Add a student to a classroom.
+#[api_v2_operation]
+async fn add_student(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    Path((id, user_id)): Path<(ClassroomId, Uuid)>,
+) -> Result<NoContent, error::Organization> {
+    check_manage(&db, id, claims.0.id).await?;
+
+    match db::classroom::add_student(&db, id, user_id).await? {
+        Some(OrganizationRole::Student) => {}
+        Some(_) => return Err(error::Organization::NotAStudent),
+        None => return Err(error::Organization::ResourceNotFound),
+    }
+
+    Ok(NoContent)
+}
+
+/// Remove a student from a classroom.
+#[api_v2_operation]
+async fn remove_student(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    Path((id, user_id)): Path<(ClassroomId, Uuid)>,
+) -> Result<NoContent, error::Organization> {
+    check_manage(&db, id, claims.0.id).await?;
+
+    db::classroom::remove_student(&db, id, user_id).await?;
+
+    Ok(NoContent)
+}
+
+/// Assign a jig to a classroom.
+#[api_v2_operation]
+async fn assign_jig(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    Path((id, jig_id)): Path<(ClassroomId, JigId)>,
+) -> Result<NoContent, error::Organization> {
+    check_manage(&db, id, claims.0.id).await?;
+
+    if !db::classroom::assign_jig(&db, id, jig_id).await? {
+        return Err(error::Organization::ResourceNotFound);
+    }
+
+    Ok(NoContent)
+}
+
+/// Unassign a jig from a classroom.
+#[api_v2_operation]
+async fn unassign_jig(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    Path((id, jig_id)): Path<(ClassroomId, JigId)>,
+) -> Result<NoContent, error::Organization> {
+    check_manage(&db, id, claims.0.id).await?;
+
+    db::classroom::unassign_jig(&db, id, jig_id).await?;
+
+    Ok(NoContent)
+}
+
+/// List the jigs assigned to a classroom.
+#[api_v2_operation]
+async fn list_jigs(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    path: Path<ClassroomId>,
+) -> Result<Json<<classroom::ListJigs as ApiEndpoint>::Res>, error::Organization> {
+    let id = path.into_inner();
+
+    check_access(&db, id, claims.0.id).await?;
+
+    let jigs = db::classroom::list_jigs(&db, id).await?;
+
+    Ok(Json(AssignedJigListResponse { jigs }))
+}
+
+pub fn configure(cfg: &mut ServiceConfig<'_>) {
+    cfg.route(
+        classroom::Create::PATH,
+        classroom::Create::METHOD.route().to(create),
+    )
+    .route(classroom::Get::PATH, classroom::Get::METHOD.route().to(get))
+    .route(
+        classroom::AddStudent::PATH,
+        classroom::AddStudent::METHOD.route().to(add_student),
+    )
+    .route(
+        classroom::RemoveStudent::PATH,
+        classroom::RemoveStudent::METHOD.route().to(remove_student),
+    )
+    .route(
+        classroom::AssignJig::PATH,
+        classroom::AssignJig::METHOD.route().to(assign_jig),
+    )
+    .route(
+        classroom::UnassignJig::PATH,
+        classroom::UnassignJig::METHOD.route().to(unassign_jig),
+    )
+    .route(
+        classroom::ListJigs::PATH,
+        classroom::ListJigs::METHOD.route().to(list_jigs),
+    );
+}
@@ -1,23 +1,26 @@
 use crate::{
     db::{self, meta::MetaWrapperError, nul_if_empty},
-    error::{self, ServiceKind},
+    error,
     extractor::{AuthUserWithScope, ScopeManageImage, WrapAuthClaimsNoDb},
-    image_ops::generate_images,
-    s3,
+    fetch,
+    jobs::JobKind,
+    language,
+    s3::{self, Location},
 };
+use actix_http::error::BlockingError;
 use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
 use paperclip::actix::{
     api_v2_operation,
-    web::{self, Bytes, Data, Json, Path, PayloadConfig, Query, ServiceConfig},
+    web::{self, Bytes, Data, HttpRequest, Json, Path, PayloadConfig, Query, ServiceConfig},
     CreatedJson, NoContent,
 };
 use shared::{
     api::{endpoints, ApiEndpoint},
     domain::{
         image::{
-            CreateResponse, Image, ImageId, ImageKind, ImageResponse, ImageSearchResponse,
-            ImageUpdateRequest,
+            CreateResponse, Image, ImageId, ImageResponse, ImageSearchResponse, ImageUpdateRequest,
+            ImageUploadQuery, ImageUploadResponse,
         },
         meta::MetaKind,
     },
@@ -27,7 +30,13 @@ use sqlx::{postgres::PgDatabaseError, PgPool};
 use uuid::Uuid;
 
 pub mod user {
-    use crate::{db, error, extractor::WrapAuthClaimsNoDb, image_ops::generate_images, s3};
+    use crate::{
+        db, error,
+        extractor::WrapAuthClaimsNoDb,
+        jobs::JobKind,
+        moderation,
+        s3::{self, Location},
+    };
     use paperclip::actix::{
         api_v2_operation,
         web::{Bytes, Data, Json, Path},
@@ -40,7 +49,7 @@ pub mod user {
         domain::{
             image::{
                 user::{UserImage, UserImageListResponse, UserImageResponse},
-                ImageId, ImageKind,
+                ImageId,
             },
             CreateResponse,
         },
@@ -53,10 +62,10 @@ pub mod user {
     #[api_v2_operation]
     pub(super) async fn create(
         db: Data<PgPool>,
-        _claims: WrapAuthClaimsNoDb,
+        claims: WrapAuthClaimsNoDb,
     ) -> Result<CreatedJson<<endpoints::image::user::Create as ApiEndpoint>::Res>, error::Server>
     {
-        let id = db::image::user::create(db.as_ref()).await?;
+        let id = db::image::user::create(db.as_ref(), claims.0.id).await?;
         Ok(CreatedJson(CreateResponse { id }))
     }
 
@@ -79,27 +88,25 @@ pub mod user {
         .await?
         .ok_or(error::Upload::ResourceNotFound)?;
 
-        let kind = ImageKind::Sticker;
-
-        let (original, resized, thumbnail) =
-            actix_web::web::block(move || -> Result<_, error::Upload> {
-                let original =
-                    image::load_from_memory(&bytes).map_err(|_| error::Upload::InvalidMedia)?;
-                Ok(generate_images(&original, kind)?)
-            })
-            .await
-            .map_err(error::Upload::blocking_error)?;
-
-        s3.upload_png_images(MediaLibrary::User, id.0, original, resized, thumbnail)
-            .await?;
+        // resizing happens in the background, but at least make sure that it's an image.
+        if image::guess_format(&bytes).is_err() {
+            return Err(error::Upload::InvalidMedia);
+        }
 
-        sqlx::query!(
-            "update user_image_library set uploaded_at = now() where id = $1",
-            id.0
+        // hidden until it's been moderated (which happens with the resizing).
+        s3.upload_media_to(
+            Location::Quarantine,
+            bytes.to_vec(),
+            MediaLibrary::User,
+            id.0,
+            FileKind::ImagePng(PngImageFile::Original),
         )
-        .execute(&mut txn)
         .await?;
 
+        moderation::quarantine(&mut txn, MediaLibrary::User, id.0).await?;
+
+        db::job::enqueue(&mut txn, JobKind::ProcessImage, MediaLibrary::User, id.0).await?;
+
         txn.commit().await?;
 
         Ok(NoContent)
@@ -159,6 +166,199 @@ pub mod user {
     }
 }
 
+pub mod import {
+    use crate::{
+        db, error,
+        extractor::{AuthUserWithScope, ScopeManageImage},
+        fetch,
+        image_import::{self, ImportError},
+        jobs::JobKind,
+        s3,
+    };
+    use actix_web::http::header;
+    use paperclip::actix::{
+        api_v2_operation,
+        web::{Bytes, Data, HttpRequest, Json, Path},
+        CreatedJson, NoContent,
+    };
+    use shared::{
+        api::{endpoints, ApiEndpoint},
+        domain::image::import::{
+            ImageImportCreateResponse, ImageImportResponse, ImageImportStatus,
+        },
+        error::MetadataNotFound,
+        media::MediaLibrary,
+    };
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    /// Maximum amount of items in an import's manifest.
+    const MAX_ITEMS: usize = 1000;
+
+    /// Start importing images from a manifest.
+    #[api_v2_operation]
+    pub(super) async fn create(
+        db: Data<PgPool>,
+        auth: AuthUserWithScope<ScopeManageImage>,
+        req: Json<<endpoints::image::import::Create as ApiEndpoint>::Req>,
+    ) -> Result<
+        CreatedJson<<endpoints::image::import::Create as ApiEndpoint>::Res>,
+        error::ImageImport,
+    > {
+        let req = req.into_inner();
+
+        if req.items.is_empty() || req.items.len() > MAX_ITEMS {
+            return Err(error::ImageImport::InvalidManifest(format!(
+                "An import needs between 1 and {} items",
+                MAX_ITEMS
+            )));
+        }
+
+        for (index, item) in req.items.iter().enumerate() {
+            let url = match &item.source_url {
+                Some(url) => url,
+                None => continue,
+            };
+
+            if !fetch::is_web_url(url) {
+                return Err(error::ImageImport::InvalidManifest(format!(
+                    "Item {} has an invalid `source_url`",
+                    index
+                )));
+            }
+        }
+
+        let mut txn = db.begin().await?;
+
+        if let Some((id, kind)) = db::image::import::missing_metadata(&mut txn, &req.items).await? {
+            return Err(error::ImageImport::MissingMetadata(MetadataNotFound {
+                id: Some(id),
+                kind,
+            }));
+        }
+
+        let (id, downloads) =
+            db::image::import::create(&mut txn, auth.claims.id, &req.items).await?;
+
+        db::job::enqueue_many(
+            &mut txn,
+            JobKind::ImportImage,
+            MediaLibrary::Global,
+            &downloads,
+        )
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(CreatedJson(ImageImportCreateResponse { id }))
+    }
+
+    /// Get the progress of an import.
+    #[api_v2_operation]
+    pub(super) async fn get(
+        db: Data<PgPool>,
+        _claims: AuthUserWithScope<ScopeManageImage>,
+        Path(id): Path<Uuid>,
+    ) -> Result<Json<<endpoints::image::import::Get as ApiEndpoint>::Res>, error::NotFound> {
+        let (created_at, items) = db::image::import::get(&db, id)
+            .await?
+            .ok_or(error::NotFound::ResourceNotFound)?;
+
+        let count = |status| items.iter().filter(|it| it.status == status).count() as u32;
+
+        Ok(Json(ImageImportResponse {
+            id,
+            created_at,
+            awaiting_upload_count: count(ImageImportStatus::AwaitingUpload),
+            importing_count: count(ImageImportStatus::Importing),
+            imported_count: count(ImageImportStatus::Imported),
+            failed_count: count(ImageImportStatus::Failed),
+            items,
+        }))
+    }
+
+    /// Gets the file out of an upload's body, which is either the file itself,
+    /// or a `multipart/form-data` form with the file in its `file` field.
+    async fn upload_file(
+        req: &HttpRequest,
+        body: Bytes,
+    ) -> Result<Vec<u8>, error::ImageImportUpload> {
+        let boundary = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|it| it.to_str().ok())
+            .and_then(|it| multer::parse_boundary(it).ok());
+
+        let boundary = match boundary {
+            Some(boundary) => boundary,
+            None => return Ok(body.to_vec()),
+        };
+
+        // the body has already been read (and limited), so there's no need to stream it.
+        let body =
+            futures::stream::once(async move { Ok::<_, std::convert::Infallible>(body.to_vec()) });
+
+        let mut form = multer::Multipart::new(body, boundary);
+
+        while let Some(field) = form
+            .next_field()
+            .await
+            .map_err(|_| error::ImageImportUpload::InvalidMedia)?
+        {
+            if field.name() == Some("file") {
+                return field
+                    .bytes()
+                    .await
+                    .map(|it| it.to_vec())
+                    .map_err(|_| error::ImageImportUpload::InvalidMedia);
+            }
+        }
+
+        Err(error::ImageImportUpload::InvalidMedia)
+    }
+
+    /// Upload the file for an import item.
+    #[api_v2_operation]
+    pub(super) async fn upload(
+        db: Data<PgPool>,
+        s3: Data<s3::Client>,
+        _claims: AuthUserWithScope<ScopeManageImage>,
+        Path((id, index)): Path<(Uuid, u32)>,
+        req: HttpRequest,
+        bytes: Bytes,
+    ) -> Result<NoContent, error::ImageImportUpload> {
+        let (item_id, status) = db::image::import::find_item(&db, id, index)
+            .await?
+            .ok_or(error::ImageImportUpload::ResourceNotFound)?;
+
+        // items that failed to download can still be uploaded by hand.
+        match status {
+            ImageImportStatus::AwaitingUpload | ImageImportStatus::Failed => {}
+            ImageImportStatus::Importing | ImageImportStatus::Imported => {
+                return Err(error::ImageImportUpload::AlreadyImporting)
+            }
+        }
+
+        let file = upload_file(&req, bytes).await?;
+
+        match image_import::import(&db, &s3, item_id, file).await {
+            Ok(Some(_)) => Ok(NoContent),
+            Ok(None) => Err(error::ImageImportUpload::AlreadyImporting),
+            Err(ImportError::InvalidImage) => Err(error::ImageImportUpload::InvalidMedia),
+            Err(ImportError::MissingMetadata { id, kind }) => Err(
+                error::ImageImportUpload::MissingMetadata(MetadataNotFound { id, kind }),
+            ),
+            Err(ImportError::Other(e)) => Err(e.into()),
+        }
+    }
+}
+
+/// How many bits of an upload's perceptual hash may differ from an existing image's for it to be considered a duplicate.
+const DUPLICATE_DISTANCE: u32 = 4;
+
+/// How many duplicates to warn about at most.
+const MAX_DUPLICATES: i64 = 20;
+
 // attempts to grab a uuid out of a string in the shape:
 // Key (<key>)=(<uuid>)<postfix>
 fn extract_uuid(s: &str) -> Option<Uuid> {
@@ -211,6 +411,15 @@ async fn create(
 {
     let req = req.into_inner();
 
+    let alt_text = language::normalize_alt_text(req.alt_text)
+        .map_err(error::CreateWithMetadata::BadRequest)?;
+
+    if !req.source_url.as_deref().map_or(true, fetch::is_web_url) {
+        return Err(error::CreateWithMetadata::BadRequest(
+            "Invalid source url".to_owned(),
+        ));
+    }
+
     let mut txn = db.begin().await?;
     let id = db::image::create(
         &mut txn,
@@ -219,9 +428,16 @@ async fn create(
         req.is_premium,
         req.publish_at.map(DateTime::<Utc>::from),
         req.kind,
+        req.attribution.as_deref(),
+        req.license,
+        req.source_url.as_deref(),
     )
     .await?;
 
+    if !alt_text.is_empty() {
+        db::image::set_alt_text(&mut txn, id, &alt_text).await?;
+    }
+
     db::image::update_metadata(
         &mut txn,
         id,
@@ -245,41 +461,82 @@ async fn upload(
     s3: Data<s3::Client>,
     _claims: AuthUserWithScope<ScopeManageImage>,
     Path(id): Path<ImageId>,
+    query: Option<Query<ImageUploadQuery>>,
     bytes: Bytes,
-) -> Result<NoContent, error::Upload> {
+) -> Result<Json<<endpoints::image::Upload as ApiEndpoint>::Res>, error::Upload> {
+    let query = query.map_or_else(ImageUploadQuery::default, Query::into_inner);
+
     let mut txn = db.begin().await?;
 
-    let kind = sqlx::query!(
-        r#"select kind as "kind: ImageKind" from image_metadata where id = $1 for update"#,
+    let is_premium = sqlx::query!(
+        r#"select is_premium from image_metadata where id = $1 for update"#,
         id.0
     )
     .fetch_optional(&mut txn)
     .await?
     .ok_or(error::Upload::ResourceNotFound)?
-    .kind;
+    .is_premium;
+
+    // resizing happens in the background, but at least make sure that it's an image.
+    if image::guess_format(&bytes).is_err() {
+        return Err(error::Upload::InvalidMedia);
+    }
 
-    let (original, resized, thumbnail) =
-        actix_web::web::block(move || -> Result<_, error::Upload> {
-            let original =
-                image::load_from_memory(&bytes).map_err(|_| error::Upload::InvalidMedia)?;
-            Ok(generate_images(&original, kind)?)
+    // checking for duplicates means decoding the image now, rather than waiting for processing.
+    let phash = if query.check_duplicates {
+        let data = bytes.clone();
+        let phash = actix_web::web::block(move || {
+            image::load_from_memory(&data).map(|it| crate::image_ops::perceptual_hash(&it))
         })
         .await
-        .map_err(error::Upload::blocking_error)?;
-
-    s3.upload_png_images(MediaLibrary::Global, id.0, original, resized, thumbnail)
-        .await?;
+        .map_err(|e| match e {
+            BlockingError::Error(_) => error::Upload::InvalidMedia,
+            BlockingError::Canceled => anyhow::anyhow!("thread pool is gone").into(),
+        })?;
+
+        Some(phash)
+    } else {
+        None
+    };
 
-    sqlx::query!(
-        "update image_metadata set uploaded_at = now() where id = $1",
-        id.0
+    s3.upload_media_to(
+        Location::visible(MediaLibrary::Global, is_premium),
+        bytes.to_vec(),
+        MediaLibrary::Global,
+        id.0,
+        FileKind::ImagePng(PngImageFile::Original),
     )
-    .execute(&mut txn)
     .await?;
 
+    db::job::enqueue(&mut txn, JobKind::ProcessImage, MediaLibrary::Global, id.0).await?;
+
     txn.commit().await?;
 
-    Ok(NoContent)
+    let phash = match phash {
+        Some(phash) => phash,
+        None => {
+            return Ok(Json(ImageUploadResponse {
+                similar_images: None,
+            }))
+        }
+    };
+
+    let similar_images = db::phash::similar(
+        &db,
+        phash,
+        DUPLICATE_DISTANCE,
+        Some(MediaLibrary::Global),
+        Some((MediaLibrary::Global, id.0)),
+        MAX_DUPLICATES,
+    )
+    .await?
+    .into_iter()
+    .map(|it| ImageId(it.media_id))
+    .collect();
+
+    Ok(Json(ImageUploadResponse {
+        similar_images: Some(similar_images),
+    }))
 }
 
 /// Get an image from the global image library.
@@ -300,36 +557,34 @@ async fn get_one(
 #[api_v2_operation]
 async fn search(
     db: Data<PgPool>,
-    algolia: Data<crate::algolia::Client>,
+    search: Data<crate::search::Client>,
     _claims: WrapAuthClaimsNoDb,
+    req: HttpRequest,
     query: Option<Query<<endpoints::image::Search as ApiEndpoint>::Req>>,
 ) -> Result<Json<<endpoints::image::Search as ApiEndpoint>::Res>, error::Service> {
-    let query = dbg!(query.map_or_else(Default::default, Query::into_inner));
-
-    let (ids, pages, total_hits) = algolia
-        .search_image(
-            &query.q,
-            query.page,
-            query.is_premium,
-            query.is_published,
-            &query.styles,
-            &query.age_ranges,
-            &query.affiliations,
-            &query.categories,
-        )
-        .await?
-        .ok_or_else(|| error::Service::DisabledService(ServiceKind::Algolia))?;
+    let query = query.map_or_else(Default::default, Query::into_inner);
+
+    let results = search.search_image(&query).await?;
 
-    let images: Vec<_> = db::image::get(db.as_ref(), &ids)
+    let images: Vec<_> = db::image::get(db.as_ref(), &results.ids)
         .err_into::<error::Service>()
         .and_then(|metadata: Image| async { Ok(ImageResponse { metadata }) })
         .try_collect()
         .await?;
 
+    let facets = match results.facets {
+        Some(counts) => {
+            let languages = language::preferred(&req, query.lang.as_deref());
+            Some(crate::search::name_facets(&db, counts, &languages).await?)
+        }
+        None => None,
+    };
+
     Ok(Json(ImageSearchResponse {
         images,
-        pages,
-        total_image_count: total_hits,
+        pages: results.pages,
+        total_image_count: results.total_hits,
+        facets,
     }))
 }
 
@@ -337,12 +592,28 @@ async fn search(
 #[api_v2_operation]
 async fn update(
     db: Data<PgPool>,
+    s3: Data<s3::Client>,
     _claims: AuthUserWithScope<ScopeManageImage>,
     req: Option<Json<<endpoints::image::UpdateMetadata as ApiEndpoint>::Req>>,
     id: Path<ImageId>,
 ) -> Result<NoContent, error::UpdateWithMetadata> {
     let req = req.map_or_else(ImageUpdateRequest::default, Json::into_inner);
     let id = id.into_inner();
+
+    let alt_text = req
+        .alt_text
+        .map(language::normalize_alt_text)
+        .transpose()
+        .map_err(error::UpdateWithMetadata::BadRequest)?;
+
+    if let Some(Some(url)) = &req.source_url {
+        if !fetch::is_web_url(url) {
+            return Err(error::UpdateWithMetadata::BadRequest(
+                "Invalid source url".to_owned(),
+            ));
+        }
+    }
+
     let mut txn = db.begin().await?;
 
     let exists = db::image::update(
@@ -352,6 +623,9 @@ async fn update(
         req.description.as_deref(),
         req.is_premium,
         req.publish_at.map(|it| it.map(DateTime::<Utc>::from)),
+        req.attribution.as_ref().map(Option::as_deref),
+        req.license,
+        req.source_url.as_ref().map(Option::as_deref),
     )
     .await?;
 
@@ -359,6 +633,10 @@ async fn update(
         return Err(error::UpdateWithMetadata::ResourceNotFound);
     }
 
+    if let Some(alt_text) = alt_text {
+        db::image::set_alt_text(&mut txn, id, &alt_text).await?;
+    }
+
     db::image::update_metadata(
         &mut txn,
         id,
@@ -372,6 +650,22 @@ async fn update(
 
     txn.commit().await?;
 
+    // premium images aren't served by the CDN (moving files that are already in place does nothing).
+    if let Some(is_premium) = req.is_premium {
+        s3.move_all_media(
+            MediaLibrary::Global,
+            id.0,
+            &[
+                FileKind::ImagePng(PngImageFile::Original),
+                FileKind::ImagePng(PngImageFile::Resized),
+                FileKind::ImagePng(PngImageFile::Thumbnail),
+            ],
+            Location::visible(MediaLibrary::Global, !is_premium),
+            Location::visible(MediaLibrary::Global, is_premium),
+        )
+        .await?;
+    }
+
     Ok(NoContent)
 }
 
@@ -388,7 +682,6 @@ fn check_conflict_delete(err: sqlx::Error) -> error::Delete {
 #[api_v2_operation]
 async fn delete(
     db: Data<PgPool>,
-    algolia: Data<crate::algolia::Client>,
     _claims: AuthUserWithScope<ScopeManageImage>,
     req: Path<ImageId>,
     s3: Data<s3::Client>,
@@ -398,14 +691,13 @@ async fn delete(
         .await
         .map_err(check_conflict_delete)?;
 
-    // todo: 501 when algolia is disabled.
+    // the image gets removed from the search index by the indexer (see `algolia::Manager`).
 
     let delete = |kind| s3.delete_media(MediaLibrary::Global, FileKind::ImagePng(kind), image.0);
-    let ((), (), (), ()) = futures::future::join4(
+    let ((), (), ()) = futures::future::join3(
         delete(PngImageFile::Original),
         delete(PngImageFile::Resized),
         delete(PngImageFile::Thumbnail),
-        algolia.delete_image(image),
     )
     .await;
 
@@ -436,6 +728,25 @@ pub fn configure(cfg: &mut ServiceConfig<'_>) {
         image::Delete::PATH,
         image::Delete::METHOD.route().to(delete),
     )
+    .route(
+        image::import::Create::PATH,
+        image::import::Create::METHOD
+            .route()
+            .to(self::import::create),
+    )
+    .route(
+        image::import::Get::PATH,
+        image::import::Get::METHOD.route().to(self::import::get),
+    )
+    .service(
+        web::resource(image::import::Upload::PATH)
+            .app_data(PayloadConfig::default().limit(config::IMAGE_BODY_SIZE_LIMIT))
+            .route(
+                image::import::Upload::METHOD
+                    .route()
+                    .to(self::import::upload),
+            ),
+    )
     .route(
         image::user::Create::PATH,
         image::user::Create::METHOD.route().to(self::user::create),
@@ -35,6 +35,9 @@ async fn create(
         req.cover,
         &req.modules,
         &req.content_types,
+        &req.categories,
+        &req.age_ranges,
+        &req.affiliations,
         req.ending,
         creator_id,
         req.publish_at.map(DateTime::<Utc>::from),
@@ -75,6 +78,9 @@ async fn update(
         req.modules.as_deref(),
         req.ending,
         req.content_types.as_deref(),
+        req.categories.as_deref(),
+        req.age_ranges.as_deref(),
+        req.affiliations.as_deref(),
         req.publish_at.map(|it| it.map(DateTime::<Utc>::from)),
     )
     .await
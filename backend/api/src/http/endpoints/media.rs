@@ -1,26 +1,33 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use crate::{
-    error,
-    extractor::{AuthUserWithScope, ScopeAdmin, WrapAuthClaimsNoDb},
+    db,
+    error::{self, ServiceKind},
+    extractor::{AuthUserWithScope, MaybeAuthClaimsNoCsrf, ScopeAdmin, WrapAuthClaimsNoDb},
+    http::pagination,
     image_ops::MediaKind,
-    s3,
+    moderation::{self, Moderator, Verdict},
+    s3::{self, Location},
 };
-use actix_web::web::Path;
+use actix_web::{http::header, web::Path, HttpResponse};
+use futures::TryStreamExt;
 use paperclip::actix::{
     api_v2_operation,
-    web::{Bytes, Data, Json, ServiceConfig},
+    web::{Data, Json, Query, ServiceConfig},
     CreatedJson, NoContent,
 };
 use sha2::Digest as _;
 use shared::{
     api::{endpoints, ApiEndpoint},
-    domain::media::{UrlCreatedResponse, WebMediaMetadataResponse, WebMediaUrlCreateRequest},
+    domain::media::{
+        UrlCreatedResponse, WebMediaImport, WebMediaListItem, WebMediaListQuery,
+        WebMediaListResponse, WebMediaMetadataResponse, WebMediaUrlCreateRequest,
+    },
     media::{FileKind, PngImageFile},
 };
 use shared::{
-    domain::{image::ImageKind, Base64},
-    media::MediaLibrary,
+    domain::{auth::AuthClaims, image::ImageKind, user::UserScope, Base64},
+    media::{MediaLibrary, ModerationStatus},
 };
 use sqlx::PgPool;
 use url::Url;
@@ -37,10 +44,11 @@ const fn max(a: usize, b: usize) -> usize {
 #[api_v2_operation]
 pub async fn create(
     pool: Data<PgPool>,
-    _claims: WrapAuthClaimsNoDb,
+    claims: WrapAuthClaimsNoDb,
     s3: Data<s3::Client>,
+    moderator: Data<Moderator>,
     request: Json<WebMediaUrlCreateRequest>,
-) -> Result<CreatedJson<UrlCreatedResponse>, error::Server> {
+) -> Result<CreatedJson<UrlCreatedResponse>, error::WebMedia> {
     let url = request.into_inner().url;
 
     const MAX_RESPONSE_SIZE: usize = max(
@@ -49,6 +57,8 @@ pub async fn create(
     );
 
     let url_string = url.to_string();
+    let host = url.host_str().map(str::to_lowercase);
+    let user_id = claims.0.id;
 
     // If we can already find the image, return early.
     if let Some(record) = sqlx::query!(
@@ -65,30 +75,15 @@ where media_url = $1"#,
     {
         log::trace!("Found the url");
 
+        check_not_rejected(&pool, record.media_id).await?;
+
         return Ok(CreatedJson(UrlCreatedResponse {
             id: record.media_id,
             kind: record.kind.to_shared(),
         }));
     }
 
-    let client: reqwest::Client = reqwest::ClientBuilder::new()
-        .connect_timeout(std::time::Duration::from_secs(5))
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
-
-    // todo: this `?` should be a ClientError or "proxy/gateway error"
-    let mut response: reqwest::Response = client.get(url).send().await?;
-
-    let mut data = Vec::new();
-
-    while let Some(chunk) = response.chunk().await? {
-        let chunk: Bytes = chunk;
-        if data.len() + chunk.len() < MAX_RESPONSE_SIZE {
-            data.extend_from_slice(&chunk[..]);
-        } else {
-            return Err(anyhow::anyhow!("todo: better error here (data too big)").into());
-        }
-    }
+    let data = crate::fetch::image(url, MAX_RESPONSE_SIZE).await?;
 
     log::trace!("data was {} bytes long", data.len());
 
@@ -117,10 +112,19 @@ for update
 
     if let Some(record) = record {
         let id = record.id;
+
+        check_not_rejected(&pool, id).await?;
+
         sqlx::query!(
-            "insert into web_media_library_url (media_id, media_url) values ($1, $2) on conflict (media_id, media_url) do nothing",
+            r#"
+insert into web_media_library_url (media_id, media_url, host, imported_by)
+values ($1, $2, $3, $4)
+on conflict (media_id, media_url) do nothing
+"#,
             id,
-            &url_string
+            &url_string,
+            host,
+            user_id
         )
         .execute(&mut txn)
         .await?;
@@ -145,6 +149,13 @@ for update
 
     log::debug!("detected image kind as: {:?}", kind);
 
+    let verdict = moderator.classify(&data).await?;
+
+    if let Verdict::Reject(reason) = &verdict {
+        log::info!("rejected web media from {}: {}", url_string, reason);
+        return Err(error::WebMedia::Rejected);
+    }
+
     let id = sqlx::query!(
         r#"insert into web_media_library ("hash", kind) values($1, $2) returning id"#,
         &hash,
@@ -155,16 +166,23 @@ for update
     .id;
 
     sqlx::query!(
-        "insert into web_media_library_url (media_id, media_url) values ($1, $2)",
+        "insert into web_media_library_url (media_id, media_url, host, imported_by) values ($1, $2, $3, $4)",
         id,
-        &url_string
+        &url_string,
+        host,
+        user_id
     )
     .execute(&mut txn)
     .await?;
 
+    // media that needs review is quarantined until an admin gets to it.
+    moderation::record(&mut txn, MediaLibrary::Web, id, &verdict).await?;
+    let location = verdict.location(MediaLibrary::Web);
+
     match kind {
         MediaKind::GifAnimation => {
-            s3.upload_media(
+            s3.upload_media_to(
+                location,
                 Arc::try_unwrap(data).expect("This should be unique by now"),
                 MediaLibrary::Web,
                 id,
@@ -174,14 +192,27 @@ for update
         }
 
         MediaKind::PngStickerImage => {
-            let (original, resized, thumbnail) = actix_web::web::block(move || {
-                let original = image::load_from_memory(&data)?;
-                crate::image_ops::generate_images(&original, ImageKind::Sticker)
-            })
+            let ((original, resized, thumbnail), phash) =
+                actix_web::web::block(move || -> anyhow::Result<_> {
+                    let original = image::load_from_memory(&data)?;
+                    Ok((
+                        crate::image_ops::generate_images(&original, ImageKind::Sticker)?,
+                        crate::image_ops::perceptual_hash(&original),
+                    ))
+                })
+                .await?;
+
+            s3.upload_png_images(
+                location,
+                MediaLibrary::Web,
+                id,
+                original,
+                resized,
+                thumbnail,
+            )
             .await?;
 
-            s3.upload_png_images(MediaLibrary::Web, id, original, resized, thumbnail)
-                .await?;
+            db::phash::set(&mut txn, MediaLibrary::Web, id, phash).await?;
         }
 
         kind => return Err(anyhow::anyhow!("unsupported media kind {:?}", kind).into()),
@@ -202,29 +233,124 @@ for update
     }))
 }
 
-// filter by media type, etc
-// list route (with filter & pagation)
-// async fn list() {}
+/// Media that was rejected by moderation can't be imported again.
+async fn check_not_rejected(db: &PgPool, id: Uuid) -> Result<(), error::WebMedia> {
+    match db::moderation::status(db, MediaLibrary::Web, id).await? {
+        Some(ModerationStatus::Rejected) => Err(error::WebMedia::Rejected),
+        _ => Ok(()),
+    }
+}
 
+/// List media, newest first.
 #[api_v2_operation]
-async fn delete_media(
+async fn list(
     pool: Data<PgPool>,
     _auth: AuthUserWithScope<ScopeAdmin>,
-    s3: Data<s3::Client>,
-    Path(id): Path<Uuid>,
-) -> Result<NoContent, error::Server> {
-    let record = sqlx::query!(
-        r#"delete from web_media_library where id = $1 returning kind as "kind: MediaKind""#,
-        id
+    query: Option<Query<<endpoints::media::List as ApiEndpoint>::Req>>,
+) -> Result<Json<<endpoints::media::List as ApiEndpoint>::Res>, error::ListMedia> {
+    let query = query.map_or_else(WebMediaListQuery::default, Query::into_inner);
+
+    let (cursor_created_at, cursor_id) = match query.cursor.as_deref() {
+        Some(cursor) => {
+            let (created_at, id) =
+                pagination::decode_cursor(cursor).ok_or(error::ListMedia::InvalidCursor)?;
+            (Some(created_at), Some(id))
+        }
+        None => (None, None),
+    };
+
+    let limit = pagination::limit(query.limit);
+
+    let domain = query
+        .domain
+        .map(|it| it.trim().trim_end_matches('.').to_lowercase());
+
+    let subdomains = domain
+        .as_deref()
+        .map(|it| format!("%.{}", db::like_escape(it)));
+
+    let kinds = query.kind.map(MediaKind::for_filter);
+
+    // fetch one extra item to know if there's another page.
+    let mut media = sqlx::query_file!(
+        "query/list_web_media.sql",
+        kinds.as_deref(),
+        domain,
+        query.imported_after,
+        query.imported_before,
+        query.imported_by,
+        cursor_created_at,
+        cursor_id,
+        i64::from(limit) + 1,
+        subdomains
     )
-    .fetch_optional(pool.as_ref())
+    .fetch_all(pool.as_ref())
     .await?;
 
-    let kind = match record {
-        Some(record) => record.kind,
-        None => return Ok(NoContent),
+    let next_cursor = if media.len() > limit as usize {
+        media.truncate(limit as usize);
+        media
+            .last()
+            .map(|it| pagination::encode_cursor(it.created_at, it.id))
+    } else {
+        None
     };
 
+    let ids: Vec<Uuid> = media.iter().map(|it| it.id).collect();
+
+    let mut imports: HashMap<Uuid, Vec<WebMediaImport>> = HashMap::new();
+
+    let mut rows = sqlx::query!(
+        r#"
+select media_id, media_url, imported_by, created_at
+from web_media_library_url
+where media_id = any($1)
+order by created_at
+"#,
+        &ids
+    )
+    .fetch(pool.as_ref());
+
+    while let Some(row) = rows.try_next().await? {
+        // one bad url shouldn't break the whole page.
+        let url = match Url::parse(&row.media_url) {
+            Ok(url) => url,
+            Err(e) => {
+                log::warn!(
+                    "skipping invalid url for web media {}: {:?} ({})",
+                    row.media_id,
+                    row.media_url,
+                    e
+                );
+                continue;
+            }
+        };
+
+        imports
+            .entry(row.media_id)
+            .or_default()
+            .push(WebMediaImport {
+                url,
+                imported_by: row.imported_by,
+                imported_at: row.created_at,
+            });
+    }
+
+    let media = media
+        .into_iter()
+        .map(|it| WebMediaListItem {
+            id: it.id,
+            kind: it.kind.to_shared(),
+            imports: imports.remove(&it.id).unwrap_or_default(),
+            created_at: it.created_at,
+            updated_at: it.updated_at,
+        })
+        .collect();
+
+    Ok(Json(WebMediaListResponse { media, next_cursor }))
+}
+
+async fn delete_files(s3: &s3::Client, id: Uuid, kind: MediaKind) -> anyhow::Result<()> {
     let delete = |file_kind| s3.delete_media(MediaLibrary::Web, file_kind, id);
     match kind {
         MediaKind::PngStickerImage => {
@@ -240,7 +366,52 @@ async fn delete_media(
             delete(FileKind::AnimationGif).await;
         }
 
-        kind => return Err(anyhow::anyhow!("unsupported media kind {:?}", kind).into()),
+        kind => return Err(anyhow::anyhow!("unsupported media kind {:?}", kind)),
+    }
+
+    Ok(())
+}
+
+#[api_v2_operation]
+async fn delete_media(
+    pool: Data<PgPool>,
+    _auth: AuthUserWithScope<ScopeAdmin>,
+    s3: Data<s3::Client>,
+    Path(id): Path<Uuid>,
+) -> Result<NoContent, error::Server> {
+    let record = sqlx::query!(
+        r#"delete from web_media_library where id = $1 returning kind as "kind: MediaKind""#,
+        id
+    )
+    .fetch_optional(pool.as_ref())
+    .await?;
+
+    if let Some(record) = record {
+        delete_files(&s3, id, record.kind).await?;
+    }
+
+    Ok(NoContent)
+}
+
+/// Remove several pieces of media at once.
+#[api_v2_operation]
+async fn delete_many(
+    pool: Data<PgPool>,
+    _auth: AuthUserWithScope<ScopeAdmin>,
+    s3: Data<s3::Client>,
+    req: Json<<endpoints::media::DeleteMany as ApiEndpoint>::Req>,
+) -> Result<NoContent, error::Server> {
+    let ids = req.into_inner().ids;
+
+    let deleted = sqlx::query!(
+        r#"delete from web_media_library where id = any($1) returning id, kind as "kind: MediaKind""#,
+        &ids
+    )
+    .fetch_all(pool.as_ref())
+    .await?;
+
+    for record in deleted {
+        delete_files(&s3, record.id, record.kind).await?;
     }
 
     Ok(NoContent)
@@ -332,6 +503,209 @@ where id = (select media_id from web_media_library_url where media_url = $1)
     }))
 }
 
+/// How long signed media urls are valid for.
+const SIGNED_URL_DURATION: Duration = Duration::from_secs(15 * 60);
+
+/// How long clients may cache a redirect to a signed url, this must be less than [`SIGNED_URL_DURATION`].
+const SIGNED_URL_CACHE_SECS: u64 = 10 * 60;
+
+/// Who can see a piece of media.
+#[derive(Copy, Clone)]
+enum Visibility {
+    Public,
+    Premium,
+    /// Only the owner (if any) can see it.
+    Private(Option<Uuid>),
+    /// It hasn't been approved by moderation, only the owner (if any) can see it.
+    Quarantined(Option<Uuid>),
+}
+
+async fn visibility(
+    db: &PgPool,
+    library: MediaLibrary,
+    id: Uuid,
+    file_kind: FileKind,
+) -> sqlx::Result<Option<Visibility>> {
+    let premium = |is_premium| match is_premium {
+        true => Visibility::Premium,
+        false => Visibility::Public,
+    };
+
+    let visibility = match (library, file_kind) {
+        (MediaLibrary::Global, FileKind::ImagePng(_)) => {
+            sqlx::query!("select is_premium from image_metadata where id = $1", id)
+                .fetch_optional(db)
+                .await?
+                .map(|it| premium(it.is_premium))
+        }
+
+        (MediaLibrary::Global, FileKind::AnimationGif)
+        | (MediaLibrary::Global, FileKind::AnimationSpritesheet) => {
+            sqlx::query!("select is_premium from animation where id = $1", id)
+                .fetch_optional(db)
+                .await?
+                .map(|it| premium(it.is_premium))
+        }
+
+        (MediaLibrary::User, FileKind::ImagePng(_)) => {
+            sqlx::query!("select user_id from user_image_library where id = $1", id)
+                .fetch_optional(db)
+                .await?
+                .map(|it| Visibility::Private(it.user_id))
+        }
+
+        (MediaLibrary::User, FileKind::AudioMp3) => {
+            sqlx::query!("select user_id from user_audio_library where id = $1", id)
+                .fetch_optional(db)
+                .await?
+                .map(|it| Visibility::Private(it.user_id))
+        }
+
+        (MediaLibrary::Web, FileKind::ImagePng(_))
+        | (MediaLibrary::Web, FileKind::AnimationGif) => {
+            let exists = sqlx::query!(
+                r#"select exists(select 1 from web_media_library where id = $1) as "exists!""#,
+                id
+            )
+            .fetch_one(db)
+            .await?
+            .exists;
+
+            match exists {
+                true => Some(Visibility::Public),
+                false => None,
+            }
+        }
+
+        _ => None,
+    };
+
+    let visibility = match visibility {
+        Some(visibility) => visibility,
+        None => return Ok(None),
+    };
+
+    // global media isn't moderated.
+    if let MediaLibrary::Global = library {
+        return Ok(Some(visibility));
+    }
+
+    match moderation::location(db, library, id).await? {
+        Location::Public | Location::Private => Ok(Some(visibility)),
+        Location::Quarantine => {
+            let owner = match visibility {
+                Visibility::Private(owner) => owner,
+                _ => None,
+            };
+
+            Ok(Some(Visibility::Quarantined(owner)))
+        }
+    }
+}
+
+async fn authorize(
+    db: &PgPool,
+    claims: Option<&AuthClaims>,
+    visibility: Visibility,
+) -> Result<(), error::MediaFile> {
+    let claims = match (visibility, claims) {
+        (Visibility::Public, _) => return Ok(()),
+        (_, None) => return Err(error::MediaFile::Unauthorized),
+        (Visibility::Private(Some(owner)), Some(claims))
+        | (Visibility::Quarantined(Some(owner)), Some(claims))
+            if owner == claims.id =>
+        {
+            return Ok(())
+        }
+        (_, Some(claims)) => claims,
+    };
+
+    let scopes = db::user::scopes(db, claims.id).await?;
+    let has_scope = |scope: UserScope| scopes.contains(&(scope as i16));
+
+    match visibility {
+        Visibility::Premium
+            if has_scope(UserScope::Admin)
+                || has_scope(UserScope::ManageImage)
+                || has_scope(UserScope::ManageAnimation)
+                || has_scope(UserScope::PremiumMedia) =>
+        {
+            Ok(())
+        }
+        Visibility::Premium => Err(error::MediaFile::Forbidden),
+        Visibility::Private(_) | Visibility::Quarantined(_) if has_scope(UserScope::Admin) => {
+            Ok(())
+        }
+        // don't leak the existence of other users' (or quarantined) media.
+        Visibility::Private(_) | Visibility::Quarantined(_) => {
+            Err(error::MediaFile::ResourceNotFound)
+        }
+        Visibility::Public => Ok(()),
+    }
+}
+
+/// Get a media file, after checking that the caller is allowed to see it.
+#[api_v2_operation]
+async fn get_file(
+    db: Data<PgPool>,
+    claims: MaybeAuthClaimsNoCsrf,
+    s3: Data<s3::Client>,
+    Path((library, id, file)): Path<(MediaLibrary, Uuid, String)>,
+) -> Result<HttpResponse, error::MediaFile> {
+    let file_kind = FileKind::from_suffix(&file).ok_or(error::MediaFile::ResourceNotFound)?;
+
+    let visibility = visibility(&db, library, id, file_kind)
+        .await?
+        .ok_or(error::MediaFile::ResourceNotFound)?;
+
+    // only public media is stored where the CDN can serve it.
+    let location = match visibility {
+        Visibility::Public => Location::Public,
+        Visibility::Premium | Visibility::Private(_) => Location::Private,
+        Visibility::Quarantined(_) => Location::Quarantine,
+    };
+
+    authorize(&db, claims.0.as_ref(), visibility).await?;
+
+    file_response(&s3, location, library, id, file_kind).await
+}
+
+/// Redirects to a signed url for the file, or responds with the file itself if the storage
+/// backend doesn't support signed urls.
+async fn file_response(
+    s3: &s3::Client,
+    location: Location,
+    library: MediaLibrary,
+    id: Uuid,
+    file_kind: FileKind,
+) -> Result<HttpResponse, error::MediaFile> {
+    if let Some(url) = s3.signed_media_url(location, library, id, file_kind, SIGNED_URL_DURATION) {
+        return Ok(HttpResponse::Found()
+            .header(header::LOCATION, url)
+            .header(
+                header::CACHE_CONTROL,
+                format!("private, max-age={}", SIGNED_URL_CACHE_SECS),
+            )
+            .finish());
+    }
+
+    let data = s3
+        .download_media_file(location, library, id, file_kind)
+        .await?
+        .ok_or(error::MediaFile::DisabledService(ServiceKind::S3))?
+        .ok_or(error::MediaFile::ResourceNotFound)?;
+
+    let cache_control = match location {
+        Location::Public => "public, max-age=86400",
+        Location::Private | Location::Quarantine => "private, max-age=3600",
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(file_kind.content_type())
+        .header(header::CACHE_CONTROL, cache_control)
+        .body(data))
+}
+
 pub fn configure(cfg: &mut ServiceConfig<'_>) {
     cfg.route(
         endpoints::media::Create::PATH,
@@ -352,5 +726,122 @@ pub fn configure(cfg: &mut ServiceConfig<'_>) {
     .route(
         endpoints::media::DeleteUrl::PATH,
         endpoints::media::DeleteUrl::METHOD.route().to(delete_media),
+    )
+    .route(
+        endpoints::media::List::PATH,
+        endpoints::media::List::METHOD.route().to(list),
+    )
+    .route(
+        endpoints::media::DeleteMany::PATH,
+        endpoints::media::DeleteMany::METHOD.route().to(delete_many),
+    )
+    .route(
+        endpoints::media::GetFile::PATH,
+        endpoints::media::GetFile::METHOD.route().to(get_file),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::file_response;
+    use crate::{
+        error,
+        s3::{self, Location, MemoryStorage, Storage},
+    };
+    use actix_web::http::{header, StatusCode};
+    use futures::future::{self, BoxFuture, FutureExt};
+    use shared::media::{FileKind, MediaLibrary, PngImageFile};
+    use std::{sync::Arc, time::Duration};
+    use uuid::Uuid;
+
+    const ID: &str = "3095d05e-f2c7-11ea-89c3-3b621dd74a1f";
+
+    const FILE: FileKind = FileKind::ImagePng(PngImageFile::Resized);
+
+    /// A storage backend that can sign urls (but can't store anything).
+    struct SigningStorage;
+
+    impl Storage for SigningStorage {
+        fn put<'a>(
+            &'a self,
+            _key: String,
+            _content_type: &'a str,
+            _data: Vec<u8>,
+        ) -> BoxFuture<'a, anyhow::Result<()>> {
+            future::ready(Ok(())).boxed()
+        }
+
+        fn get(&self, _key: String) -> BoxFuture<'_, anyhow::Result<Option<Vec<u8>>>> {
+            future::ready(Ok(None)).boxed()
+        }
+
+        fn delete(&self, _key: String) -> BoxFuture<'_, anyhow::Result<()>> {
+            future::ready(Ok(())).boxed()
+        }
+
+        fn signed_url(&self, key: String, _expires_in: Duration) -> Option<String> {
+            Some(format!("https://storage.example/{}", key))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn private_media_redirects_to_signed_url() {
+        let id = Uuid::parse_str(ID).unwrap();
+        let s3 = s3::Client::with_storage(Arc::new(SigningStorage));
+
+        let resp = file_response(&s3, Location::Private, MediaLibrary::User, id, FILE)
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers()
+                .get(header::LOCATION)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            format!(
+                "https://storage.example/private/media/user/{}/resized.png",
+                ID
+            )
+        );
+        assert!(resp
+            .headers()
+            .get(header::CACHE_CONTROL)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("private"));
+    }
+
+    #[actix_rt::test]
+    async fn media_is_served_from_its_location() {
+        let id = Uuid::parse_str(ID).unwrap();
+        let storage = Arc::new(MemoryStorage::default());
+        let s3 = s3::Client::with_storage(storage.clone());
+
+        storage
+            .put(
+                Location::Private.key(MediaLibrary::Global, id, FILE),
+                FILE.content_type(),
+                b"image".to_vec(),
+            )
+            .await
+            .unwrap();
+
+        let resp = file_response(&s3, Location::Private, MediaLibrary::Global, id, FILE)
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "private, max-age=3600"
+        );
+
+        // private media isn't at its public key.
+        let public = file_response(&s3, Location::Public, MediaLibrary::Global, id, FILE).await;
+
+        assert!(matches!(public, Err(error::MediaFile::ResourceNotFound)));
+    }
+}
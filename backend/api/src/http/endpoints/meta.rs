@@ -1,23 +1,39 @@
-use crate::{db, error};
+use crate::{
+    db, error,
+    extractor::{AuthUserWithScope, ScopeManageMetadata},
+    language,
+};
 use paperclip::actix::{
     api_v2_operation,
-    web::{Data, Json, ServiceConfig},
+    web::{Data, HttpRequest, Json, Path, Query, ServiceConfig},
+    CreatedJson, NoContent,
 };
 use shared::{
-    api::{endpoints::meta::Get, ApiEndpoint},
-    domain::meta::MetadataResponse,
+    api::{
+        endpoints::meta::{Create, Delete, Get, SetTranslations, Update},
+        ApiEndpoint,
+    },
+    domain::meta::{MetaTaxonomy, MetadataResponse, NewMetadataResponse},
 };
 use sqlx::PgPool;
+use uuid::Uuid;
 
 // TODO: Should have cache headers
 /// Get a list of all available metadata of all kinds (sans categories)
 #[api_v2_operation]
-async fn get(db: Data<PgPool>) -> Result<Json<<Get as ApiEndpoint>::Res>, error::Server> {
-    let styles = db::meta::get_style(&db).await?;
-    let affiliations = db::meta::get_affiliations(&db).await?;
-    let age_ranges = db::meta::get_age_ranges(&db).await?;
-    let subjects = db::meta::get_subjects(&db).await?;
-    let content_types = db::meta::get_content_types(&db).await?;
+async fn get(
+    db: Data<PgPool>,
+    req: HttpRequest,
+    query: Option<Query<<Get as ApiEndpoint>::Req>>,
+) -> Result<Json<<Get as ApiEndpoint>::Res>, error::Server> {
+    let query = query.map_or_else(Default::default, Query::into_inner);
+    let languages = language::preferred(&req, query.lang.as_deref());
+
+    let styles = db::meta::get_style(&db, &languages).await?;
+    let affiliations = db::meta::get_affiliations(&db, &languages).await?;
+    let age_ranges = db::meta::get_age_ranges(&db, &languages).await?;
+    let subjects = db::meta::get_subjects(&db, &languages).await?;
+    let content_types = db::meta::get_content_types(&db, &languages).await?;
 
     Ok(Json(MetadataResponse {
         styles,
@@ -28,6 +44,85 @@ async fn get(db: Data<PgPool>) -> Result<Json<<Get as ApiEndpoint>::Res>, error:
     }))
 }
 
+/// Create a piece of metadata.
+#[api_v2_operation]
+async fn create(
+    db: Data<PgPool>,
+    _claims: AuthUserWithScope<ScopeManageMetadata>,
+    kind: Path<MetaTaxonomy>,
+    req: Json<<Create as ApiEndpoint>::Req>,
+) -> Result<CreatedJson<<Create as ApiEndpoint>::Res>, error::Server> {
+    let req = req.into_inner();
+
+    let (id, index) =
+        db::meta::create(&db, kind.into_inner(), &req.display_name, req.index).await?;
+
+    Ok(CreatedJson(NewMetadataResponse { id, index }))
+}
+
+/// Rename and/or reorder a piece of metadata.
+#[api_v2_operation]
+async fn update(
+    db: Data<PgPool>,
+    _claims: AuthUserWithScope<ScopeManageMetadata>,
+    path: Path<(MetaTaxonomy, Uuid)>,
+    req: Option<Json<<Update as ApiEndpoint>::Req>>,
+) -> Result<NoContent, error::NotFound> {
+    let (kind, id) = path.into_inner();
+    let req = req.map_or_else(Default::default, Json::into_inner);
+
+    let exists = db::meta::update(&db, kind, id, req.display_name.as_deref(), req.index).await?;
+
+    if !exists {
+        return Err(error::NotFound::ResourceNotFound);
+    }
+
+    Ok(NoContent)
+}
+
+/// Delete a piece of metadata.
+#[api_v2_operation]
+async fn delete(
+    db: Data<PgPool>,
+    _claims: AuthUserWithScope<ScopeManageMetadata>,
+    path: Path<(MetaTaxonomy, Uuid)>,
+    query: Option<Query<<Delete as ApiEndpoint>::Req>>,
+) -> Result<NoContent, error::MetadataDelete> {
+    let (kind, id) = path.into_inner();
+    let query = query.map_or_else(Default::default, Query::into_inner);
+
+    db::meta::delete(&db, kind, id, query.replace_with).await?;
+
+    Ok(NoContent)
+}
+
+/// Replace the translations of a piece of metadata's name.
+#[api_v2_operation]
+async fn set_translations(
+    db: Data<PgPool>,
+    _claims: AuthUserWithScope<ScopeManageMetadata>,
+    path: Path<(MetaTaxonomy, Uuid)>,
+    req: Json<<SetTranslations as ApiEndpoint>::Req>,
+) -> Result<NoContent, error::SetTranslations> {
+    let (kind, id) = path.into_inner();
+    let translations = language::normalize_translations(req.into_inner().translations)?;
+
+    let exists = db::meta::set_translations(&db, kind, id, &translations).await?;
+
+    if !exists {
+        return Err(error::SetTranslations::NotFound);
+    }
+
+    Ok(NoContent)
+}
+
 pub fn configure(cfg: &mut ServiceConfig<'_>) {
-    cfg.route(Get::PATH, Get::METHOD.route().to(get));
+    cfg.route(Get::PATH, Get::METHOD.route().to(get))
+        .route(Create::PATH, Create::METHOD.route().to(create))
+        .route(Update::PATH, Update::METHOD.route().to(update))
+        .route(Delete::PATH, Delete::METHOD.route().to(delete))
+        .route(
+            SetTranslations::PATH,
+            SetTranslations::METHOD.route().to(set_translations),
+        );
 }
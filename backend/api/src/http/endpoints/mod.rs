@@ -2,10 +2,14 @@ pub mod admin;
 pub mod animation;
 pub mod audio;
 pub mod category;
+pub mod classroom;
 pub mod image;
 pub mod jig;
 pub mod media;
 pub mod meta;
 pub mod module;
+pub mod organization;
 pub mod search;
+pub mod session;
+pub mod upload;
 pub mod user;
@@ -0,0 +1,130 @@
+use chrono::{Duration, Utc};
+use paperclip::actix::{
+    api_v2_operation,
+    web::{self, Data, Json, ServiceConfig},
+};
+use shared::{
+    api::{endpoints::organization, ApiEndpoint},
+    domain::{
+        organization::{
+            InviteAcceptResponse, InviteCreateResponse, OrganizationId, OrganizationRole,
+        },
+        CreateResponse,
+    },
+};
+use sqlx::PgPool;
+
+use crate::{db, error, extractor::WrapAuthClaimsNoDb, password};
+
+/// How long an invite token can be used for.
+const INVITE_LIFETIME_DAYS: i64 = 7;
+
+/// Create an organization.
+#[api_v2_operation]
+async fn create(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    req: Json<<organization::Create as ApiEndpoint>::Req>,
+) -> Result<Json<<organization::Create as ApiEndpoint>::Res>, error::Server> {
+    let id = db::organization::create(&db, &req.display_name, claims.0.id).await?;
+
+    Ok(Json(CreateResponse { id }))
+}
+
+/// Get an organization.
+#[api_v2_operation]
+async fn get(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    path: web::Path<OrganizationId>,
+) -> Result<Json<<organization::Get as ApiEndpoint>::Res>, error::Organization> {
+    let id = path.into_inner();
+
+    // non-members can't tell the difference between an organization they aren't in and one that doesn't exist.
+    if db::organization::role(&db, id, claims.0.id)
+        .await?
+        .is_none()
+    {
+        return Err(error::Organization::ResourceNotFound);
+    }
+
+    let organization = db::organization::get(&db, id)
+        .await?
+        .ok_or(error::Organization::ResourceNotFound)?;
+
+    Ok(Json(organization))
+}
+
+/// Create an invite to an organization.
+#[api_v2_operation]
+async fn create_invite(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    path: web::Path<OrganizationId>,
+    req: Json<<organization::CreateInvite as ApiEndpoint>::Req>,
+) -> Result<Json<<organization::CreateInvite as ApiEndpoint>::Res>, error::Organization> {
+    let id = path.into_inner();
+
+    let can_invite = match db::organization::role(&db, id, claims.0.id).await? {
+        None => return Err(error::Organization::ResourceNotFound),
+        Some(OrganizationRole::Admin) => true,
+        Some(OrganizationRole::Teacher) => req.role == OrganizationRole::Student,
+        Some(OrganizationRole::Student) => false,
+    };
+
+    if !can_invite {
+        return Err(error::Organization::Forbidden);
+    }
+
+    let token = password::generate_token();
+    let expires_at = Utc::now() + Duration::days(INVITE_LIFETIME_DAYS);
+
+    db::organization::create_invite(
+        &db,
+        id,
+        req.role,
+        claims.0.id,
+        &password::hash_token(&token),
+        expires_at,
+    )
+    .await?;
+
+    Ok(Json(InviteCreateResponse { token, expires_at }))
+}
+
+/// Accept an invite to an organization.
+#[api_v2_operation]
+async fn accept_invite(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    req: Json<<organization::AcceptInvite as ApiEndpoint>::Req>,
+) -> Result<Json<<organization::AcceptInvite as ApiEndpoint>::Res>, error::NotFound> {
+    let (organization_id, role) =
+        db::organization::accept_invite(&db, &password::hash_token(&req.token), claims.0.id)
+            .await?
+            .ok_or(error::NotFound::ResourceNotFound)?;
+
+    Ok(Json(InviteAcceptResponse {
+        organization_id,
+        role,
+    }))
+}
+
+pub fn configure(cfg: &mut ServiceConfig<'_>) {
+    cfg.route(
+        organization::Create::PATH,
+        organization::Create::METHOD.route().to(create),
+    )
+    .route(
+        organization::AcceptInvite::PATH,
+        organization::AcceptInvite::METHOD.route().to(accept_invite),
+    )
+    .route(
+        organization::Get::PATH,
+        organization::Get::METHOD.route().to(get),
+    )
+    .route(
+        organization::CreateInvite::PATH,
+        organization::CreateInvite::METHOD.route().to(create_invite),
+    );
+}
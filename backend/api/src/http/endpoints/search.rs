@@ -1,17 +1,28 @@
 use crate::{
+    db,
     error::{self, ServiceKind},
     extractor::WrapAuthClaimsNoDb,
+    language,
 };
 use core::settings::RuntimeSettings;
 use paperclip::actix::{
     api_v2_operation,
-    web::{Data, Json, Query, ServiceConfig},
+    web::{Data, HttpRequest, Json, Query, ServiceConfig},
     CreatedJson,
 };
 use shared::{
     api::{endpoints::search, ApiEndpoint},
-    domain::search::{CreateSearchKeyResponse, WebImageSearchResponse},
+    domain::search::{
+        CreateSearchKeyResponse, MetadataSuggestion, SearchSuggestResponse, WebImageSearchResponse,
+    },
 };
+use sqlx::PgPool;
+
+/// How many suggestions of each kind are made if the query doesn't say.
+const DEFAULT_SUGGESTIONS: u32 = 5;
+
+/// The most suggestions of each kind that can be asked for.
+const MAX_SUGGESTIONS: u32 = 20;
 
 /// Create an Algolia search key based on the user's auth. Currently expires after 15 minutes, but that number is subject to change.
 /// # Errors
@@ -47,6 +58,52 @@ pub async fn search_web_images(
     Ok(Json(res))
 }
 
+/// Suggest searches and metadata for a partially typed image search query.
+#[api_v2_operation]
+async fn suggest(
+    db: Data<PgPool>,
+    client: Data<crate::search::Client>,
+    _claims: WrapAuthClaimsNoDb,
+    req: HttpRequest,
+    query: Query<<search::Suggest as ApiEndpoint>::Req>,
+) -> Result<Json<<search::Suggest as ApiEndpoint>::Res>, error::Service> {
+    let query = query.into_inner();
+    let prefix = query.q.trim();
+
+    if prefix.is_empty() {
+        return Ok(Json(SearchSuggestResponse::default()));
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SUGGESTIONS)
+        .max(1)
+        .min(MAX_SUGGESTIONS);
+
+    let languages = language::preferred(&req, query.lang.as_deref());
+
+    let mut response = SearchSuggestResponse {
+        queries: client.suggest_queries(prefix, limit).await?,
+        ..SearchSuggestResponse::default()
+    };
+
+    let metadata = db::meta::suggest(&db, &db::like_prefix(prefix), &languages, limit).await?;
+
+    for (kind, id, name) in metadata {
+        let out = match kind.as_str() {
+            "categories" => &mut response.categories,
+            "styles" => &mut response.styles,
+            "age_ranges" => &mut response.age_ranges,
+            "affiliations" => &mut response.affiliations,
+            _ => continue,
+        };
+
+        out.push(MetadataSuggestion { id, name });
+    }
+
+    Ok(Json(response))
+}
+
 pub fn configure(cfg: &mut ServiceConfig<'_>) {
     cfg.route(
         search::CreateKey::PATH,
@@ -55,5 +112,9 @@ pub fn configure(cfg: &mut ServiceConfig<'_>) {
     .route(
         search::WebImageSearch::PATH,
         search::WebImageSearch::METHOD.route().to(search_web_images),
+    )
+    .route(
+        search::Suggest::PATH,
+        search::Suggest::METHOD.route().to(suggest),
     );
 }
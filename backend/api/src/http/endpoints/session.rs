@@ -0,0 +1,195 @@
+use paperclip::actix::{
+    api_v2_operation,
+    web::{Data, Json, Path, ServiceConfig},
+    NoContent,
+};
+use shared::{
+    api::{endpoints::session, ApiEndpoint},
+    domain::{
+        classroom::ClassroomId,
+        jig::JigId,
+        session::{SessionEventKind, SessionEventRequest, SessionId},
+        CreateResponse,
+    },
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::{self, classroom::Access},
+    error,
+    extractor::WrapAuthClaimsNoDb,
+};
+
+/// Checks that the user can manage (and therefore see the progress of) the classroom.
+async fn check_manage(
+    db: &PgPool,
+    id: ClassroomId,
+    user_id: Uuid,
+) -> Result<(), error::Organization> {
+    match db::classroom::access(db, id, user_id).await? {
+        Some(Access::Manage) => Ok(()),
+        Some(Access::View) => Err(error::Organization::Forbidden),
+        None | Some(Access::Denied) => Err(error::Organization::ResourceNotFound),
+    }
+}
+
+/// Checks that the event's results make sense, only completed modules have results, and none of them are negative.
+fn validate_event(req: &SessionEventRequest) -> Result<(), error::SessionEvent> {
+    let has_results = req.score.is_some() || req.attempts.is_some() || req.time_spent_ms.is_some();
+
+    if req.kind != SessionEventKind::Completed && has_results {
+        return Err(error::SessionEvent::BadRequest(
+            "Only completed events can have results",
+        ));
+    }
+
+    let negative = req.score.map_or(false, |it| it < 0)
+        || req.attempts.map_or(false, |it| it < 0)
+        || req.time_spent_ms.map_or(false, |it| it < 0);
+
+    if negative {
+        return Err(error::SessionEvent::BadRequest("Results can't be negative"));
+    }
+
+    Ok(())
+}
+
+/// Start playing a jig.
+#[api_v2_operation]
+async fn create(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    req: Json<<session::Create as ApiEndpoint>::Req>,
+) -> Result<Json<<session::Create as ApiEndpoint>::Res>, error::Organization> {
+    let req = req.into_inner();
+    let user_id = claims.0.id;
+
+    if let Some(classroom_id) = req.classroom_id {
+        match db::classroom::access(&db, classroom_id, user_id).await? {
+            Some(Access::Manage) | Some(Access::View) => {}
+            None | Some(Access::Denied) => return Err(error::Organization::ResourceNotFound),
+        }
+
+        // sessions in a classroom are only for the jigs that were assigned to it.
+        if !db::classroom::is_jig_assigned(&db, classroom_id, req.jig_id).await? {
+            return Err(error::Organization::ResourceNotFound);
+        }
+    } else if !db::jig::exists(&db, req.jig_id).await? {
+        return Err(error::Organization::ResourceNotFound);
+    }
+
+    let id = db::session::create(&db, req.jig_id, user_id, req.classroom_id).await?;
+
+    Ok(Json(CreateResponse { id }))
+}
+
+/// Record an event that happened while playing a jig.
+#[api_v2_operation]
+async fn create_event(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    path: Path<SessionId>,
+    req: Json<<session::CreateEvent as ApiEndpoint>::Req>,
+) -> Result<NoContent, error::SessionEvent> {
+    let id = path.into_inner();
+    let req = req.into_inner();
+
+    validate_event(&req)?;
+
+    if db::session::owner(&db, id).await? != Some(claims.0.id) {
+        return Err(error::SessionEvent::ResourceNotFound);
+    }
+
+    let recorded = db::session::create_event(
+        &db,
+        id,
+        req.module_id,
+        req.kind,
+        req.score,
+        req.attempts,
+        req.time_spent_ms,
+    )
+    .await?;
+
+    if !recorded {
+        return Err(error::SessionEvent::ResourceNotFound);
+    }
+
+    Ok(NoContent)
+}
+
+/// Get the progress report for a classroom.
+#[api_v2_operation]
+async fn classroom_report(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    path: Path<ClassroomId>,
+) -> Result<Json<<session::ClassroomReport as ApiEndpoint>::Res>, error::Organization> {
+    let id = path.into_inner();
+
+    check_manage(&db, id, claims.0.id).await?;
+
+    Ok(Json(db::session::report(&db, id, None, None).await?))
+}
+
+/// Get the progress report for a jig in a classroom.
+#[api_v2_operation]
+async fn jig_report(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    Path((id, jig_id)): Path<(ClassroomId, JigId)>,
+) -> Result<Json<<session::JigReport as ApiEndpoint>::Res>, error::Organization> {
+    check_manage(&db, id, claims.0.id).await?;
+
+    Ok(Json(
+        db::session::report(&db, id, Some(jig_id), None).await?,
+    ))
+}
+
+/// Get the progress report for a student in a classroom.
+#[api_v2_operation]
+async fn student_report(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    Path((id, user_id)): Path<(ClassroomId, Uuid)>,
+) -> Result<Json<<session::StudentReport as ApiEndpoint>::Res>, error::Organization> {
+    // students can always see their own progress.
+    if user_id == claims.0.id {
+        match db::classroom::access(&db, id, user_id).await? {
+            None | Some(Access::Denied) => return Err(error::Organization::ResourceNotFound),
+            Some(_) => {}
+        }
+    } else {
+        check_manage(&db, id, claims.0.id).await?;
+    }
+
+    Ok(Json(
+        db::session::report(&db, id, None, Some(user_id)).await?,
+    ))
+}
+
+pub fn configure(cfg: &mut ServiceConfig<'_>) {
+    cfg.route(
+        session::Create::PATH,
+        session::Create::METHOD.route().to(create),
+    )
+    .route(
+        session::CreateEvent::PATH,
+        session::CreateEvent::METHOD.route().to(create_event),
+    )
+    .route(
+        session::ClassroomReport::PATH,
+        session::ClassroomReport::METHOD
+            .route()
+            .to(classroom_report),
+    )
+    .route(
+        session::JigReport::PATH,
+        session::JigReport::METHOD.route().to(jig_report),
+    )
+    .route(
+        session::StudentReport::PATH,
+        session::StudentReport::METHOD.route().to(student_report),
+    );
+}
@@ -0,0 +1,424 @@
+use std::time::Duration;
+
+use actix_web::{http::header, HttpResponse};
+use chrono::Utc;
+use core::settings::RuntimeSettings;
+use paperclip::actix::{
+    api_v2_operation,
+    web::{self, Bytes, Data, Json, Path, PayloadConfig, ServiceConfig},
+    NoContent,
+};
+use shared::{
+    api::{endpoints::upload, ApiEndpoint},
+    domain::{
+        upload::{UploadKind, UploadPart, UploadSession, UploadSessionId, UploadedPart},
+        user::UserScope,
+    },
+    media::{FileKind, MediaLibrary, PngImageFile},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::{self, upload::Session},
+    error::{self, ServiceKind},
+    extractor::WrapAuthClaimsNoDb,
+    moderation,
+    s3::{self, Location, Storage},
+};
+
+/// How long an upload session lasts, in hours.
+const SESSION_HOURS: i64 = 24;
+
+/// How long signed part urls are valid for, clients can get fresh ones with [`upload::Get`].
+const PART_URL_DURATION: Duration = Duration::from_secs(60 * 60);
+
+const fn size_limit(kind: UploadKind) -> usize {
+    match kind {
+        UploadKind::Image => config::IMAGE_BODY_SIZE_LIMIT,
+        UploadKind::Animation => config::ANIMATION_BODY_SIZE_LIMIT,
+        UploadKind::Audio => config::AUDIO_UPLOAD_SIZE_LIMIT,
+    }
+}
+
+/// The file that gets uploaded, derived files are generated from it.
+const fn file_kind(kind: UploadKind) -> FileKind {
+    match kind {
+        UploadKind::Image => FileKind::ImagePng(PngImageFile::Original),
+        UploadKind::Animation => FileKind::AnimationGif,
+        UploadKind::Audio => FileKind::AudioMp3,
+    }
+}
+
+/// Where the upload goes, user images are quarantined until they've been moderated
+/// and media that not everyone can see is private.
+async fn key(
+    db: &PgPool,
+    library: MediaLibrary,
+    media_id: Uuid,
+    kind: UploadKind,
+) -> sqlx::Result<String> {
+    let location = match (library, kind) {
+        (MediaLibrary::User, UploadKind::Image) => Location::Quarantine,
+        _ => moderation::location(db, library, media_id).await?,
+    };
+
+    Ok(location.key(library, media_id, file_kind(kind)))
+}
+
+fn storage(s3: &s3::Client) -> Result<&dyn Storage, error::UploadSession> {
+    s3.storage()
+        .ok_or(error::UploadSession::DisabledService(ServiceKind::S3))
+}
+
+/// Checks that the user can upload the media.
+async fn authorize_media(
+    db: &PgPool,
+    user_id: Uuid,
+    library: MediaLibrary,
+    kind: UploadKind,
+    media_id: Uuid,
+) -> Result<(), error::UploadSession> {
+    match library {
+        MediaLibrary::User => match db::upload::user_media_owner(db, kind, media_id).await? {
+            Some(Some(owner)) if owner == user_id => Ok(()),
+            _ => Err(error::UploadSession::ResourceNotFound),
+        },
+
+        MediaLibrary::Global => {
+            let scope = match kind {
+                UploadKind::Image => UserScope::ManageImage,
+                UploadKind::Animation => UserScope::ManageAnimation,
+                UploadKind::Audio => return Err(error::UploadSession::ResourceNotFound),
+            };
+
+            let scopes = db::user::scopes(db, user_id).await?;
+
+            if !scopes.contains(&(scope as i16)) && !scopes.contains(&(UserScope::Admin as i16)) {
+                return Err(error::UploadSession::Forbidden);
+            }
+
+            match db::upload::global_media_exists(db, kind, media_id).await? {
+                true => Ok(()),
+                false => Err(error::UploadSession::ResourceNotFound),
+            }
+        }
+
+        MediaLibrary::Web => Err(error::UploadSession::BadRequest(
+            "Web library media can't be uploaded",
+        )),
+    }
+}
+
+/// Gets a session that can still be uploaded to.
+async fn active_session(
+    db: &PgPool,
+    id: UploadSessionId,
+    user_id: Uuid,
+) -> Result<Session, error::UploadSession> {
+    let session = db::upload::get(db, id)
+        .await?
+        .filter(|it| it.user_id == user_id)
+        .ok_or(error::UploadSession::ResourceNotFound)?;
+
+    if session.completed_at.is_some() {
+        return Err(error::UploadSession::Completed);
+    }
+
+    if session.expires_at < Utc::now() {
+        return Err(error::UploadSession::Expired);
+    }
+
+    Ok(session)
+}
+
+async fn session_response(
+    storage: &dyn Storage,
+    api_url: &str,
+    session: &Session,
+) -> Result<UploadSession, error::UploadSession> {
+    let parts = (1..=session.part_count())
+        .map(|number| {
+            let url = storage
+                .signed_part_url(
+                    session.key.clone(),
+                    session.upload_id.clone(),
+                    number,
+                    session.part_len(number),
+                    PART_URL_DURATION,
+                )
+                .unwrap_or_else(|| {
+                    format!("{}/v1/upload/{}/part/{}", api_url, session.id.0, number)
+                });
+
+            UploadPart { number, url }
+        })
+        .collect();
+
+    let uploaded_parts = storage
+        .list_parts(
+            session.key.clone(),
+            session.upload_id.clone(),
+            session.part_count(),
+        )
+        .await?
+        .into_iter()
+        .map(|(number, etag)| UploadedPart { number, etag })
+        .collect();
+
+    Ok(UploadSession {
+        id: session.id,
+        part_size: session.part_size,
+        parts,
+        uploaded_parts,
+        expires_at: session.expires_at,
+    })
+}
+
+/// Start uploading a media file.
+#[api_v2_operation]
+async fn create(
+    settings: Data<RuntimeSettings>,
+    db: Data<PgPool>,
+    s3: Data<s3::Client>,
+    claims: WrapAuthClaimsNoDb,
+    req: Json<<upload::Create as ApiEndpoint>::Req>,
+) -> Result<Json<<upload::Create as ApiEndpoint>::Res>, error::UploadSession> {
+    let req = req.into_inner();
+    let user_id = claims.0.id;
+
+    if req.size <= 0 || req.size > size_limit(req.kind) as i64 {
+        return Err(error::UploadSession::BadRequest("Invalid file size"));
+    }
+
+    authorize_media(&db, user_id, req.library, req.kind, req.media_id).await?;
+
+    let storage = storage(&s3)?;
+
+    let key = key(&db, req.library, req.media_id, req.kind).await?;
+
+    let upload_id = storage
+        .create_multipart_upload(key.clone(), file_kind(req.kind).content_type())
+        .await?;
+
+    let id = db::upload::create(
+        &db,
+        user_id,
+        req.library,
+        req.media_id,
+        req.kind,
+        &key,
+        &upload_id,
+        req.size,
+        config::UPLOAD_PART_SIZE as i64,
+        Utc::now() + chrono::Duration::hours(SESSION_HOURS),
+    )
+    .await?;
+
+    let session = db::upload::get(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("upload session disappeared"))?;
+
+    Ok(Json(
+        session_response(storage, settings.remote_target().api_url(), &session).await?,
+    ))
+}
+
+/// Get an upload session (to resume it).
+#[api_v2_operation]
+async fn get(
+    settings: Data<RuntimeSettings>,
+    db: Data<PgPool>,
+    s3: Data<s3::Client>,
+    claims: WrapAuthClaimsNoDb,
+    Path(id): Path<UploadSessionId>,
+) -> Result<Json<<upload::Get as ApiEndpoint>::Res>, error::UploadSession> {
+    let session = active_session(&db, id, claims.0.id).await?;
+
+    Ok(Json(
+        session_response(storage(&s3)?, settings.remote_target().api_url(), &session).await?,
+    ))
+}
+
+/// Upload a part through the api.
+#[api_v2_operation]
+async fn upload_part(
+    db: Data<PgPool>,
+    s3: Data<s3::Client>,
+    claims: WrapAuthClaimsNoDb,
+    Path((id, number)): Path<(UploadSessionId, i32)>,
+    bytes: Bytes,
+) -> Result<HttpResponse, error::UploadSession> {
+    let session = active_session(&db, id, claims.0.id).await?;
+
+    if number < 1 || number > session.part_count() {
+        return Err(error::UploadSession::ResourceNotFound);
+    }
+
+    if bytes.len() as i64 != session.part_len(number) {
+        return Err(error::UploadSession::BadRequest("Invalid part size"));
+    }
+
+    let etag = storage(&s3)?
+        .upload_part(
+            session.key.clone(),
+            session.upload_id.clone(),
+            number,
+            bytes.to_vec(),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().header(header::ETAG, etag).finish())
+}
+
+/// Finish an upload.
+#[api_v2_operation]
+async fn complete(
+    db: Data<PgPool>,
+    s3: Data<s3::Client>,
+    claims: WrapAuthClaimsNoDb,
+    Path(id): Path<UploadSessionId>,
+    req: Json<<upload::Complete as ApiEndpoint>::Req>,
+) -> Result<NoContent, error::UploadSession> {
+    let session = active_session(&db, id, claims.0.id).await?;
+    let storage = storage(&s3)?;
+
+    let mut parts: Vec<(i32, String)> = req
+        .into_inner()
+        .parts
+        .into_iter()
+        .map(|it| (it.number, it.etag))
+        .collect();
+
+    parts.sort_by_key(|(number, _)| *number);
+
+    let mut uploaded = storage
+        .list_parts(
+            session.key.clone(),
+            session.upload_id.clone(),
+            session.part_count(),
+        )
+        .await?;
+
+    uploaded.sort_by_key(|(number, _)| *number);
+
+    let all_parts = (1..=session.part_count()).eq(parts.iter().map(|(number, _)| *number));
+
+    if !all_parts || parts != uploaded {
+        return Err(error::UploadSession::BadRequest(
+            "Parts don't match the uploaded parts",
+        ));
+    }
+
+    let mut txn = db.begin().await?;
+
+    // only one request gets to complete the upload,
+    // if anything below fails the transaction is rolled back and the session can be completed again.
+    if !db::upload::complete(&mut txn, id).await? {
+        return Err(error::UploadSession::Completed);
+    }
+
+    storage
+        .complete_multipart_upload(
+            session.key.clone(),
+            file_kind(session.kind).content_type(),
+            session.upload_id.clone(),
+            parts,
+        )
+        .await?;
+
+    // signed part urls bound each part's size, but check the whole file anyways.
+    let size = storage
+        .size(session.key.clone())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("completed upload is missing"))?;
+
+    if size > size_limit(session.kind) as u64 || size > session.size as u64 {
+        // the multipart upload is gone now, so the session stays completed (without any processing).
+        storage.delete(session.key.clone()).await?;
+        txn.commit().await?;
+
+        return Err(error::UploadSession::BadRequest("The file is too large"));
+    }
+
+    // the media could have been made premium (or public) since the upload started.
+    let key = key(&db, session.library, session.media_id, session.kind).await?;
+
+    if key != session.key {
+        let data = storage
+            .get(session.key.clone())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("completed upload is missing"))?;
+
+        storage
+            .put(key, file_kind(session.kind).content_type(), data)
+            .await?;
+
+        storage.delete(session.key.clone()).await?;
+    }
+
+    // validation and resizing happens in the background.
+    if let (MediaLibrary::User, UploadKind::Image) = (session.library, session.kind) {
+        moderation::quarantine(&mut txn, session.library, session.media_id).await?;
+    }
+
+    db::job::enqueue(
+        &mut txn,
+        session.kind.into(),
+        session.library,
+        session.media_id,
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(NoContent)
+}
+
+/// Abort an upload.
+#[api_v2_operation]
+async fn abort(
+    db: Data<PgPool>,
+    s3: Data<s3::Client>,
+    claims: WrapAuthClaimsNoDb,
+    Path(id): Path<UploadSessionId>,
+) -> Result<NoContent, error::UploadSession> {
+    let session = db::upload::get(&db, id)
+        .await?
+        .filter(|it| it.user_id == claims.0.id)
+        .ok_or(error::UploadSession::ResourceNotFound)?;
+
+    if session.completed_at.is_some() {
+        return Err(error::UploadSession::Completed);
+    }
+
+    storage(&s3)?
+        .abort_multipart_upload(
+            session.key.clone(),
+            session.upload_id.clone(),
+            session.part_count(),
+        )
+        .await?;
+
+    db::upload::delete(&db, id).await?;
+
+    Ok(NoContent)
+}
+
+pub fn configure(cfg: &mut ServiceConfig<'_>) {
+    cfg.route(
+        upload::Create::PATH,
+        upload::Create::METHOD.route().to(create),
+    )
+    .route(upload::Get::PATH, upload::Get::METHOD.route().to(get))
+    .service(
+        web::resource(upload::UploadPart::PATH)
+            .app_data(PayloadConfig::default().limit(config::UPLOAD_PART_SIZE))
+            .route(upload::UploadPart::METHOD.route().to(upload_part)),
+    )
+    .route(
+        upload::Complete::PATH,
+        upload::Complete::METHOD.route().to(complete),
+    )
+    .route(upload::Abort::PATH, upload::Abort::METHOD.route().to(abort));
+}
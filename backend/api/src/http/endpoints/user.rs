@@ -1,25 +1,38 @@
 use crate::extractor::{
-    reply_signin_auth, FirebaseUser, WrapAuthClaimsCookieDbNoCsrf, WrapAuthClaimsNoDb,
+    reply_signin_auth, BasicCredentials, Identity, IdentityUser, WrapAuthClaimsCookieDbNoCsrf,
+    WrapAuthClaimsNoDb,
 };
 use crate::{
-    db::{self, user::register},
+    db::{
+        self,
+        user::{register, AuthTokenKind, RegisterCredentials},
+    },
     error,
+    mail::{Mail, Mailer},
+    password,
 };
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
 use core::settings::RuntimeSettings;
 use jsonwebtoken as jwt;
 use paperclip::actix::{
     api_v2_operation,
-    web::{Data, Json, Query, ServiceConfig},
+    web::{Data, Json, Path, Query, ServiceConfig},
+    NoContent,
 };
 use shared::{
     api::endpoints::{
-        user::{Profile, Register, Signin, SingleSignOn, UserLookup},
+        user::{
+            CreatePasswordReset, CreateToken, DeleteToken, ListTokens, Profile, Register,
+            ResetPassword, Signin, SingleSignOn, UserLookup, VerifyEmail,
+        },
         ApiEndpoint,
     },
     domain::{
-        auth::{AuthClaims, RegisterRequest, RegisterSuccess, SigninSuccess, SingleSignOnSuccess},
-        user::UserLookupQuery,
+        auth::{
+            AuthClaims, CreatePasswordResetRequest, RegisterRequest, RegisterSuccess,
+            ResetPasswordRequest, SigninSuccess, SingleSignOnSuccess, VerifyEmailRequest,
+        },
+        user::{UserLookupQuery, UserTokenCreateResponse, UserTokenId, UserTokenListResponse},
     },
     error::auth::RegisterErrorKind,
 };
@@ -53,11 +66,25 @@ async fn user_lookup(
 async fn handle_signin_credentials(
     settings: Data<RuntimeSettings>,
     db: Data<PgPool>,
-    user: FirebaseUser,
+    user: IdentityUser,
 ) -> Result<HttpResponse, error::UserNotFound> {
-    let user_id = db::user::firebase_to_id(&db, &user.id)
-        .await?
-        .ok_or(error::UserNotFound::UserNotFound)?;
+    let user_id = match user.identity {
+        Identity::Firebase(id) => db::user::firebase_to_id(&db, &id).await?,
+        Identity::Basic(BasicCredentials { email, password }) => {
+            // note: unknown emails and wrong passwords are intentionally indistinguishable.
+            match db::user::basic_to_id(&db, &email).await? {
+                Some((user_id, hash)) => {
+                    let valid = web::block(move || password::verify(&hash, &password)).await?;
+                    Some(user_id).filter(|_| valid)
+                }
+                None => {
+                    web::block(move || password::verify(password::DUMMY_HASH, &password)).await?;
+                    None
+                }
+            }
+        }
+    }
+    .ok_or(error::UserNotFound::UserNotFound)?;
 
     let (csrf, cookie) =
         reply_signin_auth(user_id, &settings.jwt_encoding_key, settings.is_local())?;
@@ -78,17 +105,103 @@ async fn validate_register_req(req: &RegisterRequest) -> Result<(), error::Regis
     Ok(())
 }
 
+fn validate_basic_credentials(
+    credentials: &BasicCredentials,
+    req: &RegisterRequest,
+) -> Result<(), error::Register> {
+    if !credentials.email.eq_ignore_ascii_case(&req.email) {
+        return Err(error::Register::RegisterError(
+            RegisterErrorKind::EmailMismatch,
+        ));
+    }
+
+    if credentials.password.chars().count() < password::MIN_PASSWORD_LEN {
+        return Err(error::Register::RegisterError(
+            RegisterErrorKind::WeakPassword,
+        ));
+    }
+
+    Ok(())
+}
+
+fn auth_token_mail(email: &str, kind: AuthTokenKind, token: &str) -> Mail {
+    let (subject, body) = match kind {
+        AuthTokenKind::VerifyEmail => (
+            "Verify your email",
+            format!("Use this code to verify your email: {}", token),
+        ),
+        AuthTokenKind::ResetPassword => (
+            "Reset your password",
+            format!(
+                "Use this code to reset your password: {}\n\nIf you didn't ask to reset your password, you can ignore this email.",
+                token
+            ),
+        ),
+    };
+
+    Mail {
+        to: email.to_owned(),
+        subject: subject.to_owned(),
+        body,
+    }
+}
+
+async fn create_auth_token(
+    mailer: &Mailer,
+    db: &PgPool,
+    user_id: uuid::Uuid,
+    email: &str,
+    kind: AuthTokenKind,
+) -> sqlx::Result<()> {
+    let token = password::generate_token();
+
+    db::user::create_auth_token(db, user_id, kind, &password::hash_token(&token)).await?;
+
+    // the user can ask for another token, so failing to send one doesn't fail the request.
+    if let Err(e) = mailer.send(&auth_token_mail(email, kind, &token)).await {
+        log::error!("failed to send {:?} token to {}: {:?}", kind, email, e);
+    }
+
+    Ok(())
+}
+
 /// Register a new user.
 #[api_v2_operation]
 async fn handle_register(
     settings: Data<RuntimeSettings>,
+    mailer: Data<Mailer>,
     db: Data<PgPool>,
-    user: FirebaseUser,
+    user: IdentityUser,
     req: Json<RegisterRequest>,
 ) -> actix_web::Result<HttpResponse, error::Register> {
     validate_register_req(&req).await?;
 
-    let id = register(db.as_ref(), &user.id, &req).await?;
+    let id = match user.identity {
+        Identity::Firebase(id) => {
+            register(db.as_ref(), RegisterCredentials::Firebase(&id), &req).await?
+        }
+
+        Identity::Basic(credentials) => {
+            validate_basic_credentials(&credentials, &req)?;
+
+            let BasicCredentials { email, password } = credentials;
+            let password_hash = web::block(move || password::hash(&password)).await?;
+
+            let id = register(
+                db.as_ref(),
+                RegisterCredentials::Basic {
+                    email: &email,
+                    password_hash: &password_hash,
+                },
+                &req,
+            )
+            .await?;
+
+            create_auth_token(&mailer, &db, id, &email, AuthTokenKind::VerifyEmail).await?;
+
+            id
+        }
+    };
 
     let (csrf, cookie) = reply_signin_auth(id, &settings.jwt_encoding_key, settings.is_local())?;
 
@@ -127,6 +240,130 @@ async fn handle_authorize(
     Ok(Json(SingleSignOnSuccess { jwt }))
 }
 
+/// Verify a user's email, using the token they were sent on registration.
+#[api_v2_operation]
+async fn handle_verify_email(
+    db: Data<PgPool>,
+    req: Json<<VerifyEmail as ApiEndpoint>::Req>,
+) -> Result<NoContent, error::NotFound> {
+    let VerifyEmailRequest { token } = req.into_inner();
+
+    if !db::user::verify_email(&db, &password::hash_token(&token)).await? {
+        return Err(error::NotFound::ResourceNotFound);
+    }
+
+    Ok(NoContent)
+}
+
+/// Request a password reset token be sent to the given email.
+///
+/// This always succeeds, to avoid leaking which emails are registered.
+#[api_v2_operation]
+async fn handle_create_password_reset(
+    mailer: Data<Mailer>,
+    db: Data<PgPool>,
+    req: Json<<CreatePasswordReset as ApiEndpoint>::Req>,
+) -> Result<NoContent, error::Server> {
+    let CreatePasswordResetRequest { email } = req.into_inner();
+
+    if let Some((user_id, _)) = db::user::basic_to_id(&db, &email).await? {
+        create_auth_token(&mailer, &db, user_id, &email, AuthTokenKind::ResetPassword).await?;
+    }
+
+    Ok(NoContent)
+}
+
+/// Set a new password, using a password reset token.
+#[api_v2_operation]
+async fn handle_reset_password(
+    db: Data<PgPool>,
+    req: Json<<ResetPassword as ApiEndpoint>::Req>,
+) -> Result<NoContent, error::ResetPassword> {
+    let ResetPasswordRequest { token, password } = req.into_inner();
+
+    if password.chars().count() < password::MIN_PASSWORD_LEN {
+        return Err(error::ResetPassword::WeakPassword);
+    }
+
+    let password_hash = web::block(move || password::hash(&password)).await?;
+
+    if !db::user::reset_password(&db, &password::hash_token(&token), &password_hash).await? {
+        return Err(error::ResetPassword::InvalidToken);
+    }
+
+    Ok(NoContent)
+}
+
+/// Checks that the user signed in "normally".
+///
+/// Personal access tokens can't manage personal access tokens, otherwise a token could mint itself more scopes.
+fn check_not_personal_token(claims: &WrapAuthClaimsNoDb) -> Result<(), error::ManageTokens> {
+    match claims.0.csrf {
+        Some(_) => Ok(()),
+        None => Err(error::ManageTokens::PersonalToken),
+    }
+}
+
+/// Create a personal access token.
+#[api_v2_operation]
+async fn handle_create_token(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    req: Json<<CreateToken as ApiEndpoint>::Req>,
+) -> Result<Json<<CreateToken as ApiEndpoint>::Res>, error::ManageTokens> {
+    check_not_personal_token(&claims)?;
+
+    let req = req.into_inner();
+    let user_id = claims.0.id;
+
+    let user_scopes = db::user::scopes(&db, user_id).await?;
+    let scopes: Vec<i16> = req.scopes.into_iter().map(|it| it as i16).collect();
+
+    if !scopes.iter().all(|scope| user_scopes.contains(scope)) {
+        return Err(error::ManageTokens::ScopeNotHeld);
+    }
+
+    let token = password::generate_token();
+
+    let id = db::user::create_token(
+        &db,
+        user_id,
+        &req.display_name,
+        &scopes,
+        &password::hash_token(&token),
+    )
+    .await?;
+
+    Ok(Json(UserTokenCreateResponse { id, token }))
+}
+
+/// List the user's personal access tokens.
+#[api_v2_operation]
+async fn handle_list_tokens(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+) -> Result<Json<<ListTokens as ApiEndpoint>::Res>, error::ManageTokens> {
+    check_not_personal_token(&claims)?;
+
+    let tokens = db::user::list_tokens(&db, claims.0.id).await?;
+
+    Ok(Json(UserTokenListResponse { tokens }))
+}
+
+/// Revoke a personal access token.
+#[api_v2_operation]
+async fn handle_delete_token(
+    db: Data<PgPool>,
+    claims: WrapAuthClaimsNoDb,
+    path: Path<UserTokenId>,
+) -> Result<NoContent, error::ManageTokens> {
+    check_not_personal_token(&claims)?;
+
+    db::user::delete_token(&db, claims.0.id, path.into_inner()).await?;
+
+    Ok(NoContent)
+}
+
 pub fn configure(cfg: &mut ServiceConfig<'_>) {
     cfg.route(
         Profile::PATH,
@@ -141,5 +378,31 @@ pub fn configure(cfg: &mut ServiceConfig<'_>) {
         Signin::PATH,
         Signin::METHOD.route().to(handle_signin_credentials),
     )
-    .route(UserLookup::PATH, UserLookup::METHOD.route().to(user_lookup));
+    .route(UserLookup::PATH, UserLookup::METHOD.route().to(user_lookup))
+    .route(
+        VerifyEmail::PATH,
+        VerifyEmail::METHOD.route().to(handle_verify_email),
+    )
+    .route(
+        CreatePasswordReset::PATH,
+        CreatePasswordReset::METHOD
+            .route()
+            .to(handle_create_password_reset),
+    )
+    .route(
+        ResetPassword::PATH,
+        ResetPassword::METHOD.route().to(handle_reset_password),
+    )
+    .route(
+        CreateToken::PATH,
+        CreateToken::METHOD.route().to(handle_create_token),
+    )
+    .route(
+        ListTokens::PATH,
+        ListTokens::METHOD.route().to(handle_list_tokens),
+    )
+    .route(
+        DeleteToken::PATH,
+        DeleteToken::METHOD.route().to(handle_delete_token),
+    );
 }
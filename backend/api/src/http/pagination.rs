@@ -0,0 +1,81 @@
+//! Cursor pagination for routes that list items newest first (by `created_at`, then `id`).
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+/// Default amount of items listed per page.
+pub const DEFAULT_LIMIT: u32 = 100;
+
+/// Maximum amount of items listed per page.
+pub const MAX_LIMIT: u32 = 1000;
+
+/// The amount of items to list per page, given the requested amount.
+pub fn limit(requested: Option<u32>) -> u32 {
+    requested.unwrap_or(DEFAULT_LIMIT).max(1).min(MAX_LIMIT)
+}
+
+/// Encodes the last item of a page as a cursor for the next page.
+pub fn encode_cursor(created_at: DateTime<Utc>, id: Uuid) -> String {
+    base64::encode_config(
+        format!(
+            "{}|{}",
+            created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+            id.to_hyphenated()
+        ),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
+
+/// The inverse of [`encode_cursor`], returns `None` if `cursor` isn't a valid cursor.
+pub fn decode_cursor(cursor: &str) -> Option<(DateTime<Utc>, Uuid)> {
+    let cursor = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+    let cursor = std::str::from_utf8(&cursor).ok()?;
+
+    let idx = cursor.find('|')?;
+    let created_at = DateTime::parse_from_rfc3339(&cursor[..idx]).ok()?;
+    let id = Uuid::parse_str(&cursor[(idx + 1)..]).ok()?;
+
+    Some((created_at.with_timezone(&Utc), id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_cursor, encode_cursor, limit, MAX_LIMIT};
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    #[test]
+    fn cursor_roundtrips() {
+        let created_at = Utc.timestamp(1_600_000_000, 123_456_000);
+        let id = Uuid::parse_str("3095d05e-f2c7-11ea-89c3-3b621dd74a1f").unwrap();
+
+        assert_eq!(
+            decode_cursor(&encode_cursor(created_at, id)),
+            Some((created_at, id))
+        );
+    }
+
+    #[test]
+    fn invalid_cursors_are_rejected() {
+        let cursors = [
+            "not a cursor".to_owned(),
+            base64::encode_config("no separator", base64::URL_SAFE_NO_PAD),
+            base64::encode_config("2020-09-13T12:26:40Z|not-a-uuid", base64::URL_SAFE_NO_PAD),
+            base64::encode_config(
+                "yesterday|3095d05e-f2c7-11ea-89c3-3b621dd74a1f",
+                base64::URL_SAFE_NO_PAD,
+            ),
+        ];
+
+        for cursor in &cursors {
+            assert_eq!(decode_cursor(cursor), None, "{:?}", cursor);
+        }
+    }
+
+    #[test]
+    fn limit_is_clamped() {
+        assert_eq!(limit(Some(0)), 1);
+        assert_eq!(limit(Some(50)), 50);
+        assert_eq!(limit(Some(u32::MAX)), MAX_LIMIT);
+    }
+}
@@ -0,0 +1,633 @@
+//! Per-user and per-IP rate limiting, using token buckets.
+//!
+//! Each [`Rule`] (see [`RateLimitSettings`]) applies to a single route, and can limit both the user making the request and the IP it came from.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    net::{IpAddr, SocketAddr},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use actix_service::{Service, Transform};
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    web::Data,
+    HttpMessage, HttpResponse,
+};
+use chrono::{DateTime, Duration, Utc};
+use core::settings::{
+    RateLimitQuota as Quota, RateLimitRule as Rule, RateLimitSettings, RateLimitStore,
+    RuntimeSettings,
+};
+use futures::future::{self, FutureExt, LocalBoxFuture};
+use shared::domain::auth::JWT_COOKIE_NAME;
+use sqlx::PgPool;
+
+use crate::{error::BasicError, jwkkeys::JwkVerifier};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Bucket {
+    tokens: f64,
+    updated_at: DateTime<Utc>,
+}
+
+impl Bucket {
+    fn full(quota: Quota, now: DateTime<Utc>) -> Self {
+        Self {
+            tokens: f64::from(quota.burst),
+            updated_at: now,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn refill(&mut self, quota: Quota, now: DateTime<Utc>) {
+        let elapsed = (now - self.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+
+        self.tokens =
+            (self.tokens + elapsed / f64::from(quota.refill_secs)).min(f64::from(quota.burst));
+
+        self.updated_at = now;
+    }
+
+    /// Takes a token from the bucket, or returns how long until one will be available.
+    #[allow(clippy::cast_possible_truncation)]
+    fn take(&mut self, quota: Quota, now: DateTime<Utc>) -> Result<(), Duration> {
+        self.refill(quota, now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let wait_secs = (1.0 - self.tokens) * f64::from(quota.refill_secs);
+
+        Err(Duration::milliseconds((wait_secs * 1000.0).ceil() as i64))
+    }
+
+    /// When the bucket will be full again, after which it's the same as a missing one.
+    #[allow(clippy::cast_possible_truncation)]
+    fn full_at(&self, quota: Quota) -> DateTime<Utc> {
+        let missing = (f64::from(quota.burst) - self.tokens).max(0.0);
+
+        self.updated_at
+            + Duration::milliseconds((missing * f64::from(quota.refill_secs) * 1000.0).ceil() as i64)
+    }
+}
+
+/// Don't let the in memory store grow without bounds.
+const MAX_MEMORY_BUCKETS: usize = 100_000;
+
+/// In memory buckets, along with when each of them expires (is full again).
+#[derive(Default)]
+struct MemoryBuckets {
+    buckets: HashMap<String, (Bucket, DateTime<Utc>)>,
+
+    /// Ordered by expiry, so that expired buckets can be found without looking at the rest of them.
+    expiries: BTreeSet<(DateTime<Utc>, String)>,
+}
+
+impl MemoryBuckets {
+    fn take(&mut self, key: String, quota: Quota, now: DateTime<Utc>) -> Result<(), Duration> {
+        self.evict(now);
+
+        let mut bucket = match self.buckets.remove(&key) {
+            Some((bucket, expires_at)) => {
+                self.expiries.remove(&(expires_at, key.clone()));
+                bucket
+            }
+
+            None => Bucket::full(quota, now),
+        };
+
+        let res = bucket.take(quota, now);
+        let expires_at = bucket.full_at(quota);
+
+        self.expiries.insert((expires_at, key.clone()));
+        self.buckets.insert(key, (bucket, expires_at));
+
+        res
+    }
+
+    /// Removes expired buckets, and if there are still too many, the ones that are closest to expiring.
+    fn evict(&mut self, now: DateTime<Utc>) {
+        while let Some((expires_at, key)) = self.expiries.iter().next().cloned() {
+            if expires_at > now && self.buckets.len() < MAX_MEMORY_BUCKETS {
+                break;
+            }
+
+            self.expiries.remove(&(expires_at, key.clone()));
+            self.buckets.remove(&key);
+        }
+    }
+}
+
+/// How often expired buckets get deleted from postgres.
+const POSTGRES_CLEANUP_MINUTES: i64 = 10;
+
+enum Store {
+    Disabled,
+    Memory(Mutex<MemoryBuckets>),
+    Postgres {
+        pool: PgPool,
+        cleaned_up_at: Mutex<DateTime<Utc>>,
+    },
+}
+
+impl Store {
+    async fn take(&self, key: String, quota: Quota) -> anyhow::Result<Result<(), Duration>> {
+        match self {
+            Self::Disabled => Ok(Ok(())),
+
+            Self::Memory(buckets) => Ok(buckets.lock().unwrap().take(key, quota, Utc::now())),
+
+            Self::Postgres {
+                pool,
+                cleaned_up_at,
+            } => {
+                let mut txn = pool.begin().await?;
+
+                sqlx::query!(
+                    r#"
+insert into rate_limit_bucket (key, tokens, updated_at, expires_at)
+values ($1, $2, now(), now())
+on conflict (key) do nothing
+"#,
+                    &key,
+                    f64::from(quota.burst)
+                )
+                .execute(&mut txn)
+                .await?;
+
+                let row = sqlx::query!(
+                    r#"
+select tokens, updated_at, now() as "now!"
+from rate_limit_bucket
+where key = $1
+for update
+"#,
+                    &key
+                )
+                .fetch_one(&mut txn)
+                .await?;
+
+                let mut bucket = Bucket {
+                    tokens: row.tokens,
+                    updated_at: row.updated_at,
+                };
+
+                let res = bucket.take(quota, row.now);
+
+                sqlx::query!(
+                    "update rate_limit_bucket set tokens = $2, updated_at = $3, expires_at = $4 where key = $1",
+                    &key,
+                    bucket.tokens,
+                    bucket.updated_at,
+                    bucket.full_at(quota)
+                )
+                .execute(&mut txn)
+                .await?;
+
+                txn.commit().await?;
+
+                let cleanup = {
+                    let mut cleaned_up_at = cleaned_up_at.lock().unwrap();
+                    let due =
+                        row.now - *cleaned_up_at >= Duration::minutes(POSTGRES_CLEANUP_MINUTES);
+
+                    if due {
+                        *cleaned_up_at = row.now;
+                    }
+
+                    due
+                };
+
+                if cleanup {
+                    sqlx::query!("delete from rate_limit_bucket where expires_at < now()")
+                        .execute(pool)
+                        .await?;
+                }
+
+                Ok(res)
+            }
+        }
+    }
+}
+
+/// Identifies the user making a request.
+///
+/// Only verified credentials count: a JWT cookie with a valid signature, a personal access token that exists,
+/// or a valid firebase token. Anything else returns `None`, otherwise every made up token would get its own bucket.
+///
+/// This intentionally doesn't validate CSRF or token scopes (the extractors do that),
+/// it only needs to be stable for a given user.
+async fn user_key(req: &ServiceRequest) -> Option<String> {
+    let settings: &Data<RuntimeSettings> = req.app_data()?;
+
+    if let Some(cookie) = req.cookie(JWT_COOKIE_NAME) {
+        let claims = crate::jwt::get_claims(cookie.value(), &settings.jwt_decoding_key()).ok()?;
+
+        return Some(format!("user:{}", claims.id));
+    }
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+
+    let db: &Data<PgPool> = req.app_data()?;
+
+    match crate::db::user::token_owner(db, &crate::password::hash_token(token)).await {
+        Ok(Some(user_id)) => return Some(format!("user:{}", user_id)),
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to look up personal access token: {:?}", e),
+    }
+
+    let id = if settings.firebase_assume_valid() {
+        crate::extractor::try_insecure_decode(token)?
+    } else {
+        let jwk_verifier = req.app_data::<Option<Arc<JwkVerifier>>>()?.as_ref()?;
+
+        jwk_verifier.verify(token, 1).await.ok()?
+    };
+
+    Some(format!("firebase:{}", id.0))
+}
+
+/// Finds the IP that made the request.
+///
+/// Each proxy appends the address it saw to `X-Forwarded-For`, so with `trusted_proxies` in front of the api,
+/// the client's address is that many entries from the end (anything before it could've been sent by the client).
+/// Falls back to the connection's address if there aren't enough entries.
+fn client_ip(
+    peer: Option<IpAddr>,
+    forwarded_for: Option<&str>,
+    trusted_proxies: usize,
+) -> Option<IpAddr> {
+    if trusted_proxies == 0 {
+        return peer;
+    }
+
+    forwarded_for
+        .and_then(|it| it.rsplit(',').nth(trusted_proxies - 1))
+        .map(str::trim)
+        .and_then(|it| {
+            it.parse::<IpAddr>()
+                .ok()
+                .or_else(|| it.parse::<SocketAddr>().ok().map(|it| it.ip()))
+        })
+        .or(peer)
+}
+
+fn ip_key(req: &ServiceRequest, trusted_proxies: usize) -> Option<String> {
+    let forwarded_for = req
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|it| it.to_str().ok());
+
+    let ip = client_ip(
+        req.peer_addr().map(|it| it.ip()),
+        forwarded_for,
+        trusted_proxies,
+    )?;
+
+    Some(format!("ip:{}", ip))
+}
+
+/// Checks requests against the configured [`Rule`]s.
+pub struct RateLimiter {
+    store: Store,
+    trusted_proxies: usize,
+    rules: Vec<Rule>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings, pool: PgPool) -> Self {
+        let store = match settings.store {
+            RateLimitStore::Disabled => Store::Disabled,
+            RateLimitStore::Memory => Store::Memory(Mutex::default()),
+            RateLimitStore::Postgres => Store::Postgres {
+                pool,
+                cleaned_up_at: Mutex::new(Utc::now()),
+            },
+        };
+
+        Self {
+            store,
+            trusted_proxies: settings.trusted_proxies,
+            rules: settings.rules,
+        }
+    }
+
+    fn rule(&self, req: &ServiceRequest) -> Option<&Rule> {
+        let pattern = req.match_pattern();
+        let path = pattern.as_deref().unwrap_or_else(|| req.path());
+
+        self.rules.iter().find(|rule| {
+            rule.path == path && rule.method.eq_ignore_ascii_case(req.method().as_str())
+        })
+    }
+
+    /// Returns how long the caller should wait before retrying, if they've been rate limited.
+    async fn check(&self, route: &str, keys: Vec<(String, Quota)>) -> Option<Duration> {
+        let mut retry_after: Option<Duration> = None;
+
+        for (key, quota) in keys {
+            let key = format!("{}:{}", route, key);
+
+            match self.store.take(key, quota).await {
+                Ok(Ok(())) => {}
+                Ok(Err(wait)) => retry_after = Some(retry_after.map_or(wait, |it| it.max(wait))),
+                // failing open is better than taking down the routes.
+                Err(e) => log::warn!("Failed to check rate limit: {:?}", e),
+            }
+        }
+
+        retry_after
+    }
+}
+
+fn too_many_requests(retry_after: Duration) -> actix_web::Error {
+    let secs = (retry_after.num_milliseconds() + 999) / 1000;
+
+    HttpResponse::TooManyRequests()
+        .set_header(header::RETRY_AFTER, secs.to_string())
+        .json(BasicError::with_message(
+            http::StatusCode::TOO_MANY_REQUESTS,
+            format!("Too Many Requests: retry in {} seconds", secs),
+        ))
+        .into()
+}
+
+/// Middleware that applies a [`RateLimiter`] to every request.
+pub struct RateLimit(pub Arc<RateLimiter>);
+
+impl<S, B> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>
+        + 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ok(RateLimitMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            limiter: self.0.clone(),
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S, B> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>
+        + 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let rule = match self.limiter.rule(&req) {
+            Some(rule) => rule,
+            None => return self.service.borrow_mut().call(req).boxed_local(),
+        };
+
+        let route = format!("{} {}", rule.method.to_ascii_uppercase(), rule.path);
+
+        let per_user = rule.per_user;
+        let ip_key = ip_key(&req, self.limiter.trusted_proxies);
+        let ip_quota = rule.per_ip.and_then(|quota| Some((ip_key.clone()?, quota)));
+
+        let service = self.service.clone();
+        let limiter = self.limiter.clone();
+
+        async move {
+            let user_quota = match per_user {
+                // requests that can't be tied to a user share a bucket per IP instead.
+                Some(quota) => match user_key(&req).await {
+                    Some(key) => Some((key, quota)),
+                    None => ip_key.map(|it| (format!("anonymous:{}", it), quota)),
+                },
+                None => None,
+            };
+
+            let keys = user_quota.into_iter().chain(ip_quota).collect();
+
+            if let Some(retry_after) = limiter.check(&route, keys).await {
+                return Err(too_many_requests(retry_after));
+            }
+
+            let future = service.borrow_mut().call(req);
+            future.await
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_ip, Bucket, MemoryBuckets, Quota, MAX_MEMORY_BUCKETS};
+    use chrono::{Duration, TimeZone, Utc};
+    use core::settings::RateLimitRule;
+    use shared::api::{endpoints, ApiEndpoint};
+
+    const QUOTA: Quota = Quota {
+        burst: 3,
+        refill_secs: 10,
+    };
+
+    #[test]
+    fn bucket_drains() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        let mut bucket = Bucket::full(QUOTA, now);
+
+        for _ in 0..QUOTA.burst {
+            assert_eq!(bucket.take(QUOTA, now), Ok(()));
+        }
+
+        assert_eq!(bucket.take(QUOTA, now), Err(Duration::seconds(10)));
+    }
+
+    #[test]
+    fn bucket_refills() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        let mut bucket = Bucket {
+            tokens: 0.0,
+            updated_at: now,
+        };
+
+        // half of a token.
+        assert_eq!(
+            bucket.take(QUOTA, now + Duration::seconds(5)),
+            Err(Duration::seconds(5))
+        );
+
+        assert_eq!(bucket.take(QUOTA, now + Duration::seconds(10)), Ok(()));
+        assert!(bucket.tokens.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn bucket_refills_up_to_burst() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        let mut bucket = Bucket {
+            tokens: 1.0,
+            updated_at: now,
+        };
+
+        bucket.refill(QUOTA, now + Duration::days(1));
+
+        assert!((bucket.tokens - f64::from(QUOTA.burst)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn bucket_full_at() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+
+        assert_eq!(Bucket::full(QUOTA, now).full_at(QUOTA), now);
+
+        let bucket = Bucket {
+            tokens: 0.5,
+            updated_at: now,
+        };
+
+        assert_eq!(bucket.full_at(QUOTA), now + Duration::seconds(25));
+    }
+
+    #[test]
+    fn memory_evicts_expired() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        let mut buckets = MemoryBuckets::default();
+
+        assert_eq!(buckets.take("a".to_owned(), QUOTA, now), Ok(()));
+        assert_eq!(
+            buckets.take("b".to_owned(), QUOTA, now + Duration::seconds(5)),
+            Ok(())
+        );
+
+        // `a` is full again, `b` isn't.
+        assert_eq!(
+            buckets.take("c".to_owned(), QUOTA, now + Duration::seconds(10)),
+            Ok(())
+        );
+
+        assert!(!buckets.buckets.contains_key("a"));
+        assert!(buckets.buckets.contains_key("b"));
+        assert_eq!(buckets.buckets.len(), buckets.expiries.len());
+    }
+
+    #[test]
+    fn memory_keeps_state() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        let mut buckets = MemoryBuckets::default();
+
+        for _ in 0..QUOTA.burst {
+            assert_eq!(buckets.take("a".to_owned(), QUOTA, now), Ok(()));
+        }
+
+        assert!(buckets.take("a".to_owned(), QUOTA, now).is_err());
+        assert_eq!(buckets.take("b".to_owned(), QUOTA, now), Ok(()));
+        assert_eq!(buckets.expiries.len(), 2);
+    }
+
+    #[test]
+    fn memory_is_bounded() {
+        let now = Utc.timestamp(1_600_000_000, 0);
+        let mut buckets = MemoryBuckets::default();
+
+        for idx in 0..=MAX_MEMORY_BUCKETS {
+            let _ = buckets.take(idx.to_string(), QUOTA, now);
+        }
+
+        assert_eq!(buckets.buckets.len(), MAX_MEMORY_BUCKETS);
+        assert_eq!(buckets.expiries.len(), MAX_MEMORY_BUCKETS);
+        assert!(buckets
+            .buckets
+            .contains_key(&MAX_MEMORY_BUCKETS.to_string()));
+    }
+
+    #[test]
+    fn client_ip_without_proxies() {
+        let peer = Some("10.0.0.1".parse().unwrap());
+
+        assert_eq!(client_ip(peer, Some("1.2.3.4"), 0), peer);
+    }
+
+    #[test]
+    fn client_ip_behind_proxies() {
+        let peer = Some("10.0.0.1".parse().unwrap());
+        let client = Some("1.2.3.4".parse().unwrap());
+
+        // the first entry was sent by the client.
+        assert_eq!(client_ip(peer, Some("5.6.7.8, 1.2.3.4"), 1), client);
+        assert_eq!(
+            client_ip(peer, Some("5.6.7.8, 1.2.3.4, 10.0.0.2"), 2),
+            client
+        );
+        assert_eq!(
+            client_ip(peer, Some("[2001:db8::1]:443"), 1),
+            Some("2001:db8::1".parse().unwrap())
+        );
+
+        assert_eq!(client_ip(peer, None, 1), peer);
+        assert_eq!(client_ip(peer, Some("1.2.3.4"), 2), peer);
+    }
+
+    #[test]
+    fn default_rules_are_endpoints() {
+        let endpoints = [
+            (
+                endpoints::media::Create::METHOD,
+                endpoints::media::Create::PATH,
+            ),
+            (
+                endpoints::search::WebImageSearch::METHOD,
+                endpoints::search::WebImageSearch::PATH,
+            ),
+            (
+                endpoints::user::Register::METHOD,
+                endpoints::user::Register::PATH,
+            ),
+            (
+                endpoints::user::Signin::METHOD,
+                endpoints::user::Signin::PATH,
+            ),
+            (
+                endpoints::user::CreatePasswordReset::METHOD,
+                endpoints::user::CreatePasswordReset::PATH,
+            ),
+        ];
+
+        for rule in RateLimitRule::defaults() {
+            assert!(
+                endpoints
+                    .iter()
+                    .any(|(method, path)| method.as_str() == rule.method && *path == rule.path),
+                "{} {} isn't an endpoint",
+                rule.method,
+                rule.path
+            );
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Bulk importing of images into the global image library.
+//!
+//! An import is created from a manifest of images. Items with a source url get downloaded by a
+//! [`JobKind::ImportImage`] job, the rest wait for their file to be uploaded through the api.
+//! Either way the file then goes down the same path as a regular upload: the image is created,
+//! the original is stored, and a [`JobKind::ProcessImage`] job generates the resized images.
+
+use shared::{
+    domain::{
+        image::{import::ImageImportStatus, ImageId},
+        meta::MetaKind,
+    },
+    media::{FileKind, MediaLibrary, PngImageFile},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::{self, meta::MetaWrapperError, nul_if_empty},
+    jobs::JobKind,
+    s3::{self, Location},
+};
+
+pub enum ImportError {
+    /// The file isn't an image.
+    InvalidImage,
+
+    /// Some of the item's metadata has been deleted since the import was created.
+    MissingMetadata {
+        id: Option<Uuid>,
+        kind: MetaKind,
+    },
+
+    Other(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for ImportError {
+    fn from(e: T) -> Self {
+        Self::Other(e.into())
+    }
+}
+
+/// Creates the image for an import item from its file, returns `None` if the item doesn't exist or has already been imported.
+pub async fn import(
+    db: &PgPool,
+    s3: &s3::Client,
+    item_id: Uuid,
+    data: Vec<u8>,
+) -> Result<Option<ImageId>, ImportError> {
+    // resizing happens in the background, but at least make sure that it's an image.
+    if image::guess_format(&data).is_err() {
+        return Err(ImportError::InvalidImage);
+    }
+
+    let mut txn = db.begin().await?;
+
+    let item = match db::image::import::lock_item(&mut txn, item_id).await? {
+        Some(item) if item.status != ImageImportStatus::Imported => item,
+        _ => return Ok(None),
+    };
+
+    let id = db::image::create(
+        &mut txn,
+        &item.name,
+        &item.description,
+        item.is_premium,
+        item.publish_at,
+        item.kind,
+        item.attribution.as_deref(),
+        item.license,
+        item.source_url.as_deref(),
+    )
+    .await?;
+
+    db::image::update_metadata(
+        &mut txn,
+        id,
+        nul_if_empty(&item.affiliations),
+        nul_if_empty(&item.age_ranges),
+        nul_if_empty(&item.styles),
+        nul_if_empty(&item.categories),
+    )
+    .await
+    .map_err(|e| match db::meta::handle_metadata_err(e) {
+        MetaWrapperError::MissingMetadata { id, kind } => ImportError::MissingMetadata { id, kind },
+        MetaWrapperError::Sqlx(e) => e.into(),
+    })?;
+
+    s3.upload_media_to(
+        Location::visible(MediaLibrary::Global, item.is_premium),
+        data,
+        MediaLibrary::Global,
+        id.0,
+        FileKind::ImagePng(PngImageFile::Original),
+    )
+    .await?;
+
+    db::job::enqueue(&mut txn, JobKind::ProcessImage, MediaLibrary::Global, id.0).await?;
+
+    db::image::import::set_imported(&mut txn, item_id, id).await?;
+
+    txn.commit().await?;
+
+    Ok(Some(id))
+}
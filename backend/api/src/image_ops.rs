@@ -1,9 +1,9 @@
 use image::{
     gif::GifDecoder, imageops::FilterType, AnimationDecoder, DynamicImage, GenericImageView,
-    ImageOutputFormat,
+    ImageDecoder, ImageOutputFormat, RgbaImage,
 };
 use shared::domain::{animation::AnimationKind, audio::AudioKind, image::ImageKind};
-use shared::media::MediaKind as SharedMediaKind;
+use shared::media::{MediaKind as SharedMediaKind, MediaKindFilter};
 
 /// Kinds of media used with the web media library
 #[repr(i16)]
@@ -35,6 +35,21 @@ impl MediaKind {
             Self::Mp3Audio => SharedMediaKind::Audio(AudioKind::Mp3),
         }
     }
+
+    /// The kinds that match `filter` (as stored in the database).
+    pub fn for_filter(filter: MediaKindFilter) -> Vec<i16> {
+        let kinds: &[Self] = match filter {
+            MediaKindFilter::Image => &[Self::PngStickerImage, Self::PngCanvasImage],
+            MediaKindFilter::CanvasImage => &[Self::PngCanvasImage],
+            MediaKindFilter::StickerImage => &[Self::PngStickerImage],
+            MediaKindFilter::Animation => &[Self::GifAnimation, Self::SpritesheetAnimation],
+            MediaKindFilter::GifAnimation => &[Self::GifAnimation],
+            MediaKindFilter::SpritesheetAnimation => &[Self::SpritesheetAnimation],
+            MediaKindFilter::Audio => &[Self::Mp3Audio],
+        };
+
+        kinds.iter().map(|it| *it as i16).collect()
+    }
 }
 
 // todo: use a better method for this
@@ -101,3 +116,167 @@ pub fn generate_images(
 
     Ok((original, resized, thumbnail))
 }
+
+/// The most pixels a spritesheet can have.
+const SPRITESHEET_PIXEL_LIMIT: u64 = 4096 * 4096;
+
+/// Lays out every frame of the gif side by side (left to right) in a png,
+/// each frame is as large as the gif.
+///
+/// Returns `None` if the gif doesn't have any frames, or if the spritesheet would be too large.
+pub fn generate_spritesheet(gif: &[u8]) -> image::ImageResult<Option<Vec<u8>>> {
+    let decoder = GifDecoder::new(gif)?;
+    let (width, height) = decoder.dimensions();
+    let frames = decoder.into_frames().collect_frames()?;
+
+    let count = frames.len() as u64;
+    let pixels = u64::from(width) * u64::from(height) * count;
+
+    if count == 0 || pixels > SPRITESHEET_PIXEL_LIMIT {
+        return Ok(None);
+    }
+
+    let mut spritesheet = RgbaImage::new(width * count as u32, height);
+
+    for (index, frame) in frames.iter().enumerate() {
+        let x = width * index as u32 + frame.left();
+        image::imageops::overlay(&mut spritesheet, frame.buffer(), x, frame.top());
+    }
+
+    let mut buffer = Vec::new();
+    DynamicImage::ImageRgba8(spritesheet).write_to(&mut buffer, ImageOutputFormat::Png)?;
+
+    Ok(Some(buffer))
+}
+
+/// Computes a perceptual hash (a difference hash) of the image.
+///
+/// Unlike a hash of the bytes, it stays (mostly) the same when the image is resized or re-encoded,
+/// so the number of bits that differ between two hashes is a measure of how similar the images are.
+pub fn perceptual_hash(image: &DynamicImage) -> i64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_rgba8();
+
+    // transparent pixels are treated as white, their color is usually meaningless.
+    let brightness = |x, y| {
+        let [r, g, b, a] = small.get_pixel(x, y).0;
+        let luma = (u32::from(r) * 299 + u32::from(g) * 587 + u32::from(b) * 114) / 1000;
+        (luma * u32::from(a) + 255 * (255 - u32::from(a))) / 255
+    };
+
+    let mut hash = 0_u64;
+
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if brightness(x, y) < brightness(x + 1, y) {
+                hash |= 1;
+            }
+        }
+    }
+
+    hash as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_spritesheet, perceptual_hash};
+    use image::{
+        gif::GifEncoder, imageops::FilterType, DynamicImage, Frame, GenericImageView,
+        ImageOutputFormat, Rgba, RgbaImage,
+    };
+
+    /// A horizontal gradient, with a dark square in the middle.
+    fn image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            let in_square = (width / 3..width * 2 / 3).contains(&x)
+                && (height / 3..height * 2 / 3).contains(&y);
+
+            match in_square {
+                true => Rgba([20, 20, 20, 255]),
+                false => Rgba([(x * 255 / width) as u8, 128, 128, 255]),
+            }
+        }))
+    }
+
+    fn distance(a: i64, b: i64) -> u32 {
+        (a ^ b).count_ones()
+    }
+
+    #[test]
+    fn phash_survives_resizing_and_reencoding() {
+        let original = image(300, 200);
+        let hash = perceptual_hash(&original);
+
+        let resized = original.resize_exact(90, 60, FilterType::Triangle);
+        assert!(distance(hash, perceptual_hash(&resized)) <= 4);
+
+        let mut png = Vec::new();
+        original.write_to(&mut png, ImageOutputFormat::Png).unwrap();
+        let reencoded = image::load_from_memory(&png).unwrap();
+        assert_eq!(perceptual_hash(&reencoded), hash);
+    }
+
+    #[test]
+    fn phash_tells_images_apart() {
+        let hash = perceptual_hash(&image(300, 200));
+
+        // the gradient goes the other way.
+        let flipped = perceptual_hash(&image(300, 200).fliph());
+
+        assert!(distance(hash, flipped) > 7);
+    }
+
+    #[test]
+    fn phash_ignores_transparent_colors() {
+        let transparent = |color| {
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                16,
+                16,
+                Rgba([color, color, color, 0]),
+            ))
+        };
+
+        assert_eq!(
+            perceptual_hash(&transparent(0)),
+            perceptual_hash(&transparent(255))
+        );
+    }
+
+    fn gif(colors: &[[u8; 4]]) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        {
+            let frames = colors
+                .iter()
+                .map(|color| Frame::new(RgbaImage::from_pixel(4, 3, Rgba(*color))));
+
+            GifEncoder::new(&mut data).encode_frames(frames).unwrap();
+        }
+
+        data
+    }
+
+    #[test]
+    fn spritesheet_has_every_frame() {
+        let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+
+        let spritesheet = generate_spritesheet(&gif(&colors)).unwrap().unwrap();
+        let spritesheet = image::load_from_memory(&spritesheet).unwrap();
+
+        assert_eq!(spritesheet.dimensions(), (12, 3));
+
+        for (index, color) in colors.iter().enumerate() {
+            let Rgba(pixel) = spritesheet.get_pixel(index as u32 * 4 + 2, 1);
+
+            // gifs have a limited palette, so the colors might be a little off.
+            for (actual, expected) in pixel.iter().zip(color) {
+                assert!((i16::from(*actual) - i16::from(*expected)).abs() < 16);
+            }
+        }
+    }
+
+    #[test]
+    fn spritesheet_rejects_invalid_gifs() {
+        assert!(generate_spritesheet(b"not a gif").is_err());
+    }
+}
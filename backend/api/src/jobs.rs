@@ -0,0 +1,459 @@
+//! Background processing of uploaded media.
+//!
+//! Uploads store the original file and queue a job (see [`db::job`]), which a [`Worker`] picks up to
+//! validate the file and generate anything derived from it. Jobs that fail get retried with backoff,
+//! and are dead lettered once they run out of attempts (or fail in a way that retrying won't fix).
+//!
+//! User library images also get moderated here (see [`crate::moderation`]),
+//! and images being imported from a url get downloaded here (see [`crate::image_import`]).
+
+use std::time::Duration;
+
+use shared::{
+    domain::{animation::AnimationKind, image::ImageKind, upload::UploadKind},
+    media::{FileKind, MediaLibrary, ModerationStatus, PngImageFile},
+};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    db::{self, job::Job},
+    error,
+    image_import::{self, ImportError},
+    image_ops::{generate_images, generate_spritesheet, perceptual_hash},
+    moderation::{self, Moderator},
+    s3::{self, Location},
+    transcode::{TranscodeError, Transcoder},
+};
+
+/// How long to wait before checking for jobs again when the queue is empty.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a worker has to finish a job before it's given to someone else.
+const LEASE_DURATION: Duration = Duration::from_secs(10 * 60);
+
+/// What a job does.
+#[repr(i16)]
+#[derive(Copy, Clone, Debug, sqlx::Type)]
+pub enum JobKind {
+    /// Validate the original image and generate the resized image and thumbnail.
+    ProcessImage = 0,
+
+    /// Validate the animation, and generate the spritesheet for spritesheet animations.
+    ProcessAnimation = 1,
+
+    /// Validate the audio file and transcode it into a normalized mp3.
+    ProcessAudio = 2,
+
+    /// Download an image import item from its source url and create its image (`media_id` is the item's id).
+    ImportImage = 3,
+
+    /// Compute the perceptual hash of an image that was uploaded before hashes were computed while processing.
+    ///
+    /// Unlike processing, this doesn't change the image's processing status.
+    HashImage = 4,
+}
+
+impl From<UploadKind> for JobKind {
+    fn from(kind: UploadKind) -> Self {
+        match kind {
+            UploadKind::Image => Self::ProcessImage,
+            UploadKind::Animation => Self::ProcessAnimation,
+            UploadKind::Audio => Self::ProcessAudio,
+        }
+    }
+}
+
+enum JobError {
+    /// The media can't be processed, retrying won't help.
+    Invalid(&'static str),
+    Other(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for JobError {
+    fn from(e: T) -> Self {
+        Self::Other(e.into())
+    }
+}
+
+/// How long to wait before retrying a job that's been attempted `attempts` times.
+fn backoff(attempts: i32) -> Duration {
+    let exponent = attempts.max(1).min(10) as u32 - 1;
+    Duration::from_secs(15 * 2_u64.pow(exponent)).min(Duration::from_secs(60 * 60))
+}
+
+pub struct Worker {
+    db: PgPool,
+    s3: s3::Client,
+    moderator: Moderator,
+    transcoder: Option<Transcoder>,
+}
+
+impl Worker {
+    pub const fn new(
+        db: PgPool,
+        s3: s3::Client,
+        moderator: Moderator,
+        transcoder: Option<Transcoder>,
+    ) -> Self {
+        Self {
+            db,
+            s3,
+            moderator,
+            transcoder,
+        }
+    }
+
+    #[must_use]
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::task::spawn(async move {
+            loop {
+                match self.run_next().await {
+                    // there might be more work, keep going.
+                    Ok(true) => {}
+                    Ok(false) => tokio::time::delay_for(POLL_INTERVAL).await,
+                    Err(e) => {
+                        log::error!("media job worker errored: {:?}", e);
+                        sentry::integrations::anyhow::capture_anyhow(&e);
+                        tokio::time::delay_for(POLL_INTERVAL).await;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Runs the next job (if there is one), returns whether or not one was run.
+    async fn run_next(&self) -> anyhow::Result<bool> {
+        let job = match db::job::lease(&self.db, LEASE_DURATION).await? {
+            Some(job) => job,
+            None => return Ok(false),
+        };
+
+        // the job has been leased too many times without finishing (the worker probably died while running it).
+        let res = if job.attempts > job.max_attempts {
+            Err(JobError::Invalid("ran out of attempts"))
+        } else {
+            self.run(&job).await
+        };
+
+        match res {
+            Ok(()) => db::job::succeed(&self.db, &job).await?,
+
+            Err(JobError::Invalid(reason)) => {
+                log::info!(
+                    "media job {} ({:?} {}) failed permanently: {}",
+                    job.id,
+                    job.kind,
+                    job.media_id,
+                    reason
+                );
+
+                db::job::fail(&self.db, &job, reason, None).await?;
+            }
+
+            Err(JobError::Other(e)) => {
+                log::warn!(
+                    "media job {} ({:?} {}) failed (attempt {} of {}): {:?}",
+                    job.id,
+                    job.kind,
+                    job.media_id,
+                    job.attempts,
+                    job.max_attempts,
+                    e
+                );
+
+                db::job::fail(
+                    &self.db,
+                    &job,
+                    &format!("{:?}", e),
+                    Some(backoff(job.attempts)),
+                )
+                .await?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    async fn run(&self, job: &Job) -> Result<(), JobError> {
+        match job.kind {
+            JobKind::ProcessImage => self.process_image(job.library, job.media_id).await,
+            JobKind::ProcessAnimation => self.process_animation(job.library, job.media_id).await,
+            JobKind::ProcessAudio => self.process_audio(job.library, job.media_id).await,
+            JobKind::ImportImage => self.import_image(job.media_id).await,
+            JobKind::HashImage => self.hash_image(job.library, job.media_id).await,
+        }
+    }
+
+    /// Gets the original file, `None` means there's nothing to process (storage is disabled).
+    async fn download(
+        &self,
+        location: Location,
+        library: MediaLibrary,
+        id: Uuid,
+        file_kind: FileKind,
+    ) -> Result<Option<Vec<u8>>, JobError> {
+        match self
+            .s3
+            .download_media_file(location, library, id, file_kind)
+            .await?
+        {
+            Some(Some(data)) => Ok(Some(data)),
+            Some(None) => Err(JobError::Invalid("the uploaded file is missing")),
+            None => Ok(None),
+        }
+    }
+
+    async fn process_image(&self, library: MediaLibrary, id: Uuid) -> Result<(), JobError> {
+        // user images are uploaded to quarantine.
+        let location = moderation::location(&self.db, library, id).await?;
+
+        let data = match self
+            .download(
+                location,
+                library,
+                id,
+                FileKind::ImagePng(PngImageFile::Original),
+            )
+            .await?
+        {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+
+        let kind = match library {
+            MediaLibrary::Global => {
+                let kind = sqlx::query!(
+                    r#"select kind as "kind: ImageKind" from image_metadata where id = $1"#,
+                    id
+                )
+                .fetch_optional(&self.db)
+                .await?;
+
+                match kind {
+                    Some(it) => it.kind,
+                    // the image has been deleted.
+                    None => return Ok(()),
+                }
+            }
+
+            _ => ImageKind::Sticker,
+        };
+
+        // user images stay quarantined until they've been moderated (and once they've been rejected).
+        let status = match library {
+            MediaLibrary::User => db::moderation::status(&self.db, library, id).await?,
+            _ => None,
+        };
+
+        let verdict = match status {
+            Some(ModerationStatus::Pending) => {
+                let verdict = self.moderator.classify(&data).await?;
+                log::debug!("moderated user image {}: {:?}", id, verdict);
+                Some(verdict)
+            }
+
+            _ => None,
+        };
+
+        let ((original, resized, thumbnail), phash) =
+            tokio::task::spawn_blocking(move || -> Result<_, JobError> {
+                let original = image::load_from_memory(&data)
+                    .map_err(|_| JobError::Invalid("invalid image"))?;
+                Ok((
+                    generate_images(&original, kind)?,
+                    perceptual_hash(&original),
+                ))
+            })
+            .await??;
+
+        let target = verdict.as_ref().map_or(location, |it| it.location(library));
+
+        self.s3
+            .upload_png_images(target, library, id, original, resized, thumbnail)
+            .await?;
+
+        // the verdict is recorded once the files are where it says, so that a retry finds the original.
+        if let Some(verdict) = &verdict {
+            moderation::record_classified(&self.db, library, id, verdict).await?;
+        }
+
+        if target != location {
+            let original = FileKind::ImagePng(PngImageFile::Original);
+            self.s3
+                .delete_media_from(location, library, original, id)
+                .await;
+        }
+
+        db::phash::set(&mut *self.db.acquire().await?, library, id, phash).await?;
+
+        Ok(())
+    }
+
+    async fn hash_image(&self, library: MediaLibrary, id: Uuid) -> Result<(), JobError> {
+        let location = moderation::location(&self.db, library, id).await?;
+
+        let data = match self
+            .download(
+                location,
+                library,
+                id,
+                FileKind::ImagePng(PngImageFile::Original),
+            )
+            .await?
+        {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+
+        let phash = tokio::task::spawn_blocking(move || {
+            image::load_from_memory(&data)
+                .map(|it| perceptual_hash(&it))
+                .map_err(|_| JobError::Invalid("invalid image"))
+        })
+        .await??;
+
+        db::phash::set(&mut *self.db.acquire().await?, library, id, phash).await?;
+
+        Ok(())
+    }
+
+    async fn import_image(&self, id: Uuid) -> Result<(), JobError> {
+        let url = match db::image::import::source_url(&self.db, id).await? {
+            Some(url) => url,
+            // the import has been deleted.
+            None => return Ok(()),
+        };
+
+        let url = Url::parse(&url).map_err(|_| JobError::Invalid("invalid url"))?;
+
+        let data = crate::fetch::image(url, config::IMAGE_BODY_SIZE_LIMIT)
+            .await
+            .map_err(|e| match e {
+                error::WebMedia::InvalidUrl(reason) => JobError::Invalid(reason),
+                error::WebMedia::ForbiddenHost => JobError::Invalid("the url's host isn't allowed"),
+                error::WebMedia::TooLarge => JobError::Invalid("the image is too large"),
+                error::WebMedia::UnsupportedMediaType => {
+                    JobError::Invalid("the url isn't an image")
+                }
+                error::WebMedia::TooManyRedirects => JobError::Invalid("too many redirects"),
+                error::WebMedia::Rejected => JobError::Invalid("the image was rejected"),
+                error::WebMedia::Upstream(e) => JobError::Other(anyhow::anyhow!("upstream: {}", e)),
+                error::WebMedia::InternalServerError(e) => JobError::Other(e),
+            })?;
+
+        match image_import::import(&self.db, &self.s3, id, data).await {
+            Ok(_) => Ok(()),
+            Err(ImportError::InvalidImage) => Err(JobError::Invalid("invalid image")),
+            Err(ImportError::MissingMetadata { .. }) => {
+                Err(JobError::Invalid("the item's metadata has been deleted"))
+            }
+            Err(ImportError::Other(e)) => Err(JobError::Other(e)),
+        }
+    }
+
+    async fn process_animation(&self, library: MediaLibrary, id: Uuid) -> Result<(), JobError> {
+        // premium animations are private.
+        let location = moderation::location(&self.db, library, id).await?;
+
+        let data = match self
+            .download(location, library, id, FileKind::AnimationGif)
+            .await?
+        {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+
+        let kind = match library {
+            MediaLibrary::Global => {
+                let kind = sqlx::query!(
+                    r#"select variant as "kind: AnimationKind" from animation where id = $1"#,
+                    id
+                )
+                .fetch_optional(&self.db)
+                .await?;
+
+                match kind {
+                    Some(it) => it.kind,
+                    // the animation has been deleted.
+                    None => return Ok(()),
+                }
+            }
+
+            _ => AnimationKind::Gif,
+        };
+
+        // spritesheet animations are uploaded as gifs, and converted here.
+        let spritesheet = tokio::task::spawn_blocking(move || match kind {
+            AnimationKind::Gif => {
+                image::load_from_memory_with_format(&data, image::ImageFormat::Gif)
+                    .map(|_| None)
+                    .map_err(|_| JobError::Invalid("invalid gif"))
+            }
+
+            AnimationKind::Spritesheet => match generate_spritesheet(&data) {
+                Ok(Some(spritesheet)) => Ok(Some(spritesheet)),
+                Ok(None) => Err(JobError::Invalid(
+                    "the gif has too many frames to convert to a spritesheet",
+                )),
+                Err(_) => Err(JobError::Invalid("invalid gif")),
+            },
+        })
+        .await??;
+
+        if let Some(spritesheet) = spritesheet {
+            self.s3
+                .upload_media_to(
+                    location,
+                    spritesheet,
+                    library,
+                    id,
+                    FileKind::AnimationSpritesheet,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn process_audio(&self, library: MediaLibrary, id: Uuid) -> Result<(), JobError> {
+        let location = moderation::location(&self.db, library, id).await?;
+
+        let data = match self
+            .download(location, library, id, FileKind::AudioMp3)
+            .await?
+        {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+
+        let transcoder = match &self.transcoder {
+            Some(transcoder) => transcoder,
+
+            // transcoding is disabled (locally), so the mp3 is served as it was uploaded.
+            None => {
+                return tokio::task::spawn_blocking(move || {
+                    mp3_metadata::read_from_slice(&data)
+                        .map(drop)
+                        .map_err(|_| JobError::Invalid("invalid mp3"))
+                })
+                .await?
+            }
+        };
+
+        let transcoded = match transcoder.mp3(data).await {
+            Ok(transcoded) => transcoded,
+            Err(TranscodeError::InvalidAudio) => return Err(JobError::Invalid("invalid audio")),
+            Err(TranscodeError::Other(e)) => return Err(JobError::Other(e)),
+        };
+
+        // the transcoded mp3 replaces the original.
+        self.s3
+            .upload_media_to(location, transcoded, library, id, FileKind::AudioMp3)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+//! Picking which translation of a name to show.
+
+use actix_service::Service;
+use actix_web::{
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, HeaderValue},
+    HttpRequest,
+};
+use futures::Future;
+use shared::{domain::meta::Translation, media::AltText};
+
+use crate::error;
+
+/// The most languages that are looked up for a single request.
+const MAX_LANGUAGES: usize = 10;
+
+/// Marks a request whose response was translated with [`preferred`], see [`vary`].
+struct Negotiated;
+
+/// Normalizes a language tag to the form translations are stored in (`en_US` becomes `en-us`),
+/// returns `None` if it doesn't look like a language tag.
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.trim();
+
+    let is_valid = !tag.is_empty()
+        && tag.len() <= 35
+        && tag.split(|c| c == '-' || c == '_').all(|part| {
+            !part.is_empty() && part.len() <= 8 && part.bytes().all(|b| b.is_ascii_alphanumeric())
+        });
+
+    if !is_valid {
+        return None;
+    }
+
+    Some(tag.to_ascii_lowercase().replace('_', "-"))
+}
+
+/// Parses an `Accept-Language` header into language tags, most preferred first (`*` and invalid tags are skipped).
+fn accept_language(header: &str) -> Vec<String> {
+    let mut tags: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = normalize(parts.next()?)?;
+
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+
+            if quality <= 0.0 {
+                return None;
+            }
+
+            Some((tag, quality))
+        })
+        .collect();
+
+    // stable, so equally preferred languages keep their order.
+    tags.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// The languages to look for translations in, most preferred first.
+///
+/// `lang` (usually a `?lang=` query parameter) comes first, then the languages from the `Accept-Language` header.
+/// Each language is followed by its primary language (`he-il` falls back to `he`), after that the untranslated name is used.
+///
+/// The response gets a `Vary: Accept-Language` header (see [`vary`]).
+pub fn preferred(req: &HttpRequest, lang: Option<&str>) -> Vec<String> {
+    req.extensions_mut().insert(Negotiated);
+
+    let header = req
+        .headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)
+        .and_then(|it| it.to_str().ok())
+        .map_or_else(Vec::new, accept_language);
+
+    let mut languages = Vec::new();
+
+    for tag in lang.and_then(normalize).into_iter().chain(header) {
+        let primary = tag.split('-').next().map(ToOwned::to_owned);
+
+        for tag in std::iter::once(tag).chain(primary) {
+            if !languages.contains(&tag) {
+                languages.push(tag);
+            }
+        }
+
+        if languages.len() >= MAX_LANGUAGES {
+            languages.truncate(MAX_LANGUAGES);
+            break;
+        }
+    }
+
+    languages
+}
+
+/// Middleware that adds `Vary: Accept-Language` to the responses of requests that used [`preferred`],
+/// so that caches don't serve them to people who asked for another language.
+pub fn vary<B, T>(
+    request: ServiceRequest,
+    srv: &mut T,
+) -> impl Future<Output = actix_web::Result<T::Response>>
+where
+    T: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let future = srv.call(request);
+    async {
+        let mut response = future.await?;
+
+        let negotiated = response.request().extensions().contains::<Negotiated>();
+        if negotiated {
+            // appended, since other middleware might vary the response too (cors varies on `Origin`).
+            response
+                .headers_mut()
+                .append(header::VARY, HeaderValue::from_static("accept-language"));
+        }
+
+        Ok(response)
+    }
+}
+
+/// Normalizes the languages of `translations` into (language, name) pairs, making sure that each language is only translated once.
+pub fn normalize_translations(
+    translations: Vec<Translation>,
+) -> Result<Vec<(String, String)>, error::SetTranslations> {
+    let mut normalized: Vec<(String, String)> = Vec::with_capacity(translations.len());
+
+    for Translation { language, name } in translations {
+        let language =
+            normalize(&language).ok_or(error::SetTranslations::InvalidLanguage(language))?;
+
+        if normalized.iter().any(|(it, _)| *it == language) {
+            return Err(error::SetTranslations::DuplicateLanguage(language));
+        }
+
+        normalized.push((language, name));
+    }
+
+    Ok(normalized)
+}
+
+/// Normalizes the languages of `alt_text` into (language, text) pairs, making sure that each language only has one alt text.
+///
+/// The error is a message saying what's wrong with the alt text.
+pub fn normalize_alt_text(alt_text: Vec<AltText>) -> Result<Vec<(String, String)>, String> {
+    let mut normalized: Vec<(String, String)> = Vec::with_capacity(alt_text.len());
+
+    for AltText { language, text } in alt_text {
+        let language = normalize(&language)
+            .ok_or_else(|| format!("Invalid alt text language: `{}`", language))?;
+
+        if normalized.iter().any(|(it, _)| *it == language) {
+            return Err(format!("Duplicate alt text language: `{}`", language));
+        }
+
+        normalized.push((language, text));
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header, test, web, App, HttpRequest, HttpResponse};
+    use shared::{domain::meta::Translation, media::AltText};
+
+    use super::{
+        accept_language, normalize, normalize_alt_text, normalize_translations, preferred, vary,
+    };
+    use crate::error;
+
+    fn preferred_with(header: Option<&str>, lang: Option<&str>) -> Vec<String> {
+        let mut req = test::TestRequest::default();
+        if let Some(header) = header {
+            req = req.header(header::ACCEPT_LANGUAGE, header);
+        }
+
+        preferred(&req.to_http_request(), lang)
+    }
+
+    #[test]
+    fn normalize_tags() {
+        assert_eq!(normalize("he").as_deref(), Some("he"));
+        assert_eq!(normalize(" en_US ").as_deref(), Some("en-us"));
+        assert_eq!(normalize("zh-Hant-TW").as_deref(), Some("zh-hant-tw"));
+
+        assert_eq!(normalize(""), None);
+        assert_eq!(normalize("*"), None);
+        assert_eq!(normalize("en--us"), None);
+        assert_eq!(normalize("en-"), None);
+        assert_eq!(normalize("en us"), None);
+        assert_eq!(normalize("toolongpart"), None);
+        assert_eq!(normalize(&format!("{}ab", "ab-".repeat(12))), None);
+    }
+
+    #[test]
+    fn accept_language_order() {
+        assert_eq!(
+            accept_language("he-IL,he;q=0.9,en;q=0.8,*;q=0.5"),
+            ["he-il", "he", "en"]
+        );
+
+        // equally preferred languages keep their order.
+        assert_eq!(
+            accept_language("fr;q=0.5, en, de; q=0.5"),
+            ["en", "fr", "de"]
+        );
+    }
+
+    #[test]
+    fn accept_language_skips_invalid() {
+        assert_eq!(accept_language(""), Vec::<String>::new());
+        assert_eq!(accept_language("en;q=0, he"), ["he"]);
+        assert_eq!(accept_language("en;q=high, he, fr--ca"), ["he"]);
+    }
+
+    #[test]
+    fn preferred_lang_first() {
+        assert_eq!(
+            preferred_with(Some("en-US, he"), Some("he_IL")),
+            ["he-il", "he", "en-us", "en"]
+        );
+
+        assert_eq!(
+            preferred_with(None, Some("not a tag")),
+            Vec::<String>::new()
+        );
+        assert_eq!(preferred_with(Some("fr"), None), ["fr"]);
+    }
+
+    #[test]
+    fn preferred_is_bounded() {
+        let header: Vec<_> = (0..20).map(|it| format!("l{}-x", it)).collect();
+
+        assert_eq!(preferred_with(Some(&header.join(",")), None).len(), 10);
+    }
+
+    #[test]
+    fn translations_are_normalized() {
+        let translation = |language: &str| Translation {
+            language: language.to_owned(),
+            name: "name".to_owned(),
+        };
+
+        assert_eq!(
+            normalize_translations(vec![translation("he"), translation("en_US")]).ok(),
+            Some(vec![
+                ("he".to_owned(), "name".to_owned()),
+                ("en-us".to_owned(), "name".to_owned())
+            ])
+        );
+
+        assert!(matches!(
+            normalize_translations(vec![translation("he"), translation("HE")]),
+            Err(error::SetTranslations::DuplicateLanguage(it)) if it == "he"
+        ));
+
+        assert!(matches!(
+            normalize_translations(vec![translation("h e")]),
+            Err(error::SetTranslations::InvalidLanguage(it)) if it == "h e"
+        ));
+    }
+
+    #[test]
+    fn alt_text_is_normalized() {
+        let alt_text = |language: &str| AltText {
+            language: language.to_owned(),
+            text: "text".to_owned(),
+        };
+
+        assert_eq!(
+            normalize_alt_text(vec![alt_text("en_US")]).unwrap(),
+            [("en-us".to_owned(), "text".to_owned())]
+        );
+
+        assert_eq!(
+            normalize_alt_text(vec![alt_text("en-us"), alt_text("en_US")]).unwrap_err(),
+            "Duplicate alt text language: `en-us`"
+        );
+
+        assert_eq!(
+            normalize_alt_text(vec![alt_text("")]).unwrap_err(),
+            "Invalid alt text language: ``"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn vary_translated_responses() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap_fn(vary)
+                .route(
+                    "/translated",
+                    web::get().to(|req: HttpRequest| async move {
+                        preferred(&req, None);
+                        HttpResponse::Ok().finish()
+                    }),
+                )
+                .route(
+                    "/plain",
+                    web::get().to(|| async { HttpResponse::Ok().finish() }),
+                ),
+        )
+        .await;
+
+        let resp = test::call_service(
+            &mut app,
+            test::TestRequest::with_uri("/translated").to_request(),
+        )
+        .await;
+
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept-language");
+
+        let resp =
+            test::call_service(&mut app, test::TestRequest::with_uri("/plain").to_request()).await;
+
+        assert!(resp.headers().get(header::VARY).is_none());
+    }
+}
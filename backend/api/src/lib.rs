@@ -35,11 +35,20 @@ pub mod db;
 mod domain;
 mod error;
 mod extractor;
+mod fetch;
 pub mod http;
+mod image_import;
 mod image_ops;
 pub mod image_search;
+pub mod jobs;
 pub mod jwkkeys;
 mod jwt;
+mod language;
 pub mod logger;
+pub mod mail;
+pub mod moderation;
 pub(crate) mod more_futures;
+mod password;
 pub mod s3;
+pub mod search;
+pub mod transcode;
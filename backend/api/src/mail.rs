@@ -0,0 +1,225 @@
+//! Sending emails (email verification and password reset tokens).
+//!
+//! Where emails go is decided by a [`Sender`], only [`SendgridSender`] actually delivers them,
+//! the others are for local development and tests (see `core::settings::MailSettings`).
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use core::settings::MailSettings;
+use futures::future::{self, BoxFuture, FutureExt};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// A plain text email.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Something that can send emails.
+// note: see `s3::Storage` for why this doesn't use `async_trait`.
+pub trait Sender: Send + Sync {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Logs emails instead of sending them.
+pub struct LogSender;
+
+impl Sender for LogSender {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, anyhow::Result<()>> {
+        log::info!("email to {} ({}):\n{}", mail.to, mail.subject, mail.body);
+
+        future::ready(Ok(())).boxed()
+    }
+}
+
+/// Writes each email to its own json file in a directory, instead of sending it.
+pub struct DirSender {
+    dir: PathBuf,
+}
+
+impl DirSender {
+    pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create mail directory {}", dir.display()))?;
+
+        Ok(Self { dir })
+    }
+}
+
+impl Sender for DirSender {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let path = self.dir.join(format!("{}.json", Uuid::new_v4()));
+
+            // write to a temporary file first, so that readers never see a partially written email.
+            let tmp = path.with_extension("tmp");
+            tokio::fs::write(&tmp, serde_json::to_vec(mail)?).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+#[derive(Serialize)]
+struct SendgridAddress<'a> {
+    email: &'a str,
+}
+
+#[derive(Serialize)]
+struct SendgridPersonalization<'a> {
+    to: [SendgridAddress<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct SendgridContent<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    value: &'a str,
+}
+
+/// The body of a `SendGrid` `mail/send` request.
+#[derive(Serialize)]
+struct SendgridMail<'a> {
+    personalizations: [SendgridPersonalization<'a>; 1],
+    from: SendgridAddress<'a>,
+    subject: &'a str,
+    content: [SendgridContent<'a>; 1],
+}
+
+impl<'a> SendgridMail<'a> {
+    fn new(sender: &'a str, mail: &'a Mail) -> Self {
+        Self {
+            personalizations: [SendgridPersonalization {
+                to: [SendgridAddress { email: &mail.to }],
+            }],
+            from: SendgridAddress { email: sender },
+            subject: &mail.subject,
+            content: [SendgridContent {
+                kind: "text/plain",
+                value: &mail.body,
+            }],
+        }
+    }
+}
+
+/// Sends emails with `SendGrid`.
+pub struct SendgridSender {
+    http: reqwest::Client,
+    api_key: String,
+    sender: String,
+}
+
+impl SendgridSender {
+    #[must_use]
+    pub fn new(api_key: String, sender: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_key,
+            sender,
+        }
+    }
+}
+
+impl Sender for SendgridSender {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.http
+                .post("https://api.sendgrid.com/v3/mail/send")
+                .bearer_auth(&self.api_key)
+                .json(&SendgridMail::new(&self.sender, mail))
+                .send()
+                .await?
+                .error_for_status()?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+#[derive(Clone)]
+pub struct Mailer {
+    sender: Arc<dyn Sender>,
+}
+
+impl Mailer {
+    pub fn new(settings: MailSettings) -> anyhow::Result<Self> {
+        let sender: Arc<dyn Sender> = match settings {
+            MailSettings::Sendgrid { api_key, sender } => {
+                Arc::new(SendgridSender::new(api_key, sender))
+            }
+            MailSettings::Dir(dir) => Arc::new(DirSender::new(dir)?),
+            MailSettings::Log => Arc::new(LogSender),
+        };
+
+        Ok(Self::with_sender(sender))
+    }
+
+    #[must_use]
+    pub fn with_sender(sender: Arc<dyn Sender>) -> Self {
+        Self { sender }
+    }
+
+    pub async fn send(&self, mail: &Mail) -> anyhow::Result<()> {
+        self.sender.send(mail).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{DirSender, Mail, Sender, SendgridMail};
+
+    fn mail() -> Mail {
+        Mail {
+            to: "test@test.test".to_owned(),
+            subject: "Verify your email".to_owned(),
+            body: "token".to_owned(),
+        }
+    }
+
+    #[test]
+    fn sendgrid_request() {
+        let mail = mail();
+
+        assert_eq!(
+            serde_json::to_value(SendgridMail::new("noreply@test.test", &mail)).unwrap(),
+            json!({
+                "personalizations": [{ "to": [{ "email": "test@test.test" }] }],
+                "from": { "email": "noreply@test.test" },
+                "subject": "Verify your email",
+                "content": [{ "type": "text/plain", "value": "token" }],
+            })
+        );
+    }
+
+    #[actix_rt::test]
+    async fn dir_sender_writes_mail() {
+        let dir = std::env::temp_dir().join(format!("ji-cloud-mail-{}", uuid::Uuid::new_v4()));
+        let sender = DirSender::new(dir.clone()).unwrap();
+
+        sender.send(&mail()).await.unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|it| it.unwrap().path())
+            .collect();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "json");
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&files[0]).unwrap()).unwrap();
+
+        assert_eq!(written, serde_json::to_value(mail()).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
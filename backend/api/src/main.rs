@@ -13,7 +13,7 @@
 
 use anyhow::Context;
 use core::settings::{self, SettingsManager};
-use ji_cloud_api::{algolia, db, http, jwkkeys, logger, s3};
+use ji_cloud_api::{algolia, db, http, jobs, jwkkeys, logger, mail, moderation, s3, transcode};
 use std::thread;
 
 #[tokio::main]
@@ -22,7 +22,19 @@ async fn main() -> anyhow::Result<()> {
 
     logger::init()?;
 
-    let (runtime_settings, jwk_verifier, s3, algolia_client, algolia_manager, db_pool, _guard) = {
+    let (
+        runtime_settings,
+        jwk_verifier,
+        s3,
+        moderator,
+        mailer,
+        transcoder,
+        algolia_client,
+        algolia_manager,
+        rate_limit_settings,
+        db_pool,
+        _guard,
+    ) = {
         log::trace!("initializing settings and processes");
         let remote_target = settings::read_remote_target()?;
 
@@ -30,16 +42,26 @@ async fn main() -> anyhow::Result<()> {
 
         let runtime_settings = settings.runtime_settings().await?;
 
-        let jwk_verifier = jwkkeys::create_verifier(settings.jwk_settings().await?);
+        let jwk_verifier = settings.jwk_settings().await?.map(jwkkeys::create_verifier);
 
-        let _ = jwkkeys::run_task(jwk_verifier.clone());
+        if let Some(jwk_verifier) = &jwk_verifier {
+            let _ = jwkkeys::run_task(jwk_verifier.clone());
+        }
 
-        let s3 = s3::Client::new(settings.s3_settings().await?)?;
+        let s3 = s3::Client::new(settings.storage_settings().await?)?;
+
+        let moderator = moderation::Moderator::new(settings.moderation_settings().await?);
+
+        let mailer = mail::Mailer::new(settings.mail_settings().await?)?;
+
+        let transcoder = transcode::Transcoder::new(settings.audio_settings().await?);
 
         let algolia_settings = settings.algolia_settings().await?;
 
         let algolia_client = crate::algolia::Client::new(algolia_settings.clone())?;
 
+        let rate_limit_settings = settings.rate_limit_settings().await?;
+
         let db_pool = db::get_pool(
             settings
                 .db_connect_options(settings::read_sql_proxy())
@@ -55,8 +77,12 @@ async fn main() -> anyhow::Result<()> {
             runtime_settings,
             jwk_verifier,
             s3,
+            moderator,
+            mailer,
+            transcoder,
             algolia_client,
             algolia_manager,
+            rate_limit_settings,
             db_pool,
             guard,
         )
@@ -72,8 +98,20 @@ async fn main() -> anyhow::Result<()> {
         let _ = algolia_manager.spawn();
     }
 
-    let handle =
-        thread::spawn(|| http::run(db_pool, runtime_settings, jwk_verifier, s3, algolia_client));
+    let _ = jobs::Worker::new(db_pool.clone(), s3.clone(), moderator.clone(), transcoder).spawn();
+
+    let handle = thread::spawn(move || {
+        http::run(
+            db_pool,
+            runtime_settings,
+            jwk_verifier,
+            s3,
+            moderator,
+            mailer,
+            algolia_client,
+            rate_limit_settings,
+        )
+    });
 
     log::info!("app started!");
 
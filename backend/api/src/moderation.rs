@@ -0,0 +1,263 @@
+//! Moderation of uploaded images.
+//!
+//! User library images and web library imports are checked by a [`Classifier`] before they can be seen.
+//! Images the classifier isn't sure about are quarantined (only their owner and admins can see them)
+//! until an admin approves or rejects them. The files of quarantined media are kept at their
+//! [`Location::Quarantine`] key, so that the CDN can't serve them either. Approved user library media
+//! is still only visible to its owner, so it's kept at its [`Location::Private`] key.
+
+use std::sync::Arc;
+
+use core::settings::ModerationSettings;
+use futures::future::{self, BoxFuture, FutureExt};
+use shared::media::{FileKind, MediaLibrary, ModerationStatus, PngImageFile};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db,
+    s3::{self, Location},
+};
+
+/// What a [`Classifier`] thinks of an image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The image can be shown.
+    Approve,
+
+    /// The image needs to be reviewed by an admin before it can be shown.
+    Review(Option<String>),
+
+    /// The image must not be shown.
+    Reject(String),
+}
+
+impl Verdict {
+    fn status(&self) -> ModerationStatus {
+        match self {
+            Self::Approve => ModerationStatus::Approved,
+            Self::Review(_) => ModerationStatus::Pending,
+            Self::Reject(_) => ModerationStatus::Rejected,
+        }
+    }
+
+    /// Where the files of media from `library` with this verdict belong.
+    #[must_use]
+    pub const fn location(&self, library: MediaLibrary) -> Location {
+        match self {
+            Self::Approve => Location::visible(library, false),
+            Self::Review(_) | Self::Reject(_) => Location::Quarantine,
+        }
+    }
+
+    fn reason(&self) -> Option<&str> {
+        match self {
+            Self::Approve => None,
+            Self::Review(reason) => reason.as_deref(),
+            Self::Reject(reason) => Some(reason),
+        }
+    }
+}
+
+/// Something that can decide whether an image is appropriate.
+// note: see `s3::Storage` for why this doesn't use `async_trait`.
+pub trait Classifier: Send + Sync {
+    /// Classifies an image, `data` is the image file as it was uploaded.
+    fn classify<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, anyhow::Result<Verdict>>;
+}
+
+/// A classifier that gives the same verdict for every image, useful for tests and local development
+/// (or when there's no real classifier to use).
+pub struct StubClassifier(pub Verdict);
+
+impl Classifier for StubClassifier {
+    fn classify<'a>(&'a self, _data: &'a [u8]) -> BoxFuture<'a, anyhow::Result<Verdict>> {
+        future::ready(Ok(self.0.clone())).boxed()
+    }
+}
+
+#[derive(Clone)]
+pub struct Moderator {
+    classifier: Arc<dyn Classifier>,
+}
+
+impl Moderator {
+    pub fn new(settings: ModerationSettings) -> Self {
+        let classifier = match settings {
+            ModerationSettings::AllowAll => StubClassifier(Verdict::Approve),
+            ModerationSettings::ReviewAll => StubClassifier(Verdict::Review(None)),
+        };
+
+        Self::with_classifier(Arc::new(classifier))
+    }
+
+    pub fn with_classifier(classifier: Arc<dyn Classifier>) -> Self {
+        Self { classifier }
+    }
+
+    pub async fn classify(&self, data: &[u8]) -> anyhow::Result<Verdict> {
+        self.classifier.classify(data).await
+    }
+}
+
+/// Where the files of the media are stored, media that hasn't been approved is quarantined,
+/// and media that not everyone can see is private (see [`Location::visible`]).
+pub async fn location(db: &PgPool, library: MediaLibrary, id: Uuid) -> sqlx::Result<Location> {
+    if let MediaLibrary::Global = library {
+        // global media isn't moderated, but premium media is only visible to some users.
+        let is_premium = sqlx::query!(
+            r#"
+select coalesce(
+    (select is_premium from image_metadata where id = $1),
+    (select is_premium from animation where id = $1),
+    false
+) as "is_premium!"
+"#,
+            id
+        )
+        .fetch_one(db)
+        .await?
+        .is_premium;
+
+        return Ok(Location::visible(library, is_premium));
+    }
+
+    // media without a status predates moderation.
+    match db::moderation::status(db, library, id).await? {
+        Some(ModerationStatus::Pending) | Some(ModerationStatus::Rejected) => {
+            Ok(Location::Quarantine)
+        }
+        Some(ModerationStatus::Approved) | None => Ok(Location::visible(library, false)),
+    }
+}
+
+/// The files that moderated media can have, web media can be an image or an animation.
+const FILES: [FileKind; 4] = [
+    FileKind::ImagePng(PngImageFile::Original),
+    FileKind::ImagePng(PngImageFile::Resized),
+    FileKind::ImagePng(PngImageFile::Thumbnail),
+    FileKind::AnimationGif,
+];
+
+/// Moves the media's files to where media with `status` belongs, after its moderation status changed.
+pub async fn move_files(
+    s3: &s3::Client,
+    library: MediaLibrary,
+    id: Uuid,
+    status: ModerationStatus,
+) -> anyhow::Result<()> {
+    let visible = Location::visible(library, false);
+
+    let (from, to) = match status {
+        ModerationStatus::Approved => (Location::Quarantine, visible),
+        ModerationStatus::Pending | ModerationStatus::Rejected => (visible, Location::Quarantine),
+    };
+
+    // moving a file that doesn't exist does nothing.
+    s3.move_all_media(library, id, &FILES, from, to).await
+}
+
+/// Hides newly uploaded media until it's been moderated.
+pub async fn quarantine(
+    conn: &mut sqlx::PgConnection,
+    library: MediaLibrary,
+    id: Uuid,
+) -> sqlx::Result<()> {
+    db::moderation::set(conn, library, id, ModerationStatus::Pending, None).await
+}
+
+/// Records the verdict for quarantined media (see [`quarantine`]), after its files have been stored
+/// at the verdict's [`location`](Verdict::location). Does nothing if an admin has reviewed the media since.
+pub async fn record_classified(
+    db: &PgPool,
+    library: MediaLibrary,
+    id: Uuid,
+    verdict: &Verdict,
+) -> sqlx::Result<()> {
+    db::moderation::classify(db, library, id, verdict.status(), verdict.reason()).await
+}
+
+/// Records the verdict for media that was classified before it was stored.
+pub async fn record(
+    conn: &mut sqlx::PgConnection,
+    library: MediaLibrary,
+    id: Uuid,
+    verdict: &Verdict,
+) -> sqlx::Result<()> {
+    db::moderation::set(conn, library, id, verdict.status(), verdict.reason()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Moderator, StubClassifier, Verdict};
+    use crate::s3::Location;
+    use core::settings::ModerationSettings;
+    use shared::media::{MediaLibrary, ModerationStatus};
+    use std::sync::Arc;
+
+    #[actix_rt::test]
+    async fn moderator_uses_classifier() {
+        let verdicts = [
+            Verdict::Approve,
+            Verdict::Review(None),
+            Verdict::Reject("not for kids".to_owned()),
+        ];
+
+        for verdict in &verdicts {
+            let moderator = Moderator::with_classifier(Arc::new(StubClassifier(verdict.clone())));
+
+            assert_eq!(&moderator.classify(b"image").await.unwrap(), verdict);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn moderator_from_settings() {
+        let allow = Moderator::new(ModerationSettings::AllowAll);
+        assert_eq!(allow.classify(b"image").await.unwrap(), Verdict::Approve);
+
+        let review = Moderator::new(ModerationSettings::ReviewAll);
+        assert_eq!(
+            review.classify(b"image").await.unwrap(),
+            Verdict::Review(None)
+        );
+    }
+
+    #[test]
+    fn only_approved_media_is_public() {
+        let reject = Verdict::Reject("not for kids".to_owned());
+
+        assert_eq!(Verdict::Approve.status(), ModerationStatus::Approved);
+        assert_eq!(
+            Verdict::Approve.location(MediaLibrary::Web),
+            Location::Public
+        );
+        assert_eq!(Verdict::Review(None).status(), ModerationStatus::Pending);
+        assert_eq!(
+            Verdict::Review(None).location(MediaLibrary::Web),
+            Location::Quarantine
+        );
+        assert_eq!(reject.status(), ModerationStatus::Rejected);
+        assert_eq!(reject.location(MediaLibrary::Web), Location::Quarantine);
+        assert_eq!(reject.reason(), Some("not for kids"));
+    }
+
+    #[test]
+    fn approved_user_media_stays_private() {
+        assert_eq!(
+            Verdict::Approve.location(MediaLibrary::User),
+            Location::Private
+        );
+        assert_eq!(
+            Verdict::Review(None).location(MediaLibrary::User),
+            Location::Quarantine
+        );
+        assert_eq!(
+            Location::visible(MediaLibrary::Global, true),
+            Location::Private
+        );
+        assert_eq!(
+            Location::visible(MediaLibrary::Global, false),
+            Location::Public
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! Helpers for the built in (email + password) identity provider.
+
+use rand::{distributions::Alphanumeric, thread_rng, Rng, RngCore};
+use sha2::Digest as _;
+
+/// The minimum length (in `char`s) that a password can be.
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// A hash (made with the same parameters as [`hash`]) of a password that nobody uses.
+///
+/// Checking passwords for unknown emails against this keeps them from signing in noticeably faster than known ones.
+pub const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=4096,t=3,p=1$amktY2xvdWQtZHVtbXktc2FsdA$aqUeLKx0scBlJtcS+r9640HcxdT6PhCWMJOOyn51Wdk";
+
+/// Hashes a password with argon2id and a random salt.
+///
+/// The returned string is in the encoded format, which contains the salt and the parameters used.
+/// Note: this is intentionally slow, don't call it from an async context.
+pub fn hash(password: &str) -> anyhow::Result<String> {
+    let mut salt = [0_u8; 16];
+    thread_rng().fill_bytes(&mut salt);
+
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        ..argon2::Config::default()
+    };
+
+    Ok(argon2::hash_encoded(password.as_bytes(), &salt, &config)?)
+}
+
+/// Checks `password` against an encoded hash created by [`hash`].
+///
+/// Note: this is intentionally slow, don't call it from an async context.
+pub fn verify(encoded: &str, password: &str) -> anyhow::Result<bool> {
+    Ok(argon2::verify_encoded(encoded, password.as_bytes())?)
+}
+
+/// Generates a token suitable for sending to a user (for email verification, password resets, etc).
+pub fn generate_token() -> String {
+    thread_rng().sample_iter(&Alphanumeric).take(32).collect()
+}
+
+/// Hashes a token generated by [`generate_token`] for storage.
+///
+/// Tokens are long and random, so unlike passwords, a fast hash is fine here.
+pub fn hash_token(token: &str) -> Vec<u8> {
+    sha2::Sha256::digest(token.as_bytes()).to_vec()
+}
@@ -1,32 +1,203 @@
+//! Media storage.
+//!
+//! Despite the name, [`Client`] isn't tied to s3, it works with anything that implements [`Storage`].
+
+use std::{sync::Arc, time::Duration};
+
 use anyhow::Context;
-use core::settings::S3Settings;
+use core::settings::{S3Settings, StorageSettings};
+use futures::future::{BoxFuture, FutureExt};
+use http::StatusCode;
 use rusoto_core::{
     credential::{AwsCredentials, StaticProvider},
     HttpClient, Region, RusotoError,
 };
-use rusoto_s3::{DeleteObjectRequest, GetObjectError, GetObjectRequest, PutObjectRequest, S3};
-use shared::media::{self, media_key, FileKind, MediaLibrary, PngImageFile};
+use rusoto_s3::{
+    util::{PreSignedRequest, PreSignedRequestOption},
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload,
+    CompletedPart, CreateMultipartUploadRequest, DeleteObjectRequest, GetObjectError,
+    GetObjectRequest, HeadObjectError, HeadObjectRequest, ListPartsRequest, PutObjectRequest,
+    UploadPartRequest, S3,
+};
+use sha2::Digest as _;
+use shared::media::{media_key, private_key, quarantine_key, FileKind, MediaLibrary, PngImageFile};
 use tokio::io::AsyncReadExt;
 use uuid::Uuid;
 
-#[derive(Clone)]
-pub struct Client {
+mod local;
+mod memory;
+
+pub use local::LocalStorage;
+pub use memory::MemoryStorage;
+
+/// A place that media files can be stored in, keyed by [`media_key`].
+// note: `async_trait` can't be used here, it expands to `::core` paths, which resolve to our `core` crate.
+pub trait Storage: Send + Sync {
+    fn put<'a>(
+        &'a self,
+        key: String,
+        content_type: &'a str,
+        data: Vec<u8>,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Returns `None` if the object doesn't exist.
+    fn get(&self, key: String) -> BoxFuture<'_, anyhow::Result<Option<Vec<u8>>>>;
+
+    /// Does nothing if the object doesn't exist.
+    fn delete(&self, key: String) -> BoxFuture<'_, anyhow::Result<()>>;
+
+    /// Returns the size of the object in bytes, or `None` if it doesn't exist.
+    fn size(&self, key: String) -> BoxFuture<'_, anyhow::Result<Option<u64>>> {
+        async move { Ok(self.get(key).await?.map(|data| data.len() as u64)) }.boxed()
+    }
+
+    /// Returns a url that `key` can be downloaded from until `expires_in` has passed,
+    /// or `None` if the backend can't create one (the object needs to be served some other way).
+    fn signed_url(&self, _key: String, _expires_in: Duration) -> Option<String> {
+        None
+    }
+
+    /// Starts a multipart upload to `key`, returning the upload's id.
+    ///
+    /// The default multipart implementation stores each part as its own object, and joins them when the upload is completed.
+    fn create_multipart_upload<'a>(
+        &'a self,
+        _key: String,
+        _content_type: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move { Ok(Uuid::new_v4().to_hyphenated().to_string()) }.boxed()
+    }
+
+    /// Returns a url that `part` (exactly `size` bytes long) can be `PUT` to until `expires_in` has passed,
+    /// or `None` if the backend can't create one (the part needs to be uploaded with [`upload_part`](Self::upload_part)).
+    fn signed_part_url(
+        &self,
+        _key: String,
+        _upload_id: String,
+        _part: i32,
+        _size: i64,
+        _expires_in: Duration,
+    ) -> Option<String> {
+        None
+    }
+
+    /// Uploads a part of a multipart upload, returning its etag.
+    fn upload_part(
+        &self,
+        _key: String,
+        upload_id: String,
+        part: i32,
+        data: Vec<u8>,
+    ) -> BoxFuture<'_, anyhow::Result<String>> {
+        async move {
+            let etag = part_etag(&data);
+
+            self.put(part_key(&upload_id, part), "application/octet-stream", data)
+                .await?;
+
+            Ok(etag)
+        }
+        .boxed()
+    }
+
+    /// Lists the parts (out of `part_count`) that have been uploaded so far, along with their etags.
+    fn list_parts(
+        &self,
+        _key: String,
+        upload_id: String,
+        part_count: i32,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<(i32, String)>>> {
+        async move {
+            let mut parts = Vec::new();
+
+            for part in 1..=part_count {
+                if let Some(data) = self.get(part_key(&upload_id, part)).await? {
+                    parts.push((part, part_etag(&data)));
+                }
+            }
+
+            Ok(parts)
+        }
+        .boxed()
+    }
+
+    /// Joins the `parts` (in order) into `key`.
+    fn complete_multipart_upload<'a>(
+        &'a self,
+        key: String,
+        content_type: &'a str,
+        upload_id: String,
+        parts: Vec<(i32, String)>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let mut data = Vec::new();
+
+            for (part, etag) in &parts {
+                let part_data = self
+                    .get(part_key(&upload_id, *part))
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("missing part {}", part))?;
+
+                anyhow::ensure!(
+                    part_etag(&part_data) == *etag,
+                    "etag mismatch for part {}",
+                    part
+                );
+
+                data.extend_from_slice(&part_data);
+            }
+
+            self.put(key, content_type, data).await?;
+
+            for (part, _) in parts {
+                self.delete(part_key(&upload_id, part)).await?;
+            }
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    /// Discards any parts (out of `part_count`) that have been uploaded.
+    fn abort_multipart_upload(
+        &self,
+        _key: String,
+        upload_id: String,
+        part_count: i32,
+    ) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            for part in 1..=part_count {
+                self.delete(part_key(&upload_id, part)).await?;
+            }
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+fn part_key(upload_id: &str, part: i32) -> String {
+    format!("uploads/{}/{}", upload_id, part)
+}
+
+fn part_etag(data: &[u8]) -> String {
+    format!("\"{:x}\"", sha2::Sha256::digest(data))
+}
+
+pub struct S3Storage {
     creds: AwsCredentials,
     region: Region,
     bucket: String,
-    client: Option<rusoto_s3::S3Client>,
+    client: rusoto_s3::S3Client,
 }
 
-impl Client {
-    pub fn new(s3_settings: S3Settings) -> anyhow::Result<Self> {
-        let S3Settings {
-            endpoint,
-            bucket,
-            access_key_id,
-            secret_access_key,
-            use_client,
-        } = s3_settings;
-
+impl S3Storage {
+    pub fn new(
+        endpoint: String,
+        bucket: String,
+        access_key_id: String,
+        secret_access_key: String,
+    ) -> anyhow::Result<Self> {
         let region = Region::Custom {
             name: "auto".to_owned(),
             endpoint,
@@ -36,32 +207,385 @@ impl Client {
 
         let credentials_provider = StaticProvider::from(creds.clone());
 
-        let client = if use_client {
-            Some(rusoto_s3::S3Client::new_with(
-                HttpClient::new()?,
-                credentials_provider,
-                region.clone(),
-            ))
-        } else {
-            None
-        };
+        let client =
+            rusoto_s3::S3Client::new_with(HttpClient::new()?, credentials_provider, region.clone());
 
         Ok(Self {
-            region,
             creds,
+            region,
             bucket,
             client,
         })
     }
+}
+
+impl Storage for S3Storage {
+    fn put<'a>(
+        &'a self,
+        key: String,
+        content_type: &'a str,
+        data: Vec<u8>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.client
+                .put_object(PutObjectRequest {
+                    bucket: self.bucket.clone(),
+                    key,
+                    content_type: Some(content_type.to_owned()),
+                    body: Some(data.into()),
+                    ..PutObjectRequest::default()
+                })
+                .await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn get(&self, key: String) -> BoxFuture<'_, anyhow::Result<Option<Vec<u8>>>> {
+        async move {
+            let resp = self
+                .client
+                .get_object(GetObjectRequest {
+                    bucket: self.bucket.clone(),
+                    key,
+                    ..GetObjectRequest::default()
+                })
+                .await;
+
+            let resp = match resp {
+                Ok(resp) => resp,
+                Err(RusotoError::Service(GetObjectError::NoSuchKey(_))) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+
+            let mut body = vec![];
+
+            resp.body
+                .ok_or_else(|| anyhow::anyhow!("missing response"))?
+                .into_async_read()
+                .read_to_end(&mut body)
+                .await?;
+
+            Ok(Some(body))
+        }
+        .boxed()
+    }
+
+    fn delete(&self, key: String) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            self.client
+                .delete_object(DeleteObjectRequest {
+                    key,
+                    bucket: self.bucket.clone(),
+                    ..DeleteObjectRequest::default()
+                })
+                .await
+                .context("failed to delete object from s3")?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn size(&self, key: String) -> BoxFuture<'_, anyhow::Result<Option<u64>>> {
+        async move {
+            let resp = self
+                .client
+                .head_object(HeadObjectRequest {
+                    key,
+                    bucket: self.bucket.clone(),
+                    ..HeadObjectRequest::default()
+                })
+                .await;
+
+            // `HEAD` responses don't have a body, so a missing object shows up as a bare 404.
+            let resp = match resp {
+                Ok(resp) => resp,
+                Err(RusotoError::Service(HeadObjectError::NoSuchKey(_))) => return Ok(None),
+                Err(RusotoError::Unknown(resp)) if resp.status == StatusCode::NOT_FOUND => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let size = resp
+                .content_length
+                .ok_or_else(|| anyhow::anyhow!("missing content length"))?;
+
+            Ok(Some(size as u64))
+        }
+        .boxed()
+    }
+
+    fn signed_url(&self, key: String, expires_in: Duration) -> Option<String> {
+        let request = GetObjectRequest {
+            bucket: self.bucket.clone(),
+            key,
+            ..GetObjectRequest::default()
+        };
+
+        Some(request.get_presigned_url(
+            &self.region,
+            &self.creds,
+            &PreSignedRequestOption { expires_in },
+        ))
+    }
+
+    fn create_multipart_upload<'a>(
+        &'a self,
+        key: String,
+        content_type: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move {
+            self.client
+                .create_multipart_upload(CreateMultipartUploadRequest {
+                    bucket: self.bucket.clone(),
+                    key,
+                    content_type: Some(content_type.to_owned()),
+                    ..CreateMultipartUploadRequest::default()
+                })
+                .await?
+                .upload_id
+                .ok_or_else(|| anyhow::anyhow!("missing upload id"))
+        }
+        .boxed()
+    }
+
+    fn signed_part_url(
+        &self,
+        key: String,
+        upload_id: String,
+        part: i32,
+        size: i64,
+        expires_in: Duration,
+    ) -> Option<String> {
+        // the content length is signed, so s3 rejects parts of any other size.
+        let request = UploadPartRequest {
+            bucket: self.bucket.clone(),
+            key,
+            upload_id,
+            part_number: i64::from(part),
+            content_length: Some(size),
+            ..UploadPartRequest::default()
+        };
+
+        Some(request.get_presigned_url(
+            &self.region,
+            &self.creds,
+            &PreSignedRequestOption { expires_in },
+        ))
+    }
+
+    fn upload_part(
+        &self,
+        key: String,
+        upload_id: String,
+        part: i32,
+        data: Vec<u8>,
+    ) -> BoxFuture<'_, anyhow::Result<String>> {
+        async move {
+            self.client
+                .upload_part(UploadPartRequest {
+                    bucket: self.bucket.clone(),
+                    key,
+                    upload_id,
+                    part_number: i64::from(part),
+                    body: Some(data.into()),
+                    ..UploadPartRequest::default()
+                })
+                .await?
+                .e_tag
+                .ok_or_else(|| anyhow::anyhow!("missing etag"))
+        }
+        .boxed()
+    }
+
+    fn list_parts(
+        &self,
+        key: String,
+        upload_id: String,
+        _part_count: i32,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<(i32, String)>>> {
+        async move {
+            let mut parts = Vec::new();
+            let mut marker = None;
+
+            loop {
+                let resp = self
+                    .client
+                    .list_parts(ListPartsRequest {
+                        bucket: self.bucket.clone(),
+                        key: key.clone(),
+                        upload_id: upload_id.clone(),
+                        part_number_marker: marker,
+                        ..ListPartsRequest::default()
+                    })
+                    .await?;
+
+                parts.extend(
+                    resp.parts
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|part| Some((part.part_number? as i32, part.e_tag?))),
+                );
+
+                match (resp.is_truncated, resp.next_part_number_marker) {
+                    (Some(true), Some(next)) => marker = Some(next),
+                    _ => break,
+                }
+            }
+
+            Ok(parts)
+        }
+        .boxed()
+    }
+
+    fn complete_multipart_upload<'a>(
+        &'a self,
+        key: String,
+        _content_type: &'a str,
+        upload_id: String,
+        parts: Vec<(i32, String)>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let parts = parts
+                .into_iter()
+                .map(|(part, etag)| CompletedPart {
+                    part_number: Some(i64::from(part)),
+                    e_tag: Some(etag),
+                })
+                .collect();
+
+            self.client
+                .complete_multipart_upload(CompleteMultipartUploadRequest {
+                    bucket: self.bucket.clone(),
+                    key,
+                    upload_id,
+                    multipart_upload: Some(CompletedMultipartUpload { parts: Some(parts) }),
+                    ..CompleteMultipartUploadRequest::default()
+                })
+                .await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn abort_multipart_upload(
+        &self,
+        key: String,
+        upload_id: String,
+        _part_count: i32,
+    ) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            self.client
+                .abort_multipart_upload(AbortMultipartUploadRequest {
+                    bucket: self.bucket.clone(),
+                    key,
+                    upload_id,
+                    ..AbortMultipartUploadRequest::default()
+                })
+                .await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// Where a media file is stored.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    /// At its [`media_key`], which the CDN serves.
+    Public,
+
+    /// At its [`quarantine_key`], for media that hasn't been approved by moderation.
+    Quarantine,
+
+    /// At its [`private_key`], for media that not everyone can see (premium and user library media).
+    Private,
+}
+
+impl Location {
+    /// Where (approved) media from `library` belongs, user library media is never public.
+    #[must_use]
+    pub const fn visible(library: MediaLibrary, is_premium: bool) -> Self {
+        match (library, is_premium) {
+            (MediaLibrary::User, _) | (_, true) => Self::Private,
+            _ => Self::Public,
+        }
+    }
+
+    #[must_use]
+    pub fn key(self, library: MediaLibrary, id: Uuid, file_kind: FileKind) -> String {
+        match self {
+            Self::Public => media_key(library, id, file_kind),
+            Self::Quarantine => quarantine_key(library, id, file_kind),
+            Self::Private => private_key(library, id, file_kind),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Client {
+    storage: Option<Arc<dyn Storage>>,
+}
+
+impl Client {
+    pub fn new(settings: StorageSettings) -> anyhow::Result<Self> {
+        let storage: Option<Arc<dyn Storage>> = match settings {
+            StorageSettings::S3(S3Settings {
+                endpoint,
+                bucket,
+                access_key_id,
+                secret_access_key,
+                use_client,
+            }) => {
+                if use_client {
+                    Some(Arc::new(S3Storage::new(
+                        endpoint,
+                        bucket,
+                        access_key_id,
+                        secret_access_key,
+                    )?))
+                } else {
+                    log::warn!("S3 client is disabled, media won't be stored");
+                    None
+                }
+            }
+
+            StorageSettings::Local(root) => Some(Arc::new(LocalStorage::new(root)?)),
+
+            StorageSettings::Memory => Some(Arc::new(MemoryStorage::default())),
+        };
+
+        Ok(Self { storage })
+    }
+
+    /// A client that stores media in `storage`.
+    pub fn with_storage(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage: Some(storage),
+        }
+    }
+
+    /// The underlying storage, or `None` if the client is disabled.
+    pub fn storage(&self) -> Option<&dyn Storage> {
+        self.storage.as_deref()
+    }
 
     pub async fn upload_png_images_resized_thumb(
         &self,
+        location: Location,
         library: MediaLibrary,
         image: Uuid,
         resized: Vec<u8>,
         thumbnail: Vec<u8>,
     ) -> anyhow::Result<()> {
-        let upload = |data, file| self.upload_media(data, library, image, FileKind::ImagePng(file));
+        let upload = |data, file| {
+            self.upload_media_to(location, data, library, image, FileKind::ImagePng(file))
+        };
 
         let resized = upload(resized, PngImageFile::Resized);
         let thumbnail = upload(thumbnail, PngImageFile::Thumbnail);
@@ -73,13 +597,16 @@ impl Client {
 
     pub async fn upload_png_images(
         &self,
+        location: Location,
         library: MediaLibrary,
         image: Uuid,
         original: Vec<u8>,
         resized: Vec<u8>,
         thumbnail: Vec<u8>,
     ) -> anyhow::Result<()> {
-        let upload = |data, file| self.upload_media(data, library, image, FileKind::ImagePng(file));
+        let upload = |data, file| {
+            self.upload_media_to(location, data, library, image, FileKind::ImagePng(file))
+        };
 
         let original = upload(original, PngImageFile::Original);
         let resized = upload(resized, PngImageFile::Resized);
@@ -90,10 +617,25 @@ impl Client {
         Ok(())
     }
 
+    /// Deletes the file, wherever it's stored.
     pub async fn delete_media(&self, library: MediaLibrary, file: FileKind, id: Uuid) {
-        let key = media_key(library, id, file);
+        let public = self.delete_media_from(Location::Public, library, file, id);
+        let quarantined = self.delete_media_from(Location::Quarantine, library, file, id);
+        let private = self.delete_media_from(Location::Private, library, file, id);
+
+        futures::future::join3(public, quarantined, private).await;
+    }
+
+    pub async fn delete_media_from(
+        &self,
+        location: Location,
+        library: MediaLibrary,
+        file: FileKind,
+        id: Uuid,
+    ) {
+        let key = location.key(library, id, file);
         if let Err(err) = self.try_delete(key.clone()).await {
-            log::warn!("failed to delete {} from s3: {}", key, err);
+            log::warn!("failed to delete {} from storage: {}", key, err);
 
             sentry::with_scope(
                 |scope| scope.set_level(Some(sentry::Level::Warning)),
@@ -116,77 +658,120 @@ impl Client {
 
     // note: does nothing if object doesn't exist, or if the client is disabled.
     async fn try_delete(&self, key: String) -> anyhow::Result<()> {
-        if let Some(client) = self.client.as_ref() {
-            client
-                .delete_object(DeleteObjectRequest {
-                    key,
-                    bucket: self.bucket.clone(),
-                    ..DeleteObjectRequest::default()
-                })
-                .await
-                .context("failed to delete object from s3")?;
+        if let Some(storage) = self.storage.as_ref() {
+            storage.delete(key).await?;
         }
 
         Ok(())
     }
 
-    pub async fn upload_media(
+    pub async fn upload_media_to(
         &self,
+        location: Location,
         data: Vec<u8>,
         library: MediaLibrary,
         id: Uuid,
         file_kind: FileKind,
     ) -> anyhow::Result<()> {
-        let client = match &self.client {
-            Some(client) => client,
+        let storage = match &self.storage {
+            Some(storage) => storage,
             None => return Ok(()),
         };
 
-        client
-            .put_object(PutObjectRequest {
-                bucket: self.bucket.clone(),
-                key: media::media_key(library, id, file_kind),
-                content_type: Some(file_kind.content_type().to_owned()),
-                body: Some(data.into()),
-                ..PutObjectRequest::default()
-            })
+        storage
+            .put(
+                location.key(library, id, file_kind),
+                file_kind.content_type(),
+                data,
+            )
+            .await
+    }
+
+    /// Moves the file from one location to another, does nothing if it isn't stored at `from`.
+    pub async fn move_media(
+        &self,
+        library: MediaLibrary,
+        id: Uuid,
+        file_kind: FileKind,
+        from: Location,
+        to: Location,
+    ) -> anyhow::Result<()> {
+        let storage = match &self.storage {
+            Some(storage) => storage,
+            None => return Ok(()),
+        };
+
+        let from = from.key(library, id, file_kind);
+
+        let data = match storage.get(from.clone()).await? {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+
+        storage
+            .put(
+                to.key(library, id, file_kind),
+                file_kind.content_type(),
+                data,
+            )
             .await?;
+
+        storage.delete(from).await
+    }
+
+    /// Moves all of the media's `files` from one location to another, files that aren't stored at `from` are skipped.
+    pub async fn move_all_media(
+        &self,
+        library: MediaLibrary,
+        id: Uuid,
+        files: &[FileKind],
+        from: Location,
+        to: Location,
+    ) -> anyhow::Result<()> {
+        if from == to {
+            return Ok(());
+        }
+
+        futures::future::try_join_all(
+            files
+                .iter()
+                .map(|&file_kind| self.move_media(library, id, file_kind, from, to)),
+        )
+        .await?;
+
         Ok(())
     }
 
+    /// Returns a signed url for the file, if the storage backend supports them.
+    ///
+    /// Note: this doesn't check that the file exists.
+    pub fn signed_media_url(
+        &self,
+        location: Location,
+        library: MediaLibrary,
+        id: Uuid,
+        file_kind: FileKind,
+        expires_in: Duration,
+    ) -> Option<String> {
+        self.storage
+            .as_ref()?
+            .signed_url(location.key(library, id, file_kind), expires_in)
+    }
+
     pub async fn download_media_file(
         &self,
+        location: Location,
         library: MediaLibrary,
         id: Uuid,
         file_kind: FileKind,
     ) -> anyhow::Result<Option<Option<Vec<u8>>>> {
-        let client = match &self.client {
-            Some(client) => client,
+        let storage = match &self.storage {
+            Some(storage) => storage,
             None => return Ok(None),
         };
 
-        let resp = client
-            .get_object(GetObjectRequest {
-                bucket: self.bucket.clone(),
-                key: media::media_key(library, id, file_kind),
-                ..GetObjectRequest::default()
-            })
-            .await;
-
-        let resp = match resp {
-            Ok(resp) => resp,
-            Err(RusotoError::Service(GetObjectError::NoSuchKey(_))) => return Ok(Some(None)),
-            Err(e) => return Err(e.into()),
-        };
-
-        let mut body = vec![];
-
-        resp.body
-            .ok_or_else(|| anyhow::anyhow!("missing response"))?
-            .into_async_read()
-            .read_to_end(&mut body)
-            .await?;
+        let body = storage.get(location.key(library, id, file_kind)).await?;
 
-        Ok(Some(Some(body)))
+        Ok(Some(body))
     }
 }
@@ -0,0 +1,87 @@
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use futures::future::{BoxFuture, FutureExt};
+
+use super::Storage;
+
+/// Stores media in a directory, with the same layout as the bucket.
+///
+/// Pointing this at the local cdn's media directory makes uploaded media viewable from the frontend.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("failed to create storage directory {:?}", root))?;
+
+        Ok(Self { root })
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let key = Path::new(key);
+
+        // keys are always generated by us, but make sure they can't escape `root` anyway.
+        if !key
+            .components()
+            .all(|it| matches!(it, Component::Normal(_)))
+        {
+            anyhow::bail!("invalid storage key: {:?}", key);
+        }
+
+        Ok(self.root.join(key))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put<'a>(
+        &'a self,
+        key: String,
+        _content_type: &'a str,
+        data: Vec<u8>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let path = self.path(&key)?;
+
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+
+            // write to a temporary file first, so that readers never see a partially written file.
+            let tmp = path.with_extension("tmp");
+            tokio::fs::write(&tmp, data).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn get(&self, key: String) -> BoxFuture<'_, anyhow::Result<Option<Vec<u8>>>> {
+        async move {
+            match tokio::fs::read(self.path(&key)?).await {
+                Ok(data) => Ok(Some(data)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        }
+        .boxed()
+    }
+
+    fn delete(&self, key: String) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            match tokio::fs::remove_file(self.path(&key)?).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            }
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,40 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use futures::future::{BoxFuture, FutureExt};
+
+use super::Storage;
+
+/// Stores media in memory, everything is lost when the process exits.
+#[derive(Default)]
+pub struct MemoryStorage {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn put<'a>(
+        &'a self,
+        key: String,
+        _content_type: &'a str,
+        data: Vec<u8>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.objects.lock().unwrap().insert(key, data);
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn get(&self, key: String) -> BoxFuture<'_, anyhow::Result<Option<Vec<u8>>>> {
+        async move { Ok(self.objects.lock().unwrap().get(&key).cloned()) }.boxed()
+    }
+
+    fn delete(&self, key: String) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            self.objects.lock().unwrap().remove(&key);
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
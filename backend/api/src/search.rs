@@ -0,0 +1,250 @@
+//! Searching the global image library.
+//!
+//! Searches go to Algolia when it's configured, otherwise they fall back to Postgres full text search,
+//! so that local development, CI and self-hosted installs still have working search.
+//! The same goes for suggesting searches as they're typed.
+
+use std::{collections::HashMap, sync::Arc};
+
+use futures::future::{BoxFuture, FutureExt};
+use shared::domain::image::{FacetCount, ImageSearchFacets, ImageSearchQuery};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// How many images are in a page of Postgres search results (matches Algolia's default).
+const PAGE_SIZE: u32 = 20;
+
+/// How many matching images have each piece of metadata, by facet name (`styles`, `age_ranges`, `affiliations`, `categories`)
+/// then by metadata id.
+pub type FacetCounts = HashMap<String, HashMap<Uuid, u64>>;
+
+/// A page of search results.
+pub struct ImageSearchResults {
+    /// The IDs of the matching images, best match first.
+    pub ids: Vec<Uuid>,
+
+    /// The number of pages of results.
+    pub pages: u32,
+
+    /// The total number of matching images.
+    pub total_hits: u64,
+
+    /// Only present if [`ImageSearchQuery::with_facets`] was set.
+    pub facets: Option<FacetCounts>,
+}
+
+/// Something that can search the global image library.
+// note: see `s3::Storage` for why this doesn't use `async_trait`.
+pub trait ImageSearch: Send + Sync {
+    fn search_image<'a>(
+        &'a self,
+        query: &'a ImageSearchQuery,
+    ) -> BoxFuture<'a, anyhow::Result<ImageSearchResults>>;
+
+    /// Suggests (at most `limit`) searches that start with `prefix`, from the names of published images.
+    fn suggest_queries<'a>(
+        &'a self,
+        prefix: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<String>>>;
+}
+
+impl ImageSearch for crate::algolia::Client {
+    fn search_image<'a>(
+        &'a self,
+        query: &'a ImageSearchQuery,
+    ) -> BoxFuture<'a, anyhow::Result<ImageSearchResults>> {
+        async move {
+            Self::search_image(
+                self,
+                &query.q,
+                query.page,
+                query.is_premium,
+                query.is_published,
+                &query.styles,
+                &query.age_ranges,
+                &query.affiliations,
+                &query.categories,
+                query.with_facets,
+            )
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("algolia searching is disabled"))
+        }
+        .boxed()
+    }
+
+    fn suggest_queries<'a>(
+        &'a self,
+        prefix: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        async move {
+            self.suggest_image_names(prefix, limit)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("algolia searching is disabled"))
+        }
+        .boxed()
+    }
+}
+
+/// Full text search over image names, descriptions and metadata names.
+pub struct PostgresSearch {
+    db: PgPool,
+}
+
+impl PostgresSearch {
+    pub const fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+impl ImageSearch for PostgresSearch {
+    fn search_image<'a>(
+        &'a self,
+        query: &'a ImageSearchQuery,
+    ) -> BoxFuture<'a, anyhow::Result<ImageSearchResults>> {
+        async move {
+            let page = query.page.unwrap_or(0);
+
+            let results = sqlx::query_file!(
+                "query/search_image.sql",
+                query.q.trim(),
+                query.is_premium,
+                query.is_published,
+                &query.styles.iter().map(|it| it.0).collect::<Vec<_>>(),
+                &query.age_ranges.iter().map(|it| it.0).collect::<Vec<_>>(),
+                &query.affiliations.iter().map(|it| it.0).collect::<Vec<_>>(),
+                &query.categories.iter().map(|it| it.0).collect::<Vec<_>>(),
+                i64::from(PAGE_SIZE),
+                i64::from(page) * i64::from(PAGE_SIZE),
+                query.with_facets
+            )
+            .fetch_one(&self.db)
+            .await?;
+
+            let total_hits = results.total as u64;
+            let pages = ((total_hits + u64::from(PAGE_SIZE) - 1) / u64::from(PAGE_SIZE)) as u32;
+
+            let facets = if query.with_facets {
+                let mut facets = FacetCounts::new();
+                let counts = results
+                    .facet_names
+                    .into_iter()
+                    .zip(results.facet_ids)
+                    .zip(results.facet_counts);
+                for ((facet, id), count) in counts {
+                    facets.entry(facet).or_default().insert(id, count as u64);
+                }
+
+                Some(facets)
+            } else {
+                None
+            };
+
+            Ok(ImageSearchResults {
+                ids: results.ids,
+                pages,
+                total_hits,
+                facets,
+            })
+        }
+        .boxed()
+    }
+
+    fn suggest_queries<'a>(
+        &'a self,
+        prefix: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        async move {
+            // any word of the name can match, like with Algolia.
+            sqlx::query!(
+                r#"
+select min(name) as "name!"
+from image_metadata
+where publish_at <= now()
+  and (name ilike $1 or name ilike '% ' || $1)
+group by lower(name)
+order by count(*) desc, min(name)
+limit $2
+"#,
+                crate::db::like_prefix(prefix),
+                i64::from(limit)
+            )
+            .fetch_all(&self.db)
+            .await
+            .map(|it| it.into_iter().map(|it| it.name).collect())
+            .map_err(Into::into)
+        }
+        .boxed()
+    }
+}
+
+/// Picks the search backend to use.
+#[derive(Clone)]
+pub struct Client {
+    backend: Arc<dyn ImageSearch>,
+}
+
+impl Client {
+    /// Uses `algolia` if it can search, Postgres otherwise.
+    pub fn new(algolia: crate::algolia::Client, db: PgPool) -> Self {
+        let backend: Arc<dyn ImageSearch> = if algolia.can_search() {
+            Arc::new(algolia)
+        } else {
+            log::info!("Algolia searching is disabled, falling back to postgres search");
+            Arc::new(PostgresSearch::new(db))
+        };
+
+        Self { backend }
+    }
+
+    pub async fn search_image(
+        &self,
+        query: &ImageSearchQuery,
+    ) -> anyhow::Result<ImageSearchResults> {
+        self.backend.search_image(query).await
+    }
+
+    pub async fn suggest_queries(&self, prefix: &str, limit: u32) -> anyhow::Result<Vec<String>> {
+        self.backend.suggest_queries(prefix, limit).await
+    }
+}
+
+/// Looks up the names of the metadata in `counts` (in the first of `languages` they're translated to), most common first.
+pub async fn name_facets(
+    db: &PgPool,
+    counts: FacetCounts,
+    languages: &[String],
+) -> anyhow::Result<ImageSearchFacets> {
+    let ids: Vec<_> = counts.values().flat_map(HashMap::keys).copied().collect();
+    let names: HashMap<_, _> = crate::db::meta::get_names(db, &ids, languages)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut facets = ImageSearchFacets::default();
+
+    for (facet, counts) in counts {
+        let out = match facet.as_str() {
+            "styles" => &mut facets.styles,
+            "age_ranges" => &mut facets.age_ranges,
+            "affiliations" => &mut facets.affiliations,
+            "categories" => &mut facets.categories,
+            _ => continue,
+        };
+
+        // metadata that's been deleted since the search index was updated is skipped.
+        out.extend(counts.into_iter().filter_map(|(id, count)| {
+            names.get(&id).map(|name| FacetCount {
+                id,
+                name: name.clone(),
+                count,
+            })
+        }));
+
+        out.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    }
+
+    Ok(facets)
+}
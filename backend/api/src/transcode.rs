@@ -0,0 +1,169 @@
+//! Transcoding uploaded audio into a normalized mp3 (see `core::settings::AudioSettings`).
+//!
+//! This shells out to ffmpeg, which reads the upload from stdin and writes the mp3 to stdout.
+
+use std::{path::PathBuf, process::Stdio};
+
+use core::settings::AudioSettings;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// The arguments ffmpeg gets run with.
+///
+/// Everything but the first audio stream (like cover art) and the metadata gets dropped,
+/// so that all audio is served the same way no matter what it was uploaded as.
+const FFMPEG_ARGS: &[&str] = &[
+    "-hide_banner",
+    "-loglevel",
+    "error",
+    "-i",
+    "pipe:0",
+    "-map",
+    "0:a:0",
+    "-map_metadata",
+    "-1",
+    "-codec:a",
+    "libmp3lame",
+    "-b:a",
+    "128k",
+    "-ar",
+    "44100",
+    "-f",
+    "mp3",
+    "pipe:1",
+];
+
+#[derive(Debug)]
+pub enum TranscodeError {
+    /// The input isn't audio ffmpeg can decode.
+    InvalidAudio,
+    Other(anyhow::Error),
+}
+
+impl<T: Into<anyhow::Error>> From<T> for TranscodeError {
+    fn from(e: T) -> Self {
+        Self::Other(e.into())
+    }
+}
+
+#[derive(Clone)]
+pub struct Transcoder {
+    ffmpeg: PathBuf,
+}
+
+impl Transcoder {
+    /// Returns `None` if transcoding is disabled.
+    #[must_use]
+    pub fn new(settings: AudioSettings) -> Option<Self> {
+        match settings {
+            AudioSettings::Ffmpeg(ffmpeg) => Some(Self { ffmpeg }),
+            AudioSettings::None => None,
+        }
+    }
+
+    /// Transcodes `data` into an mp3.
+    pub async fn mp3(&self, data: Vec<u8>) -> Result<Vec<u8>, TranscodeError> {
+        let mut child = Command::new(&self.ffmpeg)
+            .args(FFMPEG_ARGS)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("ffmpeg's stdin wasn't piped"))?;
+
+        // the input has to be written while the output is read, otherwise both pipes can fill up.
+        let write = async move {
+            stdin.write_all(&data).await?;
+            stdin.shutdown().await
+        };
+
+        let (written, output) = futures::future::join(write, child.wait_with_output()).await;
+        let output = output?;
+
+        // note: ffmpeg stops reading as soon as it sees invalid input, so write errors are expected then.
+        if !output.status.success() {
+            log::debug!(
+                "ffmpeg failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+
+            return Err(TranscodeError::InvalidAudio);
+        }
+
+        written?;
+
+        if output.stdout.is_empty() {
+            return Err(TranscodeError::InvalidAudio);
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::fs::PermissionsExt, path::PathBuf};
+
+    use core::settings::AudioSettings;
+
+    use super::{TranscodeError, Transcoder};
+
+    /// Creates a stand-in for ffmpeg that runs `script` (and ignores its arguments).
+    fn fake_ffmpeg(script: &str) -> (PathBuf, Transcoder) {
+        let dir = std::env::temp_dir().join(format!("ji-cloud-ffmpeg-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("ffmpeg");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        (dir, Transcoder::new(AudioSettings::Ffmpeg(path)).unwrap())
+    }
+
+    #[test]
+    fn disabled() {
+        assert!(Transcoder::new(AudioSettings::None).is_none());
+    }
+
+    #[actix_rt::test]
+    async fn transcodes_through_pipes() {
+        let (dir, transcoder) = fake_ffmpeg("tr a-z A-Z");
+
+        // big enough to fill up the pipes if they aren't read from and written to at the same time.
+        let data = b"mp3".repeat(1024 * 1024);
+
+        let transcoded = transcoder.mp3(data).await.unwrap();
+
+        assert_eq!(transcoded, b"MP3".repeat(1024 * 1024));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn invalid_audio() {
+        let (dir, transcoder) = fake_ffmpeg("echo 'invalid data' >&2\nexit 1");
+
+        assert!(matches!(
+            transcoder.mp3(b"mp3".repeat(1024 * 1024)).await,
+            Err(TranscodeError::InvalidAudio)
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn missing_ffmpeg() {
+        let transcoder =
+            Transcoder::new(AudioSettings::Ffmpeg("/nonexistent/ffmpeg".into())).unwrap();
+
+        assert!(matches!(
+            transcoder.mp3(vec![1, 2, 3]).await,
+            Err(TranscodeError::Other(_))
+        ));
+    }
+}
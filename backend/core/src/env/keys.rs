@@ -4,6 +4,64 @@ pub mod google {
     pub const PROJECT_ID: &str = "PROJECT_ID";
 }
 
+pub mod firebase {
+    /// Disables firebase authentication (no JWKs will be fetched from google).
+    /// Is optional. If set, only the built in (email + password) identity provider will be available.
+    pub const DISABLE: &str = "DISABLE_FIREBASE_AUTH";
+}
+
+pub mod rate_limit {
+    /// Where rate limiting state is kept, one of `postgres`, `memory`, or `disabled`.
+    /// Is optional. Defaults to `memory` when running locally and `postgres` otherwise.
+    /// Note: `memory` is per-instance, so it isn't suitable for deployments with more than one instance.
+    pub const STORE: &str = "RATE_LIMIT_STORE";
+
+    /// How many proxies that append to `X-Forwarded-For` are in front of the api.
+    /// Is optional. Defaults to `0` (the connection's address is used) when running locally and `1` otherwise.
+    pub const TRUSTED_PROXIES: &str = "RATE_LIMIT_TRUSTED_PROXIES";
+
+    /// The rate limited routes, as a json list of `{ method, path, per_user, per_ip }`,
+    /// where `per_user` and `per_ip` are optional `{ burst, refill_secs }` quotas.
+    /// Is optional. Defaults to `RateLimitRule::defaults`.
+    pub const RULES: &str = "RATE_LIMIT_RULES";
+}
+
+pub mod moderation {
+    /// How uploaded user and web images get moderated, one of `allow` or `review`.
+    /// Is optional. Defaults to `allow`.
+    pub const CLASSIFIER: &str = "MODERATION_CLASSIFIER";
+}
+
+pub mod mail {
+    /// How emails (like email verification and password reset tokens) get sent, one of `sendgrid`, `dir`, or `log`.
+    /// Is optional. Defaults to `log` when running locally and `sendgrid` otherwise.
+    /// Note: `dir` and `log` don't deliver anything, so they're only allowed when running locally.
+    pub const BACKEND: &str = "MAIL_BACKEND";
+
+    /// The directory emails get written to (as one json file each) when using the `dir` backend.
+    /// Required if `BACKEND` is `dir`.
+    pub const DIR: &str = "MAIL_DIR";
+
+    /// The key used for SendGrid's api, needs the `mail.send` permission.
+    /// Required if `BACKEND` is `sendgrid`.
+    pub const SENDGRID_API_KEY: &str = "SENDGRID_API_KEY";
+
+    /// The address emails are sent from, it has to be verified with SendGrid.
+    /// Required if `BACKEND` is `sendgrid`.
+    pub const SENDER: &str = "MAIL_SENDER";
+}
+
+pub mod audio {
+    /// How uploaded audio gets transcoded (into a normalized mp3), one of `ffmpeg` or `none`.
+    /// Is optional. Defaults to `none` when running locally and `ffmpeg` otherwise.
+    /// Note: with `none` audio is served as it was uploaded, so it's only allowed when running locally.
+    pub const TRANSCODER: &str = "AUDIO_TRANSCODER";
+
+    /// The ffmpeg executable to transcode with when using the `ffmpeg` transcoder.
+    /// Is optional. Defaults to `ffmpeg` (looked up in `PATH`).
+    pub const FFMPEG_PATH: &str = "AUDIO_FFMPEG_PATH";
+}
+
 pub mod s3 {
     pub const ENDPOINT: &str = "S3_ENDPOINT";
 
@@ -16,6 +74,16 @@ pub mod s3 {
     pub const DISABLE: &str = "S3_LOCAL_DISABLE_CLIENT";
 }
 
+pub mod storage {
+    /// Where media gets stored, one of `s3`, `local`, or `memory`.
+    /// Is optional. Defaults to `s3`.
+    pub const BACKEND: &str = "STORAGE_BACKEND";
+
+    /// The directory media gets stored in when using the `local` backend.
+    /// Required if `BACKEND` is `local`.
+    pub const LOCAL_DIR: &str = "STORAGE_LOCAL_DIR";
+}
+
 #[cfg(feature = "db")]
 pub mod db {
     pub const DATABASE_URL: &str = "DATABASE_URL";
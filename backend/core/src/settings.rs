@@ -8,8 +8,10 @@ use crate::{
 };
 use config::RemoteTarget;
 use jsonwebtoken::{DecodingKey, EncodingKey};
+use serde::Deserialize;
 use std::{
     env::VarError,
+    path::PathBuf,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
@@ -136,6 +138,18 @@ pub struct S3Settings {
     pub secret_access_key: String,
 }
 
+/// Settings for where media gets stored.
+pub enum StorageSettings {
+    /// Media is stored in s3 (or something compatible with it).
+    S3(S3Settings),
+
+    /// Media is stored in a directory on the local filesystem.
+    Local(PathBuf),
+
+    /// Media is stored in memory, and lost when the process exits, useful for tests.
+    Memory,
+}
+
 /// Settings for managing JWKs from Google.
 #[derive(Debug)]
 pub struct JwkSettings {
@@ -170,6 +184,136 @@ pub struct AlgoliaSettings {
     pub frontend_search_key: Option<String>,
 }
 
+/// Where rate limiting state is kept.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitStore {
+    /// Rate limiting is disabled.
+    Disabled,
+
+    /// State is kept in memory (per instance), useful for tests and local development.
+    Memory,
+
+    /// State is kept in postgres, and shared between all instances.
+    Postgres,
+}
+
+/// How many requests can be made in a burst, and how quickly that allowance comes back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct RateLimitQuota {
+    /// The maximum number of requests that can be made at once.
+    pub burst: u32,
+
+    /// How long it takes (in seconds) for a single request to be "given back".
+    pub refill_secs: u32,
+}
+
+/// Rate limits for a single route.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateLimitRule {
+    /// The route's method (for example `POST`).
+    pub method: String,
+
+    /// The route's path, as it's registered (for example `/v1/user/{id}`).
+    pub path: String,
+
+    /// Limits each user (identified by their session, personal access token or firebase token).
+    ///
+    /// Requests without verified credentials share a bucket per IP instead.
+    #[serde(default)]
+    pub per_user: Option<RateLimitQuota>,
+
+    /// Limits each IP.
+    #[serde(default)]
+    pub per_ip: Option<RateLimitQuota>,
+}
+
+impl RateLimitRule {
+    fn new(
+        method: &str,
+        path: &str,
+        per_user: Option<(u32, u32)>,
+        per_ip: Option<(u32, u32)>,
+    ) -> Self {
+        let quota = |(burst, refill_secs)| RateLimitQuota { burst, refill_secs };
+
+        Self {
+            method: method.to_owned(),
+            path: path.to_owned(),
+            per_user: per_user.map(quota),
+            per_ip: per_ip.map(quota),
+        }
+    }
+
+    /// The rules used when none are configured.
+    ///
+    /// Note: these paths are the `shared::api::endpoints` ones, which `core` can't depend on.
+    #[must_use]
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            // fetches arbitrary urls server side.
+            Self::new("POST", "/v1/media/image/url", Some((10, 6)), Some((20, 3))),
+            // spends our bing quota.
+            Self::new("GET", "/v1/search/web/image", Some((10, 2)), Some((20, 1))),
+            Self::new("POST", "/v1/user", None, Some((5, 360))),
+            // slows down password guessing.
+            Self::new("POST", "/v1/login", None, Some((10, 6))),
+            Self::new("POST", "/v1/user/password-reset", None, Some((3, 300))),
+        ]
+    }
+}
+
+/// Settings for rate limiting.
+#[derive(Clone, Debug)]
+pub struct RateLimitSettings {
+    /// Where rate limiting state is kept.
+    pub store: RateLimitStore,
+
+    /// How many proxies (that append to `X-Forwarded-For`) are in front of the api.
+    ///
+    /// If `0`, the address of the connection is used, otherwise, the address that the outermost proxy saw.
+    pub trusted_proxies: usize,
+
+    /// The rate limited routes.
+    pub rules: Vec<RateLimitRule>,
+}
+
+/// Which classifier moderates uploaded images.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ModerationSettings {
+    /// Every image is approved.
+    AllowAll,
+
+    /// Every image is quarantined until an admin reviews it.
+    ReviewAll,
+}
+
+/// How emails get sent.
+pub enum MailSettings {
+    /// Emails are sent with SendGrid.
+    Sendgrid {
+        /// The key for SendGrid's api.
+        api_key: String,
+
+        /// The address emails are sent from.
+        sender: String,
+    },
+
+    /// Emails are written to a directory instead of being sent, useful for tests.
+    Dir(PathBuf),
+
+    /// Emails are only logged, useful for local development.
+    Log,
+}
+
+/// How uploaded audio gets transcoded.
+pub enum AudioSettings {
+    /// Audio is transcoded with the given ffmpeg executable.
+    Ffmpeg(PathBuf),
+
+    /// Audio is only validated, and served as it was uploaded, useful for local development.
+    None,
+}
+
 /// Manages access to settings.
 pub struct SettingsManager {
     token: Option<String>,
@@ -305,15 +449,209 @@ impl SettingsManager {
     }
 
     /// Load the settings for JWKs.
-    pub async fn jwk_settings(&self) -> anyhow::Result<JwkSettings> {
+    ///
+    /// Returns [`None`] if firebase authentication has been disabled,
+    /// in which case only the built in (email + password) identity provider is available.
+    pub async fn jwk_settings(&self) -> anyhow::Result<Option<JwkSettings>> {
+        if crate::env::env_bool(keys::firebase::DISABLE) {
+            log::warn!("Firebase authentication is disabled");
+            return Ok(None);
+        }
+
         let issuer = format!("{}/{}", config::JWK_ISSUER_URL, &self.project_id);
 
-        Ok(JwkSettings {
+        Ok(Some(JwkSettings {
             audience: self.project_id.clone(),
             issuer,
+        }))
+    }
+
+    /// Load the settings for media storage.
+    pub async fn storage_settings(&self) -> anyhow::Result<StorageSettings> {
+        let backend = match std::env::var(keys::storage::BACKEND) {
+            Ok(backend) => backend,
+            Err(VarError::NotPresent) => return Ok(StorageSettings::S3(self.s3_settings().await?)),
+            Err(VarError::NotUnicode(_)) => {
+                anyhow::bail!("`{}` wasn't unicode", keys::storage::BACKEND)
+            }
+        };
+
+        match backend.as_str() {
+            "s3" => Ok(StorageSettings::S3(self.s3_settings().await?)),
+            "local" => Ok(StorageSettings::Local(
+                req_env(keys::storage::LOCAL_DIR)?.into(),
+            )),
+            "memory" => {
+                log::warn!("Media is stored in memory and will be lost on exit");
+                Ok(StorageSettings::Memory)
+            }
+            _ => anyhow::bail!(
+                "Unknown storage backend: {} (expected s3|local|memory)",
+                backend
+            ),
+        }
+    }
+
+    /// Load the settings for rate limiting.
+    pub async fn rate_limit_settings(&self) -> anyhow::Result<RateLimitSettings> {
+        let store = match std::env::var(keys::rate_limit::STORE) {
+            Ok(store) => match store.as_str() {
+                "postgres" => RateLimitStore::Postgres,
+                "memory" => RateLimitStore::Memory,
+                "disabled" => RateLimitStore::Disabled,
+                _ => anyhow::bail!(
+                    "Unknown rate limit store: {} (expected postgres|memory|disabled)",
+                    store
+                ),
+            },
+
+            Err(VarError::NotPresent) if self.remote_target == RemoteTarget::Local => {
+                RateLimitStore::Memory
+            }
+
+            Err(VarError::NotPresent) => RateLimitStore::Postgres,
+
+            Err(VarError::NotUnicode(_)) => {
+                anyhow::bail!("`{}` wasn't unicode", keys::rate_limit::STORE)
+            }
+        };
+
+        if store == RateLimitStore::Disabled {
+            log::warn!("Rate limiting is disabled");
+        }
+
+        let trusted_proxies = match std::env::var(keys::rate_limit::TRUSTED_PROXIES) {
+            Ok(count) => count.parse().with_context(|| {
+                anyhow::anyhow!("`{}` wasn't a number", keys::rate_limit::TRUSTED_PROXIES)
+            })?,
+
+            Err(VarError::NotPresent) if self.remote_target == RemoteTarget::Local => 0,
+
+            // cloud run's load balancer.
+            Err(VarError::NotPresent) => 1,
+
+            Err(VarError::NotUnicode(_)) => {
+                anyhow::bail!("`{}` wasn't unicode", keys::rate_limit::TRUSTED_PROXIES)
+            }
+        };
+
+        let rules = match std::env::var(keys::rate_limit::RULES) {
+            Ok(rules) => serde_json::from_str(&rules).with_context(|| {
+                anyhow::anyhow!("`{}` wasn't a valid list of rules", keys::rate_limit::RULES)
+            })?,
+
+            Err(VarError::NotPresent) => RateLimitRule::defaults(),
+
+            Err(VarError::NotUnicode(_)) => {
+                anyhow::bail!("`{}` wasn't unicode", keys::rate_limit::RULES)
+            }
+        };
+
+        Ok(RateLimitSettings {
+            store,
+            trusted_proxies,
+            rules,
         })
     }
 
+    /// Load which classifier should moderate uploaded images.
+    ///
+    /// Reviewing every image is opt-in, since it hides every upload until an admin gets to it.
+    pub async fn moderation_settings(&self) -> anyhow::Result<ModerationSettings> {
+        match std::env::var(keys::moderation::CLASSIFIER) {
+            Ok(classifier) => match classifier.as_str() {
+                "allow" => Ok(ModerationSettings::AllowAll),
+                "review" => Ok(ModerationSettings::ReviewAll),
+                _ => anyhow::bail!(
+                    "Unknown moderation classifier: {} (expected allow|review)",
+                    classifier
+                ),
+            },
+
+            Err(VarError::NotPresent) => Ok(ModerationSettings::AllowAll),
+
+            Err(VarError::NotUnicode(_)) => {
+                anyhow::bail!("`{}` wasn't unicode", keys::moderation::CLASSIFIER)
+            }
+        }
+    }
+
+    /// Load how emails should be sent.
+    ///
+    /// Backends that don't deliver emails can only be used locally, otherwise nobody could verify their email.
+    pub async fn mail_settings(&self) -> anyhow::Result<MailSettings> {
+        let backend = match std::env::var(keys::mail::BACKEND) {
+            Ok(backend) => backend,
+            Err(VarError::NotPresent) if self.remote_target == RemoteTarget::Local => {
+                "log".to_owned()
+            }
+            Err(VarError::NotPresent) => "sendgrid".to_owned(),
+            Err(VarError::NotUnicode(_)) => {
+                anyhow::bail!("`{}` wasn't unicode", keys::mail::BACKEND)
+            }
+        };
+
+        let settings = match backend.as_str() {
+            "sendgrid" => MailSettings::Sendgrid {
+                api_key: self.get_secret(keys::mail::SENDGRID_API_KEY).await?,
+                sender: self.get_secret(keys::mail::SENDER).await?,
+            },
+            "dir" => MailSettings::Dir(req_env(keys::mail::DIR)?.into()),
+            "log" => MailSettings::Log,
+            _ => anyhow::bail!(
+                "Unknown mail backend: {} (expected sendgrid|dir|log)",
+                backend
+            ),
+        };
+
+        if self.remote_target != RemoteTarget::Local
+            && !matches!(settings, MailSettings::Sendgrid { .. })
+        {
+            anyhow::bail!("The `{}` mail backend can only be used locally", backend);
+        }
+
+        Ok(settings)
+    }
+
+    /// Load how uploaded audio should be transcoded.
+    ///
+    /// Not transcoding is only allowed locally, where ffmpeg might not be installed.
+    pub async fn audio_settings(&self) -> anyhow::Result<AudioSettings> {
+        let transcoder = match std::env::var(keys::audio::TRANSCODER) {
+            Ok(transcoder) => transcoder,
+            Err(VarError::NotPresent) if self.remote_target == RemoteTarget::Local => {
+                "none".to_owned()
+            }
+            Err(VarError::NotPresent) => "ffmpeg".to_owned(),
+            Err(VarError::NotUnicode(_)) => {
+                anyhow::bail!("`{}` wasn't unicode", keys::audio::TRANSCODER)
+            }
+        };
+
+        match transcoder.as_str() {
+            "ffmpeg" => {
+                let path = match std::env::var(keys::audio::FFMPEG_PATH) {
+                    Ok(path) => path,
+                    Err(VarError::NotPresent) => "ffmpeg".to_owned(),
+                    Err(VarError::NotUnicode(_)) => {
+                        anyhow::bail!("`{}` wasn't unicode", keys::audio::FFMPEG_PATH)
+                    }
+                };
+
+                Ok(AudioSettings::Ffmpeg(path.into()))
+            }
+
+            "none" if self.remote_target == RemoteTarget::Local => Ok(AudioSettings::None),
+
+            "none" => anyhow::bail!("The `none` audio transcoder can only be used locally"),
+
+            _ => anyhow::bail!(
+                "Unknown audio transcoder: {} (expected ffmpeg|none)",
+                transcoder
+            ),
+        }
+    }
+
     /// Load the settings for connecting to the db.
     #[cfg(feature = "db")]
     pub async fn db_connect_options(&self, sql_proxy: bool) -> anyhow::Result<PgConnectOptions> {
@@ -0,0 +1,671 @@
+#![warn(
+    clippy::pedantic,
+    clippy::multiple_crate_versions,
+    clippy::cognitive_complexity,
+    clippy::future_not_send,
+    clippy::missing_const_for_fn,
+    clippy::needless_borrow,
+    clippy::redundant_pub_crate,
+    clippy::string_lit_as_bytes,
+    clippy::use_self,
+    clippy::useless_let_if_seq,
+    rust_2018_idioms,
+    future_incompatible
+)]
+
+//! Reconciles the media bucket with the database.
+//!
+//! * objects that don't belong to any media row are deleted.
+//! * rows that never had a file uploaded (and are old enough that the upload isn't in progress) are deleted,
+//!   along with any objects they have. Global library rows are only reported, since they're created by curators
+//!   (with metadata) before the file gets uploaded.
+//! * rows that claim to have been uploaded, but have no objects, are reported.
+//! * multipart uploads that will never be completed (their upload session expired, was completed or aborted) are aborted,
+//!   this includes the parts that backends without multipart uploads store under `uploads/`.
+//!   Uploads that don't have a session yet are only aborted once they're old enough, the session is created after the upload is started.
+//!
+//! Nothing is deleted unless `--delete` is passed, otherwise orphans are only reported.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use clap::Clap;
+use futures::{StreamExt, TryStreamExt};
+use rusoto_s3::{AbortMultipartUploadRequest, DeleteObjectRequest, S3Client, S3};
+use shared::media::{parse_media_key, FileKind, MediaLibrary, PRIVATE_PREFIX, QUARANTINE_PREFIX};
+use simplelog::Config;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use uuid::Uuid;
+
+#[derive(Clap)]
+#[clap(rename_all = "kebab-case")]
+struct Opts {
+    /// The endpoint to access S3 from
+    #[clap(long, env = "S3_ENDPOINT")]
+    endpoint: String,
+
+    /// The s3 bucket to use
+    #[clap(long, env = "S3_BUCKET")]
+    bucket: String,
+
+    #[clap(long, env = "S3_ACCESS_KEY", hide_env_values = true)]
+    access_key_id: String,
+
+    #[clap(long, env = "S3_ACCESS_SECRET", hide_env_values = true)]
+    access_secret: String,
+
+    /// The database to compare the bucket against
+    #[clap(long, env = "DATABASE_URL", hide_env_values = true)]
+    database_url: String,
+
+    /// Delete orphans, without this they're only reported
+    #[clap(long)]
+    delete: bool,
+
+    /// How old (in hours) a row that never had a file uploaded (or a multipart upload without a session) must be before it's considered orphaned
+    #[clap(long, default_value = "24")]
+    min_age_hours: i64,
+
+    /// Controls the maximum amount of objects that will be deleted at once
+    #[clap(long, default_value = "50")]
+    max_tasks: usize,
+
+    /// The minimum log level to use.
+    #[clap(
+        long,
+        default_value = "WARN",
+        parse(try_from_str),
+        possible_values(&["OFF", "ERROR", "WARN", "INFO", "DEBUG", "TRACE"]), case_insensitive = true
+    )]
+    log_level: simplelog::LevelFilter,
+}
+
+/// The tables that media files belong to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Table {
+    ImageMetadata,
+    Animation,
+    UserImageLibrary,
+    UserAudioLibrary,
+    WebMediaLibrary,
+}
+
+impl Table {
+    const ALL: [Self; 5] = [
+        Self::ImageMetadata,
+        Self::Animation,
+        Self::UserImageLibrary,
+        Self::UserAudioLibrary,
+        Self::WebMediaLibrary,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::ImageMetadata => "image_metadata",
+            Self::Animation => "animation",
+            Self::UserImageLibrary => "user_image_library",
+            Self::UserAudioLibrary => "user_audio_library",
+            Self::WebMediaLibrary => "web_media_library",
+        }
+    }
+
+    /// Whether rows that never had a file uploaded get deleted (rather than just reported).
+    const fn deletes_never_uploaded(self) -> bool {
+        !matches!(self, Self::ImageMetadata | Self::Animation)
+    }
+
+    /// Whether the table tracks background processing (see the api's `jobs` module).
+    const fn has_processing_status(self) -> bool {
+        !matches!(self, Self::WebMediaLibrary)
+    }
+
+    /// The table that a file belongs to, `None` if there's no media that would have the file.
+    const fn for_file(library: MediaLibrary, file_kind: FileKind) -> Option<Self> {
+        match (library, file_kind) {
+            (MediaLibrary::Global, FileKind::ImagePng(_)) => Some(Self::ImageMetadata),
+            (MediaLibrary::Global, FileKind::AnimationGif)
+            | (MediaLibrary::Global, FileKind::AnimationSpritesheet) => Some(Self::Animation),
+            (MediaLibrary::User, FileKind::ImagePng(_)) => Some(Self::UserImageLibrary),
+            (MediaLibrary::User, FileKind::AudioMp3) => Some(Self::UserAudioLibrary),
+            (MediaLibrary::Web, FileKind::ImagePng(_))
+            | (MediaLibrary::Web, FileKind::AnimationGif) => Some(Self::WebMediaLibrary),
+            _ => None,
+        }
+    }
+}
+
+/// The state of a table's rows.
+struct Rows {
+    /// Every row's id.
+    all: HashSet<Uuid>,
+
+    /// Rows that have had a file uploaded (before the bucket was listed).
+    uploaded: Vec<Uuid>,
+
+    /// Rows that never had a file uploaded, and are old enough to be considered abandoned.
+    never_uploaded: Vec<Uuid>,
+}
+
+async fn load_rows(
+    db: &PgPool,
+    table: Table,
+    listed_at: DateTime<Utc>,
+    abandoned_before: DateTime<Utc>,
+) -> anyhow::Result<Rows> {
+    let all = sqlx::query_scalar::<_, Uuid>(&format!("select id from {}", table.name()))
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let uploaded = sqlx::query_scalar::<_, Uuid>(&format!(
+        "select id from {} where uploaded_at < $1",
+        table.name()
+    ))
+    .bind(listed_at)
+    .fetch_all(db)
+    .await?;
+
+    let never_uploaded = sqlx::query_scalar::<_, Uuid>(&format!(
+        "select id from {} where uploaded_at is null and created_at < $1 {}",
+        table.name(),
+        never_uploaded_filter(table)
+    ))
+    .bind(abandoned_before)
+    .fetch_all(db)
+    .await?;
+
+    Ok(Rows {
+        all,
+        uploaded,
+        never_uploaded,
+    })
+}
+
+/// Rows that are still being processed aren't abandoned, even though they don't have an `uploaded_at` yet.
+const fn never_uploaded_filter(table: Table) -> &'static str {
+    if table.has_processing_status() {
+        // 0 = processing
+        "and processing_status is distinct from 0"
+    } else {
+        ""
+    }
+}
+
+/// Deletes the rows (if they still haven't been uploaded to), returning the ones that were deleted.
+async fn delete_rows(db: &PgPool, table: Table, ids: &[Uuid]) -> anyhow::Result<Vec<Uuid>> {
+    // re-check the condition, the row might've been uploaded to since it was loaded.
+    let deleted = sqlx::query_scalar::<_, Uuid>(&format!(
+        "delete from {} where id = any($1) and uploaded_at is null {} returning id",
+        table.name(),
+        never_uploaded_filter(table)
+    ))
+    .bind(ids)
+    .fetch_all(db)
+    .await?;
+
+    Ok(deleted)
+}
+
+/// Returns which of the ids exist (now).
+async fn existing_rows(db: &PgPool, table: Table, ids: &[Uuid]) -> anyhow::Result<HashSet<Uuid>> {
+    let existing = sqlx::query_scalar::<_, Uuid>(&format!(
+        "select id from {} where id = any($1)",
+        table.name()
+    ))
+    .bind(ids)
+    .fetch_all(db)
+    .await?;
+
+    Ok(existing.into_iter().collect())
+}
+
+/// Where the parts of multipart uploads are stored, by storage backends that don't support them (see the api's `s3` module).
+const UPLOADS_PREFIX: &str = "uploads/";
+
+/// Parses the key of a multipart upload's part (`uploads/{upload_id}/{part}`), returning the upload's id.
+fn parse_part_key(key: &str) -> Option<&str> {
+    let mut parts = key.strip_prefix(UPLOADS_PREFIX)?.splitn(2, '/');
+    let upload_id = parts.next()?;
+    let part: u16 = parts.next()?.parse().ok()?;
+
+    // those backends use uuids for upload ids, and part numbers start at 1.
+    if Uuid::parse_str(upload_id).is_err() || part == 0 {
+        return None;
+    }
+
+    Some(upload_id)
+}
+
+/// Loads every upload session's upload id, along with whether the upload can still be completed.
+async fn load_upload_sessions(db: &PgPool) -> anyhow::Result<HashMap<String, bool>> {
+    let sessions = sqlx::query_as::<_, (String, bool)>(
+        "select upload_id, completed_at is null and expires_at > now() from upload_session",
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(sessions.into_iter().collect())
+}
+
+/// Whether a multipart upload will never be completed.
+///
+/// Uploads are started before their session is created, so without a session they're only abandoned once they're old enough.
+fn upload_abandoned(
+    sessions: &HashMap<String, bool>,
+    upload_id: &str,
+    initiated: Option<DateTime<Utc>>,
+    abandoned_before: DateTime<Utc>,
+) -> bool {
+    match sessions.get(upload_id) {
+        Some(active) => !active,
+        None => initiated.map_or(false, |it| it < abandoned_before),
+    }
+}
+
+async fn abort_uploads(
+    s3: &S3Client,
+    bucket: &str,
+    uploads: Vec<(String, String)>,
+    max_tasks: usize,
+) -> usize {
+    futures::stream::iter(uploads)
+        .map(|(key, upload_id)| async move {
+            log::debug!("abort: {:?} {:?}", key, upload_id);
+
+            let res = s3
+                .abort_multipart_upload(AbortMultipartUploadRequest {
+                    bucket: bucket.to_owned(),
+                    key: key.clone(),
+                    upload_id,
+                    ..AbortMultipartUploadRequest::default()
+                })
+                .await;
+
+            match res {
+                Ok(_) => true,
+                Err(e) => {
+                    log::warn!("failed to abort the upload to {:?}: {:?}", key, e);
+                    false
+                }
+            }
+        })
+        .buffer_unordered(max_tasks)
+        .filter(|aborted| futures::future::ready(*aborted))
+        .count()
+        .await
+}
+
+async fn delete_objects(s3: &S3Client, bucket: &str, keys: Vec<String>, max_tasks: usize) -> usize {
+    futures::stream::iter(keys)
+        .map(|key| async move {
+            log::debug!("delete: {:?}", key);
+
+            let res = s3
+                .delete_object(DeleteObjectRequest {
+                    bucket: bucket.to_owned(),
+                    key: key.clone(),
+                    ..DeleteObjectRequest::default()
+                })
+                .await;
+
+            match res {
+                Ok(_) => true,
+                Err(e) => {
+                    log::warn!("failed to delete {:?}: {:?}", key, e);
+                    false
+                }
+            }
+        })
+        .buffer_unordered(max_tasks)
+        .filter(|deleted| futures::future::ready(*deleted))
+        .count()
+        .await
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let _ = dotenv::dotenv().ok();
+    let opts = Opts::parse();
+
+    simplelog::WriteLogger::init(
+        opts.log_level,
+        Config::default(),
+        File::create(concat!(env!("CARGO_PKG_NAME"), ".log")).expect("failed to open log file"),
+    )
+    .expect("failed to create logger");
+
+    let s3 = s3_list::client(opts.endpoint, opts.access_key_id, opts.access_secret)?;
+
+    let db = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&opts.database_url)
+        .await?;
+
+    let Opts {
+        bucket,
+        delete,
+        min_age_hours,
+        max_tasks,
+        ..
+    } = opts;
+
+    // the bucket has to be listed *before* the rows are loaded, otherwise objects for rows created in between would look orphaned.
+    let listed_at = Utc::now();
+
+    let mut objects: HashMap<(Table, Uuid), Vec<String>> = HashMap::new();
+    let mut orphaned_objects = Vec::new();
+    let mut unrecognized = 0_usize;
+
+    // media that's waiting for moderation, and media that not everyone can see, are stored under their own prefixes.
+    let quarantine_prefix = format!("{}media/", QUARANTINE_PREFIX);
+    let private_prefix = format!("{}media/", PRIVATE_PREFIX);
+
+    for prefix in &[
+        "media/",
+        quarantine_prefix.as_str(),
+        private_prefix.as_str(),
+    ] {
+        let pages = s3_list::list_keys(&s3, &bucket, prefix);
+        futures::pin_mut!(pages);
+
+        while let Some(keys) = pages.try_next().await? {
+            log::info!("listed: {} objects", keys.len());
+
+            for key in keys {
+                let media_key = key
+                    .strip_prefix(QUARANTINE_PREFIX)
+                    .or_else(|| key.strip_prefix(PRIVATE_PREFIX))
+                    .unwrap_or(&key);
+
+                let (library, id, file_kind) = match parse_media_key(media_key) {
+                    Some(it) => it,
+                    None => {
+                        log::warn!("unrecognized key: {:?}", key);
+                        unrecognized += 1;
+                        continue;
+                    }
+                };
+
+                match Table::for_file(library, file_kind) {
+                    Some(table) => objects.entry((table, id)).or_default().push(key),
+                    None => orphaned_objects.push(key),
+                }
+            }
+        }
+    }
+
+    // parts are only uploaded after the session is created, so whichever session they belong to has been created by now.
+    let mut part_objects: Vec<(String, String)> = Vec::new();
+
+    let pages = s3_list::list_keys(&s3, &bucket, UPLOADS_PREFIX);
+    futures::pin_mut!(pages);
+
+    while let Some(keys) = pages.try_next().await? {
+        log::info!("listed: {} parts", keys.len());
+
+        for key in keys {
+            match parse_part_key(&key) {
+                Some(upload_id) => part_objects.push((upload_id.to_owned(), key)),
+                None => {
+                    log::warn!("unrecognized key: {:?}", key);
+                    unrecognized += 1;
+                }
+            }
+        }
+    }
+
+    let mut multipart_uploads = Vec::new();
+
+    let pages = s3_list::list_multipart_uploads(&s3, &bucket);
+    futures::pin_mut!(pages);
+
+    while let Some(uploads) = pages.try_next().await? {
+        log::info!("listed: {} multipart uploads", uploads.len());
+
+        for upload in uploads {
+            if let (Some(key), Some(upload_id)) = (upload.key, upload.upload_id) {
+                let initiated = upload
+                    .initiated
+                    .and_then(|it| DateTime::parse_from_rfc3339(&it).ok())
+                    .map(|it| it.with_timezone(&Utc));
+
+                multipart_uploads.push((key, upload_id, initiated));
+            }
+        }
+    }
+
+    let abandoned_before = Utc::now() - Duration::hours(min_age_hours);
+
+    let sessions = load_upload_sessions(&db).await?;
+
+    let abandoned_parts: Vec<String> = part_objects
+        .into_iter()
+        .filter(|(upload_id, _)| !sessions.get(upload_id).copied().unwrap_or(false))
+        .map(|(_, key)| key)
+        .collect();
+
+    let abandoned_uploads: Vec<(String, String)> = multipart_uploads
+        .into_iter()
+        .filter(|(_, upload_id, initiated)| {
+            upload_abandoned(&sessions, upload_id, *initiated, abandoned_before)
+        })
+        .map(|(key, upload_id, _)| (key, upload_id))
+        .collect();
+
+    let mut orphaned_rows: HashMap<Table, Vec<Uuid>> = HashMap::new();
+    let mut missing_files = Vec::new();
+    let mut rows_to_delete: HashMap<Table, Vec<Uuid>> = HashMap::new();
+    let mut reported_rows = Vec::new();
+
+    for &table in &Table::ALL {
+        let Rows {
+            all,
+            uploaded,
+            never_uploaded,
+        } = load_rows(&db, table, listed_at, abandoned_before).await?;
+
+        missing_files.extend(
+            uploaded
+                .into_iter()
+                .filter(|id| !objects.contains_key(&(table, *id)))
+                .map(|id| (table, id)),
+        );
+
+        if table.deletes_never_uploaded() {
+            rows_to_delete.insert(table, never_uploaded);
+        } else {
+            reported_rows.extend(never_uploaded.into_iter().map(|id| (table, id)));
+        }
+
+        orphaned_rows.insert(
+            table,
+            objects
+                .keys()
+                .filter(|(object_table, id)| *object_table == table && !all.contains(id))
+                .map(|(_, id)| *id)
+                .collect(),
+        );
+    }
+
+    for (table, ids) in &orphaned_rows {
+        for id in ids {
+            for key in &objects[&(*table, *id)] {
+                println!("orphaned object: {}", key);
+            }
+        }
+    }
+
+    for key in &orphaned_objects {
+        println!("orphaned object: {}", key);
+    }
+
+    for (table, ids) in &rows_to_delete {
+        for id in ids {
+            println!("abandoned row: {} {}", table.name(), id);
+        }
+    }
+
+    for (table, id) in &reported_rows {
+        println!("never uploaded (not deleted): {} {}", table.name(), id);
+    }
+
+    for (table, id) in &missing_files {
+        println!("missing file (not deleted): {} {}", table.name(), id);
+    }
+
+    for key in &abandoned_parts {
+        println!("abandoned upload part: {}", key);
+    }
+
+    for (key, upload_id) in &abandoned_uploads {
+        println!("abandoned upload: {} {}", key, upload_id);
+    }
+
+    println!(
+        "{} orphaned objects, {} abandoned rows, {} never uploaded global rows, {} rows missing files, {} abandoned uploads ({} parts), {} unrecognized keys",
+        orphaned_objects.len() + orphaned_rows.values().map(Vec::len).sum::<usize>(),
+        rows_to_delete.values().map(Vec::len).sum::<usize>(),
+        reported_rows.len(),
+        missing_files.len(),
+        abandoned_uploads.len(),
+        abandoned_parts.len(),
+        unrecognized
+    );
+
+    if !delete {
+        println!("nothing was deleted, pass `--delete` to delete orphans");
+        return Ok(());
+    }
+
+    let mut deleted_rows = 0;
+
+    for (table, ids) in &rows_to_delete {
+        if ids.is_empty() {
+            continue;
+        }
+
+        let deleted = delete_rows(&db, *table, ids).await?;
+        deleted_rows += deleted.len();
+
+        // objects of deleted rows go with them.
+        orphaned_rows.entry(*table).or_default().extend(deleted);
+    }
+
+    for (table, ids) in orphaned_rows {
+        if ids.is_empty() {
+            continue;
+        }
+
+        // the row might've been committed after it was loaded (while its file was being uploaded).
+        let existing = existing_rows(&db, table, &ids).await?;
+
+        for id in ids {
+            if existing.contains(&id) {
+                log::info!("skipping {} {}: it exists now", table.name(), id);
+                continue;
+            }
+
+            if let Some(keys) = objects.remove(&(table, id)) {
+                orphaned_objects.extend(keys);
+            }
+        }
+    }
+
+    orphaned_objects.extend(abandoned_parts);
+
+    let to_delete = orphaned_objects.len();
+    let deleted_objects = delete_objects(&s3, &bucket, orphaned_objects, max_tasks).await;
+
+    let to_abort = abandoned_uploads.len();
+    let aborted_uploads = abort_uploads(&s3, &bucket, abandoned_uploads, max_tasks).await;
+
+    println!(
+        "deleted {} rows, {}/{} objects, aborted {}/{} uploads",
+        deleted_rows, deleted_objects, to_delete, aborted_uploads, to_abort
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{Duration, Utc};
+
+    use super::{parse_part_key, upload_abandoned};
+
+    const UPLOAD_ID: &str = "3095d05e-f2c7-11ea-89c3-3b621dd74a1f";
+
+    #[test]
+    fn parses_part_keys() {
+        assert_eq!(
+            parse_part_key(&format!("uploads/{}/1", UPLOAD_ID)),
+            Some(UPLOAD_ID)
+        );
+
+        assert_eq!(
+            parse_part_key(&format!("uploads/{}/12", UPLOAD_ID)),
+            Some(UPLOAD_ID)
+        );
+    }
+
+    #[test]
+    fn rejects_other_keys() {
+        let keys = [
+            "uploads/".to_owned(),
+            format!("uploads/{}", UPLOAD_ID),
+            format!("uploads/{}/0", UPLOAD_ID),
+            format!("uploads/{}/1/extra", UPLOAD_ID),
+            "uploads/not-a-uuid/1".to_owned(),
+            format!("uploads/global/{}/original.png", UPLOAD_ID),
+            format!("media/user/{}/audio.mp3", UPLOAD_ID),
+        ];
+
+        for key in &keys {
+            assert!(
+                parse_part_key(key).is_none(),
+                "{:?} should be rejected",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn abandoned_uploads() {
+        let now = Utc::now();
+        let abandoned_before = now - Duration::hours(24);
+        let old = Some(now - Duration::hours(48));
+
+        let sessions: HashMap<_, _> = vec![("active".to_owned(), true), ("done".to_owned(), false)]
+            .into_iter()
+            .collect();
+
+        assert!(!upload_abandoned(
+            &sessions,
+            "active",
+            old,
+            abandoned_before
+        ));
+        assert!(upload_abandoned(
+            &sessions,
+            "done",
+            Some(now),
+            abandoned_before
+        ));
+
+        // the session might not have been created yet.
+        assert!(!upload_abandoned(
+            &sessions,
+            "new",
+            Some(now),
+            abandoned_before
+        ));
+        assert!(!upload_abandoned(&sessions, "new", None, abandoned_before));
+        assert!(upload_abandoned(&sessions, "new", old, abandoned_before));
+    }
+}
@@ -0,0 +1,189 @@
+#![warn(
+    clippy::pedantic,
+    clippy::multiple_crate_versions,
+    clippy::cognitive_complexity,
+    clippy::future_not_send,
+    clippy::missing_const_for_fn,
+    clippy::needless_borrow,
+    clippy::redundant_pub_crate,
+    clippy::string_lit_as_bytes,
+    clippy::use_self,
+    clippy::useless_let_if_seq,
+    rust_2018_idioms,
+    future_incompatible
+)]
+
+//! Moves media that not everyone can see (user library media, and premium global media) from its public
+//! `media/` key (which the CDN serves) to its private key (see `shared::media::PRIVATE_PREFIX`).
+//!
+//! The api stores new media at the right key, and moves global images when they're made premium (or not),
+//! so this only needs to be run once, after the api that knows about private media has been deployed.
+//! Quarantined media is left alone, it's never been public.
+
+use std::{collections::HashSet, fs::File};
+
+use clap::Clap;
+use futures::{StreamExt, TryStreamExt};
+use rusoto_s3::{CopyObjectRequest, DeleteObjectRequest, S3Client, S3};
+use shared::media::{parse_media_key, private_key, FileKind, MediaLibrary};
+use simplelog::Config;
+use sqlx::postgres::PgPoolOptions;
+use uuid::Uuid;
+
+#[derive(Clap)]
+#[clap(rename_all = "kebab-case")]
+struct Opts {
+    /// The endpoint to access S3 from
+    #[clap(long, env = "S3_ENDPOINT")]
+    endpoint: String,
+
+    /// The s3 bucket to use
+    #[clap(long, env = "S3_BUCKET")]
+    bucket: String,
+
+    #[clap(long, env = "S3_ACCESS_KEY", hide_env_values = true)]
+    access_key_id: String,
+
+    #[clap(long, env = "S3_ACCESS_SECRET", hide_env_values = true)]
+    access_secret: String,
+
+    /// The database to look up premium media in
+    #[clap(long, env = "DATABASE_URL", hide_env_values = true)]
+    database_url: String,
+
+    /// Just list through the objects, reporting the ones that would be moved, but not moving them
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Controls the maximum amount of objects that will be moved at once
+    #[clap(long, default_value = "50")]
+    max_tasks: usize,
+
+    /// The minimum log level to use.
+    #[clap(
+        long,
+        default_value = "WARN",
+        parse(try_from_str),
+        possible_values(&["OFF", "ERROR", "WARN", "INFO", "DEBUG", "TRACE"]), case_insensitive = true
+    )]
+    log_level: simplelog::LevelFilter,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let _ = dotenv::dotenv().ok();
+    let opts = Opts::parse();
+
+    simplelog::WriteLogger::init(
+        opts.log_level,
+        Config::default(),
+        File::create(concat!(env!("CARGO_PKG_NAME"), ".log")).expect("failed to open log file"),
+    )
+    .expect("failed to create logger");
+
+    let s3 = s3_list::client(opts.endpoint, opts.access_key_id, opts.access_secret)?;
+
+    let db = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&opts.database_url)
+        .await?;
+
+    let Opts {
+        bucket,
+        dry_run,
+        max_tasks,
+        ..
+    } = opts;
+
+    // images and animations share the global library (and their ids never collide).
+    let premium: HashSet<Uuid> = sqlx::query_scalar::<_, Uuid>(
+        "select id from image_metadata where is_premium union select id from animation where is_premium",
+    )
+    .fetch_all(&db)
+    .await?
+    .into_iter()
+    .collect();
+
+    log::info!("premium global media: {}", premium.len());
+
+    let mut total = 0_usize;
+    let mut moved = 0_usize;
+
+    let pages = s3_list::list_keys(&s3, &bucket, "media/");
+    futures::pin_mut!(pages);
+
+    while let Some(keys) = pages.try_next().await? {
+        log::info!("listed: {} objects", keys.len());
+
+        total += keys.len();
+
+        let to_move: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let (library, id, file_kind) = parse_media_key(&key)?;
+
+                let is_private = match library {
+                    MediaLibrary::User => true,
+                    MediaLibrary::Global => premium.contains(&id),
+                    MediaLibrary::Web => false,
+                };
+
+                is_private.then(|| (key, private_key(library, id, file_kind), file_kind))
+            })
+            .collect();
+
+        moved += to_move.len();
+
+        futures::stream::iter(to_move)
+            .for_each_concurrent(max_tasks, |(old_key, new_key, file_kind)| {
+                let s3 = &s3;
+                let bucket = &bucket;
+                async move {
+                    if let Err(e) =
+                        move_object(s3, bucket, old_key, new_key, file_kind, dry_run).await
+                    {
+                        log::warn!("error moving object: {:?}", e);
+                    }
+                }
+            })
+            .await;
+    }
+
+    log::info!("finished: moved {} out of {} objects", moved, total);
+
+    Ok(())
+}
+
+async fn move_object(
+    s3: &S3Client,
+    bucket: &str,
+    old_key: String,
+    new_key: String,
+    file_kind: FileKind,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    log::debug!("move {:?} -> {:?}", old_key, new_key);
+
+    if dry_run {
+        return Ok(());
+    }
+
+    s3.copy_object(CopyObjectRequest {
+        bucket: bucket.to_owned(),
+        copy_source: format!("{}/{}", bucket, old_key),
+        content_type: Some(file_kind.content_type().to_owned()),
+        metadata_directive: Some("REPLACE".to_owned()),
+        key: new_key,
+        ..CopyObjectRequest::default()
+    })
+    .await?;
+
+    s3.delete_object(DeleteObjectRequest {
+        bucket: bucket.to_owned(),
+        key: old_key,
+        ..DeleteObjectRequest::default()
+    })
+    .await?;
+
+    Ok(())
+}
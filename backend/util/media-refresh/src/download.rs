@@ -2,43 +2,66 @@ use std::{fs::File, io::BufWriter, path::PathBuf};
 
 use indicatif::ProgressBar;
 use shared::{
+    domain::admin::{AdminListMediaQuery, AdminListMediaResponse},
     error::{ApiError, EmptyError},
-    media::MediaKind,
+    media::MediaKindFilter,
 };
 
 pub async fn run(
     output_file: PathBuf,
     endpoint: String,
     token: String,
-    csrf: String,
+    csrf: Option<String>,
     show_progress: bool,
 ) -> anyhow::Result<()> {
-    let client = crate::create_http_client(&token, &csrf)?;
-
-    let response = client
-        .get(&format!("{}/v0/admin/media", endpoint))
-        .send()
-        .await?;
-
-    match response.error_for_status_ref() {
-        Ok(_) => {}
-        Err(_) => {
-            let error_json = response.json::<ApiError<EmptyError>>().await?;
-
-            anyhow::bail!(
-                "request failed ({}): {}",
-                error_json.code,
-                error_json.message
-            )
+    let client = crate::create_http_client(&token, csrf.as_deref())?;
+
+    let mut data = AdminListMediaResponse {
+        media: Vec::new(),
+        next_cursor: None,
+    };
+
+    let mut cursor = None;
+
+    // Note: This only supports filtering for images
+    loop {
+        let query = AdminListMediaQuery {
+            kind: Some(MediaKindFilter::Image),
+            limit: Some(1000),
+            cursor,
+            ..AdminListMediaQuery::default()
+        };
+
+        let response = client
+            .get(&format!("{}/v0/admin/media", endpoint))
+            .query(&query)
+            .send()
+            .await?;
+
+        match response.error_for_status_ref() {
+            Ok(_) => {}
+            Err(_) => {
+                let error_json = response.json::<ApiError<EmptyError>>().await?;
+
+                anyhow::bail!(
+                    "request failed ({}): {}",
+                    error_json.code,
+                    error_json.message
+                )
+            }
         }
-    }
 
-    let mut data = response
-        .json::<shared::domain::admin::AdminListMediaResponse>()
-        .await?;
+        let page = response.json::<AdminListMediaResponse>().await?;
+
+        log::info!("downloaded {} items", page.media.len());
 
-    data.media
-        .retain(|it| matches!(it.kind, MediaKind::Image(_)));
+        data.media.extend(page.media);
+
+        cursor = match page.next_cursor {
+            Some(cursor) => Some(cursor),
+            None => break,
+        };
+    }
 
     tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
         let writer = File::create(&output_file)?;
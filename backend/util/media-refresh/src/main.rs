@@ -29,14 +29,14 @@ struct Opts {
     #[clap(long, env = "API_ENDPOINT")]
     endpoint: String,
 
-    /// An admin API token
+    /// An admin API token, either a personal access token or a signin cookie
     #[clap(long, env = "API_TOKEN", hide_env_values = true)]
     token: String,
 
     // todo: just decode this from the above.
-    /// CSRF key for the token
+    /// CSRF key for the token (only needed if the token is a signin cookie)
     #[clap(long, env = "API_CSRF", hide_env_values = true)]
-    csrf: String,
+    csrf: Option<String>,
 
     /// Minimum log level to use.
     #[clap(
@@ -126,17 +126,26 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
-fn create_http_client(token: &str, csrf: &str) -> anyhow::Result<reqwest::Client> {
+fn create_http_client(token: &str, csrf: Option<&str>) -> anyhow::Result<reqwest::Client> {
     let mut default_headers = HeaderMap::new();
-    let mut csrf = HeaderValue::from_str(csrf)?;
-    csrf.set_sensitive(true);
 
-    default_headers.append("X-CSRF", csrf);
+    if let Some(csrf) = csrf {
+        let mut csrf = HeaderValue::from_str(csrf)?;
+        csrf.set_sensitive(true);
 
-    let mut cookie = HeaderValue::from_str(&format!("X-JWT={}", token))?;
-    cookie.set_sensitive(true);
+        default_headers.append("X-CSRF", csrf);
 
-    default_headers.append(header::COOKIE, cookie);
+        let mut cookie = HeaderValue::from_str(&format!("X-JWT={}", token))?;
+        cookie.set_sensitive(true);
+
+        default_headers.append(header::COOKIE, cookie);
+    } else {
+        // personal access tokens don't need a csrf header.
+        let mut authorization = HeaderValue::from_str(&format!("Bearer {}", token))?;
+        authorization.set_sensitive(true);
+
+        default_headers.append(header::AUTHORIZATION, authorization);
+    }
 
     let client = reqwest::Client::builder()
         .default_headers(default_headers)
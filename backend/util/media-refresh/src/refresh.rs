@@ -75,7 +75,7 @@ pub async fn run(
     max_tasks: usize,
     endpoint: String,
     token: String,
-    csrf: String,
+    csrf: Option<String>,
     show_progress: bool,
 ) -> anyhow::Result<()> {
     let endpoint = Arc::new(Url::parse(&endpoint)?);
@@ -122,7 +122,7 @@ pub async fn run(
         ProgressStyle::default_bar().template("[{elapsed}] {wide_bar} {pos}/{len} {msg}"),
     );
 
-    let client = crate::create_http_client(&token, &csrf)?;
+    let client = crate::create_http_client(&token, csrf.as_deref())?;
     let mut tasks = FuturesUnordered::new();
 
     while let Some(item) = data.pop() {
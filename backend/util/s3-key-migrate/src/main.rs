@@ -15,15 +15,9 @@
 
 use std::{fs::File, time::Instant};
 
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
-use rusoto_core::{
-    credential::{AwsCredentials, StaticProvider},
-    HttpClient, Region,
-};
-use rusoto_s3::{
-    CopyObjectRequest, DeleteObjectRequest, ListObjectsOutput, ListObjectsRequest, S3Client, S3,
-};
+use rusoto_s3::{CopyObjectRequest, DeleteObjectRequest, S3Client, S3};
 use shared::media::{FileKind, MediaLibrary, PngImageFile};
 use simplelog::Config;
 use uuid::Uuid;
@@ -81,16 +75,7 @@ async fn main() -> anyhow::Result<()> {
     )
     .expect("failed to create logger");
 
-    let region = Region::Custom {
-        name: "auto".to_owned(),
-        endpoint: opts.endpoint,
-    };
-
-    let creds = AwsCredentials::new(opts.access_key_id, opts.access_secret, None, None);
-
-    let credentials_provider = StaticProvider::from(creds);
-
-    let s3 = rusoto_s3::S3Client::new_with(HttpClient::new()?, credentials_provider, region);
+    let s3 = s3_list::client(opts.endpoint, opts.access_key_id, opts.access_secret)?;
 
     let Opts {
         bucket,
@@ -100,30 +85,19 @@ async fn main() -> anyhow::Result<()> {
         ..
     } = opts;
 
-    let mut last_key = None;
-
     let mut total = 0;
 
     let mut list_ops: u64 = 0;
 
-    loop {
+    let pages = s3_list::list_keys(&s3, &bucket, "image");
+    futures::pin_mut!(pages);
+
+    while let Some(keys) = pages.try_next().await? {
         let start = Instant::now();
 
         list_ops += 1;
 
-        let resp: ListObjectsOutput = s3
-            .list_objects(ListObjectsRequest {
-                bucket: bucket.clone(),
-                prefix: Some("image".to_owned()),
-                marker: last_key,
-                ..ListObjectsRequest::default()
-            })
-            .await?;
-
-        let after_list_objects = Instant::now();
-        log::trace!(target: "s3_key_migrate::timing", "s3 `list_objects` took: {:?}", after_list_objects - start);
-
-        let objects = resp.contents.as_ref().map_or(0, Vec::len);
+        let objects = keys.len();
 
         let progress = if show_progress {
             let progress = ProgressBar::new(objects as u64);
@@ -138,16 +112,7 @@ async fn main() -> anyhow::Result<()> {
 
         log::info!("processing: {} objects", objects);
 
-        last_key = None;
-
-        let iter = resp
-            .contents
-            .into_iter()
-            .flatten()
-            .filter_map(|obj| obj.key)
-            .inspect(|key| last_key = Some(key.clone()));
-
-        futures::stream::iter(iter)
+        futures::stream::iter(keys)
             .for_each_concurrent(max_tasks, |key| {
                 let s3 = s3.clone();
                 let bucket = bucket.clone();
@@ -164,14 +129,9 @@ async fn main() -> anyhow::Result<()> {
 
         progress.as_ref().map(ProgressBar::finish);
 
-        log::trace!(target: "s3_key_migrate::timing", "process objects took {:?}", after_list_objects.elapsed());
+        log::trace!(target: "s3_key_migrate::timing", "process objects took {:?}", start.elapsed());
 
         total += objects;
-
-        // Not truuncated = no more items.
-        if !resp.is_truncated.unwrap_or(false) {
-            break;
-        }
     }
 
     log::info!("finished processing: {} objects", total);
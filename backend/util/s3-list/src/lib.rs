@@ -0,0 +1,134 @@
+#![warn(
+    clippy::pedantic,
+    clippy::multiple_crate_versions,
+    clippy::cognitive_complexity,
+    clippy::future_not_send,
+    clippy::missing_const_for_fn,
+    clippy::needless_borrow,
+    clippy::redundant_pub_crate,
+    clippy::string_lit_as_bytes,
+    clippy::use_self,
+    clippy::useless_let_if_seq,
+    rust_2018_idioms,
+    future_incompatible
+)]
+
+//! Bucket listing shared by the s3 utilities.
+
+use std::time::Instant;
+
+use futures::Stream;
+use rusoto_core::{
+    credential::{AwsCredentials, StaticProvider},
+    HttpClient, Region,
+};
+use rusoto_s3::{
+    ListMultipartUploadsOutput, ListMultipartUploadsRequest, ListObjectsOutput, ListObjectsRequest,
+    MultipartUpload, S3Client, S3,
+};
+
+/// Creates a client for the s3 compatible api at `endpoint`.
+pub fn client(
+    endpoint: String,
+    access_key_id: String,
+    access_secret: String,
+) -> anyhow::Result<S3Client> {
+    let region = Region::Custom {
+        name: "auto".to_owned(),
+        endpoint,
+    };
+
+    let creds = AwsCredentials::new(access_key_id, access_secret, None, None);
+
+    let credentials_provider = StaticProvider::from(creds);
+
+    Ok(S3Client::new_with(
+        HttpClient::new()?,
+        credentials_provider,
+        region,
+    ))
+}
+
+/// Lists the keys in `bucket` that start with `prefix`, a page (of up to 1000 keys) at a time.
+pub fn list_keys<'a>(
+    s3: &'a S3Client,
+    bucket: &'a str,
+    prefix: &'a str,
+) -> impl Stream<Item = anyhow::Result<Vec<String>>> + 'a {
+    // `None` means that there are no more pages, `Some(None)` is the first page.
+    futures::stream::try_unfold(
+        Some(None),
+        move |marker: Option<Option<String>>| async move {
+            let marker = match marker {
+                Some(marker) => marker,
+                None => return Ok(None),
+            };
+
+            let start = Instant::now();
+
+            let resp: ListObjectsOutput = s3
+                .list_objects(ListObjectsRequest {
+                    bucket: bucket.to_owned(),
+                    prefix: Some(prefix.to_owned()),
+                    marker,
+                    ..ListObjectsRequest::default()
+                })
+                .await?;
+
+            log::trace!(target: "s3_list::timing", "s3 `list_objects` took: {:?}", start.elapsed());
+
+            let keys: Vec<String> = resp
+                .contents
+                .into_iter()
+                .flatten()
+                .filter_map(|obj| obj.key)
+                .collect();
+
+            // Not truncated = no more items.
+            let next = match (resp.is_truncated.unwrap_or(false), keys.last()) {
+                (true, Some(last)) => Some(Some(last.clone())),
+                _ => None,
+            };
+
+            Ok(Some((keys, next)))
+        },
+    )
+}
+
+/// Lists the multipart uploads in `bucket` that haven't been completed or aborted, a page (of up to 1000 uploads) at a time.
+pub fn list_multipart_uploads<'a>(
+    s3: &'a S3Client,
+    bucket: &'a str,
+) -> impl Stream<Item = anyhow::Result<Vec<MultipartUpload>>> + 'a {
+    // `None` means that there are no more pages, `Some((None, None))` is the first page.
+    futures::stream::try_unfold(
+        Some((None, None)),
+        move |markers: Option<(Option<String>, Option<String>)>| async move {
+            let (key_marker, upload_id_marker) = match markers {
+                Some(markers) => markers,
+                None => return Ok(None),
+            };
+
+            let start = Instant::now();
+
+            let resp: ListMultipartUploadsOutput = s3
+                .list_multipart_uploads(ListMultipartUploadsRequest {
+                    bucket: bucket.to_owned(),
+                    key_marker,
+                    upload_id_marker,
+                    ..ListMultipartUploadsRequest::default()
+                })
+                .await?;
+
+            log::trace!(target: "s3_list::timing", "s3 `list_multipart_uploads` took: {:?}", start.elapsed());
+
+            // Not truncated = no more items.
+            let next = match (resp.is_truncated.unwrap_or(false), resp.next_key_marker) {
+                (true, Some(key_marker)) => Some((Some(key_marker), resp.next_upload_id_marker)),
+                _ => None,
+            };
+
+            Ok(Some((resp.uploads.unwrap_or_default(), next)))
+        },
+    )
+}
@@ -22,19 +22,29 @@ use tokio::time::{delay_for, Duration};
 
 pub struct Credentials {
     pub token:String,
-    pub csrf:String
+    //if this is missing, the token is a personal access token rather than a cookie
+    pub csrf:Option<String>
 }
 
 impl Credentials {
     pub fn new() -> Self {
         let token = std::env::var("UTILS_TOKEN").expect("Need UTILS_TOKEN in .env");
-        let csrf = std::env::var("UTILS_CSRF").expect("Need UTILS_CSRF in .env");
+        let csrf = std::env::var("UTILS_CSRF").ok();
 
         Self {
             token,
             csrf
         }
     }
+
+    pub fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.csrf {
+            Some(csrf) => request
+                .header("X-CSRF", csrf)
+                .header("Cookie", &format!("X-JWT={}", self.token)),
+            None => request.bearer_auth(&self.token)
+        }
+    }
 }
 
 #[tokio::main]
@@ -200,10 +210,7 @@ async fn upload_image(opts:Arc<Opts>, credentials:Arc<Credentials>, album_name:A
         kind
     };
 
-    let request = reqwest::Client::new()
-        .post(&url)
-        .header("X-CSRF", &credentials.csrf)
-        .header("Cookie", &format!("X-JWT={}", credentials.token))
+    let request = credentials.authorize(reqwest::Client::new().post(&url))
         .json(&req_data);
    
 
@@ -236,10 +243,7 @@ async fn upload_image(opts:Arc<Opts>, credentials:Arc<Credentials>, album_name:A
     let stream = FramedRead::new(file, BytesCodec::new());
     let body = Body::wrap_stream(stream);
 
-    let request = reqwest::Client::new()
-        .patch(&upload_url)
-        .header("X-CSRF", &credentials.csrf)
-        .header("Cookie", &format!("X-JWT={}", credentials.token))
+    let request = credentials.authorize(reqwest::Client::new().patch(&upload_url))
         .header("Content-Type", content_type)
         .header("Content-Length", file_size) 
         .body(body);
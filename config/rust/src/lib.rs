@@ -32,6 +32,12 @@ pub const IMAGE_BODY_SIZE_LIMIT: usize = 1024 * 1024 * 10; // 10 MB
 // Animations are reasonably expected to be larger than normal images?
 pub const ANIMATION_BODY_SIZE_LIMIT: usize = 1024 * 1024 * 15; // 15 MB
 
+// Audio is only uploaded directly to storage, so it isn't limited by the api's memory.
+pub const AUDIO_UPLOAD_SIZE_LIMIT: usize = 1024 * 1024 * 100; // 100 MB
+
+// Size of each part of a direct upload, s3 needs every part but the last to be at least 5 MB.
+pub const UPLOAD_PART_SIZE: usize = 1024 * 1024 * 8; // 8 MB
+
 pub const REMOTE_DB_USER: &str = "postgres";
 pub const REMOTE_DB_NAME: &str = "jicloud";
 pub const SQL_PROXY_PORT: u16 = 6432; //must match the port number in build-utils/package.json where cloud-sql-proxy is launched
@@ -1,7 +1,7 @@
 use wasm_bindgen::prelude::*;
 use shared::{
     domain::image::{SearchQuery, SearchResponse, ImageId, GetResponse, Image},
-    media::{image_id_to_key, MediaLibraryKind, MediaVariant},
+    media::{image_id_to_key, MediaLibraryKind, MediaVariant, ProcessingStatus},
 };
 use utils::math::RectF64;
 use serde::{Serialize, Deserialize};
@@ -51,8 +51,13 @@ impl MetaImage {
             age_ranges: Vec::new(),
             affiliations: Vec::new(),
             categories: Vec::new(),
+            alt_text: Vec::new(),
+            attribution: None,
+            license: None,
+            source_url: None,
             created_at: chrono::Utc::now(),
             updated_at: None,
+            processing_status: Some(ProcessingStatus::Ready),
         };
 
         Self {
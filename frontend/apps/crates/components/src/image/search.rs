@@ -1,7 +1,8 @@
 use shared::{
-    api::endpoints::{ApiEndpoint, image::*},
+    api::endpoints::{ApiEndpoint, image::*, search::Suggest},
     domain::image::{SearchQuery, SearchResponse, ImageId, GetResponse},
-    error::image::*,
+    domain::search::{SearchSuggestQuery, SearchSuggestResponse},
+    error::{image::*, EmptyError},
     media::{image_id_to_key, MediaLibraryKind, MediaVariant},
 };
 use utils::{
@@ -188,3 +189,10 @@ pub async fn search_images(query: SearchQuery) -> Result<(Vec<MetaImage>, PageCo
             (images, pages, total_image_count)
         })
 }
+
+/// Suggestions for a partially typed query (for typeahead), see `search::Suggest`.
+pub async fn suggest(q: String) -> Result<SearchSuggestResponse, ()> {
+    let query = SearchSuggestQuery { q, lang: None, limit: None };
+    api_with_auth::<SearchSuggestResponse, EmptyError, _>(Suggest::PATH, Suggest::METHOD, Some(query)).await
+        .map_err(|_:EmptyError| ())
+}
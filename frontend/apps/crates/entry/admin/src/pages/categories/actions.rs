@@ -84,7 +84,9 @@ fn uuid_from_str(id:&str) -> Uuid {
 pub async fn load_categories() -> Result < <Get as ApiEndpoint>::Res, <Get as ApiEndpoint>::Err> {
     let req:<Get as ApiEndpoint>::Req = GetCategoryRequest {
         ids: Vec::new(), 
-        scope: Some(CategoryTreeScope::Decendants)
+        scope: Some(CategoryTreeScope::Decendants),
+        lang: None,
+        published_only: false,
     };
     
     api_with_auth(Get::PATH, Get::METHOD, Some(req)).await
@@ -1,7 +1,7 @@
 use super::settings::SETTINGS;
 use config::MEDIA_UI_PATH;
 use shared::{
-    media::{media_key,MediaLibrary, FileKind, PngImageFile},
+    media::{media_file_path, MediaLibrary, FileKind, PngImageFile},
     domain::image::ImageId
 };
 use wasm_bindgen::prelude::*;
@@ -10,15 +10,21 @@ pub fn ui<T: AsRef<str>>(path:T) -> String {
     media_url(&format!("{}/{}", MEDIA_UI_PATH, path.as_ref()))
 }
 
+// goes through the api, which checks access and redirects to a signed url
+// needed for premium and user library images, which aren't public
 pub fn library_image_id(library_kind: MediaLibrary, img_kind: PngImageFile, id:ImageId) -> String {
-    let path = media_key(library_kind, id.0, FileKind::ImagePng(img_kind));
+    let path = media_file_path(library_kind, id.0, FileKind::ImagePng(img_kind));
 
-    uploads_url(&path)
+    api_url(&path)
 }
 pub fn library_image_str(library_kind: MediaLibrary, img_kind: PngImageFile, id:&str) -> String {
     let id = uuid::Uuid::parse_str(id).unwrap_throw();
-    let path = media_key(library_kind, id, FileKind::ImagePng(img_kind));
-    uploads_url(&path)
+    let path = media_file_path(library_kind, id, FileKind::ImagePng(img_kind));
+    api_url(&path)
+}
+
+pub fn api_url(path:&str) -> String {
+    format!("{}{}", SETTINGS.get().unwrap().remote_target.api_url(), path)
 }
 
 pub fn uploads_url(path:&str) -> String {
@@ -54,3 +54,15 @@ pub mod audio;
 
 /// Web Media library endpoints
 pub mod media;
+
+/// Organization endpoints.
+pub mod organization;
+
+/// Classroom endpoints.
+pub mod classroom;
+
+/// Play session and progress report endpoints.
+pub mod session;
+
+/// Direct-to-storage upload endpoints.
+pub mod upload;
@@ -1,7 +1,15 @@
 use super::ApiEndpoint;
 use crate::{
     api::Method,
-    domain::{admin::AdminListMediaResponse, auth::SigninSuccess},
+    domain::{
+        admin::{
+            AdminBulkTagImagesRequest, AdminListMediaQuery, AdminListMediaResponse,
+            AdminMergeCategoryRequest, AdminMergeCategoryResponse, AdminModerationListQuery,
+            AdminModerationListResponse, AdminModerationRejectRequest, AdminReindexResponse,
+            AdminSearchSyncResponse, AdminSimilarImagesQuery, AdminSimilarImagesResponse,
+        },
+        auth::SigninSuccess,
+    },
     error::EmptyError,
 };
 
@@ -27,14 +35,120 @@ impl ApiEndpoint for RefreshFiles {
     const METHOD: Method = Method::Post;
 }
 
-/// List all media
-/// Note that this media is *not* sorted in any particular way.
+/// List media, newest first.
+///
+/// Results are paginated, keep passing the response's `next_cursor` back (with the same filters) to walk through all of the matching media.
 /// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
 pub struct ListMedia;
 impl ApiEndpoint for ListMedia {
-    type Req = ();
+    type Req = AdminListMediaQuery;
     type Res = AdminListMediaResponse;
     type Err = EmptyError;
     const PATH: &'static str = "/v0/admin/media";
     const METHOD: Method = Method::Get;
 }
+
+/// Find images that look like the given image (for example, the same picture resized or re-encoded).
+///
+/// Images are compared by perceptual hash, which is computed when the image is processed,
+/// so this responds with `404 Not Found` for images that haven't been processed yet.
+/// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
+pub struct SimilarImages;
+impl ApiEndpoint for SimilarImages {
+    type Req = AdminSimilarImagesQuery;
+    type Res = AdminSimilarImagesResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v0/admin/media/{library}/image/{id}/similar";
+    const METHOD: Method = Method::Get;
+}
+
+/// List media that has been through moderation, newest first.
+///
+/// Results are paginated, keep passing the response's `next_cursor` back (with the same filters) to walk through all of the matching media.
+/// Quarantined media can be viewed with [`GetFile`](super::media::GetFile), which admins always have access to.
+/// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
+pub struct ListModeration;
+impl ApiEndpoint for ListModeration {
+    type Req = AdminModerationListQuery;
+    type Res = AdminModerationListResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v0/admin/moderation";
+    const METHOD: Method = Method::Get;
+}
+
+/// Approve media, taking it out of quarantine.
+/// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
+pub struct ApproveMedia;
+impl ApiEndpoint for ApproveMedia {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v0/admin/moderation/{library}/{id}/approve";
+    const METHOD: Method = Method::Post;
+}
+
+/// Reject media, keeping it hidden.
+/// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
+pub struct RejectMedia;
+impl ApiEndpoint for RejectMedia {
+    type Req = AdminModerationRejectRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v0/admin/moderation/{library}/{id}/reject";
+    const METHOD: Method = Method::Post;
+}
+
+/// Get how far behind the search index is, and which changes to it have failed.
+/// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
+pub struct SearchSyncStatus;
+impl ApiEndpoint for SearchSyncStatus {
+    type Req = ();
+    type Res = AdminSearchSyncResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v0/admin/search/sync";
+    const METHOD: Method = Method::Get;
+}
+
+/// Queue every searchable object to be reindexed, this also retries any failed changes.
+///
+/// Objects are reindexed in the background, use [`SearchSyncStatus`] to follow along.
+/// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
+pub struct Reindex;
+impl ApiEndpoint for Reindex {
+    type Req = ();
+    type Res = AdminReindexResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v0/admin/search/reindex";
+    const METHOD: Method = Method::Post;
+}
+
+/// Merge a category into another one.
+///
+/// Every image and JIG in the category is moved to the target category, the category's children are moved to the end of the target's children,
+/// then the category (and its translations) are deleted.
+///
+/// Requires the `ManageCategory` scope.
+/// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
+pub struct MergeCategory;
+impl ApiEndpoint for MergeCategory {
+    type Req = AdminMergeCategoryRequest;
+    type Res = AdminMergeCategoryResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v0/admin/category/{id}/merge";
+    const METHOD: Method = Method::Post;
+}
+
+/// Add and remove categories, styles and age ranges on many images at once.
+///
+/// Either every image is changed or (if any of them don't exist) none are.
+///
+/// Requires the `ManageImage` scope.
+/// NOTE: This route is super unstable (v0), and may change at any time, for any reason, in any way, including removal.
+pub struct BulkTagImages;
+impl ApiEndpoint for BulkTagImages {
+    type Req = AdminBulkTagImagesRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v0/admin/image/bulk-tag";
+    const METHOD: Method = Method::Post;
+}
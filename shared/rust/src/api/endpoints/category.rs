@@ -1,14 +1,19 @@
 use super::ApiEndpoint;
 use crate::{
     api::method::Method,
-    domain::category::{
-        CategoryResponse, CreateCategoryRequest, GetCategoryRequest, NewCategoryResponse,
-        UpdateCategoryRequest,
+    domain::{
+        category::{
+            CategoryResponse, CreateCategoryRequest, GetCategoryRequest, NewCategoryResponse,
+            UpdateCategoryRequest,
+        },
+        meta::SetTranslationsRequest,
     },
     error::EmptyError,
 };
 
 /// Get a tree of categories.
+///
+/// Names are translated to the requested language when there's a translation for it.
 pub struct Get;
 impl ApiEndpoint for Get {
     type Req = GetCategoryRequest;
@@ -47,3 +52,13 @@ impl ApiEndpoint for Delete {
     const PATH: &'static str = "/v1/category/{id}";
     const METHOD: Method = Method::Delete;
 }
+
+/// Replace the translations of a category's name.
+pub struct SetTranslations;
+impl ApiEndpoint for SetTranslations {
+    type Req = SetTranslationsRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/category/{id}/translations";
+    const METHOD: Method = Method::Put;
+}
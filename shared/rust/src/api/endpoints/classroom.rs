@@ -0,0 +1,82 @@
+use crate::{
+    api::Method,
+    domain::{
+        classroom::{AssignedJigListResponse, Classroom, ClassroomCreateRequest, ClassroomId},
+        CreateResponse,
+    },
+    error::EmptyError,
+};
+
+use super::ApiEndpoint;
+
+/// Create a classroom within an organization.
+///
+/// Requires the user to be a teacher (or admin) of the organization.
+pub struct Create;
+impl ApiEndpoint for Create {
+    type Req = ClassroomCreateRequest;
+    type Res = CreateResponse<ClassroomId>;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/organization/{id}/classroom";
+    const METHOD: Method = Method::Post;
+}
+
+/// Get a classroom by ID.
+pub struct Get;
+impl ApiEndpoint for Get {
+    type Req = ();
+    type Res = Classroom;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}";
+    const METHOD: Method = Method::Get;
+}
+
+/// Add a student (who must be a member of the classroom's organization) to a classroom.
+pub struct AddStudent;
+impl ApiEndpoint for AddStudent {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}/student/{user_id}";
+    const METHOD: Method = Method::Put;
+}
+
+/// Remove a student from a classroom.
+pub struct RemoveStudent;
+impl ApiEndpoint for RemoveStudent {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}/student/{user_id}";
+    const METHOD: Method = Method::Delete;
+}
+
+/// Assign a JIG to a classroom.
+pub struct AssignJig;
+impl ApiEndpoint for AssignJig {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}/jig/{jig_id}";
+    const METHOD: Method = Method::Put;
+}
+
+/// Unassign a JIG from a classroom.
+pub struct UnassignJig;
+impl ApiEndpoint for UnassignJig {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}/jig/{jig_id}";
+    const METHOD: Method = Method::Delete;
+}
+
+/// List the JIGs assigned to a classroom.
+pub struct ListJigs;
+impl ApiEndpoint for ListJigs {
+    type Req = ();
+    type Res = AssignedJigListResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}/jig";
+    const METHOD: Method = Method::Get;
+}
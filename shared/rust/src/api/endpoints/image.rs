@@ -3,7 +3,7 @@ use crate::{
     api::Method,
     domain::image::{
         CreateResponse, ImageCreateRequest, ImageResponse, ImageSearchQuery, ImageSearchResponse,
-        ImageUpdateRequest,
+        ImageUpdateRequest, ImageUploadResponse,
     },
     error::{EmptyError, MetadataNotFound},
 };
@@ -75,6 +75,55 @@ pub mod user {
     }
 }
 
+/// Bulk importing of images into the global image library.
+pub mod import {
+    use crate::{
+        api::{ApiEndpoint, Method},
+        domain::image::import::{
+            ImageImportCreateResponse, ImageImportRequest, ImageImportResponse,
+        },
+        error::{EmptyError, MetadataNotFound},
+    };
+
+    /// Start importing images from a manifest.
+    ///
+    /// Items with a `source_url` are downloaded in the background,
+    /// the others wait for their file to be uploaded with [`Upload`].
+    /// Use [`Get`] to follow the import's progress.
+    pub struct Create;
+    impl ApiEndpoint for Create {
+        type Req = ImageImportRequest;
+        type Res = ImageImportCreateResponse;
+        type Err = MetadataNotFound;
+        const PATH: &'static str = "/v1/image/import";
+        const METHOD: Method = Method::Post;
+    }
+
+    /// Get the progress of an import, including each item's status and created image.
+    pub struct Get;
+    impl ApiEndpoint for Get {
+        type Req = ();
+        type Res = ImageImportResponse;
+        type Err = EmptyError;
+        const PATH: &'static str = "/v1/image/import/{id}";
+        const METHOD: Method = Method::Get;
+    }
+
+    /// Upload the file for an item (by its index in the manifest) that doesn't have a `source_url`.
+    ///
+    /// The body is either the file itself, or a `multipart/form-data` form with the file in its `file` field.
+    /// The image is created as soon as the file is uploaded.
+    pub struct Upload;
+    impl ApiEndpoint for Upload {
+        // raw bytes or a form
+        type Req = ();
+        type Res = ();
+        type Err = MetadataNotFound;
+        const PATH: &'static str = "/v1/image/import/{id}/item/{index}/raw";
+        const METHOD: Method = Method::Put;
+    }
+}
+
 /// Get an image by ID.
 pub struct Get;
 impl ApiEndpoint for Get {
@@ -107,11 +156,13 @@ impl ApiEndpoint for Create {
 
 /// Upload an image
 /// Note: can be used to update the raw data associated with the image.
+///
+/// Pass `?check_duplicates=true` (see [`ImageUploadQuery`](crate::domain::image::ImageUploadQuery)) to find images that look like the uploaded one.
 pub struct Upload;
 impl ApiEndpoint for Upload {
     // raw bytes
     type Req = ();
-    type Res = ();
+    type Res = ImageUploadResponse;
     type Err = EmptyError;
     const PATH: &'static str = "/v1/image/{id}/raw";
     const METHOD: Method = Method::Patch;
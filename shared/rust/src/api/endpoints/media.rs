@@ -1,6 +1,9 @@
 use crate::{
     api::Method,
-    domain::media::{UrlCreatedResponse, WebMediaMetadataResponse, WebMediaUrlCreateRequest},
+    domain::media::{
+        UrlCreatedResponse, WebMediaDeleteManyRequest, WebMediaListQuery, WebMediaListResponse,
+        WebMediaMetadataResponse, WebMediaUrlCreateRequest,
+    },
     error::EmptyError,
 };
 
@@ -56,3 +59,43 @@ impl ApiEndpoint for DeleteId {
     const PATH: &'static str = "/v1/media/id/{id}";
     const METHOD: Method = Method::Delete;
 }
+
+/// List media in the web media library, newest first, along with who imported it and from where.
+///
+/// Requires the `Admin` scope.
+pub struct List;
+impl ApiEndpoint for List {
+    type Req = WebMediaListQuery;
+    type Res = WebMediaListResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/media";
+    const METHOD: Method = Method::Get;
+}
+
+/// Remove several pieces of media from the web media library.
+///
+/// Requires the `Admin` scope.
+pub struct DeleteMany;
+impl ApiEndpoint for DeleteMany {
+    type Req = WebMediaDeleteManyRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/media/delete";
+    const METHOD: Method = Method::Post;
+}
+
+/// Get a media file.
+///
+/// Responds with a redirect to a short lived signed url, or with the file itself if the storage backend doesn't support signed urls.
+/// Public media doesn't require authorization, private (user library) media can only be accessed by its owner,
+/// and premium media requires the `PremiumMedia` scope.
+///
+/// Note: the `{file}` part of the path is [`FileKind::suffix`](crate::media::FileKind::suffix), see [`media_file_path`](crate::media::media_file_path).
+pub struct GetFile;
+impl ApiEndpoint for GetFile {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/media/file/{library}/{id}/{file}";
+    const METHOD: Method = Method::Get;
+}
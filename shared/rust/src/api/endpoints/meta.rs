@@ -1,15 +1,71 @@
 use crate::{
     api::{ApiEndpoint, Method},
-    domain::meta::MetadataResponse,
+    domain::meta::{
+        CreateMetadataRequest, DeleteMetadataQuery, GetMetadataRequest, MetadataResponse,
+        NewMetadataResponse, SetTranslationsRequest, UpdateMetadataRequest,
+    },
     error::EmptyError,
 };
 
 /// Get metadata.
+///
+/// Names are translated to the requested language when there's a translation for it.
 pub struct Get;
 impl ApiEndpoint for Get {
-    type Req = ();
+    type Req = GetMetadataRequest;
     type Res = MetadataResponse;
     type Err = EmptyError;
     const PATH: &'static str = "/v1/metadata";
     const METHOD: Method = Method::Get;
 }
+
+/// Create a piece of metadata.
+///
+/// Requires the `ManageMetadata` scope.
+pub struct Create;
+impl ApiEndpoint for Create {
+    type Req = CreateMetadataRequest;
+    type Res = NewMetadataResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/metadata/{kind}";
+    const METHOD: Method = Method::Post;
+}
+
+/// Rename and/or reorder a piece of metadata.
+///
+/// Requires the `ManageMetadata` scope.
+pub struct Update;
+impl ApiEndpoint for Update {
+    type Req = UpdateMetadataRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/metadata/{kind}/{id}";
+    const METHOD: Method = Method::Patch;
+}
+
+/// Delete a piece of metadata.
+///
+/// Responds with `409 Conflict` if any images, JIGs or users still have the metadata,
+/// unless [`replace_with`](crate::domain::meta::DeleteMetadataQuery::replace_with) is given.
+///
+/// Requires the `ManageMetadata` scope.
+pub struct Delete;
+impl ApiEndpoint for Delete {
+    type Req = DeleteMetadataQuery;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/metadata/{kind}/{id}";
+    const METHOD: Method = Method::Delete;
+}
+
+/// Replace the translations of a piece of metadata's name.
+///
+/// Requires the `ManageMetadata` scope.
+pub struct SetTranslations;
+impl ApiEndpoint for SetTranslations {
+    type Req = SetTranslationsRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/metadata/{kind}/{id}/translations";
+    const METHOD: Method = Method::Put;
+}
@@ -0,0 +1,55 @@
+use crate::{
+    api::Method,
+    domain::{
+        organization::{
+            InviteAcceptRequest, InviteAcceptResponse, InviteCreateRequest, InviteCreateResponse,
+            Organization, OrganizationCreateRequest, OrganizationId,
+        },
+        CreateResponse,
+    },
+    error::EmptyError,
+};
+
+use super::ApiEndpoint;
+
+/// Create an organization.
+pub struct Create;
+impl ApiEndpoint for Create {
+    type Req = OrganizationCreateRequest;
+    type Res = CreateResponse<OrganizationId>;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/organization";
+    const METHOD: Method = Method::Post;
+}
+
+/// Get an organization by ID.
+///
+/// Only members of the organization can see it.
+pub struct Get;
+impl ApiEndpoint for Get {
+    type Req = ();
+    type Res = Organization;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/organization/{id}";
+    const METHOD: Method = Method::Get;
+}
+
+/// Create an invite to an organization.
+pub struct CreateInvite;
+impl ApiEndpoint for CreateInvite {
+    type Req = InviteCreateRequest;
+    type Res = InviteCreateResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/organization/{id}/invite";
+    const METHOD: Method = Method::Post;
+}
+
+/// Accept an invite to an organization.
+pub struct AcceptInvite;
+impl ApiEndpoint for AcceptInvite {
+    type Req = InviteAcceptRequest;
+    type Res = InviteAcceptResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/organization/invite/accept";
+    const METHOD: Method = Method::Post;
+}
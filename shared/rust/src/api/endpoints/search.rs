@@ -2,7 +2,10 @@
 
 use crate::{
     api::{ApiEndpoint, Method},
-    domain::search::{CreateSearchKeyResponse, WebImageSearchQuery, WebImageSearchResponse},
+    domain::search::{
+        CreateSearchKeyResponse, SearchSuggestQuery, SearchSuggestResponse, WebImageSearchQuery,
+        WebImageSearchResponse,
+    },
     error::EmptyError,
 };
 
@@ -25,3 +28,15 @@ impl ApiEndpoint for WebImageSearch {
     const PATH: &'static str = "/v1/search/web/image";
     const METHOD: Method = Method::Get;
 }
+
+/// Suggest searches and metadata for a partially typed image search query (for typeahead).
+///
+/// An empty query has no suggestions.
+pub struct Suggest;
+impl ApiEndpoint for Suggest {
+    type Req = SearchSuggestQuery;
+    type Res = SearchSuggestResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/search/suggest";
+    const METHOD: Method = Method::Get;
+}
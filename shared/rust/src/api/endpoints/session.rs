@@ -0,0 +1,68 @@
+use crate::{
+    api::Method,
+    domain::{
+        session::{ProgressReport, SessionCreateRequest, SessionEventRequest, SessionId},
+        CreateResponse,
+    },
+    error::EmptyError,
+};
+
+use super::ApiEndpoint;
+
+/// Start a play session for a JIG.
+pub struct Create;
+impl ApiEndpoint for Create {
+    type Req = SessionCreateRequest;
+    type Res = CreateResponse<SessionId>;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/session";
+    const METHOD: Method = Method::Post;
+}
+
+/// Record an event for one of the session's modules.
+///
+/// Only the user who started the session can record events for it.
+pub struct CreateEvent;
+impl ApiEndpoint for CreateEvent {
+    type Req = SessionEventRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/session/{id}/event";
+    const METHOD: Method = Method::Post;
+}
+
+/// Get the progress report for a classroom.
+///
+/// Requires the user to be able to manage the classroom.
+pub struct ClassroomReport;
+impl ApiEndpoint for ClassroomReport {
+    type Req = ();
+    type Res = ProgressReport;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}/progress";
+    const METHOD: Method = Method::Get;
+}
+
+/// Get the progress report for a single JIG in a classroom.
+///
+/// Requires the user to be able to manage the classroom.
+pub struct JigReport;
+impl ApiEndpoint for JigReport {
+    type Req = ();
+    type Res = ProgressReport;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}/progress/jig/{jig_id}";
+    const METHOD: Method = Method::Get;
+}
+
+/// Get the progress report for a single student in a classroom.
+///
+/// Requires the user to be able to manage the classroom, or to *be* the student.
+pub struct StudentReport;
+impl ApiEndpoint for StudentReport {
+    type Req = ();
+    type Res = ProgressReport;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/classroom/{id}/progress/student/{user_id}";
+    const METHOD: Method = Method::Get;
+}
@@ -0,0 +1,63 @@
+use crate::{
+    api::Method,
+    domain::upload::{UploadSession, UploadSessionCompleteRequest, UploadSessionCreateRequest},
+    error::EmptyError,
+};
+
+use super::ApiEndpoint;
+
+/// Start uploading a media file directly to storage.
+///
+/// The file is split into parts which can be uploaded in any order (and retried), see [`UploadSession`].
+pub struct Create;
+impl ApiEndpoint for Create {
+    type Req = UploadSessionCreateRequest;
+    type Res = UploadSession;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/upload";
+    const METHOD: Method = Method::Post;
+}
+
+/// Get an upload session, with freshly signed part urls and the parts that have already been uploaded.
+pub struct Get;
+impl ApiEndpoint for Get {
+    type Req = ();
+    type Res = UploadSession;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/upload/{id}";
+    const METHOD: Method = Method::Get;
+}
+
+/// Upload a part through the api.
+///
+/// Only used when the storage backend doesn't support signed urls, the part urls will point here.
+pub struct UploadPart;
+impl ApiEndpoint for UploadPart {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/upload/{id}/part/{number}";
+    const METHOD: Method = Method::Put;
+}
+
+/// Finish an upload.
+///
+/// The file is validated, and derived files (like resized images) are generated.
+pub struct Complete;
+impl ApiEndpoint for Complete {
+    type Req = UploadSessionCompleteRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/upload/{id}/complete";
+    const METHOD: Method = Method::Post;
+}
+
+/// Abort an upload, discarding any uploaded parts.
+pub struct Abort;
+impl ApiEndpoint for Abort {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/upload/{id}";
+    const METHOD: Method = Method::Delete;
+}
@@ -3,8 +3,14 @@ use super::ApiEndpoint;
 use crate::{
     api::method::Method,
     domain::{
-        auth::{RegisterRequest, RegisterSuccess, SigninSuccess, SingleSignOnSuccess},
-        user::{OtherUser, UserLookupQuery, UserProfile},
+        auth::{
+            CreatePasswordResetRequest, RegisterRequest, RegisterSuccess, ResetPasswordRequest,
+            SigninSuccess, SingleSignOnSuccess, VerifyEmailRequest,
+        },
+        user::{
+            OtherUser, UserLookupQuery, UserProfile, UserTokenCreateRequest,
+            UserTokenCreateResponse, UserTokenListResponse,
+        },
     },
     error::{auth::RegisterError, EmptyError},
 };
@@ -58,3 +64,65 @@ impl ApiEndpoint for UserLookup {
     const PATH: &'static str = "/v1/user";
     const METHOD: Method = Method::Get;
 }
+
+/// Verify a user's email address.
+pub struct VerifyEmail;
+impl ApiEndpoint for VerifyEmail {
+    type Req = VerifyEmailRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/user/verify-email";
+    const METHOD: Method = Method::Post;
+}
+
+/// Send a password reset token to a user's email.
+pub struct CreatePasswordReset;
+impl ApiEndpoint for CreatePasswordReset {
+    type Req = CreatePasswordResetRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/user/password-reset";
+    const METHOD: Method = Method::Post;
+}
+
+/// Reset a user's password (with a token from [`CreatePasswordReset`]).
+pub struct ResetPassword;
+impl ApiEndpoint for ResetPassword {
+    type Req = ResetPasswordRequest;
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/user/password";
+    const METHOD: Method = Method::Put;
+}
+
+/// Create a personal access token.
+///
+/// Note: personal access tokens can't be used to manage personal access tokens.
+pub struct CreateToken;
+impl ApiEndpoint for CreateToken {
+    type Req = UserTokenCreateRequest;
+    type Res = UserTokenCreateResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/user/me/tokens";
+    const METHOD: Method = Method::Post;
+}
+
+/// List the user's personal access tokens.
+pub struct ListTokens;
+impl ApiEndpoint for ListTokens {
+    type Req = ();
+    type Res = UserTokenListResponse;
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/user/me/tokens";
+    const METHOD: Method = Method::Get;
+}
+
+/// Revoke a personal access token.
+pub struct DeleteToken;
+impl ApiEndpoint for DeleteToken {
+    type Req = ();
+    type Res = ();
+    type Err = EmptyError;
+    const PATH: &'static str = "/v1/user/me/tokens/{id}";
+    const METHOD: Method = Method::Delete;
+}
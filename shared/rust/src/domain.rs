@@ -17,12 +17,16 @@ pub mod animation;
 pub mod audio;
 pub mod auth;
 pub mod category;
+pub mod classroom;
 pub mod image;
 pub mod jig;
 pub mod media;
 pub mod meta;
+pub mod organization;
 pub mod search;
 mod ser;
+pub mod session;
+pub mod upload;
 pub mod user;
 
 use chrono::Utc;
@@ -4,15 +4,64 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::media::{MediaKind, MediaLibrary};
+use super::{
+    category::CategoryId,
+    image::ImageId,
+    meta::{AgeRangeId, StyleId},
+};
+use crate::media::{MediaKind, MediaKindFilter, MediaLibrary, ModerationStatus};
+
+/// Query for [`ListMedia`](crate::api::endpoints::admin::ListMedia), every filter is optional.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminListMediaQuery {
+    /// Only list media from this library.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library: Option<MediaLibrary>,
+
+    /// Only list media of this kind.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<MediaKindFilter>,
+
+    /// Only list media that has (`true`) or hasn't (`false`) had a file uploaded.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded: Option<bool>,
+
+    /// Only list media created at or after this time.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<DateTime<Utc>>,
+
+    /// Only list media created before this time.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<DateTime<Utc>>,
+
+    /// The maximum amount of media to return (defaults to 100, capped at 1000).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// Where to continue listing from, see [`AdminListMediaResponse::next_cursor`].
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
 
 /// Response for [`ListMedia`](crate::api::endpoints::admin::ListMedia)
 /// Super unstable, may change at any time, for any reason.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
 pub struct AdminListMediaResponse {
-    /// A list of all media items
+    /// A page of media items, newest first.
     pub media: Vec<AdminMediaItem>,
+
+    /// An opaque cursor to pass to the next request to get the next page, `None` if this is the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
 }
 
 /// Item of media
@@ -40,3 +89,243 @@ pub struct AdminMediaItem {
     /// An arbitrary (ascii) string representing the current state of the media.
     pub file_etag: Option<String>,
 }
+
+/// Query for [`SimilarImages`](crate::api::endpoints::admin::SimilarImages).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminSimilarImagesQuery {
+    /// Only find images from this library.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library: Option<MediaLibrary>,
+
+    /// How many bits of the images' perceptual hashes may differ (defaults to 4, capped at 7).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_distance: Option<u32>,
+
+    /// The maximum amount of images to return (defaults to 100, capped at 1000).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Response for [`SimilarImages`](crate::api::endpoints::admin::SimilarImages).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminSimilarImagesResponse {
+    /// The similar images, most similar first.
+    pub images: Vec<AdminSimilarImage>,
+}
+
+/// An image that's similar to another one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminSimilarImage {
+    /// The ID of the image.
+    pub id: Uuid,
+
+    /// What library the image is from.
+    pub library: MediaLibrary,
+
+    /// How many bits of the images' perceptual hashes differ, `0` means they're (almost certainly) the same picture.
+    pub distance: u32,
+}
+
+/// Query for [`ListModeration`](crate::api::endpoints::admin::ListModeration), every filter is optional.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminModerationListQuery {
+    /// Only list media with this status.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ModerationStatus>,
+
+    /// Only list media from this library.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library: Option<MediaLibrary>,
+
+    /// The maximum amount of media to return (defaults to 100, capped at 1000).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// Where to continue listing from, see [`AdminModerationListResponse::next_cursor`].
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Response for [`ListModeration`](crate::api::endpoints::admin::ListModeration).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminModerationListResponse {
+    /// A page of moderated media, newest first.
+    pub items: Vec<AdminModerationItem>,
+
+    /// An opaque cursor to pass to the next request to get the next page, `None` if this is the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// The moderation state of an item of media.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminModerationItem {
+    /// The ID of the media.
+    pub id: Uuid,
+
+    /// What library the media is from.
+    pub library: MediaLibrary,
+
+    /// Whether the media has been cleared to be shown.
+    pub status: ModerationStatus,
+
+    /// Why the media was flagged or rejected, if a reason was given.
+    pub reason: Option<String>,
+
+    /// When the media was (last) uploaded for moderation.
+    pub created_at: DateTime<Utc>,
+
+    /// The admin that reviewed the media, `None` if it hasn't been reviewed by a person.
+    pub reviewed_by: Option<Uuid>,
+
+    /// When the media was reviewed by an admin.
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+/// Request for [`RejectMedia`](crate::api::endpoints::admin::RejectMedia).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminModerationRejectRequest {
+    /// Why the media was rejected.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// A change to the search index that's waiting to be made.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+#[repr(i16)]
+pub enum SearchIndexOperation {
+    /// Add or update the object.
+    Upsert = 0,
+
+    /// Remove the object.
+    Delete = 1,
+}
+
+/// Response for [`SearchSyncStatus`](crate::api::endpoints::admin::SearchSyncStatus).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminSearchSyncResponse {
+    /// Whether the search index is being kept up to date (`false` if indexing is disabled on this server).
+    pub indexing_enabled: bool,
+
+    /// How many changes are waiting to be made (including ones that are being retried).
+    pub pending_count: u64,
+
+    /// How many changes have run out of retries.
+    pub failed_count: u64,
+
+    /// When the oldest waiting change was made, `None` if the index is up to date.
+    #[serde(default)]
+    pub oldest_pending_at: Option<DateTime<Utc>>,
+
+    /// How far behind the index is, in seconds (how long the oldest waiting change has been waiting).
+    pub lag_seconds: u64,
+
+    /// The most recent failed changes, newest first (at most 100).
+    pub failed: Vec<AdminSearchSyncFailure>,
+}
+
+/// A change to the search index that ran out of retries.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminSearchSyncFailure {
+    /// The ID of the object that failed to sync.
+    pub object_id: Uuid,
+
+    /// What was being done to the object.
+    pub operation: SearchIndexOperation,
+
+    /// How many times the change was tried.
+    pub attempts: u32,
+
+    /// The error from the last try.
+    pub last_error: Option<String>,
+
+    /// When the change gave up.
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Response for [`Reindex`](crate::api::endpoints::admin::Reindex).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminReindexResponse {
+    /// How many objects were queued to be reindexed.
+    pub queued: u64,
+}
+
+/// Request for [`MergeCategory`](crate::api::endpoints::admin::MergeCategory).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminMergeCategoryRequest {
+    /// The category to merge into, it can't be the merged category or one of its descendants.
+    pub into: CategoryId,
+}
+
+/// Response for [`MergeCategory`](crate::api::endpoints::admin::MergeCategory).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminMergeCategoryResponse {
+    /// How many images were moved to the target category (images that already had it aren't counted).
+    pub image_count: u64,
+
+    /// How many JIGs were moved to the target category (JIGs that already had it aren't counted).
+    pub jig_count: u64,
+}
+
+/// Request for [`BulkTagImages`](crate::api::endpoints::admin::BulkTagImages).
+///
+/// Removals happen before additions, so adding and removing the same item leaves it added.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AdminBulkTagImagesRequest {
+    /// The images to change (at most 1000).
+    pub image_ids: Vec<ImageId>,
+
+    /// Categories to add to every image.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub add_categories: Vec<CategoryId>,
+
+    /// Categories to remove from every image.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove_categories: Vec<CategoryId>,
+
+    /// Styles to add to every image.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub add_styles: Vec<StyleId>,
+
+    /// Styles to remove from every image.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove_styles: Vec<StyleId>,
+
+    /// Age ranges to add to every image.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub add_age_ranges: Vec<AgeRangeId>,
+
+    /// Age ranges to remove from every image.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove_age_ranges: Vec<AgeRangeId>,
+}
@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::Publish;
+use crate::media::{AltText, License, ProcessingStatus};
 
 /// Animation Variants
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -71,11 +72,26 @@ pub struct AnimationMetadata {
     /// Should the animation loop?
     pub is_looping: bool,
 
+    /// Descriptions of the animation for people who can't see it, one per language.
+    pub alt_text: Vec<AltText>,
+
+    /// Who the animation should be credited to.
+    pub attribution: Option<String>,
+
+    /// The license the animation is used under.
+    pub license: Option<License>,
+
+    /// Where the animation originally came from.
+    pub source_url: Option<String>,
+
     /// When the animation was originally created.
     pub created_at: DateTime<Utc>,
 
     /// When the animation was last updated.
     pub updated_at: Option<DateTime<Utc>>,
+
+    /// The state of the animation file's processing, `None` if no file has been uploaded.
+    pub processing_status: Option<ProcessingStatus>,
 }
 
 // todo: # errors doc section
@@ -102,6 +118,26 @@ pub struct AnimationCreateRequest {
 
     /// Should the animation loop?
     pub is_looping: bool,
+
+    /// Descriptions of the animation for people who can't see it, at most one per language.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alt_text: Vec<AltText>,
+
+    /// Who the animation should be credited to.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>,
+
+    /// The license the animation is used under.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>,
+
+    /// Where the animation originally came from (an `http` or `https` url).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
 }
 
 into_uuid![AnimationId];
@@ -13,6 +13,7 @@ pub mod user {
     use serde::{Deserialize, Serialize};
 
     use super::AudioId;
+    use crate::media::ProcessingStatus;
 
     /// Response for listing.
     #[derive(Serialize, Deserialize, Debug)]
@@ -36,6 +37,9 @@ pub mod user {
     pub struct UserAudio {
         /// The audio file's ID.
         pub id: AudioId,
+
+        /// The state of the file's processing, `None` if no file has been uploaded.
+        pub processing_status: Option<ProcessingStatus>,
         // more fields to be added
     }
 }
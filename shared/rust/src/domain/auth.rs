@@ -105,6 +105,35 @@ pub struct RegisterRequest {
     pub location: Option<serde_json::Value>,
 }
 
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+/// Request to verify a user's email address.
+pub struct VerifyEmailRequest {
+    /// The token that was sent to the user's email.
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+/// Request to send a password reset token to a user's email.
+///
+/// Note: this request always succeeds, regardless of whether or not a user with the given email exists.
+pub struct CreatePasswordResetRequest {
+    /// The email of the user whose password should be reset.
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+/// Request to reset a user's password.
+pub struct ResetPasswordRequest {
+    /// The token that was sent to the user's email.
+    pub token: String,
+
+    /// The user's new password.
+    pub password: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "backend", derive(Apiv2Schema))]
 /// The claims that are used as part of the user's jwt.
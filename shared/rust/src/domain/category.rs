@@ -122,6 +122,16 @@ pub struct GetCategoryRequest {
     /// Which direction to follow the tree.
     #[serde(default)]
     pub scope: Option<CategoryTreeScope>,
+
+    /// The language to translate category names to (for example `he` or `en-US`), falls back to the `Accept-Language` header.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+
+    /// If `true`, [`image_count`](Category::image_count) and [`jig_count`](Category::jig_count) only count published images and JIGs.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub published_only: bool,
 }
 
 #[derive(Serialize, Deserialize)]
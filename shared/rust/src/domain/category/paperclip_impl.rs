@@ -123,6 +123,22 @@ impl Apiv2Schema for super::GetCategoryRequest {
             }),
         );
 
+        schema.properties.insert(
+            "lang".to_owned(),
+            Box::new(DefaultSchemaRaw {
+                description: Some("The language to translate category names to (for example `he` or `en-US`), falls back to the `Accept-Language` header.".to_owned()),
+                ..Option::<String>::raw_schema()
+            }),
+        );
+
+        schema.properties.insert(
+            "published_only".to_owned(),
+            Box::new(DefaultSchemaRaw {
+                description: Some("If `true`, `image_count` and `jig_count` only count published images and JIGs.".to_owned()),
+                ..bool::raw_schema()
+            }),
+        );
+
         schema
     }
 }
@@ -0,0 +1,69 @@
+//! Types for classrooms.
+
+use super::{jig::JigId, organization::OrganizationId};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "backend")]
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Wrapper type around [`Uuid`], represents the ID of a classroom.
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", sqlx(transparent))]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct ClassroomId(pub Uuid);
+
+/// Request to create a new classroom.
+///
+/// The teacher creating the classroom becomes its owner.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct ClassroomCreateRequest {
+    /// The classroom's name.
+    pub display_name: String,
+}
+
+/// The over-the-wire representation of a classroom.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct Classroom {
+    /// The ID of the classroom.
+    pub id: ClassroomId,
+
+    /// The organization the classroom belongs to.
+    pub organization_id: OrganizationId,
+
+    /// The teacher who owns the classroom.
+    pub owner_id: Uuid,
+
+    /// The classroom's name.
+    pub display_name: String,
+
+    /// The IDs of the students in the classroom.
+    pub students: Vec<Uuid>,
+
+    /// When the classroom was created.
+    pub created_at: DateTime<Utc>,
+}
+
+/// A JIG that has been assigned to a classroom.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct AssignedJig {
+    /// The ID of the assigned JIG.
+    pub jig_id: JigId,
+
+    /// When the JIG was assigned.
+    pub assigned_at: DateTime<Utc>,
+}
+
+/// Response for listing the JIGs assigned to a classroom.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct AssignedJigListResponse {
+    /// The assigned JIGs, most recently assigned first.
+    pub jigs: Vec<AssignedJig>,
+}
+
+into_uuid![ClassroomId];
@@ -5,6 +5,7 @@ use super::{
     meta::{AffiliationId, AgeRangeId, StyleId},
     Publish,
 };
+use crate::media::{AltText, License, ProcessingStatus};
 use chrono::{DateTime, Utc};
 #[cfg(feature = "backend")]
 use paperclip::actix::Apiv2Schema;
@@ -20,6 +21,7 @@ pub mod user {
     use serde::{Deserialize, Serialize};
 
     use super::ImageId;
+    use crate::media::ProcessingStatus;
 
     /// Response for listing.
     #[derive(Serialize, Deserialize, Debug)]
@@ -43,10 +45,176 @@ pub mod user {
     pub struct UserImage {
         /// The image's ID.
         pub id: ImageId,
+
+        /// The state of the file's processing, `None` if no file has been uploaded.
+        pub processing_status: Option<ProcessingStatus>,
         // more fields to be added
     }
 }
 
+/// Types for bulk importing images into the global image library.
+pub mod import {
+    use chrono::{DateTime, Utc};
+    #[cfg(feature = "backend")]
+    use paperclip::actix::Apiv2Schema;
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    use super::{ImageId, ImageKind};
+    use crate::{
+        domain::{
+            category::CategoryId,
+            meta::{AffiliationId, AgeRangeId, StyleId},
+            Publish,
+        },
+        media::License,
+    };
+
+    /// An image to import, see [`ImageImportRequest`].
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+    pub struct ImageImportItem {
+        /// The name of the image.
+        pub name: String,
+
+        /// The description of the image.
+        pub description: String,
+
+        /// Is the image premium?
+        #[serde(default)]
+        pub is_premium: bool,
+
+        /// When to publish the image, if `None` it won't be published.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub publish_at: Option<Publish>,
+
+        /// The image's styles.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub styles: Vec<StyleId>,
+
+        /// The image's age ranges.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub age_ranges: Vec<AgeRangeId>,
+
+        /// The image's affiliations.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub affiliations: Vec<AffiliationId>,
+
+        /// The image's categories.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub categories: Vec<CategoryId>,
+
+        /// What kind of image this is.
+        pub kind: ImageKind,
+
+        /// Who the image should be credited to.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub attribution: Option<String>,
+
+        /// The license the image is used under.
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub license: Option<License>,
+
+        /// Where to download the image from (an `http` or `https` url), it's kept as the image's `source_url`.
+        ///
+        /// If `None` the file needs to be uploaded with [`Upload`](crate::api::endpoints::image::import::Upload).
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub source_url: Option<String>,
+    }
+
+    /// Request for [`Create`](crate::api::endpoints::image::import::Create).
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+    pub struct ImageImportRequest {
+        /// The images to import (at most 1000), items are referred to by their index in this list.
+        pub items: Vec<ImageImportItem>,
+    }
+
+    /// Response for [`Create`](crate::api::endpoints::image::import::Create).
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+    pub struct ImageImportCreateResponse {
+        /// The import's ID, used to check on its progress.
+        pub id: Uuid,
+    }
+
+    /// How far along importing an item is.
+    #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+    #[serde(rename_all = "camelCase")]
+    #[cfg_attr(feature = "backend", derive(sqlx::Type))]
+    #[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+    #[repr(i16)]
+    pub enum ImageImportStatus {
+        /// Waiting for the file to be uploaded.
+        AwaitingUpload = 0,
+
+        /// The file is being downloaded from the item's `source_url`.
+        Importing = 1,
+
+        /// The image has been created, its file might still be processing (see [`Image`](super::Image)).
+        Imported = 2,
+
+        /// Importing the image failed, see the item's `error`.
+        Failed = 3,
+    }
+
+    /// The state of an item being imported.
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+    pub struct ImageImportItemStatus {
+        /// The item's index in the import's manifest.
+        pub index: u32,
+
+        /// The name of the image.
+        pub name: String,
+
+        /// How far along importing the item is.
+        pub status: ImageImportStatus,
+
+        /// The created image, `None` until the item is imported.
+        #[serde(default)]
+        pub image_id: Option<ImageId>,
+
+        /// Why importing the item failed.
+        #[serde(default)]
+        pub error: Option<String>,
+    }
+
+    /// Response for [`Get`](crate::api::endpoints::image::import::Get).
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+    pub struct ImageImportResponse {
+        /// The import's ID.
+        pub id: Uuid,
+
+        /// When the import was created.
+        pub created_at: DateTime<Utc>,
+
+        /// How many items are waiting for their file to be uploaded.
+        pub awaiting_upload_count: u32,
+
+        /// How many items are still being downloaded.
+        pub importing_count: u32,
+
+        /// How many items have been imported.
+        pub imported_count: u32,
+
+        /// How many items failed.
+        pub failed_count: u32,
+
+        /// Every item in the import, in manifest order.
+        pub items: Vec<ImageImportItemStatus>,
+    }
+}
+
 /// Represents different kinds of images (which affects how the size is stored in the db)
 #[derive(Serialize, Deserialize, Copy, Clone, Debug)]
 #[cfg_attr(feature = "backend", derive(sqlx::Type))]
@@ -115,6 +283,26 @@ pub struct ImageCreateRequest {
 
     /// What kind of image this is.
     pub kind: ImageKind,
+
+    /// Descriptions of the image for people who can't see it, at most one per language.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alt_text: Vec<AltText>,
+
+    /// Who the image should be credited to.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>,
+
+    /// The license the image is used under.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>,
+
+    /// Where the image originally came from (an `http` or `https` url).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
 }
 
 // todo: # errors doc section.
@@ -163,6 +351,50 @@ pub struct ImageUpdateRequest {
     /// If `Some` replace the image's categories with these.
     #[serde(default)]
     pub categories: Option<Vec<CategoryId>>,
+
+    /// If `Some` replace the image's alt text with these.
+    #[serde(default)]
+    pub alt_text: Option<Vec<AltText>>,
+
+    /// If `Some`, change the image's attribution (`Some(None)` removes it).
+    #[serde(deserialize_with = "super::deserialize_optional_field")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub attribution: Option<Option<String>>,
+
+    /// If `Some`, change the image's license (`Some(None)` removes it).
+    #[serde(deserialize_with = "super::deserialize_optional_field")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub license: Option<Option<License>>,
+
+    /// If `Some`, change the image's source url (`Some(None)` removes it).
+    #[serde(deserialize_with = "super::deserialize_optional_field")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub source_url: Option<Option<String>>,
+}
+
+/// Query for [`Upload`](crate::api::endpoints::image::Upload).
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct ImageUploadQuery {
+    /// Check whether the image looks like an image that's already in the library.
+    #[serde(default)]
+    pub check_duplicates: bool,
+}
+
+/// Response for [`Upload`](crate::api::endpoints::image::Upload).
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct ImageUploadResponse {
+    /// Images in the library that look like the uploaded image, most similar first.
+    /// `None` unless duplicates were checked for.
+    ///
+    /// This is only a warning, the image was uploaded either way.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similar_images: Option<Vec<ImageId>>,
 }
 
 /// Search for images via the given query string.
@@ -214,6 +446,17 @@ pub struct ImageSearchQuery {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_published: Option<bool>,
+
+    /// Whether or not to count how many of the matching images have each style, age range, affiliation and category.
+    /// See [`ImageSearchResponse::facets`].
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub with_facets: bool,
+
+    /// The language to name facets in (for example `he` or `en-US`), falls back to the `Accept-Language` header.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
 }
 
 /// Response for successful search.
@@ -228,6 +471,42 @@ pub struct ImageSearchResponse {
 
     /// The total number of images found
     pub total_image_count: u64,
+
+    /// How many of the matching images have each piece of metadata, only present if [`ImageSearchQuery::with_facets`] was set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<ImageSearchFacets>,
+}
+
+/// Counts of how many images matching a search have each piece of metadata (only metadata that at least one image has is included).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct ImageSearchFacets {
+    /// Counts by style.
+    pub styles: Vec<FacetCount>,
+
+    /// Counts by age range.
+    pub age_ranges: Vec<FacetCount>,
+
+    /// Counts by affiliation.
+    pub affiliations: Vec<FacetCount>,
+
+    /// Counts by category.
+    pub categories: Vec<FacetCount>,
+}
+
+/// How many images have a piece of metadata.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct FacetCount {
+    /// The ID of the metadata.
+    pub id: Uuid,
+
+    /// The display name of the metadata.
+    pub name: String,
+
+    /// How many matching images have the metadata.
+    pub count: u64,
 }
 
 /// Response for getting a single image.
@@ -269,11 +548,26 @@ pub struct Image {
     /// The categories associated with the image.
     pub categories: Vec<CategoryId>,
 
+    /// Descriptions of the image for people who can't see it, one per language.
+    pub alt_text: Vec<AltText>,
+
+    /// Who the image should be credited to.
+    pub attribution: Option<String>,
+
+    /// The license the image is used under.
+    pub license: Option<License>,
+
+    /// Where the image originally came from.
+    pub source_url: Option<String>,
+
     /// When the image was originally created.
     pub created_at: DateTime<Utc>,
 
     /// When the image was last updated.
     pub updated_at: Option<DateTime<Utc>>,
+
+    /// The state of the image file's processing, `None` if no file has been uploaded.
+    pub processing_status: Option<ProcessingStatus>,
 }
 
 /// Response for successfuly creating a Image.
@@ -294,8 +588,13 @@ impl<'r> sqlx::FromRow<'r, PgRow> for Image {
             age_ranges,
             affiliations,
             categories,
+            alt_text,
+            attribution,
+            license,
+            source_url,
             created_at,
             updated_at,
+            processing_status,
         } = DbImage::from_row(row)?;
 
         Ok(Self {
@@ -308,8 +607,16 @@ impl<'r> sqlx::FromRow<'r, PgRow> for Image {
             age_ranges: age_ranges.into_iter().map(|(it,)| it).collect(),
             affiliations: affiliations.into_iter().map(|(it,)| it).collect(),
             categories: categories.into_iter().map(|(it,)| it).collect(),
+            alt_text: alt_text
+                .into_iter()
+                .map(|(language, text)| AltText { language, text })
+                .collect(),
+            attribution,
+            license,
+            source_url,
             created_at,
             updated_at,
+            processing_status,
         })
     }
 }
@@ -326,8 +633,13 @@ struct DbImage {
     pub age_ranges: Vec<(AgeRangeId,)>,
     pub affiliations: Vec<(AffiliationId,)>,
     pub categories: Vec<(CategoryId,)>,
+    pub alt_text: Vec<(String, String)>,
+    pub attribution: Option<String>,
+    pub license: Option<License>,
+    pub source_url: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub processing_status: Option<ProcessingStatus>,
 }
 
 into_uuid![ImageId];
@@ -2,7 +2,11 @@
 
 pub mod module;
 
-use super::{meta::ContentTypeId, Publish};
+use super::{
+    category::CategoryId,
+    meta::{AffiliationId, AgeRangeId, ContentTypeId},
+    Publish,
+};
 use chrono::{DateTime, Utc};
 #[cfg(feature = "backend")]
 use paperclip::actix::Apiv2Schema;
@@ -48,6 +52,21 @@ pub struct JigCreateRequest {
     #[serde(default)]
     pub content_types: Vec<ContentTypeId>,
 
+    /// The JIG's categories.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub categories: Vec<CategoryId>,
+
+    /// The JIG's age ranges.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub age_ranges: Vec<AgeRangeId>,
+
+    /// The JIG's affiliations.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub affiliations: Vec<AffiliationId>,
+
     /// When the JIG should be considered published (if at all).
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
@@ -76,6 +95,15 @@ pub struct Jig {
     /// The types of content this JIG contains.
     pub content_types: Vec<ContentTypeId>,
 
+    /// The JIG's categories.
+    pub categories: Vec<CategoryId>,
+
+    /// The JIG's age ranges.
+    pub age_ranges: Vec<AgeRangeId>,
+
+    /// The JIG's affiliations.
+    pub affiliations: Vec<AffiliationId>,
+
     /// The ID of the JIG's original creator ([`None`] if unknown).
     pub creator_id: Option<Uuid>,
 
@@ -123,6 +151,21 @@ pub struct JigUpdateRequest {
     #[serde(default)]
     pub content_types: Option<Vec<ContentTypeId>>,
 
+    /// The JIG's categories.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub categories: Option<Vec<CategoryId>>,
+
+    /// The JIG's age ranges.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub age_ranges: Option<Vec<AgeRangeId>>,
+
+    /// The JIG's affiliations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub affiliations: Option<Vec<AffiliationId>>,
+
     /// The current author
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 use url::Url;
 use uuid::Uuid;
 
-use crate::media::MediaKind;
+use crate::media::{MediaKind, MediaKindFilter};
 
 /// Response for adding a URL to the Web Media Library
 #[derive(Serialize, Deserialize, Debug)]
@@ -49,3 +49,100 @@ pub struct WebMediaMetadataResponse {
     /// When this media was last updated, if ever.
     pub updated_at: Option<DateTime<Utc>>,
 }
+
+/// Query for [`List`](crate::api::endpoints::media::List), every filter is optional.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct WebMediaListQuery {
+    /// Only list media of this kind.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<MediaKindFilter>,
+
+    /// Only list media imported from this domain (or one of its subdomains).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+
+    /// Only list media with a url imported at or after this time.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_after: Option<DateTime<Utc>>,
+
+    /// Only list media with a url imported before this time.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_before: Option<DateTime<Utc>>,
+
+    /// Only list media with a url imported by this user.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported_by: Option<Uuid>,
+
+    /// The maximum amount of media to return (defaults to 100, capped at 1000).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// Where to continue listing from, see [`WebMediaListResponse::next_cursor`].
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Response for [`List`](crate::api::endpoints::media::List).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct WebMediaListResponse {
+    /// A page of media, newest first.
+    pub media: Vec<WebMediaListItem>,
+
+    /// An opaque cursor to pass to the next request to get the next page, `None` if this is the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Media from the web media library, along with where it was imported from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct WebMediaListItem {
+    /// The ID of the media.
+    pub id: Uuid,
+
+    /// What kind of media this is.
+    pub kind: MediaKind,
+
+    /// The urls this media was imported from (can be empty).
+    pub imports: Vec<WebMediaImport>,
+
+    /// When this media was added.
+    pub created_at: DateTime<Utc>,
+
+    /// When this media was last updated, if ever.
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A url that was imported into the web media library.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+#[cfg_attr(feature = "backend", openapi(empty))]
+pub struct WebMediaImport {
+    /// The url.
+    pub url: Url,
+
+    /// The user that imported the url.
+    ///
+    /// `None` if the user has since been deleted, or if the url was imported before this was recorded.
+    pub imported_by: Option<Uuid>,
+
+    /// When the url was imported.
+    pub imported_at: DateTime<Utc>,
+}
+
+/// Request to remove several pieces of media from the web media library.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct WebMediaDeleteManyRequest {
+    /// The IDs of the media to remove, IDs that don't exist are ignored.
+    pub ids: Vec<Uuid>,
+}
@@ -129,6 +129,16 @@ pub struct ContentType {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// Request for fetching all metadata.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct GetMetadataRequest {
+    /// The language to translate names to (for example `he` or `en-US`), falls back to the `Accept-Language` header.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+}
+
 /// Response for fetching all metadata.
 #[derive(Serialize, Deserialize, Debug)]
 #[cfg_attr(feature = "backend", derive(Apiv2Schema))]
@@ -171,3 +181,96 @@ pub enum MetaKind {
     /// [`ContentType`]
     ContentType,
 }
+
+/// Kinds of metadata that can be managed through the api.
+///
+/// Categories are managed separately, see [`category`](crate::api::endpoints::category).
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub enum MetaTaxonomy {
+    /// [`Style`]
+    Style,
+
+    /// [`AgeRange`]
+    AgeRange,
+
+    /// [`Affiliation`]
+    Affiliation,
+
+    /// [`Subject`]
+    Subject,
+
+    /// [`ContentType`]
+    ContentType,
+}
+
+/// Request to create a new piece of metadata.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct CreateMetadataRequest {
+    /// The metadata's name.
+    pub display_name: String,
+
+    /// Where to put the new metadata (ie, 0 puts it at the start), defaults to the end.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u16>,
+}
+
+/// Response for successfully creating a piece of metadata.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct NewMetadataResponse {
+    /// The id of the new metadata.
+    pub id: Uuid,
+
+    /// Where the new metadata ended up.
+    pub index: u16,
+}
+
+/// Request to update a piece of metadata.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UpdateMetadataRequest {
+    /// If [`Some`] change the metadata's name to this name.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    /// If [`Some`] move to _before_ the metadata with the given index (ie, 0 moves to the start).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u16>,
+}
+
+/// Query for deleting a piece of metadata.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct DeleteMetadataQuery {
+    /// Metadata (of the same kind) to give to everything that has the deleted metadata.
+    ///
+    /// If this is [`None`], the delete is refused while anything still has the metadata.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace_with: Option<Uuid>,
+}
+
+/// A translated name.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct Translation {
+    /// The language of the name (for example `he` or `en-US`).
+    pub language: String,
+
+    /// The name in that language.
+    pub name: String,
+}
+
+/// Request to replace the translations of a category or a piece of metadata's name.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct SetTranslationsRequest {
+    /// Every translation of the name, languages that aren't listed lose their translation.
+    pub translations: Vec<Translation>,
+}
@@ -0,0 +1,110 @@
+//! Types for organizations (schools, districts, etc) and their members.
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "backend")]
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Wrapper type around [`Uuid`], represents the ID of an organization.
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", sqlx(transparent))]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct OrganizationId(pub Uuid);
+
+/// Represents the role a user has within an organization.
+#[repr(i16)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub enum OrganizationRole {
+    /// The user can manage the organization, its members, and all of its classrooms.
+    Admin = 0,
+
+    /// The user can create classrooms and assign JIGs to them.
+    Teacher = 1,
+
+    /// The user can be added to classrooms.
+    Student = 2,
+}
+
+/// Request to create a new organization.
+///
+/// The user creating the organization becomes its first [`Admin`](OrganizationRole::Admin).
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct OrganizationCreateRequest {
+    /// The organization's name.
+    pub display_name: String,
+}
+
+/// A member of an organization.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct OrganizationMember {
+    /// The member's user ID.
+    pub user_id: Uuid,
+
+    /// The member's role within the organization.
+    pub role: OrganizationRole,
+}
+
+/// The over-the-wire representation of an organization.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct Organization {
+    /// The ID of the organization.
+    pub id: OrganizationId,
+
+    /// The organization's name.
+    pub display_name: String,
+
+    /// The organization's members.
+    pub members: Vec<OrganizationMember>,
+
+    /// When the organization was created.
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request to invite a user to an organization.
+///
+/// Admins can invite users with any role, teachers can only invite students.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct InviteCreateRequest {
+    /// The role the invited user will have once they accept the invite.
+    pub role: OrganizationRole,
+}
+
+/// Response for creating an invite.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct InviteCreateResponse {
+    /// The (single use) token to hand to the invited user.
+    pub token: String,
+
+    /// When the token expires.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request to accept an invite to an organization.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct InviteAcceptRequest {
+    /// The token from the invite.
+    pub token: String,
+}
+
+/// Response for accepting an invite.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct InviteAcceptResponse {
+    /// The organization the user is now a member of.
+    pub organization_id: OrganizationId,
+
+    /// The user's role within the organization.
+    pub role: OrganizationRole,
+}
+
+into_uuid![OrganizationId];
@@ -3,6 +3,7 @@
 #[cfg(feature = "backend")]
 use paperclip::actix::Apiv2Schema;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 /// Represents the response given when an api key for algolia is requested.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -39,3 +40,52 @@ pub struct WebImageSearchResponse {
     /// the images returned.
     pub images: Vec<WebImageSearchItem>,
 }
+
+/// Query for [`Suggest`](crate::api::endpoints::search::Suggest).
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct SearchSuggestQuery {
+    /// What's been typed so far.
+    pub q: String,
+
+    /// The language to name metadata in (for example `he` or `en-US`), takes priority over the `Accept-Language` header.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+
+    /// The most suggestions to return of each kind (defaults to 5, at most 20).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// A piece of metadata whose name matches a search prefix.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct MetadataSuggestion {
+    /// The ID of the metadata (for filtering the search by it).
+    pub id: Uuid,
+
+    /// The display name of the metadata, in the requested language if it has been translated.
+    pub name: String,
+}
+
+/// Response for [`Suggest`](crate::api::endpoints::search::Suggest).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct SearchSuggestResponse {
+    /// Searches that would find published images, based on their names.
+    pub queries: Vec<String>,
+
+    /// Matching categories.
+    pub categories: Vec<MetadataSuggestion>,
+
+    /// Matching styles.
+    pub styles: Vec<MetadataSuggestion>,
+
+    /// Matching age ranges.
+    pub age_ranges: Vec<MetadataSuggestion>,
+
+    /// Matching affiliations.
+    pub affiliations: Vec<MetadataSuggestion>,
+}
@@ -0,0 +1,133 @@
+//! Types for play sessions and progress reports.
+
+use super::{
+    classroom::ClassroomId,
+    jig::{JigId, ModuleId},
+};
+#[cfg(feature = "backend")]
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Wrapper type around [`Uuid`], represents the ID of a play session.
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", sqlx(transparent))]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct SessionId(pub Uuid);
+
+/// Request to start playing a JIG.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct SessionCreateRequest {
+    /// The JIG being played.
+    pub jig_id: JigId,
+
+    /// The classroom the JIG is being played for, if any.
+    ///
+    /// Only sessions with a classroom show up in that classroom's progress reports,
+    /// and the JIG must be assigned to the classroom.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub classroom_id: Option<ClassroomId>,
+}
+
+/// Represents the kinds of events a player can record for a module.
+#[repr(i16)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub enum SessionEventKind {
+    /// The module was started.
+    Started = 0,
+
+    /// The module was completed.
+    Completed = 1,
+}
+
+/// Request to record an event that happened while playing a module.
+///
+/// Only [`Completed`](SessionEventKind::Completed) events can have results (a score, attempts and time spent),
+/// and none of them can be negative.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct SessionEventRequest {
+    /// The module the event happened in.
+    pub module_id: ModuleId,
+
+    /// What happened.
+    pub kind: SessionEventKind,
+
+    /// The score the player got (if the module is scored).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub score: Option<i32>,
+
+    /// How many attempts the player took.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub attempts: Option<i32>,
+
+    /// How long the player spent in the module, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub time_spent_ms: Option<i64>,
+}
+
+/// Aggregated results for a set of play sessions.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct ProgressSummary {
+    /// The number of sessions played.
+    pub sessions: i64,
+
+    /// The number of modules completed (across all sessions).
+    pub completed_modules: i64,
+
+    /// The average score of completed modules that were scored.
+    pub average_score: Option<f64>,
+
+    /// The total number of attempts.
+    pub attempts: i64,
+
+    /// The total time spent, in milliseconds.
+    pub time_spent_ms: i64,
+}
+
+/// Aggregated results for a single JIG.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct JigProgress {
+    /// The JIG's ID.
+    pub jig_id: JigId,
+
+    /// The results.
+    pub summary: ProgressSummary,
+}
+
+/// Aggregated results for a single student.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct StudentProgress {
+    /// The student's user ID.
+    pub user_id: Uuid,
+
+    /// The results.
+    pub summary: ProgressSummary,
+}
+
+/// A progress report for a classroom (optionally narrowed down to a single JIG or student).
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct ProgressReport {
+    /// The results across everything in the report.
+    pub summary: ProgressSummary,
+
+    /// The results broken down per JIG.
+    pub jigs: Vec<JigProgress>,
+
+    /// The results broken down per student.
+    pub students: Vec<StudentProgress>,
+}
+
+into_uuid![SessionId];
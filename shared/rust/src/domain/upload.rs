@@ -0,0 +1,103 @@
+//! Types for direct-to-storage (multipart) uploads.
+
+use crate::media::MediaLibrary;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "backend")]
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Wrapper type around [`Uuid`], represents the ID of an upload session.
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", sqlx(transparent))]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UploadSessionId(pub Uuid);
+
+/// The kind of media being uploaded.
+#[repr(i16)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub enum UploadKind {
+    /// An image (the original, resized versions are generated when the upload completes).
+    Image = 0,
+
+    /// A gif animation.
+    Animation = 1,
+
+    /// An mp3 file.
+    Audio = 2,
+}
+
+/// Request to start uploading a media file.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UploadSessionCreateRequest {
+    /// The library the media is in.
+    pub library: MediaLibrary,
+
+    /// The id of the media (which must already exist).
+    pub media_id: Uuid,
+
+    /// What's being uploaded.
+    pub kind: UploadKind,
+
+    /// The size of the file, in bytes.
+    pub size: i64,
+}
+
+/// A part of the file to upload.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UploadPart {
+    /// The part's number (starting from 1).
+    pub number: i32,
+
+    /// Where to `PUT` the part's data.
+    ///
+    /// This is either a signed storage url, or an api route (which needs the usual credentials).
+    /// Either way, the part's `ETag` is returned in the response headers and is needed to complete the upload.
+    pub url: String,
+}
+
+/// A part that has been uploaded.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UploadedPart {
+    /// The part's number.
+    pub number: i32,
+
+    /// The `ETag` returned when the part was uploaded.
+    pub etag: String,
+}
+
+/// An upload session.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UploadSession {
+    /// The session's id.
+    pub id: UploadSessionId,
+
+    /// The size of each part (except the last one, which has the remainder), in bytes.
+    pub part_size: i64,
+
+    /// Every part of the file, with freshly signed urls.
+    pub parts: Vec<UploadPart>,
+
+    /// Parts that have already been uploaded, so an interrupted upload can be resumed.
+    pub uploaded_parts: Vec<UploadedPart>,
+
+    /// When the session (and any unfinished upload) expires.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request to finish an upload.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UploadSessionCompleteRequest {
+    /// All of the file's parts.
+    pub parts: Vec<UploadedPart>,
+}
+
+into_uuid![UploadSessionId];
@@ -33,6 +33,12 @@ pub enum UserScope {
 
     /// The user can create/delete/modify animations.
     ManageAnimation = 6,
+
+    /// The user can access premium media.
+    PremiumMedia = 7,
+
+    /// The user can create/delete/modify metadata (styles, age ranges, affiliations, subjects and content types).
+    ManageMetadata = 8,
 }
 
 impl TryFrom<i16> for UserScope {
@@ -46,6 +52,8 @@ impl TryFrom<i16> for UserScope {
             4 => Ok(Self::ManageJig),
             5 => Ok(Self::ManageModule),
             6 => Ok(Self::ManageAnimation),
+            7 => Ok(Self::PremiumMedia),
+            8 => Ok(Self::ManageMetadata),
             _ => anyhow::bail!("Scope {} is invalid"),
         }
     }
@@ -151,3 +159,67 @@ pub struct UserProfile {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub location: Option<serde_json::Value>,
 }
+
+/// Wrapper type around [`Uuid`], represents the ID of a personal access token.
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", sqlx(transparent))]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UserTokenId(pub Uuid);
+
+/// Request to create a personal access token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UserTokenCreateRequest {
+    /// A name to help the user tell their tokens apart.
+    pub display_name: String,
+
+    /// The scopes the token is allowed to use.
+    ///
+    /// Must be a subset of the user's own scopes.
+    #[serde(default)]
+    pub scopes: Vec<UserScope>,
+}
+
+/// Response for creating a personal access token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UserTokenCreateResponse {
+    /// The token's id.
+    pub id: UserTokenId,
+
+    /// The token itself, to be sent as `Authorization: Bearer TOKEN`.
+    ///
+    /// This is the *only* time the token is shown, it can't be retrieved later.
+    pub token: String,
+}
+
+/// A personal access token (without the token itself).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UserToken {
+    /// The token's id.
+    pub id: UserTokenId,
+
+    /// The token's name.
+    pub display_name: String,
+
+    /// The scopes the token is allowed to use.
+    pub scopes: Vec<UserScope>,
+
+    /// When the token was created.
+    pub created_at: DateTime<Utc>,
+
+    /// When the token was last used (if ever).
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Response for listing a user's personal access tokens.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "backend", derive(Apiv2Schema))]
+pub struct UserTokenListResponse {
+    /// The user's tokens.
+    pub tokens: Vec<UserToken>,
+}
+
+into_uuid![UserTokenId];
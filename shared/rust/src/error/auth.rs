@@ -24,4 +24,10 @@ pub enum RegisterErrorKind {
 
     /// Another user with the provided username already exists.
     TakenUsername,
+
+    /// The email used to authenticate doesn't match the email in the request.
+    EmailMismatch,
+
+    /// The provided password is too short.
+    WeakPassword,
 }
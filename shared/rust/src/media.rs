@@ -1,4 +1,4 @@
-//! Mostly contains functions for getting the `key`/url of media stored in s3.
+//! Mostly contains functions for getting the `key`/url of media stored in s3 (or served by the api).
 
 use crate::domain::{animation::AnimationKind, audio::AudioKind, image::ImageKind};
 use serde::{Deserialize, Serialize};
@@ -70,6 +70,101 @@ impl MediaLibrary {
             Self::Web => "web",
         }
     }
+
+    /// returns `self` as it appears in api paths.
+    #[must_use]
+    const fn to_path_str(self) -> &'static str {
+        match self {
+            Self::Global => "Global",
+            Self::User => "User",
+            Self::Web => "Web",
+        }
+    }
+}
+
+/// The state of a media file's (background) processing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+#[repr(i16)]
+pub enum ProcessingStatus {
+    /// The file has been uploaded, but it's still being processed (resized, converted, etc).
+    Processing = 0,
+
+    /// The file has been processed and is ready to be used.
+    Ready = 1,
+
+    /// Processing the file failed, it needs to be uploaded again.
+    Failed = 2,
+}
+
+/// Whether media has been cleared to be shown.
+///
+/// Media that isn't approved is quarantined: only its owner (if any) and admins can see it.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+#[repr(i16)]
+pub enum ModerationStatus {
+    /// The media is waiting to be reviewed.
+    Pending = 0,
+
+    /// The media can be shown.
+    Approved = 1,
+
+    /// The media was rejected, and is hidden.
+    Rejected = 2,
+}
+
+/// The license that media is used under.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+#[repr(i16)]
+pub enum License {
+    /// All rights are reserved by the author, the media is used with their permission.
+    AllRightsReserved = 0,
+
+    /// The media is in the public domain.
+    PublicDomain = 1,
+
+    /// Creative Commons Zero (no rights reserved).
+    Cc0 = 2,
+
+    /// Creative Commons Attribution.
+    CcBy = 3,
+
+    /// Creative Commons Attribution-ShareAlike.
+    CcBySa = 4,
+
+    /// Creative Commons Attribution-NonCommercial.
+    CcByNc = 5,
+
+    /// Creative Commons Attribution-NonCommercial-ShareAlike.
+    CcByNcSa = 6,
+
+    /// Creative Commons Attribution-NoDerivatives.
+    CcByNd = 7,
+
+    /// Creative Commons Attribution-NonCommercial-NoDerivatives.
+    CcByNcNd = 8,
+
+    /// The media was licensed (bought) from a stock library.
+    Licensed = 9,
+}
+
+/// A description of media for people who can't see it, in a single language.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub struct AltText {
+    /// The language of the text (for example `he` or `en-US`).
+    pub language: String,
+
+    /// The alt text.
+    pub text: String,
 }
 
 /// Kinds of media used with the web media library
@@ -87,6 +182,33 @@ pub enum MediaKind {
     // Audio()
 }
 
+/// Kinds of media that media listings (like [`AdminListMediaQuery`](crate::domain::admin::AdminListMediaQuery)) can filter by.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "backend", derive(paperclip::actix::Apiv2Schema))]
+pub enum MediaKindFilter {
+    /// Any kind of image.
+    Image,
+
+    /// Canvas (background) images.
+    CanvasImage,
+
+    /// Sticker images.
+    StickerImage,
+
+    /// Any kind of animation.
+    Animation,
+
+    /// Gif animations.
+    GifAnimation,
+
+    /// Spritesheet animations.
+    SpritesheetAnimation,
+
+    /// Audio.
+    Audio,
+}
+
 /// Kinds of media files
 /// FIXME: Really awkward
 #[derive(Copy, Clone, Debug)]
@@ -94,6 +216,9 @@ pub enum FileKind {
     /// File for an Animated Gif
     AnimationGif,
 
+    /// Spritesheet generated from a spritesheet animation's gif (every frame, left to right)
+    AnimationSpritesheet,
+
     /// Files for a PNG Image
     ImagePng(PngImageFile),
 
@@ -108,21 +233,37 @@ impl FileKind {
     pub const fn content_type(self) -> &'static str {
         match self {
             Self::AnimationGif => "image/gif",
-            Self::ImagePng(_) => "image/png",
+            Self::AnimationSpritesheet | Self::ImagePng(_) => "image/png",
             Self::AudioMp3 => "audio/mp3",
         }
     }
 
+    /// Returns the name of the represented file, this is the last part of its key.
     #[must_use]
-    const fn suffix(self) -> &'static str {
+    pub const fn suffix(self) -> &'static str {
         match self {
             Self::AnimationGif => "animation.gif",
+            Self::AnimationSpritesheet => "spritesheet.png",
             Self::ImagePng(PngImageFile::Original) => "original.png",
             Self::ImagePng(PngImageFile::Thumbnail) => "thumbnail.png",
             Self::ImagePng(PngImageFile::Resized) => "resized.png",
             Self::AudioMp3 => "audio.mp3",
         }
     }
+
+    /// The inverse of [`suffix`](Self::suffix).
+    #[must_use]
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        match suffix {
+            "animation.gif" => Some(Self::AnimationGif),
+            "spritesheet.png" => Some(Self::AnimationSpritesheet),
+            "original.png" => Some(Self::ImagePng(PngImageFile::Original)),
+            "thumbnail.png" => Some(Self::ImagePng(PngImageFile::Thumbnail)),
+            "resized.png" => Some(Self::ImagePng(PngImageFile::Resized)),
+            "audio.mp3" => Some(Self::AudioMp3),
+            _ => None,
+        }
+    }
 }
 
 /// gives the key for some media with the given parameters
@@ -136,3 +277,128 @@ pub fn media_key(library: MediaLibrary, id: Uuid, file_kind: FileKind) -> String
         file_kind.suffix()
     )
 }
+
+/// Where media that hasn't been approved by moderation is stored (in front of its [`media_key`]).
+/// Unlike `media/`, this prefix isn't served by the CDN.
+pub const QUARANTINE_PREFIX: &str = "quarantine/";
+
+/// gives the key for quarantined media with the given parameters (see [`QUARANTINE_PREFIX`])
+#[must_use]
+pub fn quarantine_key(library: MediaLibrary, id: Uuid, file_kind: FileKind) -> String {
+    format!("{}{}", QUARANTINE_PREFIX, media_key(library, id, file_kind))
+}
+
+/// Where media that not everyone may see (premium and user library media) is stored (in front of its [`media_key`]).
+/// Like [`QUARANTINE_PREFIX`], this prefix isn't served by the CDN, the files are only handed out through signed urls.
+pub const PRIVATE_PREFIX: &str = "private/";
+
+/// gives the key for private media with the given parameters (see [`PRIVATE_PREFIX`])
+#[must_use]
+pub fn private_key(library: MediaLibrary, id: Uuid, file_kind: FileKind) -> String {
+    format!("{}{}", PRIVATE_PREFIX, media_key(library, id, file_kind))
+}
+
+/// The inverse of [`media_key`], returns `None` if `key` isn't a media key.
+#[must_use]
+pub fn parse_media_key(key: &str) -> Option<(MediaLibrary, Uuid, FileKind)> {
+    let mut parts = key.split('/');
+
+    if parts.next()? != "media" {
+        return None;
+    }
+
+    let library = match parts.next()? {
+        "global" => MediaLibrary::Global,
+        "user" => MediaLibrary::User,
+        "web" => MediaLibrary::Web,
+        _ => return None,
+    };
+
+    let id = Uuid::parse_str(parts.next()?).ok()?;
+    let file_kind = FileKind::from_suffix(parts.next()?)?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some((library, id, file_kind))
+}
+
+/// gives the api path that serves the media with the given parameters (see [`GetFile`](crate::api::endpoints::media::GetFile))
+/// unlike [`media_key`], this works for private and premium media, since the api checks access before handing out a (signed) url.
+/// this is *not* a full url, (it's missing the api's domain)
+#[must_use]
+pub fn media_file_path(library: MediaLibrary, id: Uuid, file_kind: FileKind) -> String {
+    format!(
+        "/v1/media/file/{}/{}/{}",
+        library.to_path_str(),
+        id.to_hyphenated(),
+        file_kind.suffix()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{media_key, parse_media_key, FileKind, MediaLibrary, PngImageFile};
+    use uuid::Uuid;
+
+    const ID: &str = "3095d05e-f2c7-11ea-89c3-3b621dd74a1f";
+
+    #[test]
+    fn parse_media_key_roundtrips() {
+        let id = Uuid::parse_str(ID).unwrap();
+
+        let files = [
+            (
+                MediaLibrary::Global,
+                FileKind::ImagePng(PngImageFile::Original),
+            ),
+            (MediaLibrary::Global, FileKind::AnimationGif),
+            (MediaLibrary::Global, FileKind::AnimationSpritesheet),
+            (
+                MediaLibrary::User,
+                FileKind::ImagePng(PngImageFile::Resized),
+            ),
+            (MediaLibrary::User, FileKind::AudioMp3),
+            (
+                MediaLibrary::Web,
+                FileKind::ImagePng(PngImageFile::Thumbnail),
+            ),
+        ];
+
+        for &(library, file_kind) in &files {
+            let key = media_key(library, id, file_kind);
+            let (parsed_library, parsed_id, parsed_file_kind) =
+                parse_media_key(&key).expect("key should parse");
+
+            assert_eq!(parsed_library as i16, library as i16);
+            assert_eq!(parsed_id, id);
+            assert_eq!(parsed_file_kind.suffix(), file_kind.suffix());
+        }
+    }
+
+    #[test]
+    fn parse_media_key_rejects_other_keys() {
+        let keys = [
+            String::new(),
+            "media".to_owned(),
+            format!("media/global/{}", ID),
+            format!("media/global/{}/original.png/extra", ID),
+            format!("media/premium/{}/original.png", ID),
+            "media/global/not-a-uuid/original.png".to_owned(),
+            format!("media/global/{}/original.jpg", ID),
+            format!("uploads/global/{}/original.png", ID),
+            // quarantined and private keys have to have their prefix stripped first.
+            format!("quarantine/media/global/{}/original.png", ID),
+            format!("private/media/user/{}/original.png", ID),
+        ];
+
+        for key in &keys {
+            assert!(
+                parse_media_key(key).is_none(),
+                "{:?} should be rejected",
+                key
+            );
+        }
+    }
+}